@@ -0,0 +1,85 @@
+use crate::json::Json;
+use crate::signatures;
+
+/// How an anchor is located in a cache.
+pub enum Locator {
+    /// A symbol, plus an offset into it.
+    Symbol { symbol: String, offset: u64 },
+    /// A byte pattern that must match exactly once.
+    Signature(Vec<Option<u8>>),
+}
+
+/// A named location to resolve in a cache, e.g. for porting hardcoded offsets to a new build.
+pub struct Anchor {
+    pub name: String,
+    /// Image to search (install path or file name); all images when absent.
+    pub module: Option<String>,
+    pub locator: Locator,
+}
+
+/// Parse a JSON list of anchors. Each entry has a `name`, an optional `module` and either a
+/// `symbol` with an optional `offset` (a number or a hex string) or a `signature`:
+///
+/// ```json
+/// [
+///   {"name": "msgSend", "module": "libobjc.A.dylib", "symbol": "_objc_msgSend"},
+///   {"name": "msgSend_cache", "symbol": "_objc_msgSend", "offset": "0x10"},
+///   {"name": "check", "module": "Foundation", "signature": "FD 7B BF A9 ?? ?? ?? ??"}
+/// ]
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Anchor>, String> {
+    let json = Json::parse(text)?;
+    let entries = json
+        .as_array()
+        .ok_or("Anchor file must contain a JSON array")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| parse_entry(entry).map_err(|e| format!("Anchor {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn parse_entry(entry: &Json) -> Result<Anchor, String> {
+    let string = |key: &str| match entry.get(key) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or(format!("'{}' must be a string", key)),
+    };
+    let name = string("name")?.ok_or("missing 'name'")?;
+    let module = string("module")?;
+    let locator = match (string("symbol")?, string("signature")?) {
+        (Some(symbol), None) => {
+            let offset = match entry.get("offset") {
+                None => 0,
+                Some(Json::String(s)) => {
+                    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"));
+                    match digits {
+                        Some(digits) => u64::from_str_radix(digits, 16),
+                        None => s.parse(),
+                    }
+                    .map_err(|_| format!("invalid offset '{}' for {}", s, name))?
+                }
+                Some(value) => value
+                    .as_u64()
+                    .ok_or(format!("'offset' of {} must be a number", name))?,
+            };
+            Locator::Symbol { symbol, offset }
+        }
+        (None, Some(pattern)) => Locator::Signature(
+            signatures::parse_pattern(&pattern).map_err(|e| format!("{} for {}", e, name))?,
+        ),
+        _ => {
+            return Err(format!(
+                "{} needs exactly one of 'symbol' or 'signature'",
+                name
+            ));
+        }
+    };
+    Ok(Anchor {
+        name,
+        module,
+        locator,
+    })
+}
@@ -0,0 +1,305 @@
+//! AArch64 disassembly through capstone, with the effects of instructions that matter for
+//! annotation: branch targets, calls, and the `adrp`/`add`/`ldr` sequences that compute
+//! addresses. Words capstone cannot decode are printed as `.word`.
+
+use capstone::arch::arm64::{Arm64Insn, Arm64InsnGroup, Arm64OperandType, Arm64Shift};
+use capstone::prelude::*;
+use capstone::{Insn, RegId};
+
+/// What an instruction does that is interesting for annotation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Effect {
+    None,
+    /// Direct branch (`b`, `bl`, `b.cond`, `cbz`, `tbz`, ...).
+    Branch {
+        target: u64,
+        call: bool,
+    },
+    /// Indirect call (`blr` and its authenticated variants).
+    IndirectCall,
+    /// `adrp` or `adr` computing an address into `rd`.
+    Address {
+        rd: u8,
+        addr: u64,
+    },
+    /// `add rd, rn, #imm` on 64-bit registers.
+    AddImm {
+        rd: u8,
+        rn: u8,
+        imm: u64,
+    },
+    /// 64-bit load from `[rn, #offset]`.
+    Load {
+        rt: u8,
+        rn: u8,
+        offset: u64,
+    },
+    /// PC-relative literal load.
+    LoadLiteral {
+        rt: u8,
+        addr: u64,
+    },
+}
+
+pub struct Instruction {
+    pub text: String,
+    pub effect: Effect,
+    /// General-purpose register written by the instruction, when known.
+    pub writes: Option<u8>,
+}
+
+const INDIRECT_CALLS: [Arm64Insn; 5] = [
+    Arm64Insn::ARM64_INS_BLR,
+    Arm64Insn::ARM64_INS_BLRAA,
+    Arm64Insn::ARM64_INS_BLRAAZ,
+    Arm64Insn::ARM64_INS_BLRAB,
+    Arm64Insn::ARM64_INS_BLRABZ,
+];
+
+/// Mnemonic prefixes of instructions whose first register operand is read, not written.
+const READS_FIRST: [&str; 8] = ["st", "cmp", "cmn", "tst", "ccm", "fcm", "msr", "prfm"];
+
+thread_local! {
+    static CAPSTONE: Capstone = Capstone::new()
+        .arm64()
+        .mode(arch::arm64::ArchMode::Arm)
+        .detail(true)
+        .build()
+        .expect("capstone supports arm64");
+}
+
+/// Decode the instruction `insn` found at `pc`.
+pub fn decode(insn: u32, pc: u64) -> Instruction {
+    CAPSTONE.with(|cs| {
+        let bytes = insn.to_le_bytes();
+        let decoded = cs.disasm_count(&bytes, pc, 1).ok();
+        match decoded.as_ref().and_then(|insns| insns.iter().next()) {
+            Some(decoded) => describe(cs, &decoded),
+            None => Instruction {
+                text: format!(".word 0x{:08X}", insn),
+                effect: Effect::None,
+                writes: None,
+            },
+        }
+    })
+}
+
+fn describe(cs: &Capstone, insn: &Insn) -> Instruction {
+    let mnemonic = insn.mnemonic().unwrap_or_default();
+    let text = match insn.op_str() {
+        Some(operands) if !operands.is_empty() => format!("{} {}", mnemonic, operands),
+        _ => mnemonic.to_string(),
+    };
+    let Ok(detail) = cs.insn_detail(insn) else {
+        return Instruction {
+            text,
+            effect: Effect::None,
+            writes: None,
+        };
+    };
+    let arch = detail.arch_detail();
+    let Some(arm64) = arch.arm64() else {
+        return Instruction {
+            text,
+            effect: Effect::None,
+            writes: None,
+        };
+    };
+    let writeback = arm64.writeback();
+    let operands: Vec<_> = arm64.operands().map(|op| (op.op_type, op.shift)).collect();
+    let id = insn.id().0;
+    let is = |which: Arm64Insn| id == which as u32;
+    let register = |reg: RegId| cs.reg_name(reg).and_then(|name| register_number(&name));
+    let x_register = |reg: RegId| {
+        cs.reg_name(reg)
+            .filter(|name| name.starts_with('x') || name == "sp" || name == "fp" || name == "lr")
+            .and_then(|name| register_number(&name))
+    };
+
+    let branch = detail
+        .groups()
+        .any(|group| group.0 as u32 == Arm64InsnGroup::ARM64_GRP_BRANCH_RELATIVE);
+    let effect = match operands.as_slice() {
+        [.., (Arm64OperandType::Imm(target), _)] if branch => Effect::Branch {
+            target: *target as u64,
+            call: is(Arm64Insn::ARM64_INS_BL),
+        },
+        _ if INDIRECT_CALLS.iter().any(|&call| is(call)) => Effect::IndirectCall,
+        [
+            (Arm64OperandType::Reg(rd), _),
+            (Arm64OperandType::Imm(addr), _),
+        ] if is(Arm64Insn::ARM64_INS_ADRP) || is(Arm64Insn::ARM64_INS_ADR) => {
+            register(*rd).map_or(Effect::None, |rd| Effect::Address {
+                rd,
+                addr: *addr as u64,
+            })
+        }
+        [
+            (Arm64OperandType::Reg(rd), _),
+            (Arm64OperandType::Reg(rn), _),
+            (Arm64OperandType::Imm(imm), shift),
+        ] if is(Arm64Insn::ARM64_INS_ADD) => match (x_register(*rd), x_register(*rn)) {
+            (Some(rd), Some(rn)) => Effect::AddImm {
+                rd,
+                rn,
+                imm: match shift {
+                    Arm64Shift::Lsl(amount) => (*imm as u64) << amount,
+                    _ => *imm as u64,
+                },
+            },
+            _ => Effect::None,
+        },
+        [
+            (Arm64OperandType::Reg(rt), _),
+            (Arm64OperandType::Mem(mem), _),
+        ] if is(Arm64Insn::ARM64_INS_LDR) && !writeback && mem.index().0 == 0 => {
+            match (x_register(*rt), x_register(mem.base())) {
+                (Some(rt), Some(rn)) => Effect::Load {
+                    rt,
+                    rn,
+                    offset: mem.disp() as i64 as u64,
+                },
+                _ => Effect::None,
+            }
+        }
+        [
+            (Arm64OperandType::Reg(rt), _),
+            (Arm64OperandType::Imm(addr), _),
+        ] if is(Arm64Insn::ARM64_INS_LDR) => {
+            x_register(*rt).map_or(Effect::None, |rt| Effect::LoadLiteral {
+                rt,
+                addr: *addr as u64,
+            })
+        }
+        _ => Effect::None,
+    };
+
+    let reads_first = branch
+        || INDIRECT_CALLS.iter().any(|&call| is(call))
+        || detail.groups().any(|group| {
+            let group = group.0 as u32;
+            group == Arm64InsnGroup::ARM64_GRP_JUMP || group == Arm64InsnGroup::ARM64_GRP_RET
+        })
+        || READS_FIRST
+            .iter()
+            .any(|prefix| mnemonic.starts_with(prefix));
+    let writes = match operands.first() {
+        Some((Arm64OperandType::Reg(rd), _)) if !reads_first => register(*rd),
+        _ => None,
+    };
+    Instruction {
+        text,
+        effect,
+        writes,
+    }
+}
+
+/// Number of a general-purpose register from its name; the stack pointer and the zero
+/// register are 31.
+fn register_number(name: &str) -> Option<u8> {
+    match name {
+        "sp" | "wsp" | "xzr" | "wzr" => Some(31),
+        "fp" => Some(29),
+        "lr" => Some(30),
+        _ => name
+            .strip_prefix(['x', 'w'])
+            .and_then(|n| n.parse().ok())
+            .filter(|&n| n < 31),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC: u64 = 0x1_0000_0000;
+
+    #[test]
+    fn text() {
+        for (insn, text) in [
+            (0x9400_0400, "bl #0x100001000"),
+            (0xD65F_03C0, "ret"),
+            (0xD503_201F, "nop"),
+            (0xA9BF_7BFD, "stp x29, x30, [sp, #-0x10]!"),
+            (0xF940_0A08, "ldr x8, [x16, #0x10]"),
+            (0xAA01_03E0, "mov x0, x1"),
+        ] {
+            assert_eq!(decode(insn, PC).text, text, "{:08X}", insn);
+        }
+        assert_eq!(decode(0xFFFF_FFFF, PC).text, ".word 0xFFFFFFFF");
+    }
+
+    #[test]
+    fn effects() {
+        assert_eq!(
+            decode(0x9400_0400, PC).effect,
+            Effect::Branch {
+                target: PC + 0x1000,
+                call: true
+            }
+        );
+        assert_eq!(
+            decode(0x1400_0400, PC).effect,
+            Effect::Branch {
+                target: PC + 0x1000,
+                call: false
+            }
+        );
+        assert_eq!(
+            decode(0xB400_0041, PC).effect,
+            Effect::Branch {
+                target: PC + 8,
+                call: false
+            }
+        );
+        let adrp = decode(0x9000_0010, PC);
+        assert_eq!(adrp.effect, Effect::Address { rd: 16, addr: PC });
+        assert_eq!(adrp.writes, Some(16));
+        assert_eq!(
+            decode(0x9100_4000, PC).effect,
+            Effect::AddImm {
+                rd: 0,
+                rn: 0,
+                imm: 0x10
+            }
+        );
+        assert_eq!(
+            decode(0x9140_0400, PC).effect,
+            Effect::AddImm {
+                rd: 0,
+                rn: 0,
+                imm: 0x1000
+            }
+        );
+        assert_eq!(
+            decode(0xF940_0A08, PC).effect,
+            Effect::Load {
+                rt: 8,
+                rn: 16,
+                offset: 0x10
+            }
+        );
+        assert_eq!(
+            decode(0x5800_0040, PC).effect,
+            Effect::LoadLiteral {
+                rt: 0,
+                addr: PC + 8
+            }
+        );
+        assert_eq!(decode(0xD63F_0100, PC).effect, Effect::IndirectCall);
+        assert_eq!(decode(0xD73F_0A11, PC).effect, Effect::IndirectCall);
+        // Writeback and 32-bit forms are not followed.
+        assert_eq!(decode(0xA9BF_7BFD, PC).effect, Effect::None);
+        assert_eq!(decode(0xB940_0A08, PC).effect, Effect::None);
+    }
+
+    #[test]
+    fn written_registers() {
+        assert_eq!(decode(0xAA01_03E0, PC).writes, Some(0));
+        assert_eq!(decode(0xF940_0A08, PC).writes, Some(8));
+        assert_eq!(decode(0xF100_051F, PC).writes, None);
+        assert_eq!(decode(0xF900_0A08, PC).writes, None);
+        assert_eq!(decode(0xB400_0041, PC).writes, None);
+        assert_eq!(decode(0xD63F_0100, PC).writes, None);
+    }
+}
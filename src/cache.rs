@@ -0,0 +1,224 @@
+use memmap2::{Mmap, MmapOptions};
+use object::LittleEndian;
+use object::macho::{DyldCacheHeader, MachHeader64};
+use object::pod;
+use object::read::macho::DyldCache;
+use std::error::Error;
+use std::fs::File;
+use std::ops::Range;
+
+use crate::select::{self, Selection};
+use crate::vm;
+
+/// Bytes from a runtime memory dump, read in place of the cache contents at `address`, an
+/// unslid cache address.
+pub struct Overlay {
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+impl Overlay {
+    /// Read a dump that starts at `address`.
+    pub fn read(path: &str, address: u64) -> Result<Self, Box<dyn Error>> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Ok(Overlay { address, data })
+    }
+}
+
+/// A cache opened from disk: the main file and its subcaches, mapped in memory. `DyldCache`
+/// borrows the mapped data, so it is parsed on demand with `parse`.
+pub struct MappedCache {
+    pub selection: Selection,
+    main: Mmap,
+    subcaches: Vec<Mmap>,
+}
+
+impl MappedCache {
+    /// Open a cache file, or the best cache of a directory (see `select::select`), and map
+    /// the subcache files it lists.
+    pub fn open(path: &str, arch: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let selection = select::select(path, arch)?;
+        let path = selection
+            .path
+            .to_str()
+            .ok_or("Cache path is not valid UTF-8")?;
+        let main = map(path)?;
+        let subcaches = DyldCache::<LittleEndian>::subcache_suffixes(&*main)?
+            .into_iter()
+            .map(|suffix| map(&format!("{}{}", path, suffix)))
+            .collect::<Result<_, _>>()?;
+        Ok(MappedCache {
+            selection,
+            main,
+            subcaches,
+        })
+    }
+
+    pub fn parse(&self) -> Result<DyldCache<'_, LittleEndian>, Box<dyn Error>> {
+        let subcaches: Vec<&[u8]> = self.subcaches.iter().map(|m| &**m).collect();
+        Ok(DyldCache::<LittleEndian>::parse(&*self.main, &subcaches)?)
+    }
+
+    /// Read `overlays` in place of the cache contents they cover, later ones winning where they
+    /// intersect. The files they touch are mapped again copy-on-write, so the files on disk
+    /// are left untouched. Returns the number of bytes overlaid, which excludes the parts of
+    /// the dumps outside the cache mappings.
+    pub fn apply_overlays(&mut self, overlays: &[Overlay]) -> Result<usize, Box<dyn Error>> {
+        // Pieces of the overlays as (file index, file offset, overlay, range in its data).
+        let mut pieces: Vec<(usize, usize, &Overlay, Range<usize>)> = Vec::new();
+        {
+            let cache = self.parse()?;
+            let files: Vec<&[u8]> = self.files().collect();
+            for overlay in overlays {
+                if overlay
+                    .address
+                    .checked_add(overlay.data.len() as u64)
+                    .is_none()
+                {
+                    return Err(format!(
+                        "Overlay at 0x{:X} of 0x{:X} bytes runs past the end of the address space",
+                        overlay.address,
+                        overlay.data.len()
+                    )
+                    .into());
+                }
+            }
+            for (overlay, mapping) in overlays
+                .iter()
+                .flat_map(|overlay| cache.mappings().map(move |mapping| (overlay, mapping)))
+            {
+                let data = mapping.data()?;
+                let start = overlay.address.max(mapping.address());
+                let end = (overlay.address + overlay.data.len() as u64)
+                    .min(mapping.address() + data.len() as u64);
+                if start >= end {
+                    continue;
+                }
+                let file = files
+                    .iter()
+                    .position(|f| f.as_ptr_range().contains(&data.as_ptr()))
+                    .ok_or("Mapping data is outside the mapped files")?;
+                let offset = data.as_ptr() as usize - files[file].as_ptr() as usize
+                    + (start - mapping.address()) as usize;
+                let from = (start - overlay.address) as usize;
+                pieces.push((file, offset, overlay, from..from + (end - start) as usize));
+            }
+        }
+
+        let path = self
+            .selection
+            .path
+            .to_str()
+            .ok_or("Cache path is not valid UTF-8")?
+            .to_string();
+        let suffixes = DyldCache::<LittleEndian>::subcache_suffixes(&*self.main)?;
+        let mut files: Vec<usize> = pieces.iter().map(|p| p.0).collect();
+        files.sort();
+        files.dedup();
+        for file in files {
+            let file_path = match file {
+                0 => path.clone(),
+                _ => format!("{}{}", path, suffixes[file - 1]),
+            };
+            let handle = File::open(&file_path)
+                .map_err(|e| format!("Failed to open {}: {}", file_path, e))?;
+            // A private mapping: writes stay in memory and never reach the file.
+            let mut copy = unsafe { MmapOptions::new().map_copy(&handle)? };
+            for (_, offset, overlay, range) in pieces.iter().filter(|p| p.0 == file) {
+                copy[*offset..*offset + range.len()].copy_from_slice(&overlay.data[range.clone()]);
+            }
+            let copy = copy.make_read_only()?;
+            match file {
+                0 => self.main = copy,
+                _ => self.subcaches[file - 1] = copy,
+            }
+        }
+        Ok(pieces.iter().map(|p| p.3.len()).sum())
+    }
+
+    fn files(&self) -> impl Iterator<Item = &[u8]> {
+        std::iter::once(&*self.main).chain(self.subcaches.iter().map(|m| &**m))
+    }
+
+    /// Number of files mapped, including the main cache file.
+    pub fn file_count(&self) -> usize {
+        1 + self.subcaches.len()
+    }
+
+    /// Total size of the mapped files.
+    pub fn mapped_size(&self) -> usize {
+        self.main.len() + self.subcaches.iter().map(|m| m.len()).sum::<usize>()
+    }
+}
+
+/// Only the main file of a cache, for questions its header answers: the image list, UUID,
+/// platform and type. Unlike `MappedCache`, opening it leaves the subcache files alone, which
+/// spares queries about large caches opening a dozen files of several gigabytes each.
+pub struct CacheHeader {
+    pub selection: Selection,
+    main: Mmap,
+}
+
+impl CacheHeader {
+    /// Open a cache file, or the best cache of a directory (see `select::select`), without its
+    /// subcaches.
+    pub fn open(path: &str, arch: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let selection = select::select(path, arch)?;
+        let main = map(selection
+            .path
+            .to_str()
+            .ok_or("Cache path is not valid UTF-8")?)?;
+        Ok(CacheHeader { selection, main })
+    }
+
+    /// Contents of the main cache file.
+    pub fn data(&self) -> &[u8] {
+        &self.main
+    }
+
+    pub fn header(&self) -> Result<&DyldCacheHeader<LittleEndian>, Box<dyn Error>> {
+        Ok(DyldCacheHeader::<LittleEndian>::parse(&*self.main)?)
+    }
+
+    /// Header address and install path of every image, in cache order.
+    pub fn images(&self) -> Result<Vec<(u64, &str)>, Box<dyn Error>> {
+        let e = LittleEndian;
+        self.header()?
+            .images(e, &*self.main)?
+            .iter()
+            .map(|info| {
+                let path = std::str::from_utf8(info.path(e, &*self.main)?)
+                    .map_err(|_| "Image path is not valid UTF-8")?;
+                Ok((info.address.get(e), path))
+            })
+            .collect()
+    }
+
+    /// Number of mappings of the main file. Those of the subcaches are described in their own
+    /// headers.
+    pub fn mapping_count(&self) -> usize {
+        vm::main_file_mappings(&self.main).map_or(0, |m| m.len())
+    }
+
+    /// Number of subcache files the cache is split into.
+    pub fn subcache_count(&self) -> usize {
+        DyldCache::<LittleEndian>::subcache_suffixes(&*self.main).map_or(0, |s| s.len())
+    }
+
+    /// Mach-O header of the image at `address`, when it lives in the main file.
+    pub fn macho_header(&self, address: u64) -> Option<&MachHeader64<LittleEndian>> {
+        let (start, _, file_offset) = vm::main_file_mappings(&self.main)?
+            .into_iter()
+            .find(|&(start, size, _)| address >= start && address < start + size)?;
+        let offset = (file_offset + (address - start)) as usize;
+        pod::from_bytes(self.main.get(offset..)?)
+            .ok()
+            .map(|(h, _)| h)
+    }
+}
+
+fn map(path: &str) -> Result<Mmap, Box<dyn Error>> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    // The cache files are not expected to change while they are mapped.
+    Ok(unsafe { Mmap::map(&file)? })
+}
@@ -0,0 +1,192 @@
+use object::LittleEndian;
+use object::macho::{self, DyldCacheHeader};
+use object::read::macho::DyldCache;
+use std::fmt;
+use std::mem::offset_of;
+
+use crate::select::Family;
+
+const TYPE_DEVELOPMENT: u64 = 0;
+const TYPE_PRODUCTION: u64 = 1;
+const TYPE_UNIVERSAL: u64 = 2;
+/// Platforms of exclave runtimes (`PLATFORM_MACOS_EXCLAVECORE` to
+/// `PLATFORM_VISIONOS_EXCLAVEKIT`), alternating core and kit per OS.
+const PLATFORM_EXCLAVES: std::ops::RangeInclusive<u32> = 15..=24;
+
+/// Build configuration of a cache, from the `cacheType` header field.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Config {
+    /// Unoptimized stubs, kept debuggable; roots can replace any dylib.
+    Development,
+    /// Customer cache with optimized stubs.
+    Production,
+    /// The header predates the field.
+    Unrecorded,
+    Unknown(u64),
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Config::Development => write!(f, "development"),
+            Config::Production => write!(f, "production"),
+            Config::Unrecorded => write!(f, "unrecorded"),
+            Config::Unknown(value) => write!(f, "unknown ({})", value),
+        }
+    }
+}
+
+/// Where the symbols stripped from the images (`nlist` locals) are kept.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LocalSymbols {
+    None,
+    /// In the main cache file, with the size of the table.
+    Inline(u64),
+    /// In the `.symbols` subcache file.
+    SymbolsFile,
+}
+
+/// Type of a cache. Older caches come as separate development and customer files ("split");
+/// a universal cache is one set of files whose `cacheSubType` tells which configuration the
+/// main file was built for, with the development-only content in extra subcache files.
+pub struct CacheKind {
+    /// `platform` header field, a `PLATFORM_*` value.
+    pub platform: u32,
+    pub config: Config,
+    pub universal: bool,
+    pub local_symbols: LocalSymbols,
+    /// Suffixes of subcache files only used by the development configuration.
+    pub development_subcaches: Vec<String>,
+}
+
+impl CacheKind {
+    pub fn new(cache: &DyldCache<LittleEndian>) -> Self {
+        Self::from_main_file(cache.data())
+    }
+
+    /// Classify a cache from its main file alone, which holds everything this looks at.
+    pub fn from_main_file(data: &[u8]) -> Self {
+        let e = LittleEndian;
+        let Ok(header) = DyldCacheHeader::<LittleEndian>::parse(data) else {
+            return CacheKind {
+                platform: 0,
+                config: Config::Unrecorded,
+                universal: false,
+                local_symbols: LocalSymbols::None,
+                development_subcaches: Vec::new(),
+            };
+        };
+        // Fields past the end of an old header overlap the mapping table.
+        let has = |offset: usize| offset < header.mapping_offset.get(e) as usize;
+
+        let config = |value: u64| match value {
+            TYPE_DEVELOPMENT => Config::Development,
+            TYPE_PRODUCTION => Config::Production,
+            _ => Config::Unknown(value),
+        };
+        let cache_type = header.cache_type.get(e);
+        let recorded = has(offset_of!(DyldCacheHeader<LittleEndian>, cache_type));
+        let universal = recorded && cache_type == TYPE_UNIVERSAL;
+        let config = if !recorded {
+            Config::Unrecorded
+        } else if !universal {
+            config(cache_type)
+        } else if has(offset_of!(DyldCacheHeader<LittleEndian>, cache_sub_type)) {
+            config(u64::from(header.cache_sub_type.get(e)))
+        } else {
+            Config::Unknown(cache_type)
+        };
+
+        let local_symbols = if header.symbols_subcache_uuid(e).is_some() {
+            LocalSymbols::SymbolsFile
+        } else if header.local_symbols_size.get(e) != 0 {
+            LocalSymbols::Inline(header.local_symbols_size.get(e))
+        } else {
+            LocalSymbols::None
+        };
+        let development_subcaches = DyldCache::<LittleEndian>::subcache_suffixes(data)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|suffix| suffix.contains("development"))
+            .collect();
+        CacheKind {
+            platform: if has(offset_of!(DyldCacheHeader<LittleEndian>, platform)) {
+                header.platform.get(e)
+            } else {
+                0
+            },
+            config,
+            universal,
+            local_symbols,
+            development_subcaches,
+        }
+    }
+
+    pub fn family(&self) -> Family {
+        match self.platform {
+            macho::PLATFORM_DRIVERKIT => Family::DriverKit,
+            p if PLATFORM_EXCLAVES.contains(&p) => Family::ExclaveKit,
+            _ => Family::System,
+        }
+    }
+
+    pub fn is_production(&self) -> bool {
+        self.config == Config::Production
+    }
+
+    /// Hint printed by symbol listings, which only read the symbol tables left in the images.
+    pub fn symbols_notice(&self) -> Option<&'static str> {
+        match self.local_symbols {
+            LocalSymbols::None => None,
+            LocalSymbols::Inline(_) => {
+                Some("Local symbols stripped into the cache's local symbol table are not listed")
+            }
+            LocalSymbols::SymbolsFile => {
+                Some("Local symbols stripped into the .symbols file are not listed")
+            }
+        }
+    }
+}
+
+/// Name of a `PLATFORM_*` value.
+pub fn platform_name(platform: u32) -> String {
+    let name = match platform {
+        macho::PLATFORM_MACOS => "macOS",
+        macho::PLATFORM_IOS => "iOS",
+        macho::PLATFORM_TVOS => "tvOS",
+        macho::PLATFORM_WATCHOS => "watchOS",
+        macho::PLATFORM_BRIDGEOS => "bridgeOS",
+        macho::PLATFORM_MACCATALYST => "Mac Catalyst",
+        macho::PLATFORM_IOSSIMULATOR => "iOS Simulator",
+        macho::PLATFORM_TVOSSIMULATOR => "tvOS Simulator",
+        macho::PLATFORM_WATCHOSSIMULATOR => "watchOS Simulator",
+        macho::PLATFORM_DRIVERKIT => "DriverKit",
+        macho::PLATFORM_XROS => "visionOS",
+        macho::PLATFORM_XROSSIMULATOR => "visionOS Simulator",
+        13 => "firmware",
+        14 => "sepOS",
+        p if PLATFORM_EXCLAVES.contains(&p) => {
+            let os = ["macOS", "iOS", "tvOS", "watchOS", "visionOS"][(p as usize - 15) / 2];
+            let kind = if p % 2 == 1 {
+                "ExclaveCore"
+            } else {
+                "ExclaveKit"
+            };
+            return format!("{} {}", os, kind);
+        }
+        0 => "unrecorded",
+        _ => return format!("unknown ({})", platform),
+    };
+    name.to_string()
+}
+
+impl fmt::Display for CacheKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})",
+            self.config,
+            if self.universal { "universal" } else { "split" }
+        )
+    }
+}
@@ -0,0 +1,177 @@
+use object::macho::{N_SECT, N_STAB, N_TYPE};
+use object::read::macho::{DyldCache, DyldCacheImage, LoadCommandVariant, MachOFile64, Segment};
+use object::{LittleEndian, Object, ObjectSection, ObjectSymbol};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+use dsc::exports::{self, ExportKind};
+use dsc::linkedit;
+
+/// Size of an `nlist_64` entry.
+const NLIST_SIZE: u32 = 16;
+/// Discrepancies listed per check before the rest are only counted.
+const MAX_EXAMPLES: usize = 5;
+
+/// Cross-check what the hand-rolled `__LINKEDIT` parsers report for an image against the view
+/// of the `object` crate, to catch parsing drift as formats evolve. Returns one warning per
+/// discrepancy found.
+pub fn check_image(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+    let mut warnings = Vec::new();
+
+    // Every __LINKEDIT range referenced by a load command must lie inside the segment.
+    let linkedit = file
+        .segments()
+        .map(|s| s.macho_segment())
+        .find(|s| s.name() == b"__LINKEDIT")
+        .ok_or("Image has no __LINKEDIT segment")?;
+    let (start, end) = (
+        linkedit.fileoff.get(e),
+        linkedit.fileoff.get(e) + linkedit.filesize.get(e),
+    );
+    let mut ranges = Vec::new();
+    let mut symtab = None;
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        match command.variant()? {
+            LoadCommandVariant::Symtab(c) => {
+                let (symoff, nsyms) = (c.symoff.get(e), c.nsyms.get(e));
+                let (stroff, strsize) = (c.stroff.get(e), c.strsize.get(e));
+                ranges.push(("symbols", symoff, nsyms * NLIST_SIZE));
+                ranges.push(("strings", stroff, strsize));
+                symtab = Some((symoff, nsyms, stroff, strsize));
+            }
+            LoadCommandVariant::LinkeditData(c) => {
+                ranges.push(("linkedit data", c.dataoff.get(e), c.datasize.get(e)));
+            }
+            _ => {}
+        }
+    }
+    for (label, offset, size) in ranges {
+        let (offset, size) = (u64::from(offset), u64::from(size));
+        if size != 0 && (offset < start || offset + size > end) {
+            warnings.push(format!(
+                "{} range 0x{:X}+0x{:X} is outside __LINKEDIT 0x{:X}-0x{:X}",
+                label, offset, size, start, end
+            ));
+        }
+    }
+
+    // Symbols read through `linkedit::bytes` against the object crate's symbol table.
+    if let Some((symoff, nsyms, stroff, strsize)) = symtab {
+        let nlists = linkedit::bytes(cache, &file, symoff, nsyms * NLIST_SIZE)?;
+        let strings = linkedit::bytes(cache, &file, stroff, strsize)?;
+        let mut ours = BTreeSet::new();
+        for nlist in nlists.chunks_exact(NLIST_SIZE as usize) {
+            let strx = u32::from_le_bytes(nlist[0..4].try_into()?) as usize;
+            let n_type = nlist[4];
+            let value = u64::from_le_bytes(nlist[8..16].try_into()?);
+            if n_type & N_STAB != 0 || n_type & N_TYPE != N_SECT {
+                continue;
+            }
+            let name = strings.get(strx..).unwrap_or_default();
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            ours.insert((String::from_utf8_lossy(name).into_owned(), value));
+        }
+        let theirs: BTreeSet<_> = file
+            .symbols()
+            .filter(|s| s.is_definition())
+            .filter_map(|s| Some((s.name().ok()?.to_string(), s.address())))
+            .collect();
+        compare(
+            &mut warnings,
+            "symbol",
+            ours.difference(&theirs),
+            "read from __LINKEDIT but not reported by object",
+        );
+        compare(
+            &mut warnings,
+            "symbol",
+            theirs.difference(&ours),
+            "reported by object but not read from __LINKEDIT",
+        );
+    }
+
+    // Exported symbols from the dynamic symbol table must be in the export trie.
+    let trie: HashMap<String, u64> = exports::image_exports(cache, image)?
+        .into_iter()
+        .filter_map(|entry| match entry.kind {
+            ExportKind::Regular { address } => Some((entry.name, address)),
+            ExportKind::StubAndResolver { stub, .. } => Some((entry.name, stub)),
+            ExportKind::Reexport { .. } => None,
+        })
+        .collect();
+    let mismatched: Vec<_> = file
+        .exports()?
+        .into_iter()
+        .filter_map(|export| {
+            let name = String::from_utf8_lossy(export.name()).into_owned();
+            (trie.get(&name) != Some(&export.address())).then_some((name, export.address()))
+        })
+        .collect();
+    compare(
+        &mut warnings,
+        "export",
+        mismatched.iter(),
+        "in the dynamic symbol table but missing from the export trie or at another address",
+    );
+
+    // Function starts must fall inside the image's sections.
+    let sections: Vec<(u64, u64)> = file
+        .sections()
+        .map(|s| (s.address(), s.address() + s.size()))
+        .collect();
+    let outside: Vec<_> = linkedit::function_starts(cache, &file)?
+        .into_iter()
+        .filter(|&a| !sections.iter().any(|&(lo, hi)| a >= lo && a < hi))
+        .map(|a| (String::new(), a))
+        .collect();
+    compare(
+        &mut warnings,
+        "function start",
+        outside.iter(),
+        "outside every section",
+    );
+    Ok(warnings)
+}
+
+/// Add a warning listing the first few `(name, address)` items of a discrepancy.
+fn compare<'a>(
+    warnings: &mut Vec<String>,
+    what: &str,
+    items: impl Iterator<Item = &'a (String, u64)>,
+    reason: &str,
+) {
+    let items: Vec<_> = items.collect();
+    if items.is_empty() {
+        return;
+    }
+    let examples: Vec<String> = items
+        .iter()
+        .take(MAX_EXAMPLES)
+        .map(|(name, address)| {
+            if name.is_empty() {
+                format!("0x{:X}", address)
+            } else {
+                format!("{} @ 0x{:X}", name, address)
+            }
+        })
+        .collect();
+    let more = items.len().saturating_sub(MAX_EXAMPLES);
+    warnings.push(format!(
+        "{} {}(s) {}: {}{}",
+        items.len(),
+        what,
+        reason,
+        examples.join(", "),
+        if more > 0 {
+            format!(" and {} more", more)
+        } else {
+            String::new()
+        }
+    ));
+}
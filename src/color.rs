@@ -0,0 +1,176 @@
+//! Color policy shared by every command: whether to emit ANSI colors, from `--color` and the
+//! `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE` conventions, and the styles commands use.
+
+use clap::ValueEnum;
+use std::fmt;
+use std::io::IsTerminal;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal, unless the environment says otherwise
+    Auto,
+    Always,
+    Never,
+}
+
+/// What a piece of output is, which decides its color.
+#[derive(Clone, Copy)]
+pub enum Style {
+    /// Lines only in the new side of a diff.
+    Added,
+    /// Lines only in the old side of a diff.
+    Removed,
+    /// Lines in both sides of a diff, with a different value.
+    Changed,
+    /// Image paths and structure names heading a group of lines.
+    Heading,
+    /// Address columns of dumps.
+    Address,
+    /// Annotations following data, such as decoded fields.
+    Note,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Added => "32",
+            Style::Removed => "31",
+            Style::Changed => "33",
+            Style::Heading => "1",
+            Style::Address => "2",
+            Style::Note => "36",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    /// Colors for standard output.
+    pub fn stdout(choice: ColorChoice) -> Self {
+        Colors::new(choice, std::io::stdout().is_terminal(), |name| {
+            std::env::var_os(name).map(|v| v.to_string_lossy().into_owned())
+        })
+    }
+
+    /// With `Auto`: no colors if `NO_COLOR` is set to a non-empty value, colors if
+    /// `CLICOLOR_FORCE` is set to anything but `0`, no colors if `CLICOLOR` is `0` or the
+    /// terminal is dumb, and otherwise colors only for a terminal.
+    pub fn new(choice: ColorChoice, terminal: bool, var: impl Fn(&str) -> Option<String>) -> Self {
+        let no_color = var("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let forced = var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto if no_color => false,
+            ColorChoice::Auto if forced => true,
+            ColorChoice::Auto => {
+                terminal
+                    && var("CLICOLOR").as_deref() != Some("0")
+                    && var("TERM").as_deref() != Some("dumb")
+            }
+        };
+        Colors { enabled }
+    }
+
+    /// `value` in `style`, or unchanged when colors are off.
+    pub fn paint<T: fmt::Display>(&self, style: Style, value: T) -> Painted<T> {
+        Painted {
+            style: self.enabled.then_some(style),
+            value,
+        }
+    }
+}
+
+pub struct Painted<T> {
+    style: Option<Style>,
+    value: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "\x1b[{}m{}\x1b[0m", style.code(), self.value),
+            None => self.value.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether colors are on for `choice`, on a terminal or not, in an environment of `vars`.
+    fn enabled(choice: ColorChoice, terminal: bool, vars: &[(&str, &str)]) -> bool {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        };
+        Colors::new(choice, terminal, var).enabled
+    }
+
+    #[test]
+    fn choices() {
+        for terminal in [true, false] {
+            assert!(enabled(ColorChoice::Always, terminal, &[]));
+            assert!(!enabled(ColorChoice::Never, terminal, &[]));
+            assert_eq!(enabled(ColorChoice::Auto, terminal, &[]), terminal);
+        }
+    }
+
+    #[test]
+    fn no_color() {
+        let vars = [("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")];
+        for terminal in [true, false] {
+            assert!(!enabled(ColorChoice::Auto, terminal, &vars));
+            assert!(enabled(ColorChoice::Always, terminal, &vars));
+        }
+        // An empty NO_COLOR is not set.
+        assert!(enabled(ColorChoice::Auto, true, &[("NO_COLOR", "")]));
+    }
+
+    #[test]
+    fn clicolor_force() {
+        for terminal in [true, false] {
+            assert!(enabled(
+                ColorChoice::Auto,
+                terminal,
+                &[("CLICOLOR_FORCE", "1")]
+            ));
+            assert!(!enabled(
+                ColorChoice::Never,
+                terminal,
+                &[("CLICOLOR_FORCE", "1")]
+            ));
+            assert_eq!(
+                enabled(ColorChoice::Auto, terminal, &[("CLICOLOR_FORCE", "0")]),
+                terminal
+            );
+        }
+        let vars = [("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0"), ("TERM", "dumb")];
+        assert!(enabled(ColorChoice::Auto, false, &vars));
+    }
+
+    #[test]
+    fn clicolor_and_dumb_terminals() {
+        assert!(!enabled(ColorChoice::Auto, true, &[("CLICOLOR", "0")]));
+        assert!(enabled(ColorChoice::Auto, true, &[("CLICOLOR", "1")]));
+        assert!(!enabled(ColorChoice::Auto, true, &[("TERM", "dumb")]));
+        assert!(enabled(ColorChoice::Always, true, &[("TERM", "dumb")]));
+    }
+
+    #[test]
+    fn painting() {
+        let on = Colors::new(ColorChoice::Always, false, |_| None);
+        let off = Colors::new(ColorChoice::Never, true, |_| None);
+        assert_eq!(
+            on.paint(Style::Added, "+x").to_string(),
+            "\x1b[32m+x\x1b[0m"
+        );
+        assert_eq!(off.paint(Style::Added, "+x").to_string(), "+x");
+    }
+}
@@ -0,0 +1,186 @@
+use std::io::{self, Write};
+
+use crate::arm64::{self, Effect};
+use crate::index::CacheIndex;
+use crate::linkedit::StartsSource;
+use crate::resolve::{AddressFormat, resolve};
+use crate::slide::PointerReader;
+use crate::strings::escape;
+use crate::symbolize::Symbolizer;
+use crate::vm;
+
+/// Sections holding NUL-terminated strings that are worth quoting in comments.
+const STRING_SECTIONS: [&str; 6] = [
+    "__cstring",
+    "__oslogstring",
+    "__objc_methname",
+    "__objc_classname",
+    "__objc_methtype",
+    "__swift5_reflstr",
+];
+/// Sections of pointers whose target is worth naming.
+const POINTER_SECTIONS: [&str; 7] = [
+    "__got",
+    "__auth_got",
+    "__auth_ptr",
+    "__la_symbol_ptr",
+    "__objc_classrefs",
+    "__objc_superrefs",
+    "__objc_protorefs",
+];
+const MAX_STRING: usize = 80;
+
+/// Disassembles code and annotates branch targets and literal references.
+pub struct Disassembler<'a, 'c> {
+    index: &'a CacheIndex<'c>,
+    symbols: &'a Symbolizer<'a, 'c>,
+    pointers: PointerReader<'a>,
+    fmt: &'a AddressFormat<'a, 'c>,
+}
+
+impl<'a, 'c> Disassembler<'a, 'c> {
+    pub fn new(
+        index: &'a CacheIndex<'c>,
+        symbols: &'a Symbolizer<'a, 'c>,
+        fmt: &'a AddressFormat<'a, 'c>,
+    ) -> Self {
+        Disassembler {
+            index,
+            symbols,
+            pointers: PointerReader::new(index.cache()),
+            fmt,
+        }
+    }
+
+    /// Bounds of the function starting at `start`: up to the next entry in the image's function
+    /// starts, or the end of the section when it is the last function. Also returns where the
+    /// end comes from, `None` for the end of the section.
+    pub fn function_end(&self, image: usize, start: u64) -> Option<(u64, Option<StartsSource>)> {
+        let section_end = self.index.image(image).section_at(start)?.end;
+        let (starts, source) = self.symbols.function_starts(image);
+        match starts.iter().find(|&&s| s > start) {
+            Some(&next) if next < section_end => Some((next, Some(source))),
+            _ => Some((section_end, None)),
+        }
+    }
+
+    /// Name a code address: a symbol, or the cache-builder region it falls in (stub islands).
+    fn describe_code(&self, addr: u64) -> Option<String> {
+        self.symbols
+            .name(addr)
+            .or_else(|| resolve(self.index, addr).map(|location| location.to_string()))
+    }
+
+    /// Describe what a data reference points at: a string literal, a selector, a CFString, the
+    /// target of a GOT-like pointer, or the nearest symbol.
+    fn describe_data(&self, addr: u64) -> Option<String> {
+        let cache = self.index.cache();
+        let quote = |addr: u64| {
+            vm::read_cstr(cache, addr).map(|s| {
+                let mut s = escape(s);
+                if s.len() > MAX_STRING {
+                    let cut = (0..=MAX_STRING).rev().find(|&i| s.is_char_boundary(i));
+                    s.truncate(cut.unwrap_or(0));
+                    s.push_str("...");
+                }
+                format!("\"{}\"", s)
+            })
+        };
+
+        let section = self
+            .index
+            .image_at(addr)
+            .and_then(|(_, image)| image.section_at(addr))
+            .map(|s| s.name.as_str());
+        match section {
+            Some(name) if STRING_SECTIONS.contains(&name) => quote(addr),
+            Some("__objc_selrefs") => {
+                let selector = vm::read_cstr(cache, self.pointers.read(addr)?)?;
+                Some(format!("@selector({})", selector))
+            }
+            Some("__cfstring") => Some(format!("@{}", quote(self.pointers.read(addr + 16)?)?)),
+            Some(name) if POINTER_SECTIONS.contains(&name) => {
+                let target = self.pointers.read(addr)?;
+                self.describe_code(target)
+                    .or_else(|| Some(format!("0x{:X}", target)))
+            }
+            _ => self.symbols.name(addr),
+        }
+    }
+
+    /// Write annotated disassembly of `[start, end)`.
+    pub fn write(&self, out: &mut dyn Write, start: u64, end: u64) -> io::Result<()> {
+        let cache = self.index.cache();
+        // Values of registers set by adrp/adr/add, to follow address computations.
+        let mut regs: [Option<u64>; 32] = [None; 32];
+
+        for pc in (start..end).step_by(4) {
+            let Some(insn) = vm::read_u32(cache, pc) else {
+                writeln!(out, "{}  <unmapped>", self.fmt.format(pc))?;
+                break;
+            };
+            let decoded = arm64::decode(insn, pc);
+            let mut set = None;
+            let comment = match decoded.effect {
+                Effect::Branch { target, .. } => self.describe_code(target),
+                Effect::Address { rd, addr } => {
+                    set = Some((rd, Some(addr)));
+                    // Pages from adrp are only meaningful once an offset is added.
+                    if decoded.text.starts_with("adrp") {
+                        None
+                    } else {
+                        self.describe_data(addr)
+                    }
+                }
+                Effect::AddImm { rd, rn, imm } => {
+                    let value = regs[rn as usize].map(|v| v.wrapping_add(imm));
+                    set = Some((rd, value));
+                    value.and_then(|v| self.describe_data(v))
+                }
+                Effect::Load { rn, offset, .. } => {
+                    regs[rn as usize].and_then(|v| self.describe_data(v.wrapping_add(offset)))
+                }
+                Effect::LoadLiteral { addr, .. } => self.describe_data(addr),
+                Effect::IndirectCall | Effect::None => None,
+            };
+
+            let call = matches!(
+                decoded.effect,
+                Effect::Branch { call: true, .. } | Effect::IndirectCall
+            );
+            if call {
+                // Caller-saved registers do not survive a call.
+                regs[..19].fill(None);
+                regs[30] = None;
+            }
+            match set {
+                Some((rd, value)) if rd != 31 => regs[rd as usize] = value,
+                Some(_) => {}
+                None => {
+                    if let Some(rd) = decoded.writes {
+                        regs[rd as usize] = None;
+                    }
+                }
+            }
+
+            match comment {
+                Some(comment) => writeln!(
+                    out,
+                    "{}  {:08X}  {:<40} ; {}",
+                    self.fmt.format(pc),
+                    insn,
+                    decoded.text,
+                    comment
+                )?,
+                None => writeln!(
+                    out,
+                    "{}  {:08X}  {}",
+                    self.fmt.format(pc),
+                    insn,
+                    decoded.text
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
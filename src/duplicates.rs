@@ -0,0 +1,178 @@
+//! Functions with identical bytes in several images: code the cache carries more than once,
+//! usually static libraries or inlined headers linked into each dylib, and whose signatures hold
+//! in every copy.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::index::CacheIndex;
+use crate::symbolize::Symbolizer;
+use crate::vm;
+
+/// A function, bounded by the next function start or the end of its section.
+#[derive(Clone, Copy)]
+pub struct Function {
+    pub image: usize,
+    pub address: u64,
+    pub size: u64,
+}
+
+/// Functions with the same bytes, in at least two images.
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub functions: Vec<Function>,
+}
+
+impl DuplicateGroup {
+    /// Number of distinct images with a copy.
+    pub fn images(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|f| f.image)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Bytes taken by every copy but one.
+    pub fn redundant_bytes(&self) -> u64 {
+        self.size * (self.functions.len() as u64 - 1)
+    }
+}
+
+/// Group the functions of `images` (indices in the cache image list, aliases of an image
+/// already given being skipped) by their bytes, keeping groups that span several images and
+/// functions of at least `min_size` bytes. With `normalize`, the PC-relative fields of ARM64
+/// instructions are masked first, so that copies calling or loading from different addresses
+/// still match. Groups are sorted by redundant bytes, largest first.
+pub fn find_duplicates(
+    symbols: &Symbolizer,
+    images: &[usize],
+    min_size: u64,
+    normalize: bool,
+) -> Vec<DuplicateGroup> {
+    let index = symbols.index();
+    let mut seen = HashSet::new();
+    let mut buckets: HashMap<(u64, u64), Vec<Function>> = HashMap::new();
+    for &image in images {
+        if !seen.insert(index.image(image).base) {
+            continue;
+        }
+        for function in functions(index, symbols, image) {
+            if function.size < min_size {
+                continue;
+            }
+            let Some(bytes) = function_bytes(symbols, &function, normalize) else {
+                continue;
+            };
+            let mut hasher = DefaultHasher::new();
+            bytes.hash(&mut hasher);
+            buckets
+                .entry((function.size, hasher.finish()))
+                .or_default()
+                .push(function);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for ((size, _), bucket) in buckets {
+        if bucket.iter().all(|f| f.image == bucket[0].image) {
+            continue;
+        }
+        // Compare the bytes again, in case of hash collisions.
+        let mut rest: Vec<(Function, Vec<u8>)> = bucket
+            .into_iter()
+            .filter_map(|f| Some((f, function_bytes(symbols, &f, normalize)?)))
+            .collect();
+        while let Some((first, bytes)) = rest.pop() {
+            let mut functions = vec![first];
+            rest.retain(|(function, other)| {
+                let same = *other == bytes;
+                if same {
+                    functions.push(*function);
+                }
+                !same
+            });
+            let group = DuplicateGroup { size, functions };
+            if group.images() > 1 {
+                groups.push(group);
+            }
+        }
+    }
+    for group in &mut groups {
+        group.functions.sort_by_key(|f| f.address);
+    }
+    groups.sort_by(|a, b| {
+        b.redundant_bytes()
+            .cmp(&a.redundant_bytes())
+            .then(a.functions[0].address.cmp(&b.functions[0].address))
+    });
+    groups
+}
+
+/// The functions of the image at `image`, from its function starts. Each ends at the next start
+/// or at the end of its section.
+pub fn functions(index: &CacheIndex, symbols: &Symbolizer, image: usize) -> Vec<Function> {
+    let entry = index.image(image);
+    let (starts, _) = symbols.function_starts(image);
+    let mut functions = Vec::with_capacity(starts.len());
+    for (i, &start) in starts.iter().enumerate() {
+        let Some(section) = entry.section_at(start) else {
+            continue;
+        };
+        let end = match starts.get(i + 1) {
+            Some(&next) if next < section.end => next,
+            _ => section.end,
+        };
+        functions.push(Function {
+            image,
+            address: start,
+            size: end - start,
+        });
+    }
+    functions
+}
+
+fn function_bytes(symbols: &Symbolizer, function: &Function, normalize: bool) -> Option<Vec<u8>> {
+    let mut bytes =
+        vm::read_bytes(symbols.index().cache(), function.address, function.size).ok()?;
+    if normalize {
+        mask_pc_relative(&mut bytes);
+    }
+    Some(bytes)
+}
+
+/// Clear the PC-relative fields of the ARM64 instructions in `code`: branch offsets, `adr` and
+/// `adrp` immediates and literal load offsets, plus the page offset of an `add` or load
+/// following an `adrp`.
+pub fn mask_pc_relative(code: &mut [u8]) {
+    let mut after_adrp = false;
+    for word in code.chunks_exact_mut(4) {
+        let insn = u32::from_le_bytes(word.try_into().unwrap());
+        let mask = if insn & 0x7C00_0000 == 0x1400_0000 {
+            // b, bl
+            0x03FF_FFFF
+        } else if insn & 0xFF00_0010 == 0x5400_0000 || insn & 0x7E00_0000 == 0x3400_0000 {
+            // b.cond, cbz, cbnz
+            0x00FF_FFE0
+        } else if insn & 0x7E00_0000 == 0x3600_0000 {
+            // tbz, tbnz
+            0x0007_FFE0
+        } else if insn & 0x1F00_0000 == 0x1000_0000 {
+            // adr, adrp
+            0x60FF_FFE0
+        } else if insn & 0x3B00_0000 == 0x1800_0000 {
+            // ldr (literal)
+            0x00FF_FFE0
+        } else if after_adrp
+            && (insn & 0x7F80_0000 == 0x1100_0000 || insn & 0x3B00_0000 == 0x3900_0000)
+        {
+            // add (immediate), ldr/str (unsigned offset)
+            0x003F_FC00
+        } else {
+            0
+        };
+        after_adrp = insn & 0x9F00_0000 == 0x9000_0000;
+        word.copy_from_slice(&(insn & !mask).to_le_bytes());
+    }
+}
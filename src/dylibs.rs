@@ -0,0 +1,270 @@
+use object::LittleEndian;
+use object::macho::{
+    DYNAMIC_LOOKUP_ORDINAL, EXECUTABLE_ORDINAL, LC_DYLD_CHAINED_FIXUPS, LC_LAZY_LOAD_DYLIB,
+    LC_LOAD_UPWARD_DYLIB, LC_LOAD_WEAK_DYLIB, LC_REEXPORT_DYLIB, N_EXT, N_STAB, N_TYPE, N_UNDF,
+    N_WEAK_REF, SELF_LIBRARY_ORDINAL,
+};
+use object::read::macho::{DyldCache, LoadCommandVariant, MachOFile64, Nlist};
+use std::error::Error;
+use std::fmt;
+
+use crate::linkedit;
+use crate::parsers::bind;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Load,
+    Weak,
+    Reexport,
+    Upward,
+    Lazy,
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LinkKind::Load => "load",
+            LinkKind::Weak => "weak",
+            LinkKind::Reexport => "reexport",
+            LinkKind::Upward => "upward",
+            LinkKind::Lazy => "lazy",
+        })
+    }
+}
+
+/// A dylib named by one of the `LC_*_DYLIB` load commands.
+pub struct LinkedDylib {
+    pub path: String,
+    pub kind: LinkKind,
+    /// Packed `X.Y.Z` versions from the load command, see `format_version`.
+    pub current_version: u32,
+    pub compatibility_version: u32,
+}
+
+/// Format a packed dylib version, `xxxx.yy.zz` in 16, 8 and 8 bits, as `X.Y.Z`.
+pub fn format_version(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        version >> 16,
+        (version >> 8) & 0xFF,
+        version & 0xFF
+    )
+}
+
+/// Linked dylibs in load command order, so that library ordinal N refers to entry N - 1.
+pub fn linked_dylibs(file: &MachOFile64<LittleEndian>) -> Result<Vec<LinkedDylib>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let mut dylibs = Vec::new();
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        if let LoadCommandVariant::Dylib(dylib) = command.variant()? {
+            let path = command.string(e, dylib.dylib.name)?;
+            dylibs.push(LinkedDylib {
+                path: String::from_utf8_lossy(path).into_owned(),
+                kind: match command.cmd() {
+                    LC_LOAD_WEAK_DYLIB => LinkKind::Weak,
+                    LC_REEXPORT_DYLIB => LinkKind::Reexport,
+                    LC_LOAD_UPWARD_DYLIB => LinkKind::Upward,
+                    LC_LAZY_LOAD_DYLIB => LinkKind::Lazy,
+                    _ => LinkKind::Load,
+                },
+                current_version: dylib.dylib.current_version.get(e),
+                compatibility_version: dylib.dylib.compatibility_version.get(e),
+            });
+        }
+    }
+    Ok(dylibs)
+}
+
+/// Umbrella and search path information from `LC_SUB_*` and `LC_RPATH` load commands.
+#[derive(Default)]
+pub struct Relationships {
+    /// Umbrella framework this image is part of (`LC_SUB_FRAMEWORK`).
+    pub umbrella: Option<String>,
+    /// Frameworks and libraries whose symbols this umbrella re-exports as its own
+    /// (`LC_SUB_UMBRELLA`, `LC_SUB_LIBRARY`).
+    pub sub_umbrellas: Vec<String>,
+    pub sub_libraries: Vec<String>,
+    /// Clients allowed to link against this sub-framework directly (`LC_SUB_CLIENT`).
+    pub clients: Vec<String>,
+    pub rpaths: Vec<String>,
+}
+
+pub fn relationships(file: &MachOFile64<LittleEndian>) -> Result<Relationships, Box<dyn Error>> {
+    let e = LittleEndian;
+    let mut relationships = Relationships::default();
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        let string = |s| {
+            command
+                .string(e, s)
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+        };
+        match command.variant()? {
+            LoadCommandVariant::SubFramework(c) => {
+                relationships.umbrella = Some(string(c.umbrella)?);
+            }
+            LoadCommandVariant::SubUmbrella(c) => {
+                relationships.sub_umbrellas.push(string(c.sub_umbrella)?);
+            }
+            LoadCommandVariant::SubLibrary(c) => {
+                relationships.sub_libraries.push(string(c.sub_library)?);
+            }
+            LoadCommandVariant::SubClient(c) => relationships.clients.push(string(c.client)?),
+            LoadCommandVariant::Rpath(c) => relationships.rpaths.push(string(c.path)?),
+            _ => {}
+        }
+    }
+    Ok(relationships)
+}
+
+/// Where an undefined symbol is expected to come from.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Provider {
+    /// Index into `linked_dylibs`.
+    Dylib(usize),
+    This,
+    MainExecutable,
+    FlatLookup,
+    /// Any image with a weak definition of the symbol, from bind opcodes.
+    WeakLookup,
+}
+
+impl Provider {
+    /// The provider named by a library ordinal as stored in chained fixups and bind opcodes,
+    /// where the special ordinals are negative.
+    pub fn from_ordinal(ordinal: i64) -> Result<Provider, String> {
+        Ok(match ordinal {
+            1.. => Provider::Dylib(ordinal as usize - 1),
+            0 => Provider::This,
+            -1 => Provider::MainExecutable,
+            -2 => Provider::FlatLookup,
+            -3 => Provider::WeakLookup,
+            _ => return Err(format!("Invalid library ordinal {}", ordinal)),
+        })
+    }
+
+    /// The library ordinal naming this provider, the inverse of `from_ordinal`.
+    pub fn ordinal(self) -> i64 {
+        match self {
+            Provider::Dylib(i) => i as i64 + 1,
+            Provider::This => 0,
+            Provider::MainExecutable => -1,
+            Provider::FlatLookup => -2,
+            Provider::WeakLookup => -3,
+        }
+    }
+}
+
+/// An undefined external symbol and where it is expected to come from.
+pub struct Import {
+    pub name: String,
+    pub provider: Provider,
+    /// Weak imports may be missing at run time.
+    pub weak: bool,
+}
+
+/// Undefined external symbols of the image, with providers from their library ordinals.
+pub fn imports(file: &MachOFile64<LittleEndian>) -> Vec<Import> {
+    let e = LittleEndian;
+    let symbols = file.macho_symbol_table();
+    symbols
+        .iter()
+        .filter(|nlist| {
+            let n_type = nlist.n_type();
+            n_type & N_STAB == 0 && n_type & N_TYPE == N_UNDF && n_type & N_EXT != 0
+        })
+        .map(|nlist| Import {
+            name: String::from_utf8_lossy(nlist.name(e, symbols.strings()).unwrap_or_default())
+                .into_owned(),
+            provider: match (nlist.n_desc(e) >> 8) as u8 {
+                SELF_LIBRARY_ORDINAL => Provider::This,
+                DYNAMIC_LOOKUP_ORDINAL => Provider::FlatLookup,
+                EXECUTABLE_ORDINAL => Provider::MainExecutable,
+                ordinal => Provider::Dylib(ordinal as usize - 1),
+            },
+            weak: nlist.n_desc(e) & N_WEAK_REF != 0,
+        })
+        .collect()
+}
+
+/// Where the imports of an image were read from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    ChainedFixups,
+    BindOpcodes,
+    SymbolTable,
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImportSource::ChainedFixups => "chained fixups",
+            ImportSource::BindOpcodes => "bind opcodes",
+            ImportSource::SymbolTable => "symbol table",
+        })
+    }
+}
+
+/// Symbols the image binds to, from the imports of `LC_DYLD_CHAINED_FIXUPS` or the bind and
+/// lazy bind opcodes of `LC_DYLD_INFO`. The cache builder applies binds and usually drops
+/// both, in which case the undefined symbols of the symbol table are used (see `imports`).
+pub fn bound_imports(
+    cache: &DyldCache<LittleEndian>,
+    file: &MachOFile64<LittleEndian>,
+) -> Result<(ImportSource, Vec<Import>), Box<dyn Error>> {
+    let e = LittleEndian;
+    let mut chained = None;
+    let mut opcodes = Vec::new();
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        match command.variant()? {
+            LoadCommandVariant::LinkeditData(c) if c.cmd.get(e) == LC_DYLD_CHAINED_FIXUPS => {
+                chained = Some((c.dataoff.get(e), c.datasize.get(e)));
+            }
+            LoadCommandVariant::DyldInfo(c) => {
+                opcodes.push((c.bind_off.get(e), c.bind_size.get(e)));
+                opcodes.push((c.lazy_bind_off.get(e), c.lazy_bind_size.get(e)));
+            }
+            _ => {}
+        }
+    }
+
+    let convert = |symbols: Vec<bind::BoundSymbol>| -> Result<Vec<Import>, Box<dyn Error>> {
+        symbols
+            .into_iter()
+            .map(|symbol| {
+                Ok(Import {
+                    provider: Provider::from_ordinal(symbol.ordinal)?,
+                    name: symbol.name,
+                    weak: symbol.weak,
+                })
+            })
+            .collect()
+    };
+    if let Some((offset, size)) = chained.filter(|&(_, size)| size != 0) {
+        let data = linkedit::bytes(cache, file, offset, size)?;
+        return Ok((
+            ImportSource::ChainedFixups,
+            convert(bind::chained_imports(data)?)?,
+        ));
+    }
+    opcodes.retain(|&(_, size)| size != 0);
+    if !opcodes.is_empty() {
+        let mut symbols = Vec::new();
+        for (offset, size) in opcodes {
+            let data = linkedit::bytes(cache, file, offset, size)?;
+            symbols.extend(bind::bind_opcodes(data)?);
+        }
+        return Ok((ImportSource::BindOpcodes, convert(symbols)?));
+    }
+    Ok((ImportSource::SymbolTable, imports(file)))
+}
+
+/// Providers of the undefined external symbols of the image, from their library ordinals.
+pub fn import_providers(file: &MachOFile64<LittleEndian>) -> Vec<Provider> {
+    imports(file)
+        .into_iter()
+        .map(|import| import.provider)
+        .collect()
+}
@@ -0,0 +1,174 @@
+use object::macho::{VM_PROT_EXECUTE, VM_PROT_READ, VM_PROT_WRITE};
+use object::read::macho::{DyldCache, MachOFile64, Segment};
+use object::{Architecture, LittleEndian, Object};
+use std::error::Error;
+use std::path::Path;
+
+use crate::extract;
+use crate::json::Json;
+use crate::resolve::find_image;
+use crate::slide::{self, AuthMode};
+
+/// A page-aligned block of memory to snapshot.
+pub struct Region {
+    pub start: u64,
+    pub end: u64,
+    pub prot: u32,
+    pub images: Vec<String>,
+}
+
+pub fn page_size(cache: &DyldCache<LittleEndian>) -> u64 {
+    match cache.architecture() {
+        Architecture::Aarch64 => 0x4000,
+        _ => 0x1000,
+    }
+}
+
+/// Collect page-aligned regions for the segments of the given images. `__LINKEDIT` is skipped
+/// because it is shared by the whole cache and never executed.
+pub fn image_regions(
+    cache: &DyldCache<LittleEndian>,
+    modules: &[String],
+) -> Result<Vec<Region>, Box<dyn Error>> {
+    let page = page_size(cache);
+    let e = LittleEndian;
+    let mut regions = Vec::new();
+
+    for module in modules {
+        let image = find_image(cache, module)?;
+        let path = image.path().unwrap_or("").to_string();
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        for segment in file.segments().map(|s| s.macho_segment()) {
+            if segment.name() == b"__LINKEDIT" || segment.vmsize.get(e) == 0 {
+                continue;
+            }
+            let start = segment.vmaddr.get(e);
+            regions.push(Region {
+                start: start & !(page - 1),
+                end: (start + segment.vmsize.get(e)).next_multiple_of(page),
+                prot: segment.initprot.get(e),
+                images: vec![path.clone()],
+            });
+        }
+    }
+    Ok(regions)
+}
+
+/// Split an address range into page-aligned regions along mapping boundaries.
+pub fn range_regions(cache: &DyldCache<LittleEndian>, start: u64, end: u64) -> Vec<Region> {
+    let page = page_size(cache);
+    cache
+        .mappings()
+        .filter_map(|m| {
+            let lo = std::cmp::max(start, m.address());
+            let hi = std::cmp::min(end, m.address() + m.size());
+            (lo < hi).then(|| Region {
+                start: lo & !(page - 1),
+                end: hi.next_multiple_of(page),
+                prot: m.init_prot(),
+                images: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Coalesce overlapping regions, since emulators refuse to map the same page twice.
+/// Protections of merged regions are combined.
+pub fn merge(mut regions: Vec<Region>) -> Vec<Region> {
+    regions.sort_by_key(|r| r.start);
+    let mut merged: Vec<Region> = Vec::new();
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start < last.end => {
+                last.end = std::cmp::max(last.end, region.end);
+                last.prot |= region.prot;
+                for image in region.images {
+                    if !last.images.contains(&image) {
+                        last.images.push(image);
+                    }
+                }
+            }
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
+
+/// Read a region, zero-filling any pages that are not backed by a mapping.
+fn snapshot(cache: &DyldCache<LittleEndian>, region: &Region) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buf = vec![0u8; (region.end - region.start) as usize];
+    for mapping in cache.mappings() {
+        let lo = std::cmp::max(region.start, mapping.address());
+        let hi = std::cmp::min(region.end, mapping.address() + mapping.size());
+        if lo >= hi {
+            continue;
+        }
+        let data = mapping.data()?;
+        let src = (lo - mapping.address()) as usize;
+        let len = std::cmp::min((hi - lo) as usize, data.len().saturating_sub(src));
+        let dst = (lo - region.start) as usize;
+        buf[dst..dst + len].copy_from_slice(&data[src..src + len]);
+    }
+    Ok(buf)
+}
+
+pub fn prot_string(prot: u32) -> String {
+    let flag = |bit: u32, c: char| if prot & bit != 0 { c } else { '-' };
+    [
+        flag(VM_PROT_READ, 'r'),
+        flag(VM_PROT_WRITE, 'w'),
+        flag(VM_PROT_EXECUTE, 'x'),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Write each region to `<dir>/<address>.bin` and describe them in `<dir>/memory_map.json`.
+/// With `auth`, slid pointers are rewritten to their runtime addresses first, and with
+/// [`AuthMode::Keep`] the authenticated ones are listed under `auth_fixups` of their region.
+/// Returns the number of pointers rewritten.
+pub fn write(
+    cache: &DyldCache<LittleEndian>,
+    regions: &[Region],
+    dir: &Path,
+    auth: Option<AuthMode>,
+) -> Result<usize, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut entries = Vec::new();
+    let mut rebased = 0;
+    for region in regions {
+        let name = format!("{:X}.bin", region.start);
+        let path = dir.join(&name);
+        let mut data = snapshot(cache, region)?;
+        let rebases = match auth {
+            Some(_) => slide::rebase(cache, region.start, &mut data)?,
+            None => Vec::new(),
+        };
+        rebased += rebases.len();
+        std::fs::write(&path, data)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        let mut entry = Json::object()
+            .field("address", region.start)
+            .field("size", region.end - region.start)
+            .field("perms", prot_string(region.prot))
+            .field("file", name)
+            .field("images", region.images.clone());
+        if auth.is_some() {
+            entry = entry.field("rebased_pointers", rebases.len());
+        }
+        if auth == Some(AuthMode::Keep) {
+            entry = entry.field("auth_fixups", extract::auth_fixups(&rebases));
+        }
+        entries.push(entry);
+    }
+
+    let map = Json::object()
+        .field("page_size", page_size(cache))
+        .field("regions", entries);
+    let map_path = dir.join("memory_map.json");
+    std::fs::write(&map_path, map.pretty() + "\n")
+        .map_err(|e| format!("Failed to write {}: {}", map_path.display(), e))?;
+    Ok(rebased)
+}
@@ -0,0 +1,90 @@
+use object::macho;
+use std::error::Error;
+
+use crate::json::Json;
+
+/// Records the decisions taken while extracting an image, for `--explain`. Each step is logged
+/// on stderr as it happens and the full trace, including bulk data such as every rewritten
+/// pointer, is written to a sidecar JSON file.
+pub struct Explain {
+    enabled: bool,
+    steps: Vec<Json>,
+}
+
+impl Explain {
+    pub fn new(enabled: bool) -> Self {
+        Explain {
+            enabled,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Log a step on stderr and record it with `details`.
+    pub fn step(&mut self, kind: &str, message: String, details: Json) {
+        if !self.enabled {
+            return;
+        }
+        eprintln!("explain: {}", message);
+        self.record(kind, details.field("message", message));
+    }
+
+    /// Record a step without logging it.
+    pub fn record(&mut self, kind: &str, details: Json) {
+        if self.enabled {
+            self.steps
+                .push(Json::object().field("kind", kind).field("details", details));
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn write(self, path: &str) -> Result<(), Box<dyn Error>> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let trace = Json::object().field("steps", self.steps);
+        std::fs::write(path, trace.pretty() + "\n")
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        eprintln!("explain: wrote {}", path);
+        Ok(())
+    }
+}
+
+/// Name of a load command, e.g. `LC_SEGMENT_64`.
+pub fn load_command_name(cmd: u32) -> String {
+    let name = match cmd {
+        macho::LC_SEGMENT_64 => "LC_SEGMENT_64",
+        macho::LC_SYMTAB => "LC_SYMTAB",
+        macho::LC_DYSYMTAB => "LC_DYSYMTAB",
+        macho::LC_ID_DYLIB => "LC_ID_DYLIB",
+        macho::LC_LOAD_DYLIB => "LC_LOAD_DYLIB",
+        macho::LC_LOAD_WEAK_DYLIB => "LC_LOAD_WEAK_DYLIB",
+        macho::LC_REEXPORT_DYLIB => "LC_REEXPORT_DYLIB",
+        macho::LC_LOAD_UPWARD_DYLIB => "LC_LOAD_UPWARD_DYLIB",
+        macho::LC_LAZY_LOAD_DYLIB => "LC_LAZY_LOAD_DYLIB",
+        macho::LC_UUID => "LC_UUID",
+        macho::LC_BUILD_VERSION => "LC_BUILD_VERSION",
+        macho::LC_VERSION_MIN_MACOSX => "LC_VERSION_MIN_MACOSX",
+        macho::LC_VERSION_MIN_IPHONEOS => "LC_VERSION_MIN_IPHONEOS",
+        macho::LC_SOURCE_VERSION => "LC_SOURCE_VERSION",
+        macho::LC_DYLD_INFO => "LC_DYLD_INFO",
+        macho::LC_DYLD_INFO_ONLY => "LC_DYLD_INFO_ONLY",
+        macho::LC_DYLD_EXPORTS_TRIE => "LC_DYLD_EXPORTS_TRIE",
+        macho::LC_DYLD_CHAINED_FIXUPS => "LC_DYLD_CHAINED_FIXUPS",
+        macho::LC_FUNCTION_STARTS => "LC_FUNCTION_STARTS",
+        macho::LC_DATA_IN_CODE => "LC_DATA_IN_CODE",
+        macho::LC_CODE_SIGNATURE => "LC_CODE_SIGNATURE",
+        macho::LC_SEGMENT_SPLIT_INFO => "LC_SEGMENT_SPLIT_INFO",
+        macho::LC_SUB_FRAMEWORK => "LC_SUB_FRAMEWORK",
+        macho::LC_SUB_UMBRELLA => "LC_SUB_UMBRELLA",
+        macho::LC_SUB_CLIENT => "LC_SUB_CLIENT",
+        macho::LC_SUB_LIBRARY => "LC_SUB_LIBRARY",
+        macho::LC_RPATH => "LC_RPATH",
+        macho::LC_ENCRYPTION_INFO_64 => "LC_ENCRYPTION_INFO_64",
+        macho::LC_LINKER_OPTIMIZATION_HINT => "LC_LINKER_OPTIMIZATION_HINT",
+        _ => return format!("0x{:X}", cmd),
+    };
+    name.to_string()
+}
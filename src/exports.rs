@@ -0,0 +1,83 @@
+use object::LittleEndian;
+use object::macho::LC_DYLD_EXPORTS_TRIE;
+use object::read::macho::{DyldCache, DyldCacheImage, LoadCommandVariant, MachOFile64};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::linkedit;
+pub use crate::parsers::trie::{ExportEntry, ExportKind, parse_trie};
+
+/// Walk the export trie of an image, from `LC_DYLD_EXPORTS_TRIE` or the export area of
+/// `LC_DYLD_INFO`. Addresses are made absolute using the image load address.
+pub fn image_exports(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+) -> Result<Vec<ExportEntry>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+    let base = image.info().address.get(e);
+
+    let mut trie = None;
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        match command.variant()? {
+            LoadCommandVariant::LinkeditData(c) if c.cmd.get(e) == LC_DYLD_EXPORTS_TRIE => {
+                trie = Some((c.dataoff.get(e), c.datasize.get(e)));
+            }
+            LoadCommandVariant::DyldInfo(c) if c.export_size.get(e) != 0 => {
+                trie = Some((c.export_off.get(e), c.export_size.get(e)));
+            }
+            _ => {}
+        }
+    }
+
+    let Some((offset, size)) = trie else {
+        return Ok(Vec::new());
+    };
+    let data = linkedit::bytes(cache, &file, offset, size)?;
+    parse_trie(data, base).map_err(Into::into)
+}
+
+pub enum ExportChange<'a> {
+    Added(&'a ExportEntry),
+    Removed(&'a ExportEntry),
+    Moved {
+        old: &'a ExportEntry,
+        new: &'a ExportEntry,
+    },
+}
+
+/// Compare two export lists by name. An export is reported as moved when its target relative
+/// to the image base differs; changes are returned sorted by symbol name.
+pub fn diff<'a>(
+    old: &'a [ExportEntry],
+    old_base: u64,
+    new: &'a [ExportEntry],
+    new_base: u64,
+) -> Vec<ExportChange<'a>> {
+    let old_map: BTreeMap<&str, &ExportEntry> = old.iter().map(|e| (e.name.as_str(), e)).collect();
+    let new_map: BTreeMap<&str, &ExportEntry> = new.iter().map(|e| (e.name.as_str(), e)).collect();
+
+    let mut changes = Vec::new();
+    for (name, old_entry) in &old_map {
+        match new_map.get(name) {
+            None => changes.push((*name, ExportChange::Removed(old_entry))),
+            Some(new_entry) if old_entry.target(old_base) != new_entry.target(new_base) => changes
+                .push((
+                    *name,
+                    ExportChange::Moved {
+                        old: old_entry,
+                        new: new_entry,
+                    },
+                )),
+            Some(_) => {}
+        }
+    }
+    for (name, new_entry) in &new_map {
+        if !old_map.contains_key(name) {
+            changes.push((*name, ExportChange::Added(new_entry)));
+        }
+    }
+    changes.sort_by_key(|(name, _)| *name);
+    changes.into_iter().map(|(_, change)| change).collect()
+}
@@ -0,0 +1,1042 @@
+use object::macho::{
+    DyldCacheHeader, DyldInfoCommand, LC_CODE_SIGNATURE, LC_DATA_IN_CODE, LC_DYLD_CHAINED_FIXUPS,
+    LC_DYLD_EXPORTS_TRIE, LC_DYLD_INFO, LC_DYLD_INFO_ONLY, LC_DYLIB_CODE_SIGN_DRS, LC_DYSYMTAB,
+    LC_FUNCTION_STARTS, LC_LINKER_OPTIMIZATION_HINT, LC_SEGMENT_64, LC_SEGMENT_SPLIT_INFO,
+    LC_SYMTAB, MH_DYLIB_IN_CACHE, MachHeader64, S_GB_ZEROFILL, S_THREAD_LOCAL_ZEROFILL, S_ZEROFILL,
+    SECTION_TYPE, Section64, SegmentCommand64, VM_PROT_READ, VM_PROT_WRITE,
+};
+use object::read::macho::{DyldCache, DyldCacheImage, MachOFile64, Segment};
+use object::{LittleEndian, Object, ObjectSection, U32, U64, pod};
+use std::collections::HashMap;
+use std::error::Error;
+use std::mem::size_of;
+
+use crate::cachetype::{CacheKind, platform_name};
+use crate::explain::{self, Explain};
+use crate::json::Json;
+use crate::layout;
+use crate::objc::ObjcReader;
+use crate::parsers::rebase;
+use crate::slide::{self, AuthMode};
+use crate::utils::format_uuid;
+use crate::vm;
+
+/// How to extract an image. Start from `ExtractOptions::default()` and set fields, as more may
+/// be added.
+#[derive(Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct ExtractOptions {
+    /// Rewrite slid pointers to runtime addresses, keeping or stripping their authentication
+    /// data. Pointers are left in their on-disk form when `None`.
+    pub auth: Option<AuthMode>,
+    /// Record where the blob comes from under `provenance` in the metadata: the cache, the
+    /// version of this tool and the patches applied.
+    pub provenance: bool,
+    /// Only copy `__TEXT`, which holds the Mach-O header and the code: enough for a
+    /// disassembler, in a fraction of the size.
+    pub text_only: bool,
+    /// Make the blob a Mach-O file that loads at any address, like the dylib it comes from:
+    /// the rewritten pointers that point into the image are encoded as rebase opcodes in a new
+    /// `__LINKEDIT`, referenced by `LC_DYLD_INFO_ONLY`. Needs `auth`.
+    pub rebase_info: bool,
+    /// Point the image's selector references and method names at copies of the selector
+    /// strings inside the blob, instead of the strings the cache builder uniqued into other
+    /// images. Needs `auth`.
+    pub fix_selectors: bool,
+    /// Copy the in-cache bytes of each segment past its file data, up to its memory size and as
+    /// far as they are mapped, instead of zero-filling them. Bytes between segments belong to
+    /// other images and stay zero.
+    pub faithful: bool,
+    /// Fail instead of writing a blob that lost data, listed in [`Extracted::warnings`].
+    pub strict: bool,
+}
+
+/// The segments of an image laid out at their VM offsets, as a flat blob.
+pub struct Extracted {
+    /// VM address of the first byte of `data`.
+    pub base: u64,
+    pub data: Vec<u8>,
+    /// Image, UUID, segment layout and authenticated pointer fixups, for loaders.
+    pub metadata: Json,
+    /// Rebase opcodes for the pointers into the image, also in the blob's `__LINKEDIT`, with
+    /// [`ExtractOptions::rebase_info`].
+    pub rebase_info: Option<Vec<u8>>,
+    /// What the blob lost or left unresolved, also listed under `warnings` in the metadata.
+    pub warnings: Vec<Warning>,
+}
+
+/// Data an extraction lost or could not resolve: the blob is written anyway unless
+/// [`ExtractOptions::strict`] is set.
+pub struct Warning {
+    /// `truncated` for a segment not entirely mapped, `filesize` for file data past the end of
+    /// a segment's memory, `unslid` for a data segment no slide info describes, `unresolved`
+    /// for pointers or selectors whose target is not mapped.
+    pub kind: &'static str,
+    pub message: String,
+}
+
+/// Largest span of segments extracted into a blob, well past any real image, so a corrupt
+/// load command fails instead of allocating gigabytes.
+const MAX_BLOB_SIZE: u64 = 1 << 32;
+
+/// Name of the segment appended to the blob for the selectors missing from the image.
+const SELECTOR_SEGMENT: &str = "__DSC_SELECTORS";
+
+/// Top byte tags of arm64e pointers, which are not part of the address.
+const TAG_MASK: u64 = 0xFF << 56;
+
+/// Page size segments of a loadable image are aligned to, that of arm64 Apple platforms.
+const SEGMENT_ALIGN: u64 = 0x4000;
+
+/// Copy the segments of an image, except the `__LINKEDIT` shared by every image of the cache,
+/// into a blob. Decisions are recorded in `explain` when it is enabled.
+pub fn extract(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    options: &ExtractOptions,
+    explain: &mut Explain,
+) -> Result<Extracted, Box<dyn Error>> {
+    let e = LittleEndian;
+    if options.fix_selectors && options.auth.is_none() {
+        return Err("Fixing selectors needs the pointers rebased".into());
+    }
+    // object stops reading load commands at the first malformed one, which would silently
+    // drop the segments after it, so they are checked up front.
+    let header = layout::header_data(cache, image.info().address.get(e))?;
+    layout::validate(&header)?;
+    let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+
+    if explain.is_enabled() {
+        let mut commands = file.macho_load_commands()?;
+        while let Some(command) = commands.next()? {
+            let name = explain::load_command_name(command.cmd());
+            explain.step(
+                "load_command",
+                format!("load command {} ({} bytes)", name, command.cmdsize()),
+                Json::object()
+                    .field("cmd", name)
+                    .field("cmdsize", command.cmdsize()),
+            );
+        }
+    }
+
+    // __LINKEDIT is shared by every image in the cache, so it is left out of the blob.
+    let (segments, skipped): (Vec<&SegmentCommand64<LittleEndian>>, Vec<_>) = file
+        .segments()
+        .map(|s| s.macho_segment())
+        .partition(|s| match options.text_only {
+            true => s.name() == b"__TEXT",
+            false => s.name() != b"__LINKEDIT",
+        });
+    let base = segments
+        .iter()
+        .map(|s| s.vmaddr.get(e))
+        .min()
+        .ok_or("Image has no segments")?;
+    let mut end = base;
+    for segment in &segments {
+        let segment_end = segment
+            .vmaddr
+            .get(e)
+            .checked_add(segment.vmsize.get(e))
+            .ok_or_else(|| {
+                format!(
+                    "Segment {} runs past the end of the address space",
+                    String::from_utf8_lossy(segment.name())
+                )
+            })?;
+        end = end.max(segment_end);
+    }
+    if end - base > MAX_BLOB_SIZE {
+        return Err(format!(
+            "Image spans 0x{:X} bytes, more than the 0x{:X} a blob can hold",
+            end - base,
+            MAX_BLOB_SIZE
+        )
+        .into());
+    }
+
+    let mut left_out = Vec::new();
+    for segment in &skipped {
+        let name = String::from_utf8_lossy(segment.name()).into_owned();
+        let reason = match name.as_str() {
+            "__LINKEDIT" => "shared by every image in the cache",
+            _ => "only __TEXT is extracted",
+        };
+        explain.step(
+            "segment",
+            format!("skip {}, {}", name, reason),
+            Json::object().field("name", name.as_str()),
+        );
+        left_out.push(
+            Json::object()
+                .field("name", name)
+                .field("vmaddr", segment.vmaddr.get(e))
+                .field("vmsize", segment.vmsize.get(e))
+                .field("reason", reason),
+        );
+    }
+
+    let mut blob = vec![0u8; (end - base) as usize];
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+    for segment in &segments {
+        let vmaddr = segment.vmaddr.get(e);
+        let vmsize = segment.vmsize.get(e);
+        let filesize = segment.filesize.get(e);
+        let size = std::cmp::min(filesize, vmsize);
+        let name = String::from_utf8_lossy(segment.name()).into_owned();
+        if filesize > vmsize {
+            warnings.push(Warning {
+                kind: "filesize",
+                message: format!(
+                    "{} has 0x{:X} bytes of file data past its 0x{:X} bytes of memory, dropped",
+                    name,
+                    filesize - vmsize,
+                    vmsize
+                ),
+            });
+        }
+        // Follows the mappings, so a segment continuing into another subcache file is
+        // assembled from each of them rather than cut at the first file's end.
+        let mut bytes = vm::read_mapped(cache, vmaddr, size)?;
+        if (bytes.len() as u64) < size {
+            warnings.push(Warning {
+                kind: "truncated",
+                message: format!(
+                    "{} is truncated at 0x{:X}: 0x{:X} of its 0x{:X} bytes are not mapped and \
+                     left zero",
+                    name,
+                    vmaddr + bytes.len() as u64,
+                    size - bytes.len() as u64,
+                    size
+                ),
+            });
+        }
+        if options.faithful && bytes.len() as u64 == size && size < vmsize {
+            bytes.extend(vm::read_mapped(cache, vmaddr + size, vmsize - size)?);
+        }
+        let copied = bytes.len() as u64;
+        let offset = (vmaddr - base) as usize;
+        blob[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        explain.step(
+            "segment",
+            format!(
+                "copy {} 0x{:X}+0x{:X} to blob offset 0x{:X}, zero-fill 0x{:X} bytes",
+                name,
+                vmaddr,
+                copied,
+                offset,
+                vmsize - copied
+            ),
+            Json::object()
+                .field("name", name.as_str())
+                .field("vmaddr", vmaddr)
+                .field("size", copied)
+                .field("offset", offset)
+                .field("zero_fill", vmsize - copied),
+        );
+
+        entries.push(
+            Json::object()
+                .field("name", name)
+                .field("vmaddr", vmaddr)
+                .field("vmsize", vmsize)
+                .field("offset", offset)
+                .field("filesize", copied)
+                .field("maxprot", segment.maxprot.get(e))
+                .field("initprot", segment.initprot.get(e)),
+        );
+    }
+
+    // Rebased first, as fixing selectors reads the plain pointers and may grow the blob.
+    let mut rebases = Vec::new();
+    if options.auth.is_some() {
+        rebases = slide::rebase(cache, base, &mut blob)?;
+        warnings.extend(unslid_segments(cache, &segments));
+        explain.step(
+            "rebase",
+            format!(
+                "rewrite {} slid pointers to runtime addresses",
+                rebases.len()
+            ),
+            Json::object().field("count", rebases.len()),
+        );
+        for rebase in &rebases {
+            explain.record(
+                "patch",
+                Json::object()
+                    .field("address", rebase.address)
+                    .field("old", rebase.old)
+                    .field("new", rebase.new),
+            );
+        }
+    }
+    let rebased = rebases.len();
+    let selectors = match options.fix_selectors {
+        true => Some(fix_selectors(
+            cache,
+            image,
+            &file,
+            &segments
+                .iter()
+                .map(|s| (s.vmaddr.get(e), s.vmaddr.get(e) + s.vmsize.get(e)))
+                .collect::<Vec<_>>(),
+            &mut blob,
+            &mut rebases,
+            explain,
+        )?),
+        false => None,
+    };
+    if let Some(fix) = selectors.as_ref().filter(|fix| fix.unresolved > 0) {
+        warnings.push(Warning {
+            kind: "unresolved",
+            message: format!(
+                "{} selector references or method names point to no string, left as stored",
+                fix.unresolved
+            ),
+        });
+    }
+    warnings.extend(unresolved_pointers(
+        cache,
+        base,
+        blob.len() as u64,
+        &rebases,
+    ));
+    for warning in &warnings {
+        explain.step(
+            "warning",
+            warning.message.clone(),
+            Json::object().field("kind", warning.kind),
+        );
+    }
+    if options.strict && !warnings.is_empty() {
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        return Err(format!("Extraction lost data: {}", messages.join("; ")).into());
+    }
+
+    let appended = selectors
+        .as_ref()
+        .map(|fix| fix.appended)
+        .filter(|&(_, size)| size > 0);
+    if let Some((vmaddr, size)) = appended {
+        entries.push(
+            Json::object()
+                .field("name", SELECTOR_SEGMENT)
+                .field("vmaddr", vmaddr)
+                .field("vmsize", size)
+                .field("offset", vmaddr - base)
+                .field("filesize", size)
+                .field("maxprot", VM_PROT_READ | VM_PROT_WRITE)
+                .field("initprot", VM_PROT_READ | VM_PROT_WRITE),
+        );
+    }
+
+    let mut rebase_info = None;
+    if options.rebase_info {
+        let ranges: Vec<(u64, u64)> = segments
+            .iter()
+            .map(|s| {
+                (
+                    s.vmaddr.get(e),
+                    s.vmaddr.get(e).saturating_add(s.vmsize.get(e)),
+                )
+            })
+            .chain(appended.map(|(address, size)| (address, address + size)))
+            .collect();
+        let end = base + blob.len() as u64;
+        let (opcodes, pointers, external) = rebase_opcodes(&ranges, base, end, &rebases)?;
+        let header = (image.info().address.get(e) - base) as usize;
+        let (linkedit, size) = make_loadable(&mut blob, base, header, appended, &opcodes)?;
+        let summary = || {
+            Json::object()
+                .field("pointers", pointers)
+                .field("external", external)
+                .field("size", opcodes.len())
+        };
+        explain.step(
+            "rebase_info",
+            format!(
+                "encode {} pointers as {} bytes of rebase opcodes in __LINKEDIT at 0x{:X}, skip \
+                 {} to other images",
+                pointers,
+                opcodes.len(),
+                linkedit,
+                external
+            ),
+            summary(),
+        );
+        entries.push(
+            Json::object()
+                .field("name", "__LINKEDIT")
+                .field("vmaddr", linkedit)
+                .field("vmsize", size)
+                .field("offset", linkedit - base)
+                .field("filesize", size)
+                .field("maxprot", VM_PROT_READ)
+                .field("initprot", VM_PROT_READ),
+        );
+        rebase_info = Some((summary(), opcodes));
+    }
+
+    let mut metadata = Json::object()
+        .field("image", image.path().unwrap_or(""))
+        .field("uuid", file.mach_uuid()?.map(|u| format_uuid(&u)))
+        .field("base", base)
+        .field("size", blob.len())
+        .field("segments", entries)
+        .field("skipped", left_out)
+        .field(
+            "warnings",
+            warnings
+                .iter()
+                .map(|w| {
+                    Json::object()
+                        .field("kind", w.kind)
+                        .field("message", w.message.as_str())
+                })
+                .collect::<Vec<_>>(),
+        );
+    if let Some(mode) = options.auth {
+        metadata = metadata.field("auth", auth_name(mode));
+        if mode == AuthMode::Keep {
+            metadata = metadata.field("auth_fixups", auth_fixups(&rebases));
+        }
+    }
+    let rebase_info = match rebase_info {
+        Some((summary, opcodes)) => {
+            metadata = metadata.field("rebase_info", summary);
+            Some(opcodes)
+        }
+        None => None,
+    };
+    if let Some(fix) = &selectors {
+        metadata = metadata.field(
+            "selectors",
+            Json::object()
+                .field("selrefs", fix.selrefs)
+                .field("methods", fix.methods)
+                .field("direct_lists", fix.direct_lists)
+                .field(
+                    "appended",
+                    Json::object()
+                        .field("address", fix.appended.0)
+                        .field("size", fix.appended.1),
+                ),
+        );
+    }
+
+    if options.provenance {
+        metadata = metadata.field("provenance", provenance(cache, options, rebased));
+    }
+
+    Ok(Extracted {
+        base,
+        data: blob,
+        metadata,
+        rebase_info,
+        warnings,
+    })
+}
+
+/// Warnings for the data segments of `segments` that no slide info describes, whose pointers
+/// stay in their on-disk form. Caches without any slide info store plain pointers, and are
+/// not reported.
+fn unslid_segments(
+    cache: &DyldCache<LittleEndian>,
+    segments: &[&SegmentCommand64<LittleEndian>],
+) -> Vec<Warning> {
+    let e = LittleEndian;
+    let coverage = slide::coverage(cache);
+    if coverage.is_empty() {
+        return Vec::new();
+    }
+    segments
+        .iter()
+        .filter(|s| s.maxprot.get(e) & VM_PROT_WRITE != 0)
+        .filter(|s| {
+            let (start, end) = (s.vmaddr.get(e), s.vmaddr.get(e) + s.vmsize.get(e));
+            !coverage
+                .iter()
+                .any(|c| c.address < end && start < c.address + c.size)
+        })
+        .map(|s| Warning {
+            kind: "unslid",
+            message: format!(
+                "{} is not described by slide info, its pointers are left as stored",
+                String::from_utf8_lossy(s.name())
+            ),
+        })
+        .collect()
+}
+
+/// A warning for the `rebases` whose target is neither mapped in the cache nor inside the
+/// blob of `size` bytes at `base`.
+fn unresolved_pointers(
+    cache: &DyldCache<LittleEndian>,
+    base: u64,
+    size: u64,
+    rebases: &[slide::Rebase],
+) -> Option<Warning> {
+    let unresolved: Vec<&slide::Rebase> = rebases
+        .iter()
+        .filter(|r| {
+            let target = r.new & !TAG_MASK;
+            !(base..base + size).contains(&target) && vm::mapped_len(cache, target, 1) == 0
+        })
+        .collect();
+    let first = unresolved.first()?;
+    Some(Warning {
+        kind: "unresolved",
+        message: format!(
+            "{} pointers point outside mapped memory, the first 0x{:X} at 0x{:X}",
+            unresolved.len(),
+            first.new,
+            first.address
+        ),
+    })
+}
+
+/// What [`fix_selectors`] rewrote.
+struct SelectorFix {
+    /// Selector references pointed at strings inside the blob.
+    selrefs: usize,
+    /// Method names pointed at strings or selector references inside the blob.
+    methods: usize,
+    /// Small method lists switched from direct selectors to selector references.
+    direct_lists: usize,
+    /// Selector references and method names whose string could not be read.
+    unresolved: usize,
+    /// Address and size of the strings and selector references appended to the blob.
+    appended: (u64, u64),
+}
+
+/// Selector strings inside the blob by name, with those appended after it.
+struct StringPool {
+    strings: HashMap<String, u64>,
+    /// Address of `data`, past the end of the blob.
+    area: u64,
+    data: Vec<u8>,
+}
+
+impl StringPool {
+    /// Address of the string `name`, appending it when the blob has none.
+    fn address(&mut self, name: &str) -> u64 {
+        if let Some(&address) = self.strings.get(name) {
+            return address;
+        }
+        let address = self.area + self.data.len() as u64;
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.push(0);
+        self.strings.insert(name.to_string(), address);
+        address
+    }
+}
+
+/// Point the selector references and method names of an image at selector strings inside the
+/// blob. The cache builder uniques selectors into a single string table, outside most images,
+/// and turns the names of small method lists into offsets from it, none of which resolve once
+/// the image is out of the cache. Strings of the image's `__objc_methname` are used when they
+/// match, the others are appended to the blob, followed by selector references for the lists
+/// switched back from direct selectors. Only the `segments` copied, as address ranges, count
+/// as inside the blob, which starts at the first: other images may lie between them. `blob`
+/// must hold plain pointers, listed in `rebases`, which are updated.
+fn fix_selectors(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    file: &MachOFile64<LittleEndian>,
+    segments: &[(u64, u64)],
+    blob: &mut Vec<u8>,
+    rebases: &mut Vec<slide::Rebase>,
+    explain: &mut Explain,
+) -> Result<SelectorFix, Box<dyn Error>> {
+    let base = segments.iter().map(|&(start, _)| start).min().unwrap_or(0);
+    let end = base + blob.len() as u64;
+    let inside = |address: u64, size: u64| {
+        segments
+            .iter()
+            .any(|&(start, end)| address >= start && address + size <= end)
+    };
+    let sections: Vec<(String, u64, u64)> = file
+        .sections()
+        .filter_map(|s| Some((s.name().ok()?.to_string(), s.address(), s.size())))
+        .filter(|&(_, address, size)| inside(address, size))
+        .collect();
+    let section = |name: &'static str| {
+        sections
+            .iter()
+            .filter(move |(n, ..)| n == name)
+            .map(|&(_, address, size)| (address, size))
+    };
+
+    let mut pool = StringPool {
+        strings: HashMap::new(),
+        area: end.next_multiple_of(8),
+        data: Vec::new(),
+    };
+    for (address, size) in section("__objc_methname") {
+        let start = (address - base) as usize;
+        let mut offset = 0;
+        for bytes in blob[start..start + size as usize].split(|&b| b == 0) {
+            if let Ok(name) = std::str::from_utf8(bytes) {
+                pool.strings
+                    .entry(name.to_string())
+                    .or_insert(address + offset);
+            }
+            offset += bytes.len() as u64 + 1;
+        }
+    }
+
+    let mut slots: HashMap<u64, usize> = rebases
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.address, i))
+        .collect();
+    let mut set_pointer = |blob: &mut Vec<u8>, address: u64, new: u64| {
+        let offset = (address - base) as usize;
+        let old = u64::from_le_bytes(blob[offset..offset + 8].try_into().unwrap());
+        blob[offset..offset + 8].copy_from_slice(&new.to_le_bytes());
+        match slots.get(&address) {
+            Some(&i) => rebases[i].new = new,
+            None => {
+                slots.insert(address, rebases.len());
+                rebases.push(slide::Rebase {
+                    address,
+                    old,
+                    new,
+                    auth: None,
+                });
+            }
+        }
+        explain.record(
+            "patch",
+            Json::object()
+                .field("address", address)
+                .field("old", old)
+                .field("new", new),
+        );
+    };
+    let read_u64 = |blob: &[u8], address: u64| {
+        let offset = (address - base) as usize;
+        u64::from_le_bytes(blob[offset..offset + 8].try_into().unwrap())
+    };
+
+    let mut fix = SelectorFix {
+        selrefs: 0,
+        methods: 0,
+        direct_lists: 0,
+        unresolved: 0,
+        appended: (end, 0),
+    };
+    // Selector references of the image by name, for the names of direct selector lists.
+    let mut selrefs: HashMap<String, u64> = HashMap::new();
+    for (address, size) in section("__objc_selrefs") {
+        for slot in (address..address + size / 8 * 8).step_by(8) {
+            let target = read_u64(blob, slot);
+            let Some(name) = vm::read_cstr(cache, target) else {
+                fix.unresolved += usize::from(target != 0);
+                continue;
+            };
+            selrefs.entry(name.to_string()).or_insert(slot);
+            if !inside(target, 1) {
+                set_pointer(blob, slot, pool.address(name));
+                fix.selrefs += 1;
+            }
+        }
+    }
+
+    let reader = ObjcReader::new(cache);
+    // Name fields of the direct selector lists, with their selector.
+    let mut direct = Vec::new();
+    for list in reader.method_lists(image)? {
+        if !inside(list.address, list.entry(list.count) - list.address) {
+            continue;
+        }
+        if !list.is_small() {
+            for i in 0..list.count {
+                let field = list.entry(i);
+                let target = read_u64(blob, field);
+                if inside(target, 1) {
+                    continue;
+                }
+                match vm::read_cstr(cache, target) {
+                    Some(name) => {
+                        set_pointer(blob, field, pool.address(name));
+                        fix.methods += 1;
+                    }
+                    None => fix.unresolved += 1,
+                }
+            }
+        } else if let (true, Some(selector_base)) =
+            (list.has_direct_selectors(), reader.selector_base())
+        {
+            let names: Vec<(u64, Option<&str>)> = (0..list.count)
+                .map(|i| {
+                    let field = list.entry(i);
+                    let name = vm::read_u32(cache, field).and_then(|offset| {
+                        let name = selector_base.wrapping_add_signed(offset as i32 as i64);
+                        vm::read_cstr(cache, name)
+                    });
+                    (field, name)
+                })
+                .collect();
+            // A list is switched over whole, or not at all.
+            let missing = names.iter().filter(|(_, name)| name.is_none()).count();
+            if missing > 0 {
+                fix.unresolved += missing;
+                continue;
+            }
+            let names = names
+                .into_iter()
+                .filter_map(|(field, name)| Some((field, name?)));
+            let offset = (list.address - base) as usize;
+            blob[offset..offset + 4]
+                .copy_from_slice(&list.flags_without_direct_selectors().to_le_bytes());
+            direct.extend(names);
+            fix.direct_lists += 1;
+        }
+    }
+
+    // Strings first, as the selector references appended for direct lists follow them.
+    for &(_, name) in &direct {
+        pool.address(name);
+    }
+    let slot_area = (pool.area + pool.data.len() as u64).next_multiple_of(8);
+    let mut appended_slots = Vec::new();
+    for (field, name) in direct {
+        let slot = match selrefs.get(name) {
+            Some(&slot) => slot,
+            None => {
+                let slot = slot_area + 8 * appended_slots.len() as u64;
+                appended_slots.push((slot, pool.address(name)));
+                selrefs.insert(name.to_string(), slot);
+                slot
+            }
+        };
+        let relative = i32::try_from(slot as i64 - field as i64)
+            .map_err(|_| format!("Selector reference of 0x{:X} is out of reach", field))?;
+        let offset = (field - base) as usize;
+        blob[offset..offset + 4].copy_from_slice(&relative.to_le_bytes());
+        explain.record(
+            "patch",
+            Json::object()
+                .field("address", field)
+                .field("name", name)
+                .field("selref", slot),
+        );
+        fix.methods += 1;
+    }
+
+    if !pool.data.is_empty() || !appended_slots.is_empty() {
+        blob.resize((pool.area - base) as usize, 0);
+        blob.extend_from_slice(&pool.data);
+        blob.resize((slot_area - base) as usize, 0);
+        for &(slot, target) in &appended_slots {
+            blob.extend_from_slice(&target.to_le_bytes());
+            rebases.push(slide::Rebase {
+                address: slot,
+                old: 0,
+                new: target,
+                auth: None,
+            });
+        }
+        fix.appended = (pool.area, base + blob.len() as u64 - pool.area);
+    }
+
+    explain.step(
+        "selectors",
+        format!(
+            "point {} selector references and {} method names at local strings, switch {} lists \
+             off direct selectors, append 0x{:X} bytes at 0x{:X}",
+            fix.selrefs, fix.methods, fix.direct_lists, fix.appended.1, fix.appended.0
+        ),
+        Json::object()
+            .field("selrefs", fix.selrefs)
+            .field("methods", fix.methods)
+            .field("direct_lists", fix.direct_lists)
+            .field("appended", fix.appended.1),
+    );
+    Ok(fix)
+}
+
+/// The authenticated pointers among `rebases`, with what a loader needs to sign them again:
+/// `{address, target, key, diversity, addr_div}`.
+pub fn auth_fixups(rebases: &[slide::Rebase]) -> Vec<Json> {
+    rebases
+        .iter()
+        .filter_map(|r| {
+            let auth = r.auth.as_ref()?;
+            Some(
+                Json::object()
+                    .field("address", r.address)
+                    .field("target", r.new)
+                    .field("key", auth.key)
+                    .field("diversity", u32::from(auth.diversity))
+                    .field("addr_div", auth.addr_div),
+            )
+        })
+        .collect()
+}
+
+/// Rebase opcodes for the `rebases` pointing into `[base, end)`, by index in `segments`, with
+/// the number of pointers encoded and left out. Pointers to other images are bound by name in
+/// the dylib the image was built from, so they are only counted.
+fn rebase_opcodes(
+    segments: &[(u64, u64)],
+    base: u64,
+    end: u64,
+    rebases: &[slide::Rebase],
+) -> Result<(Vec<u8>, usize, usize), Box<dyn Error>> {
+    let mut locations = Vec::new();
+    let mut external = 0usize;
+    for rebase in rebases {
+        if !(base..end).contains(&(rebase.new & !TAG_MASK)) {
+            external += 1;
+            continue;
+        }
+        let segment = segments
+            .iter()
+            .position(|&(start, end)| (start..end).contains(&rebase.address))
+            .ok_or_else(|| format!("Pointer at 0x{:X} is outside every segment", rebase.address))?;
+        locations.push((u8::try_from(segment)?, rebase.address - segments[segment].0));
+    }
+    Ok((rebase::encode(&locations)?, locations.len(), external))
+}
+
+/// Load commands whose data lies in the cache's `__LINKEDIT`, dropped from a loadable image.
+const LINKEDIT_COMMANDS: [u32; 10] = [
+    LC_DYLD_INFO,
+    LC_DYLD_INFO_ONLY,
+    LC_CODE_SIGNATURE,
+    LC_SEGMENT_SPLIT_INFO,
+    LC_FUNCTION_STARTS,
+    LC_DATA_IN_CODE,
+    LC_DYLIB_CODE_SIGN_DRS,
+    LC_LINKER_OPTIMIZATION_HINT,
+    LC_DYLD_EXPORTS_TRIE,
+    LC_DYLD_CHAINED_FIXUPS,
+];
+
+/// Turn the blob of an image at `base`, with its Mach-O header at `header`, into a Mach-O file
+/// that loads at any address. Segments get their offset in the blob as file offset, the load
+/// commands of the cache's `__LINKEDIT` are dropped (the symbol tables are kept, empty), and a
+/// `__LINKEDIT` holding the rebase `opcodes` is appended, referenced by `LC_DYLD_INFO_ONLY`.
+/// The `appended` selectors get a segment before it. Returns the address and size of the new
+/// `__LINKEDIT`.
+fn make_loadable(
+    blob: &mut Vec<u8>,
+    base: u64,
+    header: usize,
+    appended: Option<(u64, u64)>,
+    opcodes: &[u8],
+) -> Result<(u64, u64), Box<dyn Error>> {
+    let e = LittleEndian;
+    let malformed = || "Load commands run past the blob".to_string();
+    let (mach, rest) =
+        pod::from_bytes::<MachHeader64<LittleEndian>>(blob.get(header..).ok_or_else(malformed)?)
+            .map_err(|_| malformed())?;
+    let flags = mach.flags.get(e);
+    let sizeofcmds = mach.sizeofcmds.get(e) as usize;
+    let mut data = rest.get(..sizeofcmds).ok_or_else(malformed)?;
+
+    let mut commands: Vec<Vec<u8>> = Vec::new();
+    // Offset of the first section contents, which the load commands must stay clear of.
+    let mut contents = blob.len();
+    for _ in 0..mach.ncmds.get(e) {
+        let (cmd, cmdsize) = match data {
+            [a, b, c, d, w, x, y, z, ..] => (
+                u32::from_le_bytes([*a, *b, *c, *d]),
+                u32::from_le_bytes([*w, *x, *y, *z]) as usize,
+            ),
+            _ => return Err(malformed().into()),
+        };
+        let mut command = data.get(..cmdsize).ok_or_else(malformed)?.to_vec();
+        data = &data[cmdsize..];
+        match cmd {
+            LC_SEGMENT_64 => {
+                let (segment, rest) =
+                    pod::from_bytes_mut::<SegmentCommand64<LittleEndian>>(&mut command)
+                        .map_err(|_| malformed())?;
+                if segment.name() == b"__LINKEDIT" {
+                    continue;
+                }
+                let offset = segment.vmaddr.get(e) - base;
+                segment.fileoff.set(e, offset);
+                segment.filesize.set(e, segment.vmsize.get(e));
+                let count = segment.nsects.get(e) as usize;
+                let (sections, _) =
+                    pod::slice_from_bytes_mut::<Section64<LittleEndian>>(rest, count)
+                        .map_err(|_| malformed())?;
+                for section in sections {
+                    let zerofill = matches!(
+                        section.flags.get(e) & SECTION_TYPE,
+                        S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL
+                    );
+                    let offset = section.addr.get(e) - base;
+                    section
+                        .offset
+                        .set(e, if zerofill { 0 } else { offset as u32 });
+                    section.reloff.set(e, 0);
+                    section.nreloc.set(e, 0);
+                    if !zerofill && section.size.get(e) > 0 {
+                        contents = contents.min(offset as usize);
+                    }
+                }
+            }
+            LC_SYMTAB | LC_DYSYMTAB => command[8..].fill(0),
+            cmd if LINKEDIT_COMMANDS.contains(&cmd) => continue,
+            _ => {}
+        }
+        commands.push(command);
+    }
+
+    let linkedit_offset = (blob.len() as u64).next_multiple_of(SEGMENT_ALIGN);
+    let linkedit_size = (opcodes.len() as u64).next_multiple_of(8);
+    let segment = |name: &str, vmaddr: u64, vmsize: u64, filesize: u64, prot: u32| {
+        let mut command = SegmentCommand64::<LittleEndian> {
+            cmd: U32::new(e, LC_SEGMENT_64),
+            cmdsize: U32::new(e, size_of::<SegmentCommand64<LittleEndian>>() as u32),
+            segname: [0; 16],
+            vmaddr: U64::new(e, vmaddr),
+            vmsize: U64::new(e, vmsize),
+            fileoff: U64::new(e, vmaddr - base),
+            filesize: U64::new(e, filesize),
+            maxprot: U32::new(e, prot),
+            initprot: U32::new(e, prot),
+            nsects: U32::new(e, 0),
+            flags: U32::new(e, 0),
+        };
+        command.segname[..name.len()].copy_from_slice(name.as_bytes());
+        pod::bytes_of(&command).to_vec()
+    };
+    if let Some((vmaddr, size)) = appended {
+        commands.push(segment(
+            SELECTOR_SEGMENT,
+            vmaddr,
+            size.next_multiple_of(SEGMENT_ALIGN),
+            size,
+            VM_PROT_READ | VM_PROT_WRITE,
+        ));
+    }
+    commands.push(segment(
+        "__LINKEDIT",
+        base + linkedit_offset,
+        linkedit_size.max(1).next_multiple_of(SEGMENT_ALIGN),
+        linkedit_size,
+        VM_PROT_READ,
+    ));
+    let info = DyldInfoCommand::<LittleEndian> {
+        cmd: U32::new(e, LC_DYLD_INFO_ONLY),
+        cmdsize: U32::new(e, size_of::<DyldInfoCommand<LittleEndian>>() as u32),
+        rebase_off: U32::new(e, u32::try_from(linkedit_offset)?),
+        rebase_size: U32::new(e, linkedit_size as u32),
+        bind_off: U32::new(e, 0),
+        bind_size: U32::new(e, 0),
+        weak_bind_off: U32::new(e, 0),
+        weak_bind_size: U32::new(e, 0),
+        lazy_bind_off: U32::new(e, 0),
+        lazy_bind_size: U32::new(e, 0),
+        export_off: U32::new(e, 0),
+        export_size: U32::new(e, 0),
+    };
+    commands.push(pod::bytes_of(&info).to_vec());
+
+    let ncmds = commands.len();
+    let commands = commands.concat();
+    let start = header + layout::HEADER_SIZE;
+    if start + commands.len() > contents {
+        return Err(format!(
+            "The load commands of a loadable image need 0x{:X} bytes, the header has room for 0x{:X}",
+            commands.len(),
+            contents.saturating_sub(start)
+        )
+        .into());
+    }
+    blob[start..start + sizeofcmds.max(commands.len())].fill(0);
+    blob[start..start + commands.len()].copy_from_slice(&commands);
+    let mach = pod::from_bytes_mut::<MachHeader64<LittleEndian>>(&mut blob[header..])
+        .map_err(|_| malformed())?
+        .0;
+    mach.ncmds.set(e, ncmds as u32);
+    mach.sizeofcmds.set(e, commands.len() as u32);
+    // dyld expects images flagged as in the cache to be mapped with it.
+    mach.flags.set(e, flags & !MH_DYLIB_IN_CACHE);
+
+    blob.resize(linkedit_offset as usize, 0);
+    blob.extend_from_slice(opcodes);
+    blob.resize((linkedit_offset + linkedit_size) as usize, 0);
+    Ok((base + linkedit_offset, linkedit_size))
+}
+
+/// Where a blob comes from, for reproducing it or telling blobs of different caches apart.
+fn provenance(cache: &DyldCache<LittleEndian>, options: &ExtractOptions, rebased: usize) -> Json {
+    let kind = CacheKind::new(cache);
+    let uuid = DyldCacheHeader::<LittleEndian>::parse(cache.data())
+        .ok()
+        .map(|header| format_uuid(&header.uuid));
+    Json::object()
+        .field("tool", env!("CARGO_PKG_NAME"))
+        .field("tool_version", env!("CARGO_PKG_VERSION"))
+        .field(
+            "cache",
+            Json::object()
+                .field("uuid", uuid)
+                .field("platform", platform_name(kind.platform))
+                .field("type", kind.to_string())
+                .field(
+                    "subcaches",
+                    DyldCache::<LittleEndian>::subcache_suffixes(cache.data()).unwrap_or_default(),
+                ),
+        )
+        .field(
+            "patches",
+            Json::object()
+                .field("auth", options.auth.map(auth_name))
+                .field("rebased_pointers", rebased)
+                .field("text_only", options.text_only)
+                .field("faithful", options.faithful)
+                .field("rebase_info", options.rebase_info)
+                .field("fix_selectors", options.fix_selectors),
+        )
+}
+
+fn auth_name(mode: AuthMode) -> &'static str {
+    match mode {
+        AuthMode::Keep => "keep",
+        AuthMode::Strip => "strip",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pointer(address: u64, new: u64) -> slide::Rebase {
+        slide::Rebase {
+            address,
+            old: 0,
+            new,
+            auth: None,
+        }
+    }
+
+    #[test]
+    fn rebase_opcodes_by_segment() {
+        let segments = [(0x1000, 0x2000), (0x4000, 0x5000), (0x5000, 0x5010)];
+        let rebases = [
+            pointer(0x4008, 0x1100),
+            // Top byte tags are not part of the address.
+            pointer(0x4010, 0x2A00_0000_0000_1200),
+            pointer(0x4018, 0x9000),
+            pointer(0x5008, 0x4000),
+        ];
+        let (opcodes, pointers, external) =
+            rebase_opcodes(&segments, 0x1000, 0x5010, &rebases).unwrap();
+        assert_eq!((pointers, external), (3, 1));
+        assert_eq!(
+            rebase::decode(&opcodes, &[0x1000, 0x1000, 0x10]).unwrap(),
+            [(1, 8), (1, 0x10), (2, 8)]
+        );
+        assert!(rebase_opcodes(&segments, 0x1000, 0x5010, &[pointer(0x3000, 0x1000)]).is_err());
+    }
+}
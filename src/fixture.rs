@@ -0,0 +1,703 @@
+//! Tiny synthetic dyld caches built in memory, so the parsers, the address resolution and
+//! image extraction can be exercised on a known layout without shipping a real cache. Built
+//! with the `fixtures` feature.
+//!
+//! A fixture is a single arm64 cache file without subcaches or slide info. Its images have a
+//! `__TEXT,__text` section holding their functions back to back, a `__DATA,__data` section and
+//! a `__LINKEDIT` shared with the other images, with a symbol table, an export trie and
+//! function starts. Images given selectors or methods also get the ObjC sections of the cache
+//! builder's output, with selectors uniqued across images:
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use dsc::fixture::Fixture;
+//! use object::LittleEndian;
+//! use object::read::macho::DyldCache;
+//!
+//! let ret = [0xC0, 0x03, 0x5F, 0xD6];
+//! let fixture = Fixture::new()
+//!     .image("/usr/lib/libfoo.dylib")
+//!     .function("_foo", &ret)
+//!     .function("_bar", &ret);
+//! let data = fixture.build();
+//! let cache = DyldCache::<LittleEndian>::parse(&*data, &[])?;
+//! let index = dsc::CacheIndex::new(&cache);
+//! let bar = fixture.address("_bar").unwrap();
+//! assert_eq!(index.image_at(bar).unwrap().1.path, "/usr/lib/libfoo.dylib");
+//! # Ok(())
+//! # }
+//! ```
+
+use object::macho::{
+    CPU_TYPE_ARM64, DyldCacheHeader, DyldCacheImageInfo, DyldCacheMappingAndSlideInfo,
+    DyldCacheMappingInfo, DylibCommand, LC_DYLD_EXPORTS_TRIE, LC_FUNCTION_STARTS, LC_ID_DYLIB,
+    LC_SEGMENT_64, LC_SYMTAB, LC_UUID, LinkeditDataCommand, MH_CIGAM_64, MH_DYLDLINK, MH_DYLIB,
+    MH_DYLIB_IN_CACHE, MH_NOUNDEFS, MH_TWOLEVEL, MachHeader64, N_EXT, N_SECT,
+    S_ATTR_PURE_INSTRUCTIONS, S_ATTR_SOME_INSTRUCTIONS, S_CSTRING_LITERALS, S_LITERAL_POINTERS,
+    S_REGULAR, Section64, SegmentCommand64, SymtabCommand, UuidCommand, VM_PROT_EXECUTE,
+    VM_PROT_READ, VM_PROT_WRITE,
+};
+use object::pod::{self, Pod};
+use object::{BigEndian, LittleEndian};
+use std::collections::HashMap;
+use std::error::Error;
+use std::mem::size_of;
+use std::path::Path;
+
+/// Address of the first mapping, the usual arm64 shared region start.
+pub const BASE_ADDRESS: u64 = 0x1_8000_0000;
+/// UUID of every fixture cache.
+pub const UUID: [u8; 16] = *b"dsc fixture uuid";
+
+const PAGE_SIZE: u64 = 0x4000;
+/// Mach-O header and load commands of an image, without the install name and the sections.
+const COMMANDS_SIZE: usize = size_of::<MachHeader64<LittleEndian>>()
+    + 3 * size_of::<SegmentCommand64<LittleEndian>>()
+    + size_of::<DylibCommand<LittleEndian>>()
+    + size_of::<UuidCommand<LittleEndian>>()
+    + size_of::<SymtabCommand<LittleEndian>>()
+    + 2 * size_of::<LinkeditDataCommand<LittleEndian>>();
+/// Room left after the load commands, as `ld -headerpad` does, for tools that add commands.
+const HEADER_PAD: usize = 0x100;
+/// Size of the ObjC optimization header, up to the offset of the selector base.
+const OBJC_OPTS_SIZE: usize = 56;
+/// Size of a `category_t`, followed by its method list in `__objc_const`.
+const CATEGORY_SIZE: u64 = 48;
+/// Flags of a small method list with direct selectors and 12-byte entries.
+const SMALL_DIRECT_METHOD_LIST: u32 = 0xC000_000C;
+
+/// One image of a fixture.
+pub struct FixtureImage {
+    pub path: String,
+    /// Functions of `__TEXT,__text` in order, each exported and in the symbol table.
+    pub functions: Vec<(String, Vec<u8>)>,
+    /// Contents of `__DATA,__data`.
+    pub data: Vec<u8>,
+    /// Strings of `__TEXT,__objc_methname`, each with a reference in `__DATA,__objc_selrefs` to
+    /// the string of the first image that has it, as the cache builder uniques them.
+    pub selectors: Vec<String>,
+    /// Instance methods of a category in `__DATA,__objc_const`, by selector, in a small method
+    /// list with direct selectors. Each must be a selector of some image.
+    pub methods: Vec<String>,
+}
+
+impl FixtureImage {
+    /// Sections of the image: `__text` and `__data`, and the ObjC sections it needs.
+    fn section_count(&self) -> usize {
+        2 + 2 * usize::from(!self.selectors.is_empty()) + 2 * usize::from(!self.methods.is_empty())
+    }
+}
+
+/// A synthetic cache, described image by image.
+#[derive(Default)]
+pub struct Fixture {
+    pub images: Vec<FixtureImage>,
+}
+
+/// Where the parts of an image land in the cache.
+struct ImageLayout {
+    base: u64,
+    text_size: u64,
+    text: u64,
+    functions: Vec<u64>,
+    data: u64,
+    data_size: u64,
+    methname: u64,
+    selrefs: u64,
+    catlist: u64,
+    category: u64,
+    /// Offsets in the `__LINKEDIT` mapping.
+    symbols: usize,
+    strings: (usize, usize),
+    trie: (usize, usize),
+    starts: (usize, usize),
+}
+
+struct Layout {
+    images: Vec<ImageLayout>,
+    /// File offset of the ObjC optimization header, when an image has methods.
+    objc_opts: Option<usize>,
+    /// Address the direct selectors of method lists are offsets from.
+    selector_base: u64,
+    /// Address of each selector string, in the first image that has it.
+    selectors: HashMap<String, u64>,
+    text_size: u64,
+    data_size: u64,
+    linkedit: Vec<u8>,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an image. The following calls fill it in.
+    pub fn image(mut self, path: &str) -> Self {
+        self.images.push(FixtureImage {
+            path: path.to_string(),
+            functions: Vec::new(),
+            data: Vec::new(),
+            selectors: Vec::new(),
+            methods: Vec::new(),
+        });
+        self
+    }
+
+    /// Append a function to the last image. `code` is padded to 4 bytes.
+    pub fn function(mut self, name: &str, code: &[u8]) -> Self {
+        self.last()
+            .functions
+            .push((name.to_string(), code.to_vec()));
+        self
+    }
+
+    /// Append bytes to `__DATA,__data` of the last image.
+    pub fn data(mut self, bytes: &[u8]) -> Self {
+        self.last().data.extend_from_slice(bytes);
+        self
+    }
+
+    /// Add a selector string and reference to the last image.
+    pub fn selector(mut self, name: &str) -> Self {
+        self.last().selectors.push(name.to_string());
+        self
+    }
+
+    /// Add an instance method to the category of the last image.
+    pub fn method(mut self, name: &str) -> Self {
+        self.last().methods.push(name.to_string());
+        self
+    }
+
+    fn last(&mut self) -> &mut FixtureImage {
+        self.images
+            .last_mut()
+            .expect("Fixture::image must be called before adding to an image")
+    }
+
+    /// Address of the first function named `name`.
+    pub fn address(&self, name: &str) -> Option<u64> {
+        let layout = self.layout();
+        self.images
+            .iter()
+            .zip(&layout.images)
+            .flat_map(|(image, at)| image.functions.iter().zip(&at.functions))
+            .find(|((n, _), _)| n == name)
+            .map(|(_, &address)| address)
+    }
+
+    /// Address of the Mach-O header of the image at `index`.
+    pub fn image_address(&self, index: usize) -> Option<u64> {
+        self.layout().images.get(index).map(|at| at.base)
+    }
+
+    /// Address of `__DATA,__data` of the image at `index`.
+    pub fn data_address(&self, index: usize) -> Option<u64> {
+        self.layout().images.get(index).map(|at| at.data)
+    }
+
+    /// Address of the uniqued string of the selector `name`, in the first image that has it.
+    pub fn selector_address(&self, name: &str) -> Option<u64> {
+        self.layout().selectors.get(name).copied()
+    }
+
+    fn layout(&self) -> Layout {
+        let header_size = (size_of::<DyldCacheHeader<LittleEndian>>()
+            + 3 * size_of::<DyldCacheMappingInfo<LittleEndian>>()
+            + 3 * size_of::<DyldCacheMappingAndSlideInfo<LittleEndian>>()
+            + self.images.len() * size_of::<DyldCacheImageInfo<LittleEndian>>()
+            + self.images.iter().map(|i| i.path.len() + 1).sum::<usize>())
+        .next_multiple_of(8);
+        let objc_opts = self
+            .images
+            .iter()
+            .any(|image| !image.methods.is_empty())
+            .then_some(header_size);
+        let header_size = header_size + objc_opts.map_or(0, |_| OBJC_OPTS_SIZE);
+
+        let mut images = Vec::new();
+        let mut text_end = (header_size as u64).next_multiple_of(PAGE_SIZE);
+        for image in &self.images {
+            let base = BASE_ADDRESS + text_end;
+            let commands = COMMANDS_SIZE
+                + image.section_count() * size_of::<Section64<LittleEndian>>()
+                + (image.path.len() + 1).next_multiple_of(8);
+            let text = base + (commands + HEADER_PAD).next_multiple_of(16) as u64;
+            let mut functions = Vec::new();
+            let mut end = text;
+            for (_, code) in &image.functions {
+                functions.push(end);
+                end += code.len().next_multiple_of(4) as u64;
+            }
+            let methname = end;
+            end += image
+                .selectors
+                .iter()
+                .map(|name| name.len() as u64 + 1)
+                .sum::<u64>();
+            let text_size = (end - base).next_multiple_of(PAGE_SIZE);
+            text_end += text_size;
+
+            // Offsets in __DATA until it is placed: __data, __objc_selrefs, __objc_catlist, then
+            // the category and its method list in __objc_const.
+            let selrefs = (image.data.len() as u64).next_multiple_of(8);
+            let catlist = selrefs + 8 * image.selectors.len() as u64;
+            let category = catlist + 8 * u64::from(!image.methods.is_empty());
+            let data_end = match image.methods.len() {
+                0 => category,
+                count => category + CATEGORY_SIZE + 8 + 12 * count as u64,
+            };
+            images.push(ImageLayout {
+                base,
+                text_size,
+                text,
+                functions,
+                data: 0,
+                data_size: data_end.max(1).next_multiple_of(PAGE_SIZE),
+                methname,
+                selrefs,
+                catlist,
+                category,
+                symbols: 0,
+                strings: (0, 0),
+                trie: (0, 0),
+                starts: (0, 0),
+            });
+        }
+
+        let mut data_end = 0;
+        for at in &mut images {
+            at.data = BASE_ADDRESS + text_end + data_end;
+            at.selrefs += at.data;
+            at.catlist += at.data;
+            at.category += at.data;
+            data_end += at.data_size;
+        }
+
+        let mut selectors = HashMap::new();
+        for (image, at) in self.images.iter().zip(&images) {
+            let mut address = at.methname;
+            for name in &image.selectors {
+                selectors.entry(name.clone()).or_insert(address);
+                address += name.len() as u64 + 1;
+            }
+        }
+
+        let mut linkedit = Vec::new();
+        for (image, at) in self.images.iter().zip(&mut images) {
+            at.symbols = linkedit.len();
+            let mut strings = vec![0u8];
+            for ((name, _), &address) in image.functions.iter().zip(&at.functions) {
+                linkedit.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+                // Defined in section 1, `__TEXT,__text`.
+                linkedit.extend_from_slice(&[N_SECT | N_EXT, 1, 0, 0]);
+                linkedit.extend_from_slice(&address.to_le_bytes());
+                strings.extend_from_slice(name.as_bytes());
+                strings.push(0);
+            }
+            at.strings = append(&mut linkedit, &strings);
+
+            let exports: Vec<(&str, u64)> = image
+                .functions
+                .iter()
+                .zip(&at.functions)
+                .map(|((name, _), &address)| (name.as_str(), address - at.base))
+                .collect();
+            at.trie = append(&mut linkedit, &export_trie(&exports));
+
+            let mut starts = Vec::new();
+            let mut previous = at.base;
+            for &address in &at.functions {
+                uleb128(&mut starts, address - previous);
+                previous = address;
+            }
+            starts.push(0);
+            at.starts = append(&mut linkedit, &starts);
+        }
+        linkedit.resize(
+            linkedit.len().max(1).next_multiple_of(PAGE_SIZE as usize),
+            0,
+        );
+
+        Layout {
+            selector_base: images.first().map_or(BASE_ADDRESS, |at| at.base),
+            images,
+            objc_opts,
+            selectors,
+            text_size: text_end,
+            data_size: data_end,
+            linkedit,
+        }
+    }
+
+    /// The cache file.
+    pub fn build(&self) -> Vec<u8> {
+        let e = LittleEndian;
+        let layout = self.layout();
+        let linkedit_offset = layout.text_size + layout.data_size;
+        let linkedit_address = BASE_ADDRESS + linkedit_offset;
+        let mut out = vec![0u8; (linkedit_offset as usize) + layout.linkedit.len()];
+
+        let mappings = [
+            (
+                BASE_ADDRESS,
+                layout.text_size,
+                0,
+                VM_PROT_READ | VM_PROT_EXECUTE,
+            ),
+            (
+                BASE_ADDRESS + layout.text_size,
+                layout.data_size,
+                layout.text_size,
+                VM_PROT_READ | VM_PROT_WRITE,
+            ),
+            (
+                linkedit_address,
+                layout.linkedit.len() as u64,
+                linkedit_offset,
+                VM_PROT_READ,
+            ),
+        ];
+        let mut offset = size_of::<DyldCacheHeader<LittleEndian>>();
+        let mapping_offset = offset;
+        for &(address, size, file_offset, prot) in &mappings {
+            let info = at::<DyldCacheMappingInfo<LittleEndian>>(&mut out, &mut offset);
+            info.address.set(e, address);
+            info.size.set(e, size);
+            info.file_offset.set(e, file_offset);
+            info.max_prot.set(e, prot);
+            info.init_prot.set(e, prot);
+        }
+        let mapping_with_slide_offset = offset;
+        for &(address, size, file_offset, prot) in &mappings {
+            let info = at::<DyldCacheMappingAndSlideInfo<LittleEndian>>(&mut out, &mut offset);
+            info.address.set(e, address);
+            info.size.set(e, size);
+            info.file_offset.set(e, file_offset);
+            info.max_prot.set(e, prot);
+            info.init_prot.set(e, prot);
+        }
+        let images_offset = offset;
+        let mut path_offset =
+            offset + self.images.len() * size_of::<DyldCacheImageInfo<LittleEndian>>();
+        for (image, at_image) in self.images.iter().zip(&layout.images) {
+            let info = at::<DyldCacheImageInfo<LittleEndian>>(&mut out, &mut offset);
+            info.address.set(e, at_image.base);
+            info.path_file_offset.set(e, path_offset as u32);
+            out[path_offset..path_offset + image.path.len()].copy_from_slice(image.path.as_bytes());
+            path_offset += image.path.len() + 1;
+        }
+
+        let header = at::<DyldCacheHeader<LittleEndian>>(&mut out, &mut 0);
+        header.magic = *b"dyld_v1   arm64\0";
+        header.mapping_offset.set(e, mapping_offset as u32);
+        header.mapping_count.set(e, mappings.len() as u32);
+        header
+            .mapping_with_slide_offset
+            .set(e, mapping_with_slide_offset as u32);
+        header
+            .mapping_with_slide_count
+            .set(e, mappings.len() as u32);
+        header.images_offset_old.set(e, images_offset as u32);
+        header.images_count_old.set(e, self.images.len() as u32);
+        header.images_offset.set(e, images_offset as u32);
+        header.images_count.set(e, self.images.len() as u32);
+        header.uuid = UUID;
+        // A development cache of macOS.
+        header.platform.set(e, object::macho::PLATFORM_MACOS);
+        header.shared_region_start.set(e, BASE_ADDRESS);
+        header
+            .shared_region_size
+            .set(e, linkedit_offset + layout.linkedit.len() as u64);
+        if let Some(opts) = layout.objc_opts {
+            // Only the selector base is filled in, as an offset from the first mapping.
+            header.objc_opts_offset.set(e, opts as u64);
+            header.objc_opts_size.set(e, OBJC_OPTS_SIZE as u64);
+            let selector_base = layout.selector_base - BASE_ADDRESS;
+            out[opts + 48..opts + 56].copy_from_slice(&selector_base.to_le_bytes());
+        }
+
+        for (index, (image, at_image)) in self.images.iter().zip(&layout.images).enumerate() {
+            for ((_, code), &address) in image.functions.iter().zip(&at_image.functions) {
+                put(&mut out, address, code);
+            }
+            put(&mut out, at_image.data, &image.data);
+
+            let mut methname = Vec::new();
+            for name in &image.selectors {
+                methname.extend_from_slice(name.as_bytes());
+                methname.push(0);
+            }
+            put(&mut out, at_image.methname, &methname);
+            let selrefs: Vec<u8> = image
+                .selectors
+                .iter()
+                .flat_map(|name| layout.selectors[name].to_le_bytes())
+                .collect();
+            put(&mut out, at_image.selrefs, &selrefs);
+            let mut objc_const = Vec::new();
+            if !image.methods.is_empty() {
+                put(&mut out, at_image.catlist, &at_image.category.to_le_bytes());
+                // A category_t whose instance methods follow it.
+                objc_const.resize(CATEGORY_SIZE as usize, 0);
+                objc_const[16..24]
+                    .copy_from_slice(&(at_image.category + CATEGORY_SIZE).to_le_bytes());
+                objc_const.extend_from_slice(&SMALL_DIRECT_METHOD_LIST.to_le_bytes());
+                objc_const.extend_from_slice(&(image.methods.len() as u32).to_le_bytes());
+                for name in &image.methods {
+                    let selector = layout
+                        .selectors
+                        .get(name)
+                        .expect("fixture methods are selectors of some image");
+                    let offset = (selector - layout.selector_base) as i32;
+                    objc_const.extend_from_slice(&offset.to_le_bytes());
+                    // No types or implementation.
+                    objc_const.extend_from_slice(&[0; 8]);
+                }
+                put(&mut out, at_image.category, &objc_const);
+            }
+
+            let linkedit = |range: (usize, usize)| {
+                ((linkedit_offset as usize + range.0) as u32, range.1 as u32)
+            };
+            let mut text_sections = vec![(
+                &b"__text"[..],
+                at_image.text,
+                at_image.methname - at_image.text,
+                S_REGULAR | S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS,
+            )];
+            let mut data_sections = vec![(
+                &b"__data"[..],
+                at_image.data,
+                image.data.len() as u64,
+                S_REGULAR,
+            )];
+            if !image.selectors.is_empty() {
+                text_sections.push((
+                    b"__objc_methname",
+                    at_image.methname,
+                    methname.len() as u64,
+                    S_CSTRING_LITERALS,
+                ));
+                data_sections.push((
+                    b"__objc_selrefs",
+                    at_image.selrefs,
+                    selrefs.len() as u64,
+                    S_LITERAL_POINTERS,
+                ));
+            }
+            if !image.methods.is_empty() {
+                data_sections.push((b"__objc_catlist", at_image.catlist, 8, S_REGULAR));
+                data_sections.push((
+                    b"__objc_const",
+                    at_image.category,
+                    objc_const.len() as u64,
+                    S_REGULAR,
+                ));
+            }
+            let name_size = (image.path.len() + 1).next_multiple_of(8);
+            let header_offset = (at_image.base - BASE_ADDRESS) as usize;
+            let mut offset = header_offset + size_of::<MachHeader64<LittleEndian>>();
+
+            segment(
+                &mut out,
+                &mut offset,
+                b"__TEXT",
+                (at_image.base, at_image.text_size),
+                VM_PROT_READ | VM_PROT_EXECUTE,
+                &text_sections,
+            );
+            segment(
+                &mut out,
+                &mut offset,
+                b"__DATA",
+                (at_image.data, at_image.data_size),
+                VM_PROT_READ | VM_PROT_WRITE,
+                &data_sections,
+            );
+            segment(
+                &mut out,
+                &mut offset,
+                b"__LINKEDIT",
+                (linkedit_address, layout.linkedit.len() as u64),
+                VM_PROT_READ,
+                &[],
+            );
+
+            let dylib = at::<DylibCommand<LittleEndian>>(&mut out, &mut offset);
+            dylib.cmd.set(e, LC_ID_DYLIB);
+            dylib.cmdsize.set(
+                e,
+                (size_of::<DylibCommand<LittleEndian>>() + name_size) as u32,
+            );
+            dylib
+                .dylib
+                .name
+                .offset
+                .set(e, size_of::<DylibCommand<LittleEndian>>() as u32);
+            dylib.dylib.current_version.set(e, 0x1_0000);
+            dylib.dylib.compatibility_version.set(e, 0x1_0000);
+            out[offset..offset + image.path.len()].copy_from_slice(image.path.as_bytes());
+            offset += name_size;
+
+            let uuid = at::<UuidCommand<LittleEndian>>(&mut out, &mut offset);
+            uuid.cmd.set(e, LC_UUID);
+            uuid.cmdsize
+                .set(e, size_of::<UuidCommand<LittleEndian>>() as u32);
+            uuid.uuid = UUID;
+            uuid.uuid[12..].copy_from_slice(&(index as u32).to_be_bytes());
+
+            let symtab = at::<SymtabCommand<LittleEndian>>(&mut out, &mut offset);
+            symtab.cmd.set(e, LC_SYMTAB);
+            symtab
+                .cmdsize
+                .set(e, size_of::<SymtabCommand<LittleEndian>>() as u32);
+            symtab.symoff.set(e, linkedit((at_image.symbols, 0)).0);
+            symtab.nsyms.set(e, image.functions.len() as u32);
+            let (stroff, strsize) = linkedit(at_image.strings);
+            symtab.stroff.set(e, stroff);
+            symtab.strsize.set(e, strsize);
+
+            for (cmd, range) in [
+                (LC_DYLD_EXPORTS_TRIE, at_image.trie),
+                (LC_FUNCTION_STARTS, at_image.starts),
+            ] {
+                let data = at::<LinkeditDataCommand<LittleEndian>>(&mut out, &mut offset);
+                data.cmd.set(e, cmd);
+                data.cmdsize
+                    .set(e, size_of::<LinkeditDataCommand<LittleEndian>>() as u32);
+                let (dataoff, datasize) = linkedit(range);
+                data.dataoff.set(e, dataoff);
+                data.datasize.set(e, datasize);
+            }
+
+            let sizeofcmds = offset - header_offset - size_of::<MachHeader64<LittleEndian>>();
+            let header = at::<MachHeader64<LittleEndian>>(&mut out, &mut { header_offset });
+            header.magic.set(BigEndian, MH_CIGAM_64);
+            header.cputype.set(e, CPU_TYPE_ARM64);
+            header.filetype.set(e, MH_DYLIB);
+            header.ncmds.set(e, 8);
+            header.sizeofcmds.set(e, sizeofcmds as u32);
+            header.flags.set(
+                e,
+                MH_NOUNDEFS | MH_DYLDLINK | MH_TWOLEVEL | MH_DYLIB_IN_CACHE,
+            );
+        }
+
+        out[linkedit_offset as usize..].copy_from_slice(&layout.linkedit);
+        out
+    }
+
+    /// Write the cache file to `path`, for the code that opens caches from disk.
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, self.build())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e).into())
+    }
+}
+
+/// A `T` at `*offset` in `out`, moving `offset` past it.
+fn at<'a, T: Pod>(out: &'a mut [u8], offset: &mut usize) -> &'a mut T {
+    let start = *offset;
+    *offset += size_of::<T>();
+    pod::from_bytes_mut::<T>(&mut out[start..])
+        .expect("fixture structures are unaligned and fit the file")
+        .0
+}
+
+/// Write a segment command with its sections, as `(name, address, size, flags)`. Segments are
+/// mapped at `address - BASE_ADDRESS` in the file.
+fn segment(
+    out: &mut [u8],
+    offset: &mut usize,
+    name: &[u8],
+    (address, size): (u64, u64),
+    prot: u32,
+    sections: &[(&[u8], u64, u64, u32)],
+) {
+    let e = LittleEndian;
+    let command = at::<SegmentCommand64<LittleEndian>>(out, offset);
+    command.cmd.set(e, LC_SEGMENT_64);
+    command.cmdsize.set(
+        e,
+        (size_of::<SegmentCommand64<LittleEndian>>()
+            + sections.len() * size_of::<Section64<LittleEndian>>()) as u32,
+    );
+    command.segname[..name.len()].copy_from_slice(name);
+    command.vmaddr.set(e, address);
+    command.vmsize.set(e, size);
+    command.fileoff.set(e, address - BASE_ADDRESS);
+    command.filesize.set(e, size);
+    command.maxprot.set(e, prot);
+    command.initprot.set(e, prot);
+    command.nsects.set(e, sections.len() as u32);
+    for &(sectname, addr, size, flags) in sections {
+        let section = at::<Section64<LittleEndian>>(out, offset);
+        section.sectname[..sectname.len()].copy_from_slice(sectname);
+        section.segname[..name.len()].copy_from_slice(name);
+        section.addr.set(e, addr);
+        section.size.set(e, size);
+        section.offset.set(e, (addr - BASE_ADDRESS) as u32);
+        section.align.set(e, 2);
+        section.flags.set(e, flags);
+    }
+}
+
+/// Copy `bytes` to the file offset of `address`.
+fn put(out: &mut [u8], address: u64, bytes: &[u8]) {
+    let start = (address - BASE_ADDRESS) as usize;
+    out[start..start + bytes.len()].copy_from_slice(bytes);
+}
+
+/// An export trie with one edge from the root per symbol, `(name, offset from the image)`.
+fn export_trie(exports: &[(&str, u64)]) -> Vec<u8> {
+    let terminals: Vec<Vec<u8>> = exports
+        .iter()
+        .map(|&(_, offset)| {
+            let mut info = vec![0];
+            uleb128(&mut info, offset);
+            let mut node = Vec::new();
+            uleb128(&mut node, info.len() as u64);
+            node.extend_from_slice(&info);
+            node.push(0);
+            node
+        })
+        .collect();
+    // Child offsets are ULEB128 and count towards the root size, so grow it until it settles.
+    let mut root_size = 0;
+    loop {
+        let count = u8::try_from(exports.len()).expect("at most 255 exports per fixture image");
+        let mut root = vec![0, count];
+        let mut child = root_size;
+        for ((name, _), node) in exports.iter().zip(&terminals) {
+            root.extend_from_slice(name.as_bytes());
+            root.push(0);
+            uleb128(&mut root, child as u64);
+            child += node.len();
+        }
+        if root.len() == root_size {
+            root.extend(terminals.concat());
+            return root;
+        }
+        root_size = root.len();
+    }
+}
+
+fn uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append `bytes` 8-byte aligned, returning their `(offset, size)`.
+fn append(out: &mut Vec<u8>, bytes: &[u8]) -> (usize, usize) {
+    out.resize(out.len().next_multiple_of(8), 0);
+    let offset = out.len();
+    out.extend_from_slice(bytes);
+    (offset, bytes.len())
+}
@@ -0,0 +1,921 @@
+//! Objective-C headers in the style of class-dump, rendered from the metadata of an image: type
+//! encodings of ivars, properties and methods are decoded into C declarations.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::objc::{ObjcClass, ObjcMetadata, ObjcMethod, ObjcProperty, ObjcProtocol};
+
+/// Shared structure definitions, imported by every header of an image that has any.
+pub const STRUCTURES: &str = "CDStructures.h";
+
+/// A C type decoded from an ObjC type encoding.
+#[derive(Clone)]
+pub enum Type {
+    /// A scalar or an ObjC typedef such as `SEL`.
+    Named(&'static str),
+    /// `id`, `id <P>` or a pointer to an instance of `class`.
+    Object {
+        class: Option<String>,
+        protocols: Vec<String>,
+    },
+    Block,
+    Pointer(Box<Type>),
+    Const(Box<Type>),
+    Array(u64, Box<Type>),
+    /// A struct or union, with its fields when the encoding spells them out.
+    Record {
+        union: bool,
+        name: Option<String>,
+        fields: Fields,
+    },
+    Bitfield(u64),
+}
+
+/// Fields of a struct or union, named when the encoding names them.
+pub type Fields = Vec<(Option<String>, Type)>;
+
+/// Decode a single type encoding, e.g. of an ivar.
+pub fn decode(encoding: &str) -> Type {
+    Parser::new(encoding).parse()
+}
+
+/// Decode a method type encoding, e.g. `v24@0:8@16`, into the return type followed by the
+/// argument types, `self` and `_cmd` included.
+pub fn decode_method(types: &str) -> Vec<Type> {
+    let mut parser = Parser::new(types);
+    let mut decoded = Vec::new();
+    while parser.peek().is_some() {
+        decoded.push(parser.parse());
+        // Stack offsets follow each type.
+        while parser
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || c == b'-' || c == b'+')
+        {
+            parser.pos += 1;
+        }
+    }
+    decoded
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Set while parsing the fields of a record whose fields are named, where a quoted string
+    /// after `@` may be the name of the next field rather than a class.
+    named_fields: bool,
+    /// Number of types being parsed that enclose the current one.
+    depth: usize,
+}
+
+/// Deepest nesting of pointers, arrays and records decoded. Encodings nested further are
+/// malformed or hostile, and are cut short as `void *`.
+const MAX_DEPTH: usize = 64;
+
+impl<'a> Parser<'a> {
+    fn new(encoding: &'a str) -> Self {
+        Parser {
+            bytes: encoding.as_bytes(),
+            pos: 0,
+            named_fields: false,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn number(&mut self) -> u64 {
+        let mut n = 0u64;
+        while let Some(c) = self.peek().filter(u8::is_ascii_digit) {
+            n = n.saturating_mul(10).saturating_add((c - b'0') as u64);
+            self.pos += 1;
+        }
+        n
+    }
+
+    /// A string between double quotes at the current position.
+    fn quoted(&mut self) -> Option<String> {
+        if self.peek() != Some(b'"') {
+            return None;
+        }
+        let start = self.pos + 1;
+        let len = self.bytes[start..].iter().position(|&c| c == b'"')?;
+        self.pos = start + len + 1;
+        Some(String::from_utf8_lossy(&self.bytes[start..start + len]).into_owned())
+    }
+
+    /// Whether a quoted string at the current position names the class of an object.
+    fn class_name_follows(&self) -> bool {
+        if self.peek() != Some(b'"') {
+            return false;
+        }
+        if !self.named_fields {
+            return true;
+        }
+        let rest = &self.bytes[self.pos + 1..];
+        match rest.iter().position(|&c| c == b'"') {
+            Some(end) => matches!(rest.get(end + 1), None | Some(b'"' | b'}' | b')')),
+            None => false,
+        }
+    }
+
+    fn parse(&mut self) -> Type {
+        if self.depth >= MAX_DEPTH {
+            self.pos = self.bytes.len();
+            return Type::Pointer(Box::new(Type::Named("void")));
+        }
+        self.depth += 1;
+        let ty = self.parse_one();
+        self.depth -= 1;
+        ty
+    }
+
+    fn parse_one(&mut self) -> Type {
+        let Some(c) = self.peek() else {
+            return Type::Named("void");
+        };
+        self.pos += 1;
+        match c {
+            b'c' => Type::Named("char"),
+            b'i' | b'l' => Type::Named("int"),
+            b's' => Type::Named("short"),
+            b'q' => Type::Named("long long"),
+            b'C' => Type::Named("unsigned char"),
+            b'I' | b'L' => Type::Named("unsigned int"),
+            b'S' => Type::Named("unsigned short"),
+            b'Q' => Type::Named("unsigned long long"),
+            b't' => Type::Named("__int128"),
+            b'T' => Type::Named("unsigned __int128"),
+            b'f' => Type::Named("float"),
+            b'd' => Type::Named("double"),
+            b'D' => Type::Named("long double"),
+            b'B' => Type::Named("BOOL"),
+            b'v' | b'?' => Type::Named("void"),
+            b'*' => Type::Pointer(Box::new(Type::Named("char"))),
+            b'#' => Type::Named("Class"),
+            b':' => Type::Named("SEL"),
+            b'@' if self.peek() == Some(b'?') => {
+                self.pos += 1;
+                Type::Block
+            }
+            b'@' if self.class_name_follows() => {
+                let name = self.quoted().unwrap_or_default();
+                let (class, protocols) = name.split_once('<').unwrap_or((&name, ""));
+                Type::Object {
+                    class: (!class.is_empty()).then(|| class.to_string()),
+                    protocols: protocols
+                        .split(['<', '>'])
+                        .filter(|p| !p.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                }
+            }
+            b'@' => Type::Object {
+                class: None,
+                protocols: Vec::new(),
+            },
+            b'^' => Type::Pointer(Box::new(self.parse())),
+            b'r' => Type::Const(Box::new(self.parse())),
+            // Method qualifiers (in, out, bycopy, oneway...), atomics and complex numbers.
+            b'n' | b'N' | b'o' | b'O' | b'R' | b'V' | b'A' | b'j' => self.parse(),
+            b'[' => {
+                let count = self.number();
+                let element = self.parse();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                }
+                Type::Array(count, Box::new(element))
+            }
+            b'{' | b'(' => self.record(c == b'('),
+            b'b' => Type::Bitfield(self.number()),
+            _ => Type::Named("void"),
+        }
+    }
+
+    /// The rest of a `{name=fields}` struct or `(name=fields)` union.
+    fn record(&mut self, union: bool) -> Type {
+        let close = if union { b')' } else { b'}' };
+        let start = self.pos;
+        // The name ends at `=`, which C++ template arguments do not hold.
+        while self.peek().is_some_and(|c| c != b'=' && c != close) {
+            self.pos += 1;
+        }
+        let name = String::from_utf8_lossy(&self.bytes[start..self.pos]);
+        let name = (name != "?" && !name.is_empty()).then(|| identifier(&name));
+
+        let mut fields = Vec::new();
+        if self.peek() == Some(b'=') {
+            self.pos += 1;
+            let named = self.named_fields;
+            self.named_fields = self.peek() == Some(b'"');
+            while self.peek().is_some_and(|c| c != close) {
+                let field_name = self.quoted();
+                let before = self.pos;
+                let field = self.parse();
+                if self.pos == before {
+                    break;
+                }
+                fields.push((field_name, field));
+            }
+            self.named_fields = named;
+        }
+        if self.peek() == Some(close) {
+            self.pos += 1;
+        }
+        Type::Record {
+            union,
+            name,
+            fields,
+        }
+    }
+}
+
+/// `name` with characters that cannot appear in a C identifier replaced.
+fn identifier(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_');
+    }
+    id
+}
+
+/// Declare `name` with type `ty`, e.g. `NSString *name` or `int name[4]`. An empty name gives
+/// the type alone, as in a method signature.
+pub fn declare(ty: &Type, name: &str) -> String {
+    let join = |base: &str| {
+        if name.is_empty() {
+            base.to_string()
+        } else {
+            format!("{} {}", base, name)
+        }
+    };
+    match ty {
+        Type::Named(base) => join(base),
+        Type::Object { class, protocols } => {
+            let protocols = if protocols.is_empty() {
+                String::new()
+            } else {
+                format!("<{}>", protocols.join(", "))
+            };
+            match class {
+                Some(class) => format!("{}{} *{}", class, protocols, name),
+                None => join(&format!("id{}", protocols)),
+            }
+        }
+        Type::Block => join("id /* block */"),
+        Type::Pointer(inner) => match **inner {
+            Type::Array(..) => declare(inner, &format!("(*{})", name)),
+            _ => declare(inner, &format!("*{}", name)),
+        },
+        Type::Const(inner) => format!("const {}", declare(inner, name)),
+        Type::Array(count, element) => declare(element, &format!("{}[{}]", name, count)),
+        Type::Record {
+            union,
+            name: record,
+            fields,
+        } => {
+            let keyword = if *union { "union" } else { "struct" };
+            match record {
+                Some(record) => join(&format!("{} {}", keyword, record)),
+                None => join(&format!("{} {{ {}}}", keyword, field_list(fields, "", " "))),
+            }
+        }
+        Type::Bitfield(bits) => format!("unsigned int {} : {}", name, bits),
+    }
+}
+
+/// Fields as declarations ending in `;`, each preceded by `indent` and followed by `separator`.
+fn field_list(fields: &[(Option<String>, Type)], indent: &str, separator: &str) -> String {
+    let mut out = String::new();
+    for (i, (name, ty)) in fields.iter().enumerate() {
+        let name = name
+            .as_deref()
+            .map(identifier)
+            .unwrap_or_else(|| format!("_field{}", i + 1));
+        let _ = write!(out, "{}{};{}", indent, declare(ty, &name), separator);
+    }
+    out
+}
+
+/// Classes and protocols a type refers to, for forward declarations.
+fn references(ty: &Type, classes: &mut BTreeSet<String>, protocols: &mut BTreeSet<String>) {
+    match ty {
+        Type::Object {
+            class,
+            protocols: adopted,
+        } => {
+            classes.extend(class.clone());
+            protocols.extend(adopted.iter().cloned());
+        }
+        Type::Pointer(inner) | Type::Const(inner) | Type::Array(_, inner) => {
+            references(inner, classes, protocols)
+        }
+        Type::Record { fields, .. } => {
+            for (_, field) in fields {
+                references(field, classes, protocols);
+            }
+        }
+        Type::Named(_) | Type::Block | Type::Bitfield(_) => {}
+    }
+}
+
+/// A property declaration, and the selectors of its accessors.
+struct Property {
+    declaration: String,
+    ty: Type,
+    getter: String,
+    setter: Option<String>,
+}
+
+fn property(property: &ObjcProperty, class: bool) -> Property {
+    let attributes = &property.attributes;
+    // The type comes first and may hold commas, in C++ template names.
+    let (ty, rest) = match attributes.strip_prefix('T') {
+        Some(encoding) => {
+            let mut parser = Parser::new(encoding);
+            let ty = parser.parse();
+            (ty, encoding[parser.pos..].trim_start_matches(','))
+        }
+        None => (
+            Type::Object {
+                class: None,
+                protocols: Vec::new(),
+            },
+            attributes.as_str(),
+        ),
+    };
+
+    let mut flags = Vec::new();
+    if class {
+        flags.push("class".to_string());
+    }
+    let (mut getter, mut setter, mut readonly) = (None, None, false);
+    for attribute in rest.split(',').filter(|a| !a.is_empty()) {
+        let (key, value) = attribute.split_at(1);
+        match key {
+            "R" => {
+                readonly = true;
+                flags.push("readonly".to_string());
+            }
+            "C" => flags.push("copy".to_string()),
+            "&" => flags.push("retain".to_string()),
+            "W" => flags.push("weak".to_string()),
+            "N" => flags.push("nonatomic".to_string()),
+            "G" => {
+                getter = Some(value.to_string());
+                flags.push(format!("getter={}", value));
+            }
+            "S" => {
+                setter = Some(value.to_string());
+                flags.push(format!("setter={}", value));
+            }
+            _ => {}
+        }
+    }
+    let flags = if flags.is_empty() {
+        String::new()
+    } else {
+        format!("({}) ", flags.join(", "))
+    };
+    let name = &property.name;
+    let setter = (!readonly).then(|| {
+        setter.unwrap_or_else(|| {
+            let mut chars = name.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            format!(
+                "set{}{}:",
+                first.into_iter().collect::<String>(),
+                chars.as_str()
+            )
+        })
+    });
+    Property {
+        declaration: format!("@property {}{};", flags, declare(&ty, name)),
+        ty,
+        getter: getter.unwrap_or_else(|| name.clone()),
+        setter,
+    }
+}
+
+/// A method declaration such as `- (void)setName:(NSString *)arg1;`. Arguments whose types are
+/// unknown are declared as `id`.
+fn method(sign: char, method: &ObjcMethod) -> (String, Vec<Type>) {
+    let mut types = method
+        .types
+        .as_deref()
+        .map(decode_method)
+        .unwrap_or_default();
+    let parts: Vec<&str> = method.selector.split(':').collect();
+    let arguments = parts.len() - 1;
+    if types.len() != arguments + 3 {
+        types = vec![
+            Type::Object {
+                class: None,
+                protocols: Vec::new(),
+            };
+            arguments + 3
+        ];
+    }
+    let mut declaration = format!("{} ({})", sign, declare(&types[0], ""));
+    if arguments == 0 {
+        declaration.push_str(&method.selector);
+    } else {
+        for (i, part) in parts[..arguments].iter().enumerate() {
+            if i > 0 {
+                declaration.push(' ');
+            }
+            let _ = write!(
+                declaration,
+                "{}:({})arg{}",
+                part,
+                declare(&types[i + 3], ""),
+                i + 1
+            );
+        }
+    }
+    declaration.push(';');
+    (declaration, types)
+}
+
+/// Declarations of the body of an `@interface` or `@protocol`: properties, then the methods
+/// that are not property accessors, each list in metadata order.
+#[derive(Default)]
+struct Body {
+    lines: Vec<String>,
+    types: Vec<Type>,
+}
+
+impl Body {
+    fn add(
+        &mut self,
+        class_properties: &[ObjcProperty],
+        class_methods: &[ObjcMethod],
+        properties: &[ObjcProperty],
+        instance_methods: &[ObjcMethod],
+    ) {
+        for (class, properties, sign, methods) in [
+            (true, class_properties, '+', class_methods),
+            (false, properties, '-', instance_methods),
+        ] {
+            let mut accessors = BTreeSet::new();
+            for p in properties {
+                let p = property(p, class);
+                accessors.insert(p.getter);
+                accessors.extend(p.setter);
+                self.lines.push(p.declaration);
+                self.types.push(p.ty);
+            }
+            for m in methods {
+                // .cxx_construct and .cxx_destruct cannot be declared.
+                if accessors.contains(&m.selector) || m.selector.starts_with('.') {
+                    continue;
+                }
+                let (declaration, types) = method(sign, m);
+                self.lines.push(declaration);
+                self.types.extend(types);
+            }
+        }
+    }
+}
+
+/// A header file to write.
+pub struct Header {
+    pub file_name: String,
+    pub text: String,
+}
+
+/// Headers for the classes, categories and protocols of an image: `Class.h`,
+/// `Class+Category.h` and `Protocol-Protocol.h`, with the structures their types use in
+/// `CDStructures.h`.
+pub fn image_headers(image: &str, metadata: &ObjcMetadata) -> Vec<Header> {
+    let mut records = Records::default();
+    for class in metadata.classes.iter().chain(&metadata.categories) {
+        for ivar in &class.ivars {
+            records.collect(&ivar_type(ivar.encoding.as_deref()));
+        }
+    }
+    let mut headers = Vec::new();
+    let mut bodies = Vec::new();
+
+    for class in &metadata.classes {
+        let mut body = Body::default();
+        body.add(
+            &class.class_properties,
+            &class.class_methods,
+            &class.properties,
+            &class.instance_methods,
+        );
+        bodies.push((class, None, body));
+    }
+    for category in &metadata.categories {
+        // Categories of classes that are not bound cannot be declared.
+        let Some((class, name)) = category
+            .name
+            .strip_suffix(')')
+            .and_then(|n| n.split_once('('))
+            .filter(|(class, _)| *class != "?")
+        else {
+            continue;
+        };
+        let mut body = Body::default();
+        body.add(
+            &category.class_properties,
+            &category.class_methods,
+            &category.properties,
+            &category.instance_methods,
+        );
+        bodies.push((category, Some((class, name)), body));
+    }
+    for (_, _, body) in &bodies {
+        for ty in &body.types {
+            records.collect(ty);
+        }
+    }
+    let mut protocol_bodies = Vec::new();
+    for protocol in &metadata.protocols {
+        let mut required = Body::default();
+        required.add(
+            &[],
+            &protocol.class_methods,
+            &protocol.properties,
+            &protocol.instance_methods,
+        );
+        let mut optional = Body::default();
+        optional.add(
+            &[],
+            &protocol.optional_class_methods,
+            &[],
+            &protocol.optional_instance_methods,
+        );
+        for ty in required.types.iter().chain(&optional.types) {
+            records.collect(ty);
+        }
+        protocol_bodies.push((protocol, required, optional));
+    }
+
+    let structures = !records.is_empty();
+    for (class, category, body) in &bodies {
+        headers.push(class_header(image, class, *category, body, structures));
+    }
+    for (protocol, required, optional) in &protocol_bodies {
+        headers.push(protocol_header(
+            image, protocol, required, optional, structures,
+        ));
+    }
+    if structures {
+        headers.push(Header {
+            file_name: STRUCTURES.to_string(),
+            text: records.header(image),
+        });
+    }
+    headers
+}
+
+/// The type of an ivar, a pointer when its encoding is missing.
+fn ivar_type(encoding: Option<&str>) -> Type {
+    match encoding.map(decode) {
+        Some(Type::Named("void")) | None => Type::Pointer(Box::new(Type::Named("void"))),
+        Some(ty) => ty,
+    }
+}
+
+fn preamble(image: &str, structures: bool) -> String {
+    let mut text = format!("//\n// Generated by dsc from {}\n//\n\n", image);
+    text.push_str("#import <objc/NSObject.h>\n");
+    if structures {
+        let _ = writeln!(text, "#import \"{}\"", STRUCTURES);
+    }
+    text
+}
+
+/// `#import` lines for the headers declaring `protocols`, and `@class` and `@protocol` forward
+/// declarations for what `types` refer to, except `own`.
+fn imports(text: &mut String, protocols: &[String], types: &[Type], own: &[&str]) {
+    for protocol in protocols.iter().filter(|p| *p != "NSObject") {
+        let _ = writeln!(text, "#import \"{}-Protocol.h\"", file_stem(protocol));
+    }
+    let (mut classes, mut referenced) = (BTreeSet::new(), BTreeSet::new());
+    for ty in types {
+        references(ty, &mut classes, &mut referenced);
+    }
+    let classes: Vec<_> = classes
+        .into_iter()
+        .filter(|c| !own.contains(&c.as_str()))
+        .collect();
+    let referenced: Vec<_> = referenced
+        .into_iter()
+        .filter(|p| p != "NSObject" && !protocols.contains(p))
+        .collect();
+    text.push('\n');
+    if !classes.is_empty() {
+        let _ = writeln!(text, "@class {};", classes.join(", "));
+    }
+    if !referenced.is_empty() {
+        let _ = writeln!(text, "@protocol {};", referenced.join(", "));
+    }
+    if !classes.is_empty() || !referenced.is_empty() {
+        text.push('\n');
+    }
+}
+
+fn class_header(
+    image: &str,
+    class: &ObjcClass,
+    category: Option<(&str, &str)>,
+    body: &Body,
+    structures: bool,
+) -> Header {
+    let mut text = preamble(image, structures);
+    let ivars: Vec<_> = class
+        .ivars
+        .iter()
+        .map(|ivar| (ivar, ivar_type(ivar.encoding.as_deref())))
+        .collect();
+    let mut types: Vec<Type> = ivars.iter().map(|(_, ty)| ty.clone()).collect();
+    types.extend(body.types.iter().cloned());
+
+    let protocols = protocol_suffix(&class.protocols);
+    let (file_name, interface) = match category {
+        Some((extended, name)) => {
+            let _ = writeln!(text, "#import \"{}.h\"", file_stem(extended));
+            imports(&mut text, &class.protocols, &types, &[extended]);
+            (
+                format!("{}+{}.h", file_stem(extended), file_stem(name)),
+                format!("@interface {} ({}){}", extended, name, protocols),
+            )
+        }
+        None => {
+            let interface = match &class.superclass {
+                Some(superclass) => {
+                    if superclass != "NSObject" {
+                        let _ = writeln!(text, "#import \"{}.h\"", file_stem(superclass));
+                    }
+                    format!("@interface {} : {}{}", class.name, superclass, protocols)
+                }
+                None => format!(
+                    "__attribute__((objc_root_class))\n@interface {}{}",
+                    class.name, protocols
+                ),
+            };
+            let own = [
+                class.name.as_str(),
+                class.superclass.as_deref().unwrap_or(""),
+            ];
+            imports(&mut text, &class.protocols, &types, &own);
+            (format!("{}.h", file_stem(&class.name)), interface)
+        }
+    };
+
+    text.push_str(&interface);
+    text.push('\n');
+    if !ivars.is_empty() {
+        text.push_str("{\n");
+        for (ivar, ty) in &ivars {
+            let _ = writeln!(text, "    {};", declare(ty, &identifier(&ivar.name)));
+        }
+        text.push_str("}\n");
+    }
+    text.push('\n');
+    for line in &body.lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if !body.lines.is_empty() {
+        text.push('\n');
+    }
+    text.push_str("@end\n");
+    Header { file_name, text }
+}
+
+fn protocol_header(
+    image: &str,
+    protocol: &ObjcProtocol,
+    required: &Body,
+    optional: &Body,
+    structures: bool,
+) -> Header {
+    let mut text = preamble(image, structures);
+    let types: Vec<Type> = required
+        .types
+        .iter()
+        .chain(&optional.types)
+        .cloned()
+        .collect();
+    imports(&mut text, &protocol.protocols, &types, &[]);
+    let _ = writeln!(
+        text,
+        "@protocol {}{}",
+        protocol.name,
+        protocol_suffix(&protocol.protocols)
+    );
+    for line in &required.lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if !optional.lines.is_empty() {
+        text.push_str("\n@optional\n");
+        for line in &optional.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    if !required.lines.is_empty() || !optional.lines.is_empty() {
+        text.push('\n');
+    }
+    text.push_str("@end\n");
+    Header {
+        file_name: format!("{}-Protocol.h", file_stem(&protocol.name)),
+        text,
+    }
+}
+
+fn protocol_suffix(protocols: &[String]) -> String {
+    if protocols.is_empty() {
+        String::new()
+    } else {
+        format!(" <{}>", protocols.join(", "))
+    }
+}
+
+/// `name` usable as a file name.
+fn file_stem(name: &str) -> String {
+    name.replace('/', "_")
+}
+
+/// Named structs and unions, with their fields when any encoding spells them out.
+#[derive(Default)]
+struct Records {
+    records: BTreeMap<String, (bool, Fields)>,
+}
+
+impl Records {
+    fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn collect(&mut self, ty: &Type) {
+        match ty {
+            Type::Pointer(inner) | Type::Const(inner) | Type::Array(_, inner) => {
+                self.collect(inner)
+            }
+            Type::Record {
+                union,
+                name,
+                fields,
+            } => {
+                for (_, field) in fields {
+                    self.collect(field);
+                }
+                let Some(name) = name else {
+                    return;
+                };
+                let entry = self
+                    .records
+                    .entry(name.clone())
+                    .or_insert_with(|| (*union, Vec::new()));
+                // Prefer the most complete definition, with field names.
+                let named = |fields: &[(Option<String>, Type)]| {
+                    fields.iter().filter(|(name, _)| name.is_some()).count()
+                };
+                if fields.len() > entry.1.len()
+                    || (fields.len() == entry.1.len() && named(fields) > named(&entry.1))
+                {
+                    entry.1 = fields.clone();
+                }
+            }
+            Type::Named(_) | Type::Object { .. } | Type::Block | Type::Bitfield(_) => {}
+        }
+    }
+
+    /// `CDStructures.h`: forward declarations of every record, then the definitions, each
+    /// after those of the records it holds by value.
+    fn header(&self, image: &str) -> String {
+        let mut text = format!("//\n// Generated by dsc from {}\n//\n\n", image);
+        text.push_str("#import <objc/NSObject.h>\n");
+        let types: Vec<Type> = self
+            .records
+            .values()
+            .flat_map(|(_, fields)| fields.iter().map(|(_, ty)| ty.clone()))
+            .collect();
+        imports(&mut text, &[], &types, &[]);
+
+        for (name, (union, _)) in &self.records {
+            let _ = writeln!(text, "{} {};", keyword(*union), name);
+        }
+        let mut defined = BTreeSet::new();
+        for name in self.records.keys() {
+            self.define(name, &mut defined, &mut text);
+        }
+        text
+    }
+
+    fn define<'s>(&'s self, name: &'s str, defined: &mut BTreeSet<&'s str>, text: &mut String) {
+        if !defined.insert(name) {
+            return;
+        }
+        let Some((union, fields)) = self.records.get(name) else {
+            return;
+        };
+        if fields.is_empty() {
+            return;
+        }
+        for (_, field) in fields {
+            for dependency in by_value(field) {
+                self.define(dependency, defined, text);
+            }
+        }
+        let _ = write!(
+            text,
+            "\n{} {} {{\n{}}};\n",
+            keyword(*union),
+            name,
+            field_list(fields, "    ", "\n")
+        );
+    }
+}
+
+fn keyword(union: bool) -> &'static str {
+    if union { "union" } else { "struct" }
+}
+
+/// Names of the records `ty` holds by value, which must be defined before it.
+fn by_value(ty: &Type) -> Vec<&str> {
+    match ty {
+        Type::Record {
+            name: Some(name), ..
+        } => vec![name.as_str()],
+        Type::Record { fields, .. } => fields.iter().flat_map(|(_, f)| by_value(f)).collect(),
+        Type::Array(_, inner) | Type::Const(inner) => by_value(inner),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declares_type_encodings() {
+        let cases = [
+            ("i", "x", "int x"),
+            ("@\"NSString\"", "x", "NSString *x"),
+            ("@\"<NSCopying>\"", "x", "id<NSCopying> x"),
+            (
+                "@\"NSArray<NSFastEnumeration>\"",
+                "x",
+                "NSArray<NSFastEnumeration> *x",
+            ),
+            ("@?", "x", "id /* block */ x"),
+            ("^{CGPoint=dd}", "x", "struct CGPoint *x"),
+            ("r*", "x", "const char *x"),
+            ("[4i]", "x", "int x[4]"),
+            ("{CGRect={CGPoint=dd}{CGSize=dd}}", "x", "struct CGRect x"),
+            (
+                "(?=iQ)",
+                "x",
+                "union { int _field1; unsigned long long _field2; } x",
+            ),
+            ("b3", "x", "unsigned int x : 3"),
+        ];
+        for (encoding, name, expected) in cases {
+            assert_eq!(declare(&decode(encoding), name), expected, "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn decodes_method_types() {
+        let types: Vec<String> = decode_method("v24@0:8@\"NSString\"16")
+            .iter()
+            .map(|ty| declare(ty, ""))
+            .collect();
+        assert_eq!(types, ["void", "id", "SEL", "NSString *"]);
+    }
+
+    /// Levels of types nested in `ty`, `ty` included.
+    fn nesting(ty: &Type) -> usize {
+        1 + match ty {
+            Type::Pointer(inner) | Type::Const(inner) | Type::Array(_, inner) => nesting(inner),
+            Type::Record { fields, .. } => {
+                fields.iter().map(|(_, f)| nesting(f)).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_cut_short() {
+        for opener in ["^", "r", "[1", "{a=", "(a=", "n"] {
+            let ty = decode(&opener.repeat(100_000));
+            // The levels decoded, then the `void *` standing in for the rest.
+            assert!(nesting(&ty) <= MAX_DEPTH + 2, "{}", opener);
+        }
+        let ty = decode(&"^".repeat(100_000));
+        assert!(declare(&ty, "x").ends_with(&format!("void {}x", "*".repeat(MAX_DEPTH + 1))));
+        assert_eq!(decode_method(&"^".repeat(100_000)).len(), 1);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::color::{Colors, Style};
+
+pub fn print_hex_dump(start_addr: u64, data: &[u8], colors: Colors) {
+    for (row_idx, row) in data.chunks(16).enumerate() {
+        let addr = start_addr + (row_idx * 16) as u64;
+        println!("{}", hex_row(addr, row, colors));
+    }
+}
+
+/// Hex-dump `data` like `print_hex_dump`, with `note` after the first row.
+pub fn print_annotated_hex(start_addr: u64, data: &[u8], note: &str, colors: Colors) {
+    for (row_idx, row) in data.chunks(16).enumerate() {
+        let addr = start_addr + (row_idx * 16) as u64;
+        if row_idx == 0 {
+            // Keep the notes of short rows aligned.
+            let pad = " ".repeat(16 - row.len());
+            println!(
+                "{}{}  {}",
+                hex_row(addr, row, colors),
+                pad,
+                colors.paint(Style::Note, note)
+            );
+        } else {
+            println!("{}", hex_row(addr, row, colors));
+        }
+    }
+}
+
+fn hex_row(addr: u64, row: &[u8], colors: Colors) -> String {
+    let mut line = format!(
+        "{}: ",
+        colors.paint(Style::Address, format!("{:016X}", addr))
+    );
+    for b in row {
+        line.push_str(&format!("{:02X} ", b));
+    }
+
+    if row.len() < 16 {
+        for _ in 0..(16 - row.len()) {
+            line.push_str("   ");
+        }
+    }
+
+    line.push_str(" |");
+
+    for b in row {
+        let ch = if b.is_ascii_graphic() || *b == b' ' {
+            *b as char
+        } else {
+            '.'
+        };
+        line.push(ch);
+    }
+    line.push('|');
+    line
+}
@@ -0,0 +1,300 @@
+//! Candidate interposition points for a function: the branches calling it, the stubs and GOT
+//! slots calls go through, the uses dyld patches when the function is replaced, and for ObjC
+//! methods the `objc_msgSend` stubs of their selector.
+
+use object::LittleEndian;
+use object::macho::VM_PROT_EXECUTE;
+use object::read::macho::DyldCache;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+use crate::arm64::{self, Effect};
+use crate::index::CacheIndex;
+use crate::objc::ObjcReader;
+use crate::patchtable;
+use crate::slide::{self, PointerReader};
+use crate::vm;
+
+/// Sections holding pointers that code loads to call through.
+const GOT_SECTIONS: [&str; 4] = ["__got", "__auth_got", "__auth_ptr", "__la_symbol_ptr"];
+/// Scratch registers of stubs and branch islands.
+const STUB_REGISTERS: [u8; 2] = [16, 17];
+/// First argument register of `objc_msgSend`, holding the selector.
+const SELECTOR_REGISTER: u8 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HookKind {
+    /// `bl` to the function.
+    Call,
+    /// `b` to the function.
+    TailCall,
+    /// Stub or branch island jumping to the function, directly or through a GOT slot.
+    Stub,
+    /// Branch to one of the stubs.
+    StubCall,
+    /// Code computing the function's address with `adrp` and `add`, e.g. to pass a callback.
+    AddressTaken,
+    /// GOT slot holding the function's address.
+    GotSlot,
+    /// Other data pointer to the function (vtables, method lists, callback tables).
+    Pointer,
+    /// Use recorded in the dyld patch table, rewritten when the function is interposed.
+    PatchSite,
+    /// Uniqued GOT slot recorded in the dyld patch table.
+    PatchGot,
+    /// `objc_msgSend` stub of the method's selector, reaching every implementation of it.
+    ObjcStub,
+    /// Branch to one of the `objc_msgSend` stubs.
+    ObjcStubCall,
+}
+
+impl HookKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::Call => "call",
+            HookKind::TailCall => "tail-call",
+            HookKind::Stub => "stub",
+            HookKind::StubCall => "stub-call",
+            HookKind::AddressTaken => "address-taken",
+            HookKind::GotSlot => "got",
+            HookKind::Pointer => "pointer",
+            HookKind::PatchSite => "patch",
+            HookKind::PatchGot => "patch-got",
+            HookKind::ObjcStub => "objc-stub",
+            HookKind::ObjcStubCall => "objc-stub-call",
+        }
+    }
+
+    /// What hooking points of this kind intercepts.
+    pub fn summary(self) -> &'static str {
+        match self {
+            HookKind::Call => "direct calls, each redirected by rewriting its bl",
+            HookKind::TailCall => "tail calls, each redirected by rewriting its b",
+            HookKind::Stub => "stubs and branch islands, intercepting every caller using them",
+            HookKind::StubCall => "calls through those stubs",
+            HookKind::AddressTaken => "code taking the function's address",
+            HookKind::GotSlot => "GOT slots, intercepting every load through them",
+            HookKind::Pointer => "other pointers to the function",
+            HookKind::PatchSite => "patch table uses, the sites dyld rewrites to interpose it",
+            HookKind::PatchGot => "patch table GOT slots",
+            HookKind::ObjcStub => "objc_msgSend stubs of the selector, reached by any receiver",
+            HookKind::ObjcStubCall => "message sends through those stubs",
+        }
+    }
+}
+
+pub struct HookPoint {
+    pub kind: HookKind,
+    pub address: u64,
+    /// The stub or GOT slot the point reaches the function through.
+    pub via: Option<u64>,
+}
+
+/// Every candidate hook point of the function at `target` in the image at `image`, sorted by
+/// kind and address. Points of the patch table are left out when the cache has none, or when
+/// it cannot be read.
+pub fn hook_points(
+    index: &CacheIndex,
+    image: usize,
+    target: u64,
+) -> Result<Vec<HookPoint>, Box<dyn Error>> {
+    let cache = index.cache();
+    let mut points = Vec::new();
+
+    let mut slots = HashSet::new();
+    for address in slide::references(cache, target)? {
+        // Uniqued GOTs of the cache builder are outside every image.
+        let got = index.image_at(address).is_none_or(|(_, image)| {
+            image
+                .section_at(address)
+                .is_some_and(|s| GOT_SECTIONS.contains(&s.name.as_str()))
+        });
+        if got {
+            slots.insert(address);
+        }
+        points.push(HookPoint {
+            kind: if got {
+                HookKind::GotSlot
+            } else {
+                HookKind::Pointer
+            },
+            address,
+            via: None,
+        });
+    }
+
+    let mut stubs = HashMap::new();
+    for_each_code_word(cache, |pc, insn| {
+        // Only adrp starts the sequences looked for.
+        if insn & 0x9F00_0000 != 0x9000_0000 {
+            return;
+        }
+        let Effect::Address { rd, addr: page } = arm64::decode(insn, pc).effect else {
+            return;
+        };
+        let Some(next) = vm::read_u32(cache, pc + 4) else {
+            return;
+        };
+        let (address, loads) = match arm64::decode(next, pc + 4).effect {
+            Effect::AddImm { rn, imm, .. } if rn == rd => (page + imm, false),
+            Effect::Load { rn, offset, .. } if rn == rd => (page + offset, true),
+            _ => return,
+        };
+        let stub = STUB_REGISTERS.contains(&rd);
+        if address == target && !loads {
+            let kind = if stub {
+                stubs.insert(pc, HookKind::StubCall);
+                HookKind::Stub
+            } else {
+                HookKind::AddressTaken
+            };
+            points.push(HookPoint {
+                kind,
+                address: pc,
+                via: None,
+            });
+        } else if stub && slots.contains(&address) {
+            // adrp x16, slot@PAGE; ldr x16, [x16, slot@PAGEOFF], or computing the slot address
+            // in x17 first for authenticated stubs.
+            stubs.insert(pc, HookKind::StubCall);
+            points.push(HookPoint {
+                kind: HookKind::Stub,
+                address: pc,
+                via: Some(address),
+            });
+        }
+    });
+
+    if let Some(selector) = method_selector(cache, image, target) {
+        for stub in objc_stubs(index, &selector) {
+            stubs.insert(stub, HookKind::ObjcStubCall);
+            points.push(HookPoint {
+                kind: HookKind::ObjcStub,
+                address: stub,
+                via: None,
+            });
+        }
+    }
+
+    for_each_code_word(cache, |pc, insn| {
+        // b and bl, with a 26-bit word offset.
+        if insn & 0x7C00_0000 != 0x1400_0000 {
+            return;
+        }
+        let offset = ((insn & 0x03FF_FFFF) as i64) << 38 >> 36;
+        let destination = pc.wrapping_add_signed(offset);
+        let call = insn & 0x8000_0000 != 0;
+        if destination == target {
+            points.push(HookPoint {
+                kind: if call {
+                    HookKind::Call
+                } else {
+                    HookKind::TailCall
+                },
+                address: pc,
+                via: None,
+            });
+        } else if let Some(&kind) = stubs.get(&destination) {
+            points.push(HookPoint {
+                kind,
+                address: pc,
+                via: Some(destination),
+            });
+        }
+    });
+
+    if let Ok(sites) = patchtable::patch_sites(cache, image, target) {
+        points.extend(sites.into_iter().map(|site| HookPoint {
+            kind: if site.got {
+                HookKind::PatchGot
+            } else {
+                HookKind::PatchSite
+            },
+            address: site.address,
+            via: None,
+        }));
+    }
+
+    points.sort_by_key(|p| (p.kind, p.address));
+    Ok(points)
+}
+
+/// The selector of the ObjC method implemented at `target`, when the image's classes or
+/// categories have one.
+pub fn method_selector(
+    cache: &DyldCache<LittleEndian>,
+    image: usize,
+    target: u64,
+) -> Option<String> {
+    let image = cache.images().nth(image)?;
+    let metadata = ObjcReader::new(cache).image_metadata(&image).ok()?;
+    metadata
+        .classes
+        .iter()
+        .chain(&metadata.categories)
+        .flat_map(|class| class.instance_methods.iter().chain(&class.class_methods))
+        .find(|method| method.imp == target)
+        .map(|method| method.selector.clone())
+}
+
+/// Addresses of the `objc_msgSend` stubs of `selector`, from the `__objc_stubs` sections of the
+/// images. Stubs load the selector into x1 from its selector reference, or compute its address
+/// directly once the cache builder has optimized the reference away.
+fn objc_stubs(index: &CacheIndex, selector: &str) -> Vec<u64> {
+    let cache = index.cache();
+    let pointers = PointerReader::new(cache);
+    let mut stubs = Vec::new();
+    for i in 0..index.len() {
+        for section in &index.image(i).sections {
+            if section.name != "__objc_stubs" {
+                continue;
+            }
+            for pc in (section.start..section.end).step_by(4) {
+                let Some(insn) = vm::read_u32(cache, pc) else {
+                    break;
+                };
+                let Effect::Address { rd, addr: page } = arm64::decode(insn, pc).effect else {
+                    continue;
+                };
+                if rd != SELECTOR_REGISTER {
+                    continue;
+                }
+                let Some(next) = vm::read_u32(cache, pc + 4) else {
+                    continue;
+                };
+                let name = match arm64::decode(next, pc + 4).effect {
+                    Effect::Load { rn, offset, .. } if rn == rd => pointers
+                        .read(page + offset)
+                        .and_then(|selref| vm::read_cstr(cache, selref)),
+                    Effect::AddImm { rn, imm, .. } if rn == rd => vm::read_cstr(cache, page + imm),
+                    _ => None,
+                };
+                if name == Some(selector) {
+                    stubs.push(pc);
+                }
+            }
+        }
+    }
+    stubs.sort_unstable();
+    stubs.dedup();
+    stubs
+}
+
+/// Call `f` with the address and value of every instruction word of the executable mappings.
+fn for_each_code_word(cache: &DyldCache<LittleEndian>, mut f: impl FnMut(u64, u32)) {
+    for mapping in cache.mappings() {
+        if mapping.init_prot() & VM_PROT_EXECUTE == 0 {
+            continue;
+        }
+        let Ok(data) = mapping.data() else {
+            continue;
+        };
+        let data = &data[..std::cmp::min(data.len(), mapping.size() as usize)];
+        for (i, word) in data.chunks_exact(4).enumerate() {
+            f(
+                mapping.address() + i as u64 * 4,
+                u32::from_le_bytes(word.try_into().unwrap()),
+            );
+        }
+    }
+}
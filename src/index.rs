@@ -0,0 +1,196 @@
+use object::LittleEndian;
+use object::read::macho::{DyldCache, DyldCacheImage, MachOFile64};
+use object::{Object, ObjectSection, ObjectSegment};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Parsed Mach-O files kept by an index. Parsing an image walks its load commands and builds
+/// its section table, which adds up when a session keeps returning to the same images.
+const FILE_CACHE_SIZE: usize = 32;
+
+pub struct SegmentEntry {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+pub struct SectionEntry {
+    pub segment: String,
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Metadata for one image, parsed once on first use.
+pub struct ImageEntry {
+    pub path: String,
+    /// Address of the Mach-O header.
+    pub base: u64,
+    pub uuid: Option<[u8; 16]>,
+    pub segments: Vec<SegmentEntry>,
+    pub sections: Vec<SectionEntry>,
+}
+
+impl ImageEntry {
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or("")
+    }
+
+    pub fn segment_at(&self, addr: u64) -> Option<&SegmentEntry> {
+        self.segments
+            .iter()
+            .find(|s| addr >= s.start && addr < s.end)
+    }
+
+    pub fn section_at(&self, addr: u64) -> Option<&SectionEntry> {
+        self.sections
+            .iter()
+            .find(|s| addr >= s.start && addr < s.end)
+    }
+}
+
+/// A lazily populated index of per-image metadata.
+///
+/// Entries are parsed on first access and then shared; every slot is a `OnceLock`, so the index
+/// can be queried from several threads at once (e.g. behind an `Arc` or from scoped threads)
+/// without locking on the read path and without parsing an image twice. Parsed Mach-O files
+/// are kept in a small least-recently-used cache behind a lock, see `file`.
+pub struct CacheIndex<'a> {
+    cache: &'a DyldCache<'a, LittleEndian>,
+    /// The cache's images, collected once so that looking one up does not walk the list.
+    handles: Vec<DyldCacheImage<'a, 'a, LittleEndian>>,
+    images: Vec<OnceLock<ImageEntry>>,
+    /// Sorted segment ranges of canonical (non-alias) images: (start, end, image index).
+    ranges: OnceLock<Vec<(u64, u64, usize)>>,
+    /// Recently parsed files by image index, least recently used first.
+    files: Mutex<Vec<(usize, ParsedFile<'a>)>>,
+}
+
+/// A parsed image, shared between the index and its callers.
+pub type ParsedFile<'a> = Arc<MachOFile64<'a, LittleEndian>>;
+
+// The index is meant to be shared between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CacheIndex<'static>>();
+};
+
+impl<'a> CacheIndex<'a> {
+    pub fn new(cache: &'a DyldCache<'a, LittleEndian>) -> Self {
+        let handles: Vec<_> = cache.images().collect();
+        CacheIndex {
+            cache,
+            images: handles.iter().map(|_| OnceLock::new()).collect(),
+            handles,
+            ranges: OnceLock::new(),
+            files: Mutex::new(Vec::with_capacity(FILE_CACHE_SIZE)),
+        }
+    }
+
+    /// The parsed Mach-O file of an image. The most recently used files are kept, so repeated
+    /// queries on the same images do not parse them again.
+    pub fn file(&self, index: usize) -> Result<ParsedFile<'a>, object::Error> {
+        if let Some(file) = self.cached_file(index) {
+            return Ok(file);
+        }
+        // Parsed without holding the lock, so other threads are not held up meanwhile. Two
+        // threads may both parse the same image; the first one stored wins.
+        let file = Arc::new(MachOFile64::<LittleEndian>::parse_dyld_cache_image(
+            &self.handles[index],
+        )?);
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, existing)) = files.iter().find(|(i, _)| *i == index) {
+            return Ok(existing.clone());
+        }
+        if files.len() == FILE_CACHE_SIZE {
+            files.remove(0);
+        }
+        files.push((index, file.clone()));
+        Ok(file)
+    }
+
+    /// A file from the recently used list, marked as the most recent.
+    fn cached_file(&self, index: usize) -> Option<ParsedFile<'a>> {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let pos = files.iter().position(|(i, _)| *i == index)?;
+        let entry = files.remove(pos);
+        let file = entry.1.clone();
+        files.push(entry);
+        Some(file)
+    }
+
+    pub fn cache(&self) -> &'a DyldCache<'a, LittleEndian> {
+        self.cache
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn image(&self, index: usize) -> &ImageEntry {
+        self.images[index].get_or_init(|| {
+            let image = &self.handles[index];
+            let path = image.path().unwrap_or("").to_string();
+            let base = image.info().address.get(LittleEndian);
+
+            let mut entry = ImageEntry {
+                path,
+                base,
+                uuid: None,
+                segments: Vec::new(),
+                sections: Vec::new(),
+            };
+            if let Ok(obj) = self.file(index) {
+                entry.uuid = obj.mach_uuid().ok().flatten();
+                entry.segments = obj
+                    .segments()
+                    .map(|s| SegmentEntry {
+                        name: s.name().ok().flatten().unwrap_or("").to_string(),
+                        start: s.address(),
+                        end: s.address() + s.size(),
+                    })
+                    .collect();
+                entry.sections = obj
+                    .sections()
+                    .map(|s| SectionEntry {
+                        segment: s.segment_name().ok().flatten().unwrap_or("").to_string(),
+                        name: s.name().unwrap_or("").to_string(),
+                        start: s.address(),
+                        end: s.address() + s.size(),
+                    })
+                    .collect();
+            }
+            entry
+        })
+    }
+
+    /// Find the image whose segments (other than the shared `__LINKEDIT`) contain `addr`.
+    pub fn image_at(&self, addr: u64) -> Option<(usize, &ImageEntry)> {
+        let ranges = self.ranges.get_or_init(|| {
+            let mut seen = HashSet::new();
+            let mut ranges = Vec::new();
+            for index in 0..self.len() {
+                let image = self.image(index);
+                // Aliases share their segments with the canonical image listed first.
+                if !seen.insert(image.base) {
+                    continue;
+                }
+                for segment in &image.segments {
+                    if segment.name != "__LINKEDIT" {
+                        ranges.push((segment.start, segment.end, index));
+                    }
+                }
+            }
+            ranges.sort_by_key(|r| r.0);
+            ranges
+        });
+
+        let pos = ranges.partition_point(|r| r.0 <= addr);
+        let &(_, end, index) = ranges[..pos].last()?;
+        (addr < end).then(|| (index, self.image(index)))
+    }
+}
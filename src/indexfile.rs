@@ -0,0 +1,142 @@
+//! A versioned binary index of the images, sections and symbols of a cache, for tools that
+//! want to map a file and look things up without parsing the cache format. See the README for
+//! the layout.
+
+use object::LittleEndian;
+use object::macho::DyldCacheHeader;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+
+use crate::index::CacheIndex;
+use crate::symbolize::Symbolizer;
+
+pub const MAGIC: &[u8; 8] = b"DSCINDEX";
+pub const VERSION: u32 = 1;
+pub const HEADER_SIZE: u32 = 0x50;
+pub const IMAGE_SIZE: u32 = 32;
+pub const SECTION_SIZE: u32 = 32;
+pub const SYMBOL_SIZE: u32 = 16;
+
+/// Number of records written.
+pub struct Counts {
+    pub images: usize,
+    pub sections: usize,
+    pub symbols: usize,
+}
+
+/// Deduplicated, NUL-terminated strings, with the empty string at offset 0.
+struct Strings {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Strings {
+    fn new() -> Self {
+        Strings {
+            data: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, s: &str) -> Result<u32, Box<dyn Error>> {
+        if s.is_empty() {
+            return Ok(0);
+        }
+        if let Some(&offset) = self.offsets.get(s) {
+            return Ok(offset);
+        }
+        let offset = u32::try_from(self.data.len()).map_err(|_| "String table exceeds 4 GiB")?;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(s.to_string(), offset);
+        Ok(offset)
+    }
+}
+
+/// Write the index of every image of the cache to `out`.
+pub fn write(index: &CacheIndex, out: &mut impl Write) -> Result<Counts, Box<dyn Error>> {
+    let symbolizer = Symbolizer::new(index);
+    let mut strings = Strings::new();
+    let (mut images, mut sections, mut symbols) = (Vec::new(), Vec::new(), Vec::new());
+    // Aliases share the records of the canonical image listed first.
+    let mut ranges: HashMap<u64, [u32; 4]> = HashMap::new();
+
+    for i in 0..index.len() {
+        let image = index.image(i);
+        let range = match ranges.get(&image.base) {
+            Some(&range) => range,
+            None => {
+                let image_index = i as u32;
+                let first_section = (sections.len() / SECTION_SIZE as usize) as u32;
+                for section in &image.sections {
+                    sections.extend_from_slice(&section.start.to_le_bytes());
+                    sections.extend_from_slice(&(section.end - section.start).to_le_bytes());
+                    sections.extend_from_slice(&strings.add(&section.segment)?.to_le_bytes());
+                    sections.extend_from_slice(&strings.add(&section.name)?.to_le_bytes());
+                    sections.extend_from_slice(&image_index.to_le_bytes());
+                    sections.extend_from_slice(&0u32.to_le_bytes());
+                }
+                let first_symbol = (symbols.len() / SYMBOL_SIZE as usize) as u32;
+                let image_symbols = symbolizer.symbols(i);
+                for (address, name) in image_symbols {
+                    symbols.extend_from_slice(&address.to_le_bytes());
+                    symbols.extend_from_slice(&strings.add(name)?.to_le_bytes());
+                    symbols.extend_from_slice(&image_index.to_le_bytes());
+                }
+                let range = [
+                    first_section,
+                    image.sections.len() as u32,
+                    first_symbol,
+                    image_symbols.len() as u32,
+                ];
+                ranges.insert(image.base, range);
+                range
+            }
+        };
+        images.extend_from_slice(&image.base.to_le_bytes());
+        images.extend_from_slice(&strings.add(&image.path)?.to_le_bytes());
+        for value in range {
+            images.extend_from_slice(&value.to_le_bytes());
+        }
+        images.extend_from_slice(&0u32.to_le_bytes());
+    }
+
+    let uuid = DyldCacheHeader::<LittleEndian>::parse(index.cache().data())
+        .map(|header| header.uuid)
+        .unwrap_or_default();
+    let counts = Counts {
+        images: images.len() / IMAGE_SIZE as usize,
+        sections: sections.len() / SECTION_SIZE as usize,
+        symbols: symbols.len() / SYMBOL_SIZE as usize,
+    };
+    let count = |n: usize| u32::try_from(n).map_err(|_| "Too many index records");
+    let images_offset = HEADER_SIZE as u64;
+    let sections_offset = images_offset + images.len() as u64;
+    let symbols_offset = sections_offset + sections.len() as u64;
+    let strings_offset = symbols_offset + symbols.len() as u64;
+
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&uuid);
+    header.extend_from_slice(&count(counts.images)?.to_le_bytes());
+    header.extend_from_slice(&count(counts.sections)?.to_le_bytes());
+    header.extend_from_slice(&count(counts.symbols)?.to_le_bytes());
+    header.extend_from_slice(&count(strings.data.len())?.to_le_bytes());
+    for offset in [
+        images_offset,
+        sections_offset,
+        symbols_offset,
+        strings_offset,
+    ] {
+        header.extend_from_slice(&offset.to_le_bytes());
+    }
+    debug_assert_eq!(header.len(), HEADER_SIZE as usize);
+
+    for part in [&header, &images, &sections, &symbols, &strings.data] {
+        out.write_all(part)?;
+    }
+    Ok(counts)
+}
@@ -0,0 +1,141 @@
+//! Recognize files that are mistaken for a dyld shared cache, kernelcaches in particular, to
+//! say what they are and which tools handle them instead of failing to parse them.
+
+use object::LittleEndian;
+use object::macho::{
+    FAT_MAGIC, FAT_MAGIC_64, MH_BUNDLE, MH_DYLIB, MH_EXECUTE, MH_FILESET, MH_KEXT_BUNDLE, MH_MAGIC,
+    MH_MAGIC_64, MachHeader64,
+};
+use object::read::macho::{LoadCommandVariant, MachHeader};
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// A file format other than a dyld shared cache.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A Mach-O fileset, the format of kernelcaches since iOS 15 and macOS 11.
+    Fileset,
+    /// A thin Mach-O file with its `filetype`.
+    MachO(u32),
+    /// A universal (fat) binary.
+    Universal,
+    /// An IMG4 payload, as kernelcaches and firmware ship in IPSWs.
+    Img4,
+    /// A kernelcache compressed with LZFSE or LZSS, as found in older IPSWs.
+    Compressed,
+}
+
+impl FileKind {
+    /// Why the file is not a cache and what to use for it instead.
+    pub fn advice(self) -> String {
+        match self {
+            FileKind::Fileset => "is a fileset kernelcache (MH_FILESET), not a dyld shared cache. \
+                `dsc images` lists its entries; use `ipsw kernel` or `kmutil` to extract and \
+                inspect kexts"
+                .to_string(),
+            FileKind::MachO(MH_EXECUTE) => "is a Mach-O executable, not a dyld shared cache. \
+                For a kernel or prelinked kernelcache, use `ipsw kernel`; otherwise `otool` or \
+                `nm`"
+                .to_string(),
+            FileKind::MachO(filetype) => format!(
+                "is a Mach-O {}, not a dyld shared cache. Use `otool` or `nm` on it, or point \
+                 this tool at the cache the image comes from",
+                match filetype {
+                    MH_DYLIB => "dylib",
+                    MH_BUNDLE => "bundle",
+                    MH_KEXT_BUNDLE => "kext",
+                    _ => "file",
+                }
+            ),
+            FileKind::Universal => "is a universal binary, not a dyld shared cache. Use `lipo` \
+                to extract a slice and `otool` to inspect it"
+                .to_string(),
+            FileKind::Img4 => "is an IMG4 payload (IM4P), not a dyld shared cache. Extract it \
+                with `ipsw img4 extract` or `img4tool`; a kernelcache then needs a kernel tool \
+                such as `ipsw kernel`"
+                .to_string(),
+            FileKind::Compressed => "is a compressed kernelcache, not a dyld shared cache. \
+                Decompress it with `ipsw kernel dec`, then use a kernel tool"
+                .to_string(),
+        }
+    }
+}
+
+/// Identify a file from its first bytes. `None` for dyld shared caches and unknown formats.
+pub fn identify(data: &[u8]) -> Option<FileKind> {
+    if data.starts_with(b"dyld_v") {
+        return None;
+    }
+    let word = |i: usize| {
+        data.get(i..i + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    };
+    match word(0)? {
+        MH_MAGIC_64 if word(12) == Some(MH_FILESET) => return Some(FileKind::Fileset),
+        MH_MAGIC_64 | MH_MAGIC => return word(12).map(FileKind::MachO),
+        _ => {}
+    }
+    let magic = u32::from_be_bytes(data[..4].try_into().unwrap());
+    if magic == FAT_MAGIC || magic == FAT_MAGIC_64 {
+        return Some(FileKind::Universal);
+    }
+    // A DER sequence whose first element is the "IM4P" string.
+    if data[0] == 0x30
+        && data
+            .get(..16)
+            .is_some_and(|h| h.windows(4).any(|w| w == b"IM4P"))
+    {
+        return Some(FileKind::Img4);
+    }
+    if data.starts_with(b"complzss") || data.starts_with(b"bvx2") || data.starts_with(b"bvx1") {
+        return Some(FileKind::Compressed);
+    }
+    None
+}
+
+/// Identify the file at `path` from its first bytes.
+pub fn identify_file(path: &Path) -> Option<FileKind> {
+    let mut data = [0u8; 32];
+    let len = File::open(path).ok()?.read(&mut data).ok()?;
+    identify(&data[..len])
+}
+
+/// An entry of a fileset (`LC_FILESET_ENTRY`): the kernel or a kext.
+pub struct FilesetEntry {
+    /// Bundle identifier, or `com.apple.kernel`.
+    pub id: String,
+    pub vmaddr: u64,
+    pub fileoff: u64,
+}
+
+/// Entries of the fileset at `path`, or `None` when the file is not a fileset. Only the Mach-O
+/// header and load commands are read.
+pub fn fileset_entries(path: &Path) -> Result<Option<Vec<FilesetEntry>>, Box<dyn Error>> {
+    if path.is_dir() || identify_file(path) != Some(FileKind::Fileset) {
+        return Ok(None);
+    }
+    let e = LittleEndian;
+    let mut file = File::open(path)?;
+    let header_size = size_of::<MachHeader64<LittleEndian>>();
+    let mut data = vec![0u8; header_size];
+    file.read_exact(&mut data)?;
+    let sizeofcmds = u32::from_le_bytes(data[20..24].try_into().unwrap());
+    data.resize(header_size + sizeofcmds as usize, 0);
+    file.read_exact(&mut data[header_size..])?;
+
+    let header = MachHeader64::<LittleEndian>::parse(&*data, 0)?;
+    let mut entries = Vec::new();
+    let mut commands = header.load_commands(e, &*data, 0)?;
+    while let Some(command) = commands.next()? {
+        if let LoadCommandVariant::FilesetEntry(entry) = command.variant()? {
+            entries.push(FilesetEntry {
+                id: String::from_utf8_lossy(command.string(e, entry.entry_id)?).into_owned(),
+                vmaddr: entry.vmaddr.get(e),
+                fileoff: entry.fileoff.get(e),
+            });
+        }
+    }
+    Ok(Some(entries))
+}
@@ -0,0 +1,316 @@
+//! User-defined labels for address ranges, loaded from JSON or CSV files so that manual
+//! annotations can be shared and shown by `a2l`, `dump` and `disasm` next to cache symbols.
+
+use object::LittleEndian;
+use object::read::macho::DyldCache;
+
+use crate::json::Json;
+use crate::resolve::find_image;
+
+/// A named range `[start, end)` of unslid cache addresses.
+pub struct Label {
+    pub start: u64,
+    pub end: u64,
+    pub name: String,
+    pub comment: Option<String>,
+}
+
+/// Labels from one or more files, sorted by start address.
+#[derive(Default)]
+pub struct Labels {
+    labels: Vec<Label>,
+}
+
+impl Labels {
+    /// Parse labels from `text`, a JSON array or CSV lines, and add them. Addresses are
+    /// numbers, hex strings or `module+0xOFF` relative to an image of `cache`:
+    ///
+    /// ```json
+    /// [
+    ///   {"address": "0x1A2B3C000", "size": 64, "name": "parse_header"},
+    ///   {"address": "libobjc.A.dylib+0x4000", "end": "libobjc.A.dylib+0x4100",
+    ///    "name": "cache_fill", "comment": "inlined by the compiler"}
+    /// ]
+    /// ```
+    ///
+    /// CSV lines are `address,size,name[,comment]`, with `#` comments and an optional
+    /// `address,...` header. A label without a size covers a single byte.
+    pub fn add(&mut self, text: &str, cache: &DyldCache<LittleEndian>) -> Result<(), String> {
+        let address = |s: &str| parse_address(s, cache);
+        let parsed = if text.trim_start().starts_with('[') {
+            parse_json(text, &address)?
+        } else {
+            parse_csv(text, &address)?
+        };
+        self.labels.extend(parsed);
+        self.labels
+            .sort_by_key(|l| (l.start, std::cmp::Reverse(l.end)));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The innermost label containing `addr`.
+    pub fn at(&self, addr: u64) -> Option<&Label> {
+        let pos = self.labels.partition_point(|l| l.start <= addr);
+        self.labels[..pos]
+            .iter()
+            .filter(|l| addr < l.end)
+            .min_by_key(|l| l.end - l.start)
+    }
+
+    /// Labels overlapping `[start, end)`, by start address.
+    pub fn overlapping(&self, start: u64, end: u64) -> impl Iterator<Item = &Label> {
+        self.labels
+            .iter()
+            .filter(move |l| l.start < end && start < l.end)
+    }
+}
+
+fn parse_json(
+    text: &str,
+    address: &dyn Fn(&str) -> Result<u64, String>,
+) -> Result<Vec<Label>, String> {
+    let json = Json::parse(text)?;
+    let entries = json
+        .as_array()
+        .ok_or("Label file must contain a JSON array")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let value = |key: &str| match entry.get(key) {
+                None => Ok(None),
+                Some(Json::String(s)) => address(s).map(Some),
+                Some(value) => value
+                    .as_u64()
+                    .map(Some)
+                    .ok_or(format!("'{}' must be a number or a string", key)),
+            };
+            let string = |key: &str| match entry.get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .map(|s| Some(s.to_string()))
+                    .ok_or(format!("'{}' must be a string", key)),
+            };
+            let label = || {
+                let name = string("name")?.ok_or("missing 'name'")?;
+                let start = value("address")?.ok_or("missing 'address'")?;
+                let end = match (value("end")?, entry.get("size")) {
+                    (Some(end), None) => end,
+                    (None, Some(Json::String(size))) => start.saturating_add(parse_number(size)?),
+                    (None, Some(size)) => {
+                        start.saturating_add(size.as_u64().ok_or("'size' must be a number")?)
+                    }
+                    (None, None) => start.saturating_add(1),
+                    (Some(_), Some(_)) => {
+                        return Err("only one of 'end' or 'size' may be given".into());
+                    }
+                };
+                make_label(start, end, name, string("comment")?)
+            };
+            label().map_err(|e: String| format!("Label {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+fn parse_csv(
+    text: &str,
+    address: &dyn Fn(&str) -> Result<u64, String>,
+) -> Result<Vec<Label>, String> {
+    let mut labels = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("address,") {
+            continue;
+        }
+        let label = || {
+            let fields: Vec<&str> = line.splitn(4, ',').map(str::trim).collect();
+            let [start, size, name, rest @ ..] = fields.as_slice() else {
+                return Err("expected address,size,name[,comment]".to_string());
+            };
+            let start = address(start)?;
+            let size = if size.is_empty() {
+                1
+            } else {
+                parse_number(size)?
+            };
+            let comment = rest
+                .first()
+                .filter(|c| !c.is_empty())
+                .map(|c| c.to_string());
+            make_label(start, start.saturating_add(size), name.to_string(), comment)
+        };
+        labels.push(label().map_err(|e| format!("Line {}: {}", i + 1, e))?);
+    }
+    Ok(labels)
+}
+
+fn make_label(
+    start: u64,
+    end: u64,
+    name: String,
+    comment: Option<String>,
+) -> Result<Label, String> {
+    if name.is_empty() {
+        return Err("empty name".to_string());
+    }
+    if end <= start {
+        return Err(format!(
+            "empty range 0x{:X}-0x{:X} for {}",
+            start, end, name
+        ));
+    }
+    Ok(Label {
+        start,
+        end,
+        name,
+        comment,
+    })
+}
+
+/// A number, or `module+0xOFF` relative to the base of an image.
+fn parse_address(input: &str, cache: &DyldCache<LittleEndian>) -> Result<u64, String> {
+    let input = input.trim();
+    match input.rsplit_once('+') {
+        Some((module, offset)) => {
+            let image = find_image(cache, module.trim()).map_err(|e| e.to_string())?;
+            image
+                .info()
+                .address
+                .get(LittleEndian)
+                .checked_add(parse_number(offset)?)
+                .ok_or_else(|| format!("'{}' is past the end of the address space", input))
+        }
+        None => parse_number(input),
+    }
+}
+
+fn parse_number(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    match input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    {
+        Some(digits) => u64::from_str_radix(digits, 16),
+        None => input.parse(),
+    }
+    .map_err(|_| format!("invalid number '{}'", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Numbers, or `libfoo+0xOFF` for an image at 0x1000.
+    fn address(s: &str) -> Result<u64, String> {
+        match s.split_once('+') {
+            Some(("libfoo", offset)) => Ok(0x1000 + parse_number(offset)?),
+            Some((module, _)) => Err(format!("no image {}", module)),
+            None => parse_number(s),
+        }
+    }
+
+    fn ranges(labels: &[Label]) -> Vec<(u64, u64, &str, Option<&str>)> {
+        labels
+            .iter()
+            .map(|l| (l.start, l.end, l.name.as_str(), l.comment.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn json_labels() {
+        let text = r#"[
+            {"address": "0x2000", "size": 64, "name": "parse_header"},
+            {"address": "libfoo+0x10", "end": "libfoo+0x20", "name": "fill", "comment": "hot"},
+            {"address": 12288, "size": "0x10", "name": "table"},
+            {"address": 16384, "name": "byte"}
+        ]"#;
+        assert_eq!(
+            ranges(&parse_json(text, &address).unwrap()),
+            [
+                (0x2000, 0x2040, "parse_header", None),
+                (0x1010, 0x1020, "fill", Some("hot")),
+                (0x3000, 0x3010, "table", None),
+                (0x4000, 0x4001, "byte", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_json_labels() {
+        for (text, error) in [
+            (r#"{"address": 1}"#, "must contain a JSON array"),
+            (r#"[{"address": 1}]"#, "Label 1: missing 'name'"),
+            (r#"[{"name": "a"}]"#, "Label 1: missing 'address'"),
+            (
+                r#"[{"address": 1, "end": 2, "size": 1, "name": "a"}]"#,
+                "only one of",
+            ),
+            (r#"[{"address": 2, "end": 1, "name": "a"}]"#, "empty range"),
+            (
+                r#"[{"address": true, "name": "a"}]"#,
+                "must be a number or a string",
+            ),
+            (r#"[{"address": 1, "name": 2}]"#, "'name' must be a string"),
+            (
+                r#"[{"address": "libbar+0x10", "name": "a"}]"#,
+                "no image libbar",
+            ),
+            (
+                r#"[{"address": "0xzz", "name": "a"}]"#,
+                "invalid number '0xzz'",
+            ),
+        ] {
+            let result = parse_json(text, &address);
+            assert!(result.err().unwrap().contains(error), "{}", text);
+        }
+    }
+
+    #[test]
+    fn csv_labels() {
+        let text = "\
+# exported from a disassembler
+address,size,name,comment
+0x2000,64,parse_header
+libfoo+0x10, 0x10 , fill, hot, inlined
+0x3000,,byte,
+";
+        assert_eq!(
+            ranges(&parse_csv(text, &address).unwrap()),
+            [
+                (0x2000, 0x2040, "parse_header", None),
+                (0x1010, 0x1020, "fill", Some("hot, inlined")),
+                (0x3000, 0x3001, "byte", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_csv_labels() {
+        for (text, error) in [
+            ("0x2000,64", "Line 1: expected address,size,name"),
+            ("\n0x2000,64,", "Line 2: empty name"),
+            ("0x2000,0,a", "empty range"),
+            ("0x2000,x,a", "invalid number 'x'"),
+            ("0xFFFFFFFFFFFFFFFF,,a", "empty range"),
+        ] {
+            let result = parse_csv(text, &address);
+            assert!(result.err().unwrap().contains(error), "{}", text);
+        }
+    }
+
+    #[test]
+    fn innermost_label() {
+        let labels = Labels {
+            labels: parse_csv("0x1000,0x100,outer\n0x1010,0x10,inner", &address).unwrap(),
+        };
+        assert_eq!(labels.at(0x1018).map(|l| l.name.as_str()), Some("inner"));
+        assert_eq!(labels.at(0x1020).map(|l| l.name.as_str()), Some("outer"));
+        assert!(labels.at(0x1100).is_none());
+        assert_eq!(labels.overlapping(0x1018, 0x1200).count(), 2);
+    }
+}
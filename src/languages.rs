@@ -0,0 +1,80 @@
+use clap::ValueEnum;
+use object::read::macho::{DyldCache, DyldCacheImage};
+use object::{LittleEndian, Object, ObjectSection, ObjectSymbol};
+use std::error::Error;
+use std::fmt;
+
+use crate::exports;
+use crate::objc::ObjcReader;
+
+/// Prefix of C++ `typeinfo` symbols.
+const CXX_TYPEINFO: &str = "__ZTI";
+
+/// Implementation language of an image, from the runtime metadata it carries.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    /// Swift metadata and no plain ObjC classes
+    Swift,
+    /// Plain ObjC classes and no Swift metadata
+    Objc,
+    /// Both Swift metadata and plain ObjC classes
+    Mixed,
+    /// Neither; C or C++
+    Native,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Swift => "swift-only",
+            Kind::Objc => "objc-only",
+            Kind::Mixed => "mixed",
+            Kind::Native => "native",
+        })
+    }
+}
+
+pub struct Languages {
+    /// The image has `__swift5_*` metadata sections.
+    pub swift: bool,
+    /// The image defines ObjC classes that are not Swift classes.
+    pub objc: bool,
+    /// The image defines or exports C++ `typeinfo`.
+    pub cxx: bool,
+}
+
+impl Languages {
+    pub fn kind(&self) -> Kind {
+        match (self.swift, self.objc) {
+            (true, false) => Kind::Swift,
+            (false, true) => Kind::Objc,
+            (true, true) => Kind::Mixed,
+            (false, false) => Kind::Native,
+        }
+    }
+}
+
+/// Detect the languages an image is implemented in. Swift classes are also listed in
+/// `__objc_classlist`, so only classes without the Swift bits in `class_t::data` count as ObjC.
+pub fn image_languages(
+    cache: &DyldCache<LittleEndian>,
+    reader: &ObjcReader,
+    image: &DyldCacheImage<LittleEndian>,
+) -> Result<Languages, Box<dyn Error>> {
+    let file = image.parse_object()?;
+    let swift = file
+        .sections()
+        .any(|s| s.size() != 0 && s.name().is_ok_and(|n| n.starts_with("__swift5_")));
+    let (objc, _) = reader.class_languages(image)?;
+    let cxx = file
+        .symbols()
+        .any(|s| s.name().is_ok_and(|n| n.starts_with(CXX_TYPEINFO)))
+        || exports::image_exports(cache, image)?
+            .iter()
+            .any(|e| e.name.starts_with(CXX_TYPEINFO));
+    Ok(Languages {
+        swift,
+        objc: objc != 0,
+        cxx,
+    })
+}
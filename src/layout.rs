@@ -0,0 +1,545 @@
+use object::LittleEndian;
+use object::macho;
+use object::read::macho::DyldCache;
+
+use crate::cachetype::platform_name;
+use crate::explain::load_command_name;
+use crate::utils::format_uuid;
+use crate::vm;
+
+/// A field of a Mach-O structure, at `offset` from the start of the header.
+pub struct Field {
+    pub offset: usize,
+    pub size: usize,
+    pub name: &'static str,
+    pub value: String,
+}
+
+/// The header, a load command or a section of a Mach-O, broken down into its fields.
+pub struct Struct {
+    pub name: String,
+    pub offset: usize,
+    pub fields: Vec<Field>,
+}
+
+/// Size of `mach_header_64`.
+pub const HEADER_SIZE: usize = 32;
+
+/// Read the header and load commands of the image at `address`, as far as `sizeofcmds` says
+/// and the mapping they are in allows.
+pub fn header_data(cache: &DyldCache<LittleEndian>, address: u64) -> Result<Vec<u8>, String> {
+    let header = vm::read_mapped(cache, address, HEADER_SIZE as u64)?;
+    let sizeofcmds = header
+        .get(20..24)
+        .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()));
+    vm::read_mapped(cache, address, (HEADER_SIZE as u64) + u64::from(sizeofcmds))
+}
+
+/// Break down a 64-bit Mach-O header and the load commands following it, as a binary template
+/// view would. `data` starts at the header; fields that would run past its end, or past the
+/// `cmdsize` of their command, are not decoded.
+pub fn annotate(data: &[u8]) -> Vec<Struct> {
+    let mut structs = Vec::new();
+    let mut header = Reader::new(data, 0, data.len().min(HEADER_SIZE));
+    header.u32("magic", hex);
+    header.u32("cputype", hex);
+    header.u32("cpusubtype", hex);
+    header.u32("filetype", dec);
+    let ncmds = header.u32("ncmds", dec).unwrap_or(0);
+    let sizeofcmds = header.u32("sizeofcmds", hex).unwrap_or(0) as usize;
+    header.u32("flags", hex);
+    header.u32("reserved", hex);
+    structs.push(header.finish("mach_header_64".to_string()));
+
+    let end = data.len().min(HEADER_SIZE + sizeofcmds);
+    let mut offset = HEADER_SIZE;
+    for i in 0..ncmds {
+        if offset + 8 > end {
+            break;
+        }
+        let cmd = read_u32(data, offset);
+        let cmdsize = read_u32(data, offset + 4) as usize;
+        // A command too small to hold its own header would make the walk loop forever.
+        let size = cmdsize.max(8).min(end - offset);
+        let mut r = Reader::new(data, offset, offset + size);
+        r.u32("cmd", load_command_name);
+        r.u32("cmdsize", hex);
+        let name = format!("{} ({} of {})", load_command_name(cmd), i + 1, ncmds);
+        let sections = match cmd {
+            macho::LC_SEGMENT_64 => segment(&mut r),
+            _ => {
+                command(&mut r, cmd);
+                0
+            }
+        };
+        structs.push(r.finish(name));
+        // Sections follow the segment command they belong to, inside its `cmdsize`.
+        let mut section_offset = offset + 72;
+        for _ in 0..sections {
+            if section_offset + 80 > offset + size {
+                break;
+            }
+            let mut s = Reader::new(data, section_offset, section_offset + 80);
+            let sectname = s.fixed_string("sectname", 16);
+            s.fixed_string("segname", 16);
+            s.u64("addr", hex64);
+            s.u64("size", hex64);
+            s.u32("offset", hex);
+            s.u32("align", |v| format!("2^{}", v));
+            s.u32("reloff", hex);
+            s.u32("nreloc", dec);
+            s.u32("flags", hex);
+            s.u32("reserved1", hex);
+            s.u32("reserved2", hex);
+            s.u32("reserved3", hex);
+            structs.push(s.finish(format!("section_64 {}", sectname)));
+            section_offset += 80;
+        }
+        offset += size;
+    }
+    structs
+}
+
+/// Check that the load commands following a 64-bit Mach-O header fit in `sizeofcmds` and in
+/// `data`, and that each `cmdsize` can hold the structure of its command. Errors name the index
+/// of the offending command.
+pub fn validate(data: &[u8]) -> Result<(), String> {
+    if data.len() < HEADER_SIZE {
+        return Err(format!(
+            "Mach-O header truncated: 0x{:X} of 0x{:X} bytes mapped",
+            data.len(),
+            HEADER_SIZE
+        ));
+    }
+    let ncmds = read_u32(data, 16) as usize;
+    let sizeofcmds = read_u32(data, 20) as usize;
+    if HEADER_SIZE + sizeofcmds > data.len() {
+        return Err(format!(
+            "sizeofcmds 0x{:X} runs past the 0x{:X} bytes mapped after the header",
+            sizeofcmds,
+            data.len() - HEADER_SIZE
+        ));
+    }
+    let end = HEADER_SIZE + sizeofcmds;
+    let mut offset = HEADER_SIZE;
+    for i in 0..ncmds {
+        let command = |message: String| {
+            let cmd = if offset + 4 <= end {
+                load_command_name(read_u32(data, offset))
+            } else {
+                "?".to_string()
+            };
+            format!("Load command {} ({}): {}", i, cmd, message)
+        };
+        if offset + 8 > end {
+            return Err(command(format!(
+                "starts at 0x{:X}, past sizeofcmds 0x{:X} ({} commands declared)",
+                offset - HEADER_SIZE,
+                sizeofcmds,
+                ncmds
+            )));
+        }
+        let cmd = read_u32(data, offset);
+        let cmdsize = read_u32(data, offset + 4) as usize;
+        if cmdsize < 8 || !cmdsize.is_multiple_of(8) {
+            return Err(command(format!(
+                "cmdsize 0x{:X} is not a multiple of 8 of at least 8 bytes",
+                cmdsize
+            )));
+        }
+        if offset + cmdsize > end {
+            return Err(command(format!(
+                "cmdsize 0x{:X} runs past sizeofcmds 0x{:X}",
+                cmdsize, sizeofcmds
+            )));
+        }
+        let command_data = &data[offset..offset + cmdsize];
+        if let Some((size, structure)) = expected_size(cmd, command_data)
+            && cmdsize < size
+        {
+            return Err(command(format!(
+                "cmdsize 0x{:X} is smaller than the 0x{:X} bytes of its {}",
+                cmdsize, size, structure
+            )));
+        }
+        offset += cmdsize;
+    }
+    Ok(())
+}
+
+/// Size a command needs for its structure, including the sections of a segment, the tools of
+/// a build version and the string of a command naming a dylib or path.
+fn expected_size(cmd: u32, data: &[u8]) -> Option<(usize, &'static str)> {
+    // Count fields are only read when the fixed part of the structure is there.
+    let count = |at: usize| {
+        data.get(at..at + 4)
+            .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+    };
+    let lc_str = |fixed: usize, structure| {
+        // The string must start inside the command.
+        let offset = count(8).max(fixed);
+        (offset + 1, structure)
+    };
+    Some(match cmd {
+        macho::LC_SEGMENT_64 if data.len() < 72 => (72, "segment_command_64"),
+        macho::LC_SEGMENT_64 => (72 + count(64) * 80, "segment_command_64 and sections"),
+        macho::LC_ID_DYLIB
+        | macho::LC_LOAD_DYLIB
+        | macho::LC_LOAD_WEAK_DYLIB
+        | macho::LC_REEXPORT_DYLIB
+        | macho::LC_LOAD_UPWARD_DYLIB
+        | macho::LC_LAZY_LOAD_DYLIB => lc_str(24, "dylib_command and name"),
+        macho::LC_SUB_FRAMEWORK
+        | macho::LC_SUB_UMBRELLA
+        | macho::LC_SUB_CLIENT
+        | macho::LC_SUB_LIBRARY
+        | macho::LC_RPATH => lc_str(12, "command and name"),
+        macho::LC_UUID => (24, "uuid_command"),
+        macho::LC_SYMTAB => (24, "symtab_command"),
+        macho::LC_DYSYMTAB => (80, "dysymtab_command"),
+        macho::LC_DYLD_INFO | macho::LC_DYLD_INFO_ONLY => (48, "dyld_info_command"),
+        macho::LC_FUNCTION_STARTS
+        | macho::LC_DATA_IN_CODE
+        | macho::LC_CODE_SIGNATURE
+        | macho::LC_SEGMENT_SPLIT_INFO
+        | macho::LC_DYLD_EXPORTS_TRIE
+        | macho::LC_DYLD_CHAINED_FIXUPS
+        | macho::LC_LINKER_OPTIMIZATION_HINT => (16, "linkedit_data_command"),
+        macho::LC_BUILD_VERSION if data.len() < 24 => (24, "build_version_command"),
+        macho::LC_BUILD_VERSION => (24 + count(20) * 8, "build_version_command and tools"),
+        macho::LC_VERSION_MIN_MACOSX | macho::LC_VERSION_MIN_IPHONEOS => {
+            (16, "version_min_command")
+        }
+        macho::LC_SOURCE_VERSION => (16, "source_version_command"),
+        macho::LC_ENCRYPTION_INFO_64 => (24, "encryption_info_command_64"),
+        _ => return None,
+    })
+}
+
+/// Fields of `segment_command_64` after `cmd` and `cmdsize`; returns the number of sections.
+fn segment(r: &mut Reader) -> u32 {
+    r.fixed_string("segname", 16);
+    r.u64("vmaddr", hex64);
+    r.u64("vmsize", hex64);
+    r.u64("fileoff", hex64);
+    r.u64("filesize", hex64);
+    r.u32("maxprot", prot);
+    r.u32("initprot", prot);
+    let nsects = r.u32("nsects", dec).unwrap_or(0);
+    r.u32("flags", hex);
+    // The sections are shown as structures of their own.
+    r.pos = r.end.min(r.pos + nsects as usize * 80);
+    nsects
+}
+
+/// Fields of the commands other than segments, after `cmd` and `cmdsize`.
+fn command(r: &mut Reader, cmd: u32) {
+    match cmd {
+        macho::LC_ID_DYLIB
+        | macho::LC_LOAD_DYLIB
+        | macho::LC_LOAD_WEAK_DYLIB
+        | macho::LC_REEXPORT_DYLIB
+        | macho::LC_LOAD_UPWARD_DYLIB
+        | macho::LC_LAZY_LOAD_DYLIB => {
+            let name = r.u32("name.offset", hex);
+            r.u32("timestamp", dec);
+            r.u32("current_version", version);
+            r.u32("compatibility_version", version);
+            r.lc_str("name", name);
+        }
+        macho::LC_SUB_FRAMEWORK
+        | macho::LC_SUB_UMBRELLA
+        | macho::LC_SUB_CLIENT
+        | macho::LC_SUB_LIBRARY
+        | macho::LC_RPATH => {
+            let name = r.u32("name.offset", hex);
+            r.lc_str("name", name);
+        }
+        macho::LC_UUID => {
+            if let Some(bytes) = r.bytes(16) {
+                let uuid: [u8; 16] = bytes.try_into().unwrap();
+                r.push(16, "uuid", format_uuid(&uuid));
+            }
+        }
+        macho::LC_SYMTAB => {
+            r.u32("symoff", hex);
+            r.u32("nsyms", dec);
+            r.u32("stroff", hex);
+            r.u32("strsize", hex);
+        }
+        macho::LC_DYSYMTAB => {
+            for name in [
+                "ilocalsym",
+                "nlocalsym",
+                "iextdefsym",
+                "nextdefsym",
+                "iundefsym",
+                "nundefsym",
+                "tocoff",
+                "ntoc",
+                "modtaboff",
+                "nmodtab",
+                "extrefsymoff",
+                "nextrefsyms",
+                "indirectsymoff",
+                "nindirectsyms",
+                "extreloff",
+                "nextrel",
+                "locreloff",
+                "nlocrel",
+            ] {
+                r.u32(name, if name.ends_with("off") { hex } else { dec });
+            }
+        }
+        macho::LC_DYLD_INFO | macho::LC_DYLD_INFO_ONLY => {
+            for (off, size) in [
+                ("rebase_off", "rebase_size"),
+                ("bind_off", "bind_size"),
+                ("weak_bind_off", "weak_bind_size"),
+                ("lazy_bind_off", "lazy_bind_size"),
+                ("export_off", "export_size"),
+            ] {
+                r.u32(off, hex);
+                r.u32(size, hex);
+            }
+        }
+        macho::LC_FUNCTION_STARTS
+        | macho::LC_DATA_IN_CODE
+        | macho::LC_CODE_SIGNATURE
+        | macho::LC_SEGMENT_SPLIT_INFO
+        | macho::LC_DYLD_EXPORTS_TRIE
+        | macho::LC_DYLD_CHAINED_FIXUPS
+        | macho::LC_LINKER_OPTIMIZATION_HINT => {
+            r.u32("dataoff", hex);
+            r.u32("datasize", hex);
+        }
+        macho::LC_BUILD_VERSION => {
+            r.u32("platform", platform_name);
+            r.u32("minos", version);
+            r.u32("sdk", version);
+            let ntools = r.u32("ntools", dec).unwrap_or(0);
+            for _ in 0..ntools {
+                if r.u32("tool", dec).is_none() {
+                    break;
+                }
+                r.u32("version", version);
+            }
+        }
+        macho::LC_VERSION_MIN_MACOSX | macho::LC_VERSION_MIN_IPHONEOS => {
+            r.u32("version", version);
+            r.u32("sdk", version);
+        }
+        macho::LC_SOURCE_VERSION => {
+            r.u64("version", |v| {
+                format!(
+                    "{}.{}.{}.{}.{}",
+                    v >> 40,
+                    (v >> 30) & 0x3FF,
+                    (v >> 20) & 0x3FF,
+                    (v >> 10) & 0x3FF,
+                    v & 0x3FF
+                )
+            });
+        }
+        macho::LC_ENCRYPTION_INFO_64 => {
+            r.u32("cryptoff", hex);
+            r.u32("cryptsize", hex);
+            r.u32("cryptid", dec);
+            r.u32("pad", hex);
+        }
+        _ => {}
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    start: usize,
+    pos: usize,
+    end: usize,
+    fields: Vec<Field>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], start: usize, end: usize) -> Self {
+        Reader {
+            data,
+            start,
+            pos: start,
+            end,
+            fields: Vec::new(),
+        }
+    }
+
+    fn bytes(&self, size: usize) -> Option<&'a [u8]> {
+        if self.pos + size > self.end {
+            return None;
+        }
+        Some(&self.data[self.pos..self.pos + size])
+    }
+
+    fn push(&mut self, size: usize, name: &'static str, value: String) {
+        self.fields.push(Field {
+            offset: self.pos,
+            size,
+            name,
+            value,
+        });
+        self.pos += size;
+    }
+
+    fn u32(&mut self, name: &'static str, format: impl Fn(u32) -> String) -> Option<u32> {
+        self.bytes(4)?;
+        let value = read_u32(self.data, self.pos);
+        self.push(4, name, format(value));
+        Some(value)
+    }
+
+    fn u64(&mut self, name: &'static str, format: impl Fn(u64) -> String) -> Option<u64> {
+        let bytes = self.bytes(8)?;
+        let value = u64::from_le_bytes(bytes.try_into().unwrap());
+        self.push(8, name, format(value));
+        Some(value)
+    }
+
+    /// A NUL padded name such as `segname`.
+    fn fixed_string(&mut self, name: &'static str, size: usize) -> String {
+        let Some(bytes) = self.bytes(size) else {
+            return String::new();
+        };
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(size);
+        let value = String::from_utf8_lossy(&bytes[..len]).into_owned();
+        self.push(size, name, value.clone());
+        value
+    }
+
+    /// The string an `lc_str` points to, at `offset` from the start of the command and running
+    /// up to its end, NUL padded.
+    fn lc_str(&mut self, name: &'static str, offset: Option<u32>) {
+        let Some(offset) = offset else {
+            return;
+        };
+        let at = self.start + offset as usize;
+        if at < self.pos || at >= self.end {
+            return;
+        }
+        if at > self.pos {
+            self.push(at - self.pos, "(unknown)", String::new());
+        }
+        let bytes = &self.data[at..self.end];
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let value = String::from_utf8_lossy(&bytes[..len]).into_owned();
+        self.push(self.end - at, name, value);
+    }
+
+    fn finish(mut self, name: String) -> Struct {
+        // Bytes no field accounts for, such as data of an unknown command.
+        if self.pos < self.end {
+            self.push(self.end - self.pos, "(unknown)", String::new());
+        }
+        Struct {
+            name,
+            offset: self.start,
+            fields: self.fields,
+        }
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn hex(value: u32) -> String {
+    format!("0x{:X}", value)
+}
+
+fn hex64(value: u64) -> String {
+    format!("0x{:X}", value)
+}
+
+fn dec(value: u32) -> String {
+    value.to_string()
+}
+
+/// A version packed as `xxxx.yy.zz` nibbles.
+fn version(value: u32) -> String {
+    format!("{}.{}.{}", value >> 16, (value >> 8) & 0xFF, value & 0xFF)
+}
+
+fn prot(value: u32) -> String {
+    let flag = |bit: u32, c: char| if value & bit != 0 { c } else { '-' };
+    format!(
+        "{}{}{}",
+        flag(macho::VM_PROT_READ, 'r'),
+        flag(macho::VM_PROT_WRITE, 'w'),
+        flag(macho::VM_PROT_EXECUTE, 'x')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header declaring `ncmds` and `sizeofcmds`, followed by `commands` as `(cmd, cmdsize,
+    /// bytes written)` with zero bodies.
+    fn macho(ncmds: u32, sizeofcmds: u32, commands: &[(u32, u32, usize)]) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[..4].copy_from_slice(&macho::MH_MAGIC_64.to_le_bytes());
+        data[16..20].copy_from_slice(&ncmds.to_le_bytes());
+        data[20..24].copy_from_slice(&sizeofcmds.to_le_bytes());
+        for &(cmd, cmdsize, len) in commands {
+            let start = data.len();
+            data.resize(start + len, 0);
+            data[start..start + 4].copy_from_slice(&cmd.to_le_bytes());
+            data[start + 4..start + 8].copy_from_slice(&cmdsize.to_le_bytes());
+        }
+        data
+    }
+
+    fn error(data: &[u8]) -> String {
+        validate(data).unwrap_err()
+    }
+
+    #[test]
+    fn valid_commands() {
+        let uuid = (macho::LC_UUID, 24, 24);
+        assert!(validate(&macho(2, 48, &[uuid, uuid])).is_ok());
+        // Bytes past sizeofcmds belong to the image, not to the commands.
+        assert!(validate(&macho(1, 24, &[uuid, uuid])).is_ok());
+    }
+
+    #[test]
+    fn sizeofcmds_past_the_data() {
+        let data = macho(1, 48, &[(macho::LC_UUID, 24, 24)]);
+        assert!(error(&data).starts_with("sizeofcmds 0x30 runs past the 0x18 bytes mapped"));
+        assert!(error(&data[..16]).starts_with("Mach-O header truncated"));
+    }
+
+    #[test]
+    fn misaligned_or_empty_commands() {
+        let data = macho(1, 24, &[(macho::LC_UUID, 0, 24)]);
+        assert_eq!(
+            error(&data),
+            "Load command 0 (LC_UUID): cmdsize 0x0 is not a multiple of 8 of at least 8 bytes"
+        );
+        let data = macho(1, 24, &[(macho::LC_UUID, 20, 24)]);
+        assert!(error(&data).contains("cmdsize 0x14 is not a multiple of 8"));
+    }
+
+    #[test]
+    fn commands_past_sizeofcmds() {
+        let uuid = (macho::LC_UUID, 24, 24);
+        let data = macho(2, 40, &[uuid, uuid]);
+        assert_eq!(
+            error(&data),
+            "Load command 1 (LC_UUID): cmdsize 0x18 runs past sizeofcmds 0x28"
+        );
+        let data = macho(3, 48, &[uuid, uuid]);
+        assert!(error(&data).starts_with("Load command 2 (?): starts at 0x30, past sizeofcmds"));
+    }
+
+    #[test]
+    fn commands_smaller_than_their_structure() {
+        let data = macho(1, 16, &[(macho::LC_UUID, 16, 16)]);
+        assert!(error(&data).contains("smaller than the 0x18 bytes of its uuid_command"));
+    }
+}
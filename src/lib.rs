@@ -0,0 +1,107 @@
+//! Inspect dyld shared caches: open a cache and its subcaches, iterate and symbolicate its
+//! images, read memory through its mappings and extract images. Without the default `std`
+//! feature, only the buffer parsers in [`crate::parsers`] are built.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mapped = dsc::MappedCache::open("/System/Library/dyld", None)?;
+//! let cache = mapped.parse()?;
+//! let index = dsc::CacheIndex::new(&cache);
+//! for i in 0..index.len() {
+//!     println!("0x{:X} {}", index.image(i).base, index.image(i).path);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod parsers;
+
+#[cfg(feature = "std")]
+pub mod anchors;
+#[cfg(feature = "std")]
+pub mod arm64;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod cachetype;
+#[cfg(feature = "std")]
+pub mod disasm;
+#[cfg(feature = "std")]
+pub mod duplicates;
+#[cfg(feature = "std")]
+pub mod dylibs;
+#[cfg(feature = "std")]
+pub mod emu;
+#[cfg(feature = "std")]
+pub(crate) mod explain;
+#[cfg(feature = "std")]
+pub mod exports;
+#[cfg(feature = "std")]
+pub mod extract;
+#[cfg(feature = "fixtures")]
+pub mod fixture;
+#[cfg(feature = "std")]
+pub mod headers;
+#[cfg(feature = "std")]
+pub mod hooks;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(feature = "std")]
+pub mod indexfile;
+#[cfg(feature = "std")]
+pub(crate) mod json;
+#[cfg(feature = "std")]
+pub mod kernel;
+#[cfg(feature = "std")]
+pub mod labels;
+#[cfg(feature = "std")]
+pub mod languages;
+#[cfg(feature = "std")]
+pub mod layout;
+#[cfg(feature = "std")]
+pub mod linkedit;
+#[cfg(feature = "std")]
+pub mod namematch;
+#[cfg(feature = "std")]
+pub mod objc;
+#[cfg(feature = "std")]
+pub mod overrides;
+#[cfg(feature = "std")]
+pub mod patchtable;
+#[cfg(feature = "std")]
+pub mod resolve;
+#[cfg(feature = "std")]
+pub mod select;
+#[cfg(feature = "std")]
+pub mod shim;
+#[cfg(feature = "std")]
+pub mod signatures;
+#[cfg(feature = "std")]
+pub mod simulate;
+#[cfg(feature = "std")]
+pub mod slide;
+#[cfg(feature = "std")]
+pub mod strings;
+#[cfg(feature = "std")]
+pub mod symbolfilter;
+#[cfg(feature = "std")]
+pub mod symbolize;
+#[cfg(feature = "std")]
+pub mod utils;
+#[cfg(feature = "std")]
+pub mod vm;
+
+#[cfg(feature = "std")]
+pub use cache::{CacheHeader, MappedCache, Overlay};
+#[cfg(feature = "std")]
+pub use explain::Explain;
+#[cfg(feature = "std")]
+pub use extract::{ExtractOptions, Extracted, extract};
+#[cfg(feature = "std")]
+pub use index::{CacheIndex, ImageEntry as Image};
+#[cfg(feature = "std")]
+pub use json::Json;
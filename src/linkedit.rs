@@ -0,0 +1,108 @@
+use object::LittleEndian;
+use object::macho::LC_FUNCTION_STARTS;
+use object::read::macho::{DyldCache, LoadCommandVariant, MachOFile64, Segment};
+use object::{Object, ObjectSection};
+use std::error::Error;
+
+pub use crate::parsers::cursor::Cursor;
+use crate::parsers::{starts, unwind};
+
+/// Read `size` bytes at a `__LINKEDIT` file offset (as stored in `symoff`, `dataoff`, ...).
+///
+/// These offsets are relative to the start of the cache file that holds the image's
+/// `__LINKEDIT`, which is not necessarily the main cache file.
+pub fn bytes<'data>(
+    cache: &DyldCache<'data, LittleEndian>,
+    file: &MachOFile64<'data, LittleEndian>,
+    offset: u32,
+    size: u32,
+) -> Result<&'data [u8], Box<dyn Error>> {
+    let e = LittleEndian;
+    let linkedit = file
+        .segments()
+        .map(|s| s.macho_segment())
+        .find(|s| s.name() == b"__LINKEDIT")
+        .ok_or("Image has no __LINKEDIT segment")?;
+    let (data, _) = cache
+        .data_and_offset_for_address(linkedit.vmaddr.get(e))
+        .ok_or("__LINKEDIT is not mapped")?;
+    let start = offset as usize;
+    data.get(start..start + size as usize).ok_or_else(|| {
+        format!(
+            "LINKEDIT range 0x{:X}+0x{:X} is out of bounds",
+            offset, size
+        )
+        .into()
+    })
+}
+
+/// Addresses from `LC_FUNCTION_STARTS`, in ascending order. Empty when the image has none.
+pub fn function_starts(
+    cache: &DyldCache<LittleEndian>,
+    file: &MachOFile64<LittleEndian>,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let mut range = None;
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        if let LoadCommandVariant::LinkeditData(c) = command.variant()?
+            && c.cmd.get(e) == LC_FUNCTION_STARTS
+        {
+            range = Some((c.dataoff.get(e), c.datasize.get(e)));
+        }
+    }
+    let Some((offset, size)) = range else {
+        return Ok(Vec::new());
+    };
+
+    // Deltas start from the address of __TEXT.
+    let data = bytes(cache, file, offset, size)?;
+    Ok(starts::decode(data, text_address(file)?)?)
+}
+
+/// Address of `__TEXT`, which starts with the Mach-O header.
+fn text_address(file: &MachOFile64<LittleEndian>) -> Result<u64, Box<dyn Error>> {
+    Ok(file
+        .segments()
+        .map(|s| s.macho_segment())
+        .find(|s| s.name() == b"__TEXT")
+        .ok_or("Image has no __TEXT segment")?
+        .vmaddr
+        .get(LittleEndian))
+}
+
+/// Where the function starts of an image come from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StartsSource {
+    /// `LC_FUNCTION_STARTS`, which lists every function.
+    FunctionStarts,
+    /// Entries of `__unwind_info`, for images without function starts. Adjacent functions with
+    /// the same unwind encoding share an entry, so some boundaries are missing.
+    UnwindInfo,
+}
+
+impl StartsSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            StartsSource::FunctionStarts => "function-starts",
+            StartsSource::UnwindInfo => "unwind-info",
+        }
+    }
+}
+
+/// Function start addresses in ascending order, from `LC_FUNCTION_STARTS` or, when the image
+/// has none, from `__unwind_info`. Empty when the image has neither.
+pub fn function_boundaries(
+    cache: &DyldCache<LittleEndian>,
+    file: &MachOFile64<LittleEndian>,
+) -> Result<(Vec<u64>, StartsSource), Box<dyn Error>> {
+    let starts = function_starts(cache, file)?;
+    if !starts.is_empty() {
+        return Ok((starts, StartsSource::FunctionStarts));
+    }
+    let Some(section) = file.section_by_name("__unwind_info") else {
+        return Ok((starts, StartsSource::FunctionStarts));
+    };
+    let starts = unwind::function_starts(section.data()?, text_address(file)?)?;
+    Ok((starts, StartsSource::UnwindInfo))
+}
@@ -1,12 +1,35 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use memmap2::Mmap;
-use object::read::macho::DyldCache;
-use object::{LittleEndian, Object, ObjectSection, ObjectSymbol};
+use object::read::macho::{DyldCache, DyldCacheImage, MachOFile64};
+use object::{LittleEndian, Object, ObjectSection, ObjectSegment, ObjectSymbol};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::sync::{Mutex, mpsc};
+use std::time::Instant;
 
-mod utils;
-use utils::print_hex_dump;
+use color::{ColorChoice, Colors, Style};
+use dsc::{
+    Explain, Json, anchors, cachetype, disasm, duplicates, dylibs, emu, exports, extract, headers,
+    hooks, index, indexfile, kernel, labels, languages, layout, linkedit, namematch, objc,
+    overrides, patchtable, resolve, select, shim, signatures, simulate, slide, strings,
+    symbolfilter, symbolize, utils, vm,
+};
+use hexdump::{print_annotated_hex, print_hex_dump};
+use index::CacheIndex;
+use namematch::NameMatcher;
+use resolve::{AddressFormat, find_image, images_by_path, resolve};
+use slide::AuthMode;
+use utils::{format_uuid, wildcard_match};
+
+mod check;
+mod color;
+mod hexdump;
+mod pager;
+mod throttle;
 
 #[derive(Parser)]
 #[command(name = "dsc")]
@@ -14,171 +37,5399 @@ use utils::print_hex_dump;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Show addresses relative to the image containing them (e.g. CoreFoundation+0x1234)
+    #[arg(long, global = true)]
+    relative: bool,
+    /// Report time spent mapping, parsing and executing the command on stderr
+    #[arg(long, global = true)]
+    timing: bool,
+    /// Read bytes of a runtime memory dump in place of the cache contents, from the unslid
+    /// cache address the dump starts at (repeatable, later dumps win)
+    #[arg(long, global = true, value_name = "FILE@ADDR", value_parser = parse_overlay)]
+    overlay: Vec<(String, u64)>,
+    /// Name address ranges from a JSON or CSV file of labels in `a2l`, `symbolicate`, `dump`
+    /// and `disasm` (repeatable)
+    #[arg(long, global = true, value_name = "FILE")]
+    labels: Vec<String>,
+    /// Show demangled Swift and C++ names next to mangled ones in `symbols`, `exports`,
+    /// `lookup`, `symbolicate`, `disasm`, `hooks` and `duplicates`, and match them in `lookup`
+    #[arg(long, global = true)]
+    demangle: bool,
+    /// Hide symbols matching the patterns of this file from `symbols`, `exports` and
+    /// `exports-diff`, or only show those matching its `+` patterns
+    #[arg(long, global = true, value_name = "FILE")]
+    symbol_filter: Option<String>,
+    /// Report images `sections` and `symbols` cannot parse as they are reached, instead of
+    /// together at the end
+    #[arg(long, global = true)]
+    show_errors: bool,
+    /// Cache architecture (e.g. arm64e, x86_64h) to open when a directory is given; defaults to
+    /// the best match for the host
+    #[arg(long, global = true)]
+    arch: Option<String>,
+    /// Color output: `auto` colors a terminal unless NO_COLOR or CLICOLOR=0 is set (or
+    /// CLICOLOR_FORCE forces it)
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Aggregate the output of `images` (with sizes and symbol counts), `objc-diff` and
+    /// `strings-diff` per image, or per `.framework` or `.app` bundle
+    #[arg(long, global = true, value_enum, default_value_t = Group::Image)]
+    group: Group,
+    /// Output format of `images`, `map`, `sections`, `symbols`, `exports`, `imports`, `deps`,
+    /// `rdeps`, `symbolicate`, `strings`, `grep`, `search-string`, `search-bytes`, `xrefs`,
+    /// `hooks`, `duplicates`, `segments`, `dump`, `port`, `function-starts` and the `objc`
+    /// commands other than `ivar-offset` and `headers`; other commands reject `json`
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+impl Commands {
+    /// Whether the command honors the global `--format json`.
+    fn prints_json(&self) -> bool {
+        match self {
+            Commands::Objc { command } => !matches!(
+                command,
+                ObjcCommands::IvarOffset { .. } | ObjcCommands::Headers { .. }
+            ),
+            Commands::Map { .. }
+            | Commands::Images { .. }
+            | Commands::Sections { .. }
+            | Commands::Symbolicate { .. }
+            | Commands::Port { .. }
+            | Commands::Grep { .. }
+            | Commands::SearchString { .. }
+            | Commands::SearchBytes { .. }
+            | Commands::Xrefs { .. }
+            | Commands::Hooks { .. }
+            | Commands::Duplicates { .. }
+            | Commands::FunctionStarts { .. }
+            | Commands::Strings { .. }
+            | Commands::Dump { .. }
+            | Commands::Segments { .. }
+            | Commands::Exports { .. }
+            | Commands::Deps { .. }
+            | Commands::Rdeps { .. }
+            | Commands::Imports { .. }
+            | Commands::Symbols { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Show the cache architecture, CPU subtype and which file was picked from a directory
+    Info { path: String },
+    /// List the system, DriverKit and ExclaveKit caches found in the standard locations
+    Caches,
+    /// List the cache mappings with their protections, flags and the file holding each
+    Map { path: String },
+    Images {
+        path: String,
+        /// Show load address, UUID and alias groups for each image
+        #[arg(short, long)]
+        verbose: bool,
+        /// Only list images whose export trie has this symbol; may be repeated
+        #[arg(long)]
+        exports_symbol: Vec<String>,
+        /// Only list images with this section (`__swift5_types` or `__TEXT,__swift5_types`);
+        /// may be repeated
+        #[arg(long)]
+        has_section: Vec<String>,
+    },
+    Sections {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Also show the mapping holding each section and how its pages are covered by slide
+        /// info
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    Symbols {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only list exported symbols of public frameworks, under /System/Library/Frameworks
+        #[arg(long)]
+        public_only: bool,
+    },
+    /// List the symbols exported by images, from their export trie
+    Exports {
+        path: String,
+        /// Only list exports of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// List the dylibs images depend on, with their compatibility and current versions
+    Deps {
+        path: String,
+        /// Only list dependencies of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// List the images whose load commands link against a dylib
+    Rdeps {
+        path: String,
+        /// Install path or file name of the dylib; aliases of a cached dylib match too
+        dylib: String,
+    },
+    /// List the symbols images import, with the library ordinal and dylib providing each
+    Imports {
+        path: String,
+        /// Only list imports of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find symbols matching wildcard patterns or regular expressions, with the estimated end of
+    /// each
+    Lookup {
+        path: String,
+        /// Symbol names, where `*` matches any run of characters and `?` a single one, or
+        /// regular expressions with --regex
+        #[arg(required = true)]
+        patterns: Vec<String>,
+        /// Only look in this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only match symbols exported by public frameworks, under /System/Library/Frameworks
+        #[arg(long)]
+        public_only: bool,
+        /// Treat patterns as regular expressions matching anywhere in the name
+        #[arg(long)]
+        regex: bool,
+    },
+    /// List images whose segments intersect an address range
+    Overlaps {
+        path: String,
+        #[arg(value_parser = parse_u64)]
+        start: u64,
+        /// End of the range (exclusive)
+        #[arg(value_parser = parse_u64)]
+        end: u64,
+    },
+    /// Resolve an address to the image, segment and section containing it
+    A2l {
+        path: String,
+        #[arg(value_parser = parse_u64)]
+        addr: u64,
+    },
+    /// Resolve addresses to their image and the nearest preceding symbol and offset, or the
+    /// section containing them when no symbol precedes them
+    Symbolicate {
+        path: String,
+        /// Addresses to symbolicate; read from stdin, one per line, when omitted
+        #[arg(value_parser = parse_u64)]
+        addrs: Vec<u64>,
+    },
+    /// Compute the cache slide from an image's runtime load address and symbolicate runtime
+    /// addresses with it
+    Slide {
+        path: String,
+        /// Install path or file name of the image whose load address was observed
+        module: String,
+        /// Runtime address of the image's Mach-O header, e.g. from a crash log or lldb
+        #[arg(value_parser = parse_u64)]
+        base: u64,
+        /// Runtime addresses to symbolicate; read from stdin, one per line, when omitted
+        #[arg(value_parser = parse_u64)]
+        addrs: Vec<u64>,
+    },
+    /// Find the symbol containing an address in one cache and the address of the same symbol
+    /// and offset in another
+    Translate {
+        #[arg(value_parser = parse_u64)]
+        addr: u64,
+        /// Cache the address belongs to
+        #[arg(long)]
+        from: String,
+        /// Cache to translate the address to
+        #[arg(long)]
+        to: String,
+    },
+    /// Resolve a JSON list of named anchors (symbol, symbol and offset, or byte signature) to
+    /// addresses in a cache
+    Port {
+        path: String,
+        /// JSON file with the anchors
+        anchors: String,
+    },
+    /// Summarize exports, imports by providing dylib, ObjC classes and linked dylibs per image
+    Surface {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Classify images as Swift-only, ObjC-only, mixed or native, and flag C++ RTTI
+    Languages {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only list images of this kind
+        #[arg(short, long, value_enum)]
+        kind: Option<languages::Kind>,
+    },
+    /// List unified logging format strings (`__oslogstring`) with their addresses
+    Oslog {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only list format strings containing this text
+        #[arg(short, long)]
+        contains: Option<String>,
+    },
+    /// Find the images whose C-string literal sections contain a string
+    Grep {
+        path: String,
+        /// String to look for; must match a whole string unless --substring is given
+        #[arg(long)]
+        string: String,
+        /// Also report strings that only contain the text
+        #[arg(long)]
+        substring: bool,
+        /// Only search this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find a string anywhere in the readable mappings of the cache
+    SearchString {
+        path: String,
+        /// Text to look for, matched as UTF-8 bytes
+        needle: String,
+        /// Only report hits inside this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find a hex byte pattern, with `??` wildcards, in the code of the whole cache
+    SearchBytes {
+        path: String,
+        /// Hex bytes separated by spaces, e.g. "FF 83 ?? D1 ?? ?? 40 F9"
+        pattern: String,
+        /// Only report matches inside this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find the pointers in data segments that point to an address
+    Xrefs {
+        path: String,
+        /// Target address; PAC and tag bits are stripped
+        #[arg(value_parser = parse_u64)]
+        addr: u64,
+        /// Only report references inside this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Report where calls to a function can be intercepted: callers, stubs, GOT slots, patch
+    /// table uses and objc_msgSend stubs of its selector
+    Hooks {
+        path: String,
+        /// Function symbol, or its address
+        function: String,
+        /// Look for the symbol in this image only (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Report rpaths, sub-framework and umbrella load commands, and umbrella membership
+    Umbrellas {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Write the exported and re-exported symbols of an image and the linker flags to build a
+    /// shim dylib that stands in for it
+    Shim {
+        path: String,
+        /// Install path or file name of the image
+        module: String,
+        /// Directory to write the symbol lists and flags to
+        #[arg(short, long)]
+        output: String,
+    },
+    /// List the cache images dyld would load for an executable on disk, and the dependencies
+    /// and imported symbols it could not resolve
+    SimulateLoad {
+        path: String,
+        /// Main executable (Mach-O or fat binary)
+        executable: String,
+    },
+    /// Report load commands of cached images naming dylibs that are neither in the cache nor on
+    /// disk, including broken re-exports
+    AuditDeps {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find functions matching a list of named byte patterns in executable sections
+    Signatures {
+        path: String,
+        /// File with one `name hex-bytes` pattern per line; `??` matches any byte
+        signatures: String,
+        /// Image to scan (install path or file name); may be repeated. Defaults to all images
+        #[arg(short, long)]
+        module: Vec<String>,
+    },
+    /// Find functions with identical bytes in several images, code duplicated across the cache
+    Duplicates {
+        path: String,
+        /// Image to compare (install path or file name); may be repeated. Defaults to all images
+        #[arg(short, long)]
+        module: Vec<String>,
+        /// Ignore functions smaller than this many bytes
+        #[arg(long, value_parser = parse_u64, default_value = "32")]
+        min_size: u64,
+        /// Mask branch offsets and adrp page addresses before comparing, so copies calling or
+        /// loading from different addresses still match
+        #[arg(long)]
+        normalize: bool,
+    },
+    /// Write the function starts of every image to a CSV or compact binary file
+    FunctionStarts {
+        path: String,
+        #[arg(short, long)]
+        output: String,
+        /// File format (defaults to csv, or json with the global `--format json`)
+        #[arg(long, value_enum)]
+        encoding: Option<StartsFormat>,
+        /// Only export this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Write the images, sections and symbols of the cache to a binary index other tools can
+    /// map directly
+    Index {
+        path: String,
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Cross-check the built-in __LINKEDIT parsers against the object crate and report
+    /// discrepancies as warnings
+    Check {
+        path: String,
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Write an image as a flat memory blob plus JSON segment metadata
+    Blob {
+        path: String,
+        #[arg(
+            required_unless_present_any = ["all", "filter", "regex"],
+            conflicts_with_all = ["all", "filter", "regex"]
+        )]
+        module: Option<String>,
+        /// Output prefix; writes <output>.bin and <output>.json. With --all, --filter or
+        /// --regex, the directory under which the install path tree is recreated
+        #[arg(short, long)]
+        output: String,
+        /// Extract every image of the cache. Aliases of an image are symlinked to its files
+        #[arg(long)]
+        all: bool,
+        /// Extract the images whose install path matches this pattern (`*` matches any run of
+        /// characters, including `/`); may be repeated
+        #[arg(long)]
+        filter: Vec<String>,
+        /// Extract the images whose install path matches this regular expression
+        #[arg(long)]
+        regex: Option<String>,
+        /// Rebase pointers to their runtime addresses, unsigned. `keep` lists the arm64e signing
+        /// information in the JSON, not in the blob. Without it, pointers are left encoded as
+        /// stored in the cache
+        #[arg(long, value_enum)]
+        auth: Option<AuthMode>,
+        /// Log each load command, segment copied and pointer rewritten, and write the trace to
+        /// <output>.explain.json
+        #[arg(long)]
+        explain: bool,
+        /// Record the source cache, tool version and patches applied in the JSON sidecar
+        #[arg(long)]
+        provenance: bool,
+        /// Only extract __TEXT (the Mach-O header and the code), for disassemblers. The output
+        /// is a raw blob of __TEXT, not a Mach-O: its load commands still list every segment
+        #[arg(long, conflicts_with = "auth")]
+        text_only: bool,
+        /// Write a Mach-O file that loads at any address: the pointers into the image become
+        /// rebase opcodes in a new __LINKEDIT, referenced by LC_DYLD_INFO_ONLY
+        #[arg(long, requires = "auth")]
+        rebase_info: bool,
+        /// Point the image's selector references and method names at selector strings inside
+        /// the blob, appending those the image lacks, instead of the cache's shared strings
+        #[arg(long, requires = "auth")]
+        fix_selectors: bool,
+        /// Fail instead of writing an image that lost data: segments not entirely mapped, data
+        /// segments without slide info, pointers or selectors to unmapped memory
+        #[arg(long)]
+        strict: bool,
+        /// Fill each segment past its file data with the bytes mapped there in the cache instead
+        /// of zeros, for byte-level comparison with the original dylib
+        #[arg(long)]
+        faithful: bool,
+        /// Print the planned layout (segments at their blob offsets, segments left out, total
+        /// size, files) without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Number of images extracted at once with --all, --filter or --regex (defaults to
+        /// the number of CPUs, or 1 with --io-throttle)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Run at low CPU and IO priority, keeping a single extracted image in memory, so a
+        /// batch extraction can run in the background
+        #[arg(long)]
+        io_throttle: bool,
+    },
+    /// Export images or an address range as page-aligned memory snapshots for emulators
+    EmuExport {
+        path: String,
+        /// Output directory for the snapshots and memory_map.json
+        #[arg(short, long)]
+        output: String,
+        /// Image to export (install path or file name); may be repeated
+        #[arg(short, long)]
+        module: Vec<String>,
+        /// Start of an address range to export
+        #[arg(long, value_parser = parse_u64, requires = "end")]
+        start: Option<u64>,
+        /// End of the address range (exclusive)
+        #[arg(long, value_parser = parse_u64, requires = "start")]
+        end: Option<u64>,
+        /// Rewrite slid pointers to their runtime addresses, unsigned. `keep` lists the arm64e
+        /// signing information in the JSON, not in the snapshots. Without it, pointers are left
+        /// encoded as stored in the cache
+        #[arg(long, value_enum)]
+        auth: Option<AuthMode>,
+    },
+    /// Compare the export tries of an image in two caches, or of two different images
+    ExportsDiff {
+        /// Cache containing the old image
+        old_path: String,
+        /// Install path or file name of the old image
+        old_module: String,
+        /// Cache containing the new image (may be the same file as the old cache)
+        new_path: String,
+        /// Install path or file name of the new image; defaults to the old one
+        new_module: Option<String>,
+    },
+    /// Report ObjC classes, categories, methods and protocols added or removed between two caches
+    ObjcDiff {
+        old_path: String,
+        new_path: String,
+        /// Only compare this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Inspect Objective-C runtime metadata
+    Objc {
+        #[command(subcommand)]
+        command: ObjcCommands,
+    },
+    /// List the strings of images with their addresses, like strings(1)
+    Strings {
+        path: String,
+        /// Only list strings of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only list strings from this section, e.g. __cstring or __oslogstring
+        #[arg(short, long)]
+        section: Option<String>,
+        /// Scan every section with contents, not only the C-string literal sections
+        #[arg(long)]
+        all_sections: bool,
+        /// Skip strings shorter than this many characters
+        #[arg(long, default_value_t = 4)]
+        min_length: usize,
+        /// Skip strings with a smaller fraction of printable characters
+        #[arg(long, default_value_t = 1.0, value_parser = parse_ratio)]
+        printable: f64,
+    },
+    /// Report strings added or removed between two caches, per image
+    StringsDiff {
+        old_path: String,
+        new_path: String,
+        /// Only compare this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+        /// Only compare strings from this section, e.g. __cstring or __oslogstring
+        #[arg(short, long)]
+        section: Option<String>,
+    },
+    /// Disassemble a function with branch targets and literal references annotated
+    Disasm {
+        path: String,
+        /// Address to start at, instead of a function; PAC and tag bits are stripped
+        #[arg(
+            value_parser = parse_u64,
+            required_unless_present = "function",
+            conflicts_with = "function"
+        )]
+        addr: Option<u64>,
+        /// Number of instructions to disassemble from the address; by default, up to the next
+        /// function start
+        #[arg(value_parser = parse_u64)]
+        count: Option<u64>,
+        /// Symbol name of the function; its end is taken from the image's function starts
+        #[arg(long)]
+        function: Option<String>,
+        /// Only look for the symbol in this image (install path or file name)
+        #[arg(short, long, requires = "function")]
+        module: Option<String>,
+        /// Write the listing to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Show where each segment of images is stored: its mapping, cache file, file offsets
+    /// and whether slide info applies
+    Segments {
+        path: String,
+        /// Only show this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Hex-dump an image's Mach-O header and load commands with each field annotated
+    Layout {
+        path: String,
+        /// Image (install path or file name)
+        module: String,
+    },
+    Dump {
+        path: String,
+        /// Address, or an image (install path or file name) whose section to dump
+        #[arg(value_name = "ADDR|MODULE")]
+        addr: String,
+        /// Number of bytes (256 by default), or the `segment,section` of the image to dump
+        #[arg(value_name = "SIZE|SEGMENT,SECTION")]
+        size: Option<String>,
+        /// Treat the address as a file offset into the main cache file
+        #[arg(long)]
+        file_offset: bool,
+        /// Show the image, section and symbol each aligned 8-byte value pointing into the
+        /// cache refers to, one pointer per row
+        #[arg(long)]
+        annotate: bool,
+        /// Write the raw bytes to this file instead of a hex dump, or to stdout with `-`
+        #[arg(short, long, conflicts_with = "annotate")]
+        output: Option<String>,
+        /// Pause after this many rows when writing to a terminal, until space or enter is
+        /// pressed (q quits)
+        #[arg(long, value_name = "ROWS", conflicts_with = "output")]
+        page_size: Option<usize>,
+        /// Keep dumping the memory after the range a page at a time, until q or the end of
+        /// mapped memory; needs a terminal
+        #[arg(long, conflicts_with = "output")]
+        follow: bool,
+        /// Dump more than 1 MiB as hex or JSON
+        #[arg(long)]
+        no_limit: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ObjcCommands {
+    /// Print the value of the variable holding an ivar's offset (OBJC_IVAR_$_Class.ivar)
+    IvarOffset {
+        path: String,
+        class: String,
+        ivar: String,
+        /// Only look for the class in this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Map every method implementation address to its `-[Class selector]` name
+    Impmap {
+        path: String,
+        /// Only list methods of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// List the selectors uniqued in the cache-wide selector table, with their addresses
+    Selectors {
+        path: String,
+        /// Print only the selector names, one per line, as a wordlist
+        #[arg(long)]
+        wordlist: bool,
+        /// Sort by the number of methods implementing each selector, most common first
+        #[arg(long)]
+        by_frequency: bool,
+    },
+    /// Dump the classes and categories of images with their superclass, ivars and methods
+    Classes {
+        path: String,
+        /// Only dump classes of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// List the protocols of images with the protocols they incorporate and their methods
+    Protocols {
+        path: String,
+        /// Only list protocols of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// List the categories of images with the class each extends and their methods
+    Categories {
+        path: String,
+        /// Only list categories of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Write class-dump style headers of the classes, categories and protocols of images, in
+    /// a directory per image
+    Headers {
+        path: String,
+        /// Directory to write `<image name>/<header>.h` files into
+        #[arg(short, long)]
+        output: String,
+        /// Only write headers of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+    /// Find classes by the ivars they declare, their instance size or their selectors
+    Find {
+        path: String,
+        /// Ivar name the class must declare (repeatable)
+        #[arg(long)]
+        ivar: Vec<String>,
+        /// Instance size, or an inclusive range such as `0x40-0x60`
+        #[arg(long, value_parser = parse_size_range)]
+        size: Option<(u32, u32)>,
+        /// Selector the class must implement itself (repeatable)
+        #[arg(long)]
+        selector: Vec<String>,
+        /// Only look at classes of this image (install path or file name)
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human readable listing
+    Text,
+    /// Pretty-printed JSON document, with addresses as numbers
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Group {
+    /// Each image on its own
+    Image,
+    /// Images under the same `.framework` or `.app` directory together, nested bundles such
+    /// as XPC services included
+    Framework,
+}
+
+impl Group {
+    /// The unit an image belongs to: its install path, or the path of the innermost
+    /// `.framework` or `.app` directory holding it. Dylibs outside bundles stay on their own.
+    fn unit(self, path: &str) -> &str {
+        if self == Group::Image {
+            return path;
+        }
+        let mut end = None;
+        let mut offset = 0;
+        for component in path.split('/') {
+            offset += component.len();
+            if component.ends_with(".framework") || component.ends_with(".app") {
+                end = Some(offset);
+            }
+            offset += 1;
+        }
+        end.map_or(path, |end| &path[..end])
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            Group::Image => "image",
+            Group::Framework => "bundle",
+        }
+    }
+
+    /// Install paths by the unit they belong to.
+    fn paths<'p>(
+        self,
+        paths: impl IntoIterator<Item = &'p String>,
+    ) -> BTreeMap<&'p str, Vec<&'p String>> {
+        let mut units: BTreeMap<&str, Vec<&String>> = BTreeMap::new();
+        for path in paths {
+            units.entry(self.unit(path)).or_default().push(path);
+        }
+        units
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StartsFormat {
+    /// `image,address,offset` rows with a header line
+    Csv,
+    /// Per image: path, base address and 32-bit offsets of each start (see README)
+    Binary,
+    /// An array of `{image, base, source, offsets}` objects
+    Json,
+}
+
+fn parse_u64(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    if input.to_ascii_lowercase().starts_with("0x") {
+        u64::from_str_radix(&input[2..], 16).map_err(|e| format!("Invalid hex: {}", e))
+    } else {
+        input
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid number: {}", e))
+    }
+}
+
+fn parse_overlay(input: &str) -> Result<(String, u64), String> {
+    let (path, address) = input
+        .rsplit_once('@')
+        .ok_or_else(|| format!("Expected <file>@<address>, got {}", input))?;
+    Ok((path.to_string(), parse_u64(address)?))
+}
+
+/// A size or an inclusive `min-max` range of sizes.
+fn parse_size_range(input: &str) -> Result<(u32, u32), String> {
+    let number = |s: &str| {
+        u32::try_from(parse_u64(s)?).map_err(|_| format!("Size {} is too large", s.trim()))
+    };
+    let (min, max) = match input.split_once('-') {
+        Some((min, max)) => (number(min)?, number(max)?),
+        None => (number(input)?, number(input)?),
+    };
+    if min > max {
+        return Err(format!("Invalid size range {}", input));
+    }
+    Ok((min, max))
+}
+
+/// A fraction between 0 and 1.
+fn parse_ratio(input: &str) -> Result<f64, String> {
+    let ratio: f64 = input
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid number: {}", e))?;
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("{} is not between 0 and 1", input.trim()));
+    }
+    Ok(ratio)
+}
+
+/// Accept pointers pasted from an arm64e process: PAC and tag bits are stripped, with a notice.
+fn canonical_address(cache: &DyldCache<LittleEndian>, addr: u64) -> u64 {
+    match resolve::strip_pointer_bits(cache, addr) {
+        Some(stripped) => {
+            eprintln!(
+                "Stripped PAC/tag bits from 0x{:X}, using 0x{:X}",
+                addr, stripped
+            );
+            stripped
+        }
+        None => addr,
+    }
+}
+
+fn load_labels(
+    cache: &DyldCache<LittleEndian>,
+    paths: &[String],
+) -> Result<labels::Labels, Box<dyn Error>> {
+    let mut labels = labels::Labels::default();
+    for path in paths {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        labels
+            .add(&text, cache)
+            .map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(labels)
+}
+
+fn with_dyld_cache<F>(path: &str, cli: &Cli, action: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&DyldCache<LittleEndian>) -> Result<(), Box<dyn Error>>,
+{
+    let timing = cli.timing;
+    let started = Instant::now();
+    let mut mapped_cache = dsc::MappedCache::open(path, cli.arch.as_deref())?;
+    if !cli.overlay.is_empty() {
+        let overlays = cli
+            .overlay
+            .iter()
+            .map(|(file, address)| dsc::Overlay::read(file, *address))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total: usize = overlays.iter().map(|o| o.data.len()).sum();
+        let overlaid = mapped_cache.apply_overlays(&overlays)?;
+        eprintln!(
+            "Overlaid {} of {} bytes from {} dumps",
+            overlaid,
+            total,
+            overlays.len()
+        );
+    }
+    let mapped = started.elapsed();
+
+    let cache = mapped_cache.parse()?;
+    let parsed = started.elapsed();
+
+    let result = action(&cache);
+
+    if timing {
+        let total = started.elapsed();
+        eprintln!(
+            "timing: open/map {:>10.3?} ({} files, {} bytes)",
+            mapped,
+            mapped_cache.file_count(),
+            mapped_cache.mapped_size()
+        );
+        eprintln!(
+            "timing: parse    {:>10.3?} ({} images, {} mappings)",
+            parsed - mapped,
+            cache.images().count(),
+            cache.mappings().count()
+        );
+        eprintln!("timing: execute  {:>10.3?}", total - parsed);
+        eprintln!("timing: total    {:>10.3?}", total);
+    }
+    result
+}
+
+/// Like `with_dyld_cache`, for commands that only need the main cache file.
+fn with_cache_header<F>(path: &str, cli: &Cli, action: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&dsc::CacheHeader) -> Result<(), Box<dyn Error>>,
+{
+    let started = Instant::now();
+    let cache = dsc::CacheHeader::open(path, cli.arch.as_deref())?;
+    let mapped = started.elapsed();
+
+    let result = action(&cache);
+
+    if cli.timing {
+        let total = started.elapsed();
+        eprintln!(
+            "timing: open/map {:>10.3?} (1 file, {} bytes)",
+            mapped,
+            cache.data().len()
+        );
+        eprintln!("timing: execute  {:>10.3?}", total - mapped);
+        eprintln!("timing: total    {:>10.3?}", total);
+    }
+    result
+}
+
+/// Name of a cache file by its index in `vm::Mapping::file`: `main` or the subcache suffix.
+fn cache_file_name(suffixes: &[String], file: usize) -> String {
+    match file {
+        0 => "main".to_string(),
+        _ => suffixes
+            .get(file - 1)
+            .cloned()
+            .unwrap_or_else(|| format!("subcache {}", file)),
+    }
+}
+
+fn cmd_map(cache: &DyldCache<LittleEndian>, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let suffixes = DyldCache::<LittleEndian>::subcache_suffixes(cache.data())?;
+    let file_name = |file: usize| cache_file_name(&suffixes, file);
+    let mappings = vm::mappings(cache);
+
+    if format == OutputFormat::Json {
+        let items: Vec<_> = mappings
+            .iter()
+            .map(|m| {
+                Json::object()
+                    .field("name", m.name())
+                    .field("address", m.address)
+                    .field("size", m.size)
+                    .field("file", file_name(m.file))
+                    .field("file_offset", m.file_offset)
+                    .field("max_prot", m.max_prot)
+                    .field("init_prot", m.init_prot)
+                    .field("flags", m.flags)
+                    .field("slide_info_offset", m.slide_info.map(|s| s.0))
+                    .field("slide_info_size", m.slide_info.map(|s| s.1))
+            })
+            .collect();
+        println!("{}", Json::from(items).pretty());
+        return Ok(());
+    }
+
+    for m in &mappings {
+        let flags = m.flags.map_or("-".to_string(), mapping_flags);
+        let slide = match m.slide_info {
+            Some((offset, size)) if size != 0 => format!(" slide 0x{:X}+0x{:X}", offset, size),
+            _ => String::new(),
+        };
+        println!(
+            "0x{:X}-0x{:X} {:<13} {}/{} file 0x{:<10X} {:<10} {}{}",
+            m.address,
+            m.address + m.size,
+            m.name(),
+            emu::prot_string(m.init_prot),
+            emu::prot_string(m.max_prot),
+            m.file_offset,
+            file_name(m.file),
+            flags,
+            slide
+        );
+    }
+    Ok(())
+}
+
+/// Names of the `DYLD_CACHE_MAPPING_*` flags set in `flags`.
+fn mapping_flags(flags: u64) -> String {
+    let names = [
+        (object::macho::DYLD_CACHE_MAPPING_AUTH_DATA, "auth"),
+        (object::macho::DYLD_CACHE_MAPPING_DIRTY_DATA, "dirty"),
+        (object::macho::DYLD_CACHE_MAPPING_CONST_DATA, "const"),
+        (object::macho::DYLD_CACHE_MAPPING_TEXT_STUBS, "stubs"),
+    ];
+    let set: Vec<_> = names
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        "-".to_string()
+    } else {
+        set.join(",")
+    }
+}
+
+fn cmd_info(cache: &dsc::CacheHeader) -> Result<(), Box<dyn Error>> {
+    let e = LittleEndian;
+    let selection = &cache.selection;
+    let images = cache.images()?;
+    println!("path         {}", selection.path.display());
+    println!("arch         {}", selection.arch);
+    if let Some(header) = images
+        .first()
+        .and_then(|&(address, _)| cache.macho_header(address))
+    {
+        println!(
+            "cpu          type 0x{:X}, subtype 0x{:X}",
+            header.cputype.get(e),
+            header.cpusubtype.get(e) & !object::macho::CPU_SUBTYPE_MASK
+        );
+    }
+    println!("uuid         {}", format_uuid(&cache.header()?.uuid));
+    println!("images       {}", images.len());
+    println!("mappings     {} in the main file", cache.mapping_count());
+    println!("subcaches    {}", cache.subcache_count());
+    let kind = cachetype::CacheKind::from_main_file(cache.data());
+    println!("platform     {}", cachetype::platform_name(kind.platform));
+    if kind.family() != select::Family::System {
+        println!("runtime      {}", kind.family().name());
+    }
+    println!("type         {}", kind);
+    match kind.local_symbols {
+        cachetype::LocalSymbols::None => println!("local syms   none"),
+        cachetype::LocalSymbols::Inline(size) => {
+            println!("local syms   in main cache file (0x{:X} bytes)", size)
+        }
+        cachetype::LocalSymbols::SymbolsFile => println!("local syms   in .symbols file"),
+    }
+    for suffix in &kind.development_subcaches {
+        println!("dev only     {}", suffix);
+    }
+    if let Some(reason) = &selection.reason {
+        println!("selected     {} ({})", selection.arch, reason);
+        for (path, arch) in &selection.alternatives {
+            println!("alternative  {} {}", arch, path.display());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_caches() -> Result<(), Box<dyn Error>> {
+    let found = select::discover();
+    if found.is_empty() {
+        return Err("No dyld shared cache found in the standard locations".into());
+    }
+    for (family, dir) in found {
+        println!("@{} {}", family.name(), dir.display());
+        for (path, arch) in select::main_caches(&dir) {
+            // Only the main file is opened, so listing stays quick for split caches.
+            let images = path
+                .to_str()
+                .and_then(|p| dsc::CacheHeader::open(p, None).ok())
+                .and_then(|cache| cache.images().ok().map(|images| images.len()));
+            match images {
+                Some(images) => println!("  {:<8} {} ({} images)", arch, path.display(), images),
+                None => println!("  {:<8} {}", arch, path.display()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Index of the image whose install path or file name is `module`.
+fn image_index(index: &CacheIndex, module: &str) -> Result<usize, String> {
+    (0..index.len())
+        .find(|&i| {
+            let image = index.image(i);
+            image.path == module || image.name() == module
+        })
+        .ok_or_else(|| format!("Image {} not found in dyld cache", module))
+}
+
+/// The section of `module` named by `section`, as `segment,section` or a bare section name
+/// when only one segment has it.
+fn find_section<'i>(
+    index: &'i CacheIndex,
+    module: &str,
+    section: &str,
+) -> Result<&'i index::SectionEntry, String> {
+    let image = index.image(image_index(index, module)?);
+    let mut found = image
+        .sections
+        .iter()
+        .filter(|s| match section.split_once(',') {
+            Some((segment, name)) => s.segment == segment && s.name == name,
+            None => s.name == section,
+        });
+    match (found.next(), found.next()) {
+        (Some(s), None) => Ok(s),
+        (Some(_), Some(_)) => Err(format!(
+            "{} has several {} sections; give segment,section",
+            image.path, section
+        )),
+        (None, _) => Err(format!("{} has no section {}", image.path, section)),
+    }
+}
+
+/// Which images have every symbol of `symbols` in their export trie and every section of
+/// `sections`, by image index.
+fn select_images(
+    index: &CacheIndex,
+    symbols: &[String],
+    sections: &[String],
+) -> Result<Vec<bool>, Box<dyn Error>> {
+    // Aliases share their header, and so their exports, with the image listed first.
+    let mut exported: HashMap<u64, bool> = HashMap::new();
+    let mut selected = Vec::with_capacity(index.len());
+    for (i, image) in index.cache().images().enumerate() {
+        let entry = index.image(i);
+        let has_sections = sections.iter().all(|wanted| {
+            entry.sections.iter().any(|s| {
+                *wanted == s.name
+                    || wanted.split_once(',') == Some((s.segment.as_str(), s.name.as_str()))
+            })
+        });
+        let has_symbols = symbols.is_empty()
+            || match exported.get(&entry.base) {
+                Some(&found) => found,
+                None => {
+                    let names: HashSet<String> = exports::image_exports(index.cache(), &image)?
+                        .into_iter()
+                        .map(|export| export.name)
+                        .collect();
+                    let found = symbols.iter().all(|symbol| names.contains(symbol));
+                    exported.insert(entry.base, found);
+                    found
+                }
+            };
+        selected.push(has_sections && has_symbols);
+    }
+    Ok(selected)
+}
+
+/// The `images` of a fileset kernelcache given by mistake: its entries, which is as far as
+/// this tool goes with kernelcaches.
+fn cmd_fileset_images(
+    path: &str,
+    entries: &[kernel::FilesetEntry],
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    eprintln!(
+        "{} is a fileset kernelcache, not a dyld shared cache; listing its entries",
+        path
+    );
+    if format == OutputFormat::Json {
+        let items: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                Json::object()
+                    .field("id", entry.id.as_str())
+                    .field("address", entry.vmaddr)
+                    .field("file_offset", entry.fileoff)
+            })
+            .collect();
+        println!("{}", Json::from(items).pretty());
+        return Ok(());
+    }
+    for entry in entries {
+        println!("0x{:X} {}", entry.vmaddr, entry.id);
+    }
+    Ok(())
+}
+
+fn cmd_images(
+    index: &CacheIndex,
+    verbose: bool,
+    selected: &[bool],
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    if !verbose && format == OutputFormat::Text {
+        for (i, image) in index.cache().images().enumerate() {
+            if selected[i] {
+                println!("{}", image.path().unwrap_or(""));
+            }
+        }
+        return Ok(());
+    }
+
+    // Several install paths may point at the same Mach-O in the cache (symlink-style
+    // aliases). The first path seen for a header address or UUID is treated as canonical.
+    let mut canonical: HashMap<u64, String> = HashMap::new();
+    let mut by_uuid: HashMap<[u8; 16], String> = HashMap::new();
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut images = Vec::new();
+    let policy = overrides::OverridePolicy::new(index.cache());
+
+    for (i, image) in index.cache().images().enumerate() {
+        let entry = index.image(i);
+        let image_path = entry.path.as_str();
+        let addr = entry.base;
+        let uuid = entry.uuid;
+
+        let alias_of = canonical
+            .get(&addr)
+            .or_else(|| uuid.as_ref().and_then(|u| by_uuid.get(u)))
+            .cloned();
+
+        if alias_of.is_none() {
+            canonical.insert(addr, image_path.to_string());
+            if let Some(u) = uuid {
+                by_uuid.insert(u, image_path.to_string());
+            }
+        }
+        if !selected[i] {
+            continue;
+        }
+
+        let disk = policy
+            .expected_on_disk
+            .then(|| overrides::disk_identity(&image));
+        let overridable = policy.is_overridable(image_path);
+        if format == OutputFormat::Json {
+            let mut item = Json::object()
+                .field("path", image_path)
+                .field("address", addr)
+                .field("uuid", uuid.map(|u| format_uuid(&u)))
+                .field("alias_of", alias_of)
+                .field("overridable", overridable);
+            if let Some((mtime, inode)) = disk {
+                item = item.field("mtime", mtime).field("inode", inode);
+            }
+            images.push(item);
+            continue;
+        }
+
+        let uuid_str = uuid.map(|u| format_uuid(&u)).unwrap_or_else(|| "-".into());
+        let mut flags = String::new();
+        if let Some((mtime, inode)) = disk {
+            flags.push_str(&format!(" (mtime 0x{:X}, inode 0x{:X})", mtime, inode));
+        }
+        if overridable {
+            flags.push_str(" (overridable)");
+        }
+        match alias_of {
+            Some(target) => {
+                println!(
+                    "0x{:X} {} {} (alias of {}){}",
+                    addr, uuid_str, image_path, target, flags
+                );
+                if let Some((_, aliases)) = groups.iter_mut().find(|(c, _)| *c == target) {
+                    aliases.push(image_path.to_string());
+                } else {
+                    groups.push((target, vec![image_path.to_string()]));
+                }
+            }
+            None => println!("0x{:X} {} {}{}", addr, uuid_str, image_path, flags),
+        }
+    }
+
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+        return Ok(());
+    }
+    if !groups.is_empty() {
+        println!();
+        println!("Alias groups:");
+        for (target, aliases) in &groups {
+            println!("{}", target);
+            for alias in aliases {
+                println!("  {}", alias);
+            }
+        }
+    }
+    println!();
+    println!("Overrides: {}", policy.describe());
+    Ok(())
+}
+
+/// Images aggregated per bundle, with their combined size (segments other than the shared
+/// `__LINKEDIT`) and symbol count. Aliases are not counted twice.
+fn cmd_images_grouped(
+    index: &CacheIndex,
+    selected: &[bool],
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let symbolizer = symbolize::Symbolizer::new(index);
+    let mut seen = HashSet::new();
+    let mut units: BTreeMap<&str, (Vec<&str>, u64, usize)> = BTreeMap::new();
+    for (i, &selected) in selected.iter().enumerate() {
+        let image = index.image(i);
+        if !selected || !seen.insert(image.base) {
+            continue;
+        }
+        let size: u64 = image
+            .segments
+            .iter()
+            .filter(|s| s.name != "__LINKEDIT")
+            .map(|s| s.end - s.start)
+            .sum();
+        let unit = units.entry(Group::Framework.unit(&image.path)).or_default();
+        unit.0.push(&image.path);
+        unit.1 += size;
+        unit.2 += symbolizer.symbols(i).len();
+    }
+
+    if format == OutputFormat::Json {
+        let items: Vec<_> = units
+            .iter()
+            .map(|(name, (images, size, symbols))| {
+                Json::object()
+                    .field("name", *name)
+                    .field("images", images.clone())
+                    .field("size", *size)
+                    .field("symbols", *symbols)
+            })
+            .collect();
+        println!("{}", Json::from(items).pretty());
+        return Ok(());
+    }
+    for (name, (images, size, symbols)) in &units {
+        println!(
+            "{} ({} images, {} bytes, {} symbols)",
+            name,
+            images.len(),
+            size,
+            symbols
+        );
+    }
+    Ok(())
+}
+
+fn cmd_segments(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let suffixes = DyldCache::<LittleEndian>::subcache_suffixes(cache.data())?;
+    let mappings = vm::mappings(cache);
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let file = image.parse_object()?;
+        let mut pieces = Vec::new();
+        for segment in file.segments() {
+            let name = segment.name().ok().flatten().unwrap_or("").to_string();
+            // A segment could cross mappings, each with its own file and slide info.
+            let end = segment.address() + segment.size();
+            let mut address = segment.address();
+            while address < end {
+                let mapping = mappings
+                    .iter()
+                    .find(|m| address >= m.address && address < m.address + m.size);
+                let piece_end = mapping.map_or(end, |m| end.min(m.address + m.size));
+                pieces.push((name.clone(), address, piece_end, mapping));
+                address = piece_end;
+            }
+        }
+
+        if format == OutputFormat::Json {
+            let segments: Vec<_> = pieces
+                .iter()
+                .map(|(name, start, end, mapping)| {
+                    Json::object()
+                        .field("name", name.as_str())
+                        .field("address", *start)
+                        .field("size", end - start)
+                        .field("mapping", mapping.map(|m| m.name()))
+                        .field("file", mapping.map(|m| cache_file_name(&suffixes, m.file)))
+                        .field(
+                            "file_offset",
+                            mapping.map(|m| m.file_offset + (start - m.address)),
+                        )
+                        .field("slid", mapping.is_some_and(has_slide_info))
+                })
+                .collect();
+            images.push(
+                Json::object()
+                    .field("image", path)
+                    .field("segments", segments),
+            );
+            continue;
+        }
+        println!("{}", path);
+        for (name, start, end, mapping) in pieces {
+            let range = fmt.format_range(start, end);
+            let Some(m) = mapping else {
+                println!("  {:<16} {} not mapped", name, range);
+                continue;
+            };
+            let offset = m.file_offset + (start - m.address);
+            println!(
+                "  {:<16} {} {:<13} {:<10} file 0x{:X}-0x{:X}{}",
+                name,
+                range,
+                m.name(),
+                cache_file_name(&suffixes, m.file),
+                offset,
+                offset + (end - start),
+                if has_slide_info(m) { " slid" } else { "" }
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
+
+/// Whether pointers in the mapping are rewritten with slide info when the cache is loaded.
+fn has_slide_info(mapping: &vm::Mapping) -> bool {
+    mapping.slide_info.is_some_and(|(_, size)| size != 0)
+}
+
+fn cmd_sections(
+    cache: &DyldCache<LittleEndian>,
+    filter_module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+    show_errors: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mappings = vm::mappings(cache);
+    let coverage = slide::coverage(cache);
+    // The mapping holding a section, and its slide info: version and rebased pages.
+    let slide_info = |start: u64, end: u64| {
+        let mapping = mappings
+            .iter()
+            .find(|m| start >= m.address && start < m.address + m.size)?;
+        let slid = coverage
+            .iter()
+            .find(|c| start >= c.address && start < c.address + c.size)
+            .map(|c| (c.version, c.rebased_pages(start, end)));
+        Some((mapping.name(), slid))
+    };
+    let mut images = Vec::new();
+    let mut errors = ImageErrors::new(show_errors);
+    for image in cache.images() {
+        let image_path = image.path().unwrap_or("");
+
+        if let Some(filter) = filter_module
+            && image_path != filter
+        {
+            continue;
+        }
+        let obj = errors.check(image_path, image.parse_object());
+
+        if format == OutputFormat::Json {
+            let sections: Vec<_> = obj
+                .map(|obj| {
+                    obj.sections()
+                        .map(|section| {
+                            let item = Json::object()
+                                .field("segment", section.segment_name().ok().flatten())
+                                .field("name", section.name().unwrap_or(""))
+                                .field("address", section.address())
+                                .field("size", section.size());
+                            if !verbose {
+                                return item;
+                            }
+                            let start = section.address();
+                            let info = slide_info(start, start + section.size());
+                            let slid = info.and_then(|(_, slid)| slid);
+                            let pages = slid.and_then(|(_, pages)| pages);
+                            item.field("mapping", info.map(|(name, _)| name))
+                                .field("slide_version", slid.map(|(version, _)| version))
+                                .field("rebased_pages", pages.map(|(rebased, _)| rebased))
+                                .field("pages", pages.map(|(_, total)| total))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            images.push(
+                Json::object()
+                    .field("image", image_path)
+                    .field("sections", sections),
+            );
+            continue;
+        }
+
+        println!("{}", image_path);
+        if let Some(obj) = obj {
+            for section in obj.sections() {
+                let base = section.address();
+                let end = base + section.size();
+                if !verbose {
+                    println!(
+                        "  {:16} {}",
+                        section.name().unwrap_or(""),
+                        fmt.format_range(base, end)
+                    );
+                    continue;
+                }
+                let detail = match slide_info(base, end) {
+                    None => "not mapped".to_string(),
+                    Some((mapping, None)) => format!("{:<13} no slide info", mapping),
+                    Some((mapping, Some((version, None)))) => {
+                        format!("{:<13} slide v{}", mapping, version)
+                    }
+                    Some((mapping, Some((version, Some((rebased, pages)))))) => format!(
+                        "{:<13} slide v{}, {}/{} pages rebased",
+                        mapping, version, rebased, pages
+                    ),
+                };
+                println!(
+                    "  {:16} {} {}",
+                    section.name().unwrap_or(""),
+                    fmt.format_range(base, end),
+                    detail
+                );
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    errors.report();
+    Ok(())
+}
+
+fn cmd_symbols(
+    cache: &DyldCache<LittleEndian>,
+    filter_module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+    show_errors: bool,
+    demangle: bool,
+    (public_only, symbol_filter): (bool, &symbolfilter::SymbolFilter),
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    let mut errors = ImageErrors::new(show_errors);
+    if let Some(notice) = cachetype::CacheKind::new(cache).symbols_notice() {
+        eprintln!("{}", notice);
+    }
+    for image in cache.images() {
+        let image_path = image.path().unwrap_or("");
+
+        if let Some(filter) = filter_module
+            && image_path != filter
+        {
+            continue;
+        }
+        if public_only && !is_public_framework(image_path) {
+            continue;
+        }
+        let obj = errors.check(image_path, image.parse_object());
+        // Public API is what public frameworks export.
+        let listed = |symbol: &object::Symbol| {
+            (!public_only
+                || (symbol.is_definition() && symbol.scope() == object::SymbolScope::Dynamic))
+                && symbol_filter.keeps(symbol.name().unwrap_or(""))
+        };
+
+        if format == OutputFormat::Json {
+            let symbols: Vec<_> = obj
+                .map(|obj| {
+                    obj.symbols()
+                        .filter(listed)
+                        .map(|symbol| {
+                            let (kind, scope, section) = symbol_columns(&obj, &symbol);
+                            let name = symbol.name().unwrap_or("");
+                            let item = Json::object()
+                                .field("name", name)
+                                .field(
+                                    "address",
+                                    (!symbol.is_undefined()).then(|| symbol.address()),
+                                )
+                                .field("kind", kind)
+                                .field("scope", scope)
+                                .field("section", section);
+                            if demangle {
+                                item.field("demangled", symbolize::demangle(name))
+                            } else {
+                                item
+                            }
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            images.push(
+                Json::object()
+                    .field("image", image_path)
+                    .field("symbols", symbols),
+            );
+            continue;
+        }
+
+        println!("{}", image_path);
+        if let Some(obj) = obj {
+            let rows: Vec<_> = obj
+                .symbols()
+                .filter(listed)
+                .map(|symbol| {
+                    let address = if symbol.is_undefined() {
+                        "-".to_string()
+                    } else {
+                        fmt.format(symbol.address()).to_string()
+                    };
+                    let name = symbol.name().unwrap_or("");
+                    (
+                        address,
+                        symbol_columns(&obj, &symbol),
+                        if demangle {
+                            symbolize::with_demangled(name)
+                        } else {
+                            name.into()
+                        },
+                    )
+                })
+                .collect();
+            let width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+            for (address, (kind, scope, section), name) in rows {
+                println!(
+                    "{:<width$} {:<9} {:<11} {:<24} {}",
+                    address,
+                    kind,
+                    scope,
+                    section,
+                    name,
+                    width = width
+                );
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    errors.report();
+    Ok(())
+}
+
+/// Whether an image is a public framework, whose exported symbols are API apps may use.
+/// Frameworks nested in umbrella frameworks count too.
+fn is_public_framework(path: &str) -> bool {
+    path.starts_with("/System/Library/Frameworks/")
+}
+
+/// Images a listing command could not parse, reported on stderr so that they are not mistaken
+/// for images without sections or symbols: as they fail with `--show-errors`, and otherwise
+/// together once the listing is done.
+struct ImageErrors {
+    inline: bool,
+    errors: Vec<(String, String)>,
+}
+
+impl ImageErrors {
+    fn new(inline: bool) -> Self {
+        ImageErrors {
+            inline,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The parsed value, or `None` after recording why `path` could not be parsed.
+    fn check<T, E: std::fmt::Display>(&mut self, path: &str, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                if self.inline {
+                    eprintln!("warning: {}: {}", path, e);
+                }
+                self.errors.push((path.to_string(), e.to_string()));
+                None
+            }
+        }
+    }
+
+    fn report(&self) {
+        if self.errors.is_empty() {
+            return;
+        }
+        if !self.inline {
+            for (path, error) in &self.errors {
+                eprintln!("warning: {}: {}", path, error);
+            }
+        }
+        eprintln!(
+            "{} images could not be parsed and are listed without data",
+            self.errors.len()
+        );
+    }
+}
+
+fn cmd_deps(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        let linked = dylibs::linked_dylibs(&file)?;
+
+        if format == OutputFormat::Json {
+            let items: Vec<_> = linked
+                .iter()
+                .map(|dylib| {
+                    Json::object()
+                        .field("path", dylib.path.as_str())
+                        .field("kind", dylib.kind.to_string())
+                        .field(
+                            "compatibility_version",
+                            dylibs::format_version(dylib.compatibility_version),
+                        )
+                        .field(
+                            "current_version",
+                            dylibs::format_version(dylib.current_version),
+                        )
+                })
+                .collect();
+            images.push(
+                Json::object()
+                    .field("image", path)
+                    .field("dependencies", items),
+            );
+            continue;
+        }
+
+        // The same layout as `otool -L`.
+        println!("{}:", path);
+        for dylib in &linked {
+            let kind = match dylib.kind {
+                dylibs::LinkKind::Load => String::new(),
+                kind => format!("{}, ", kind),
+            };
+            println!(
+                "\t{} ({}compatibility version {}, current version {})",
+                dylib.path,
+                kind,
+                dylibs::format_version(dylib.compatibility_version),
+                dylibs::format_version(dylib.current_version)
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
+
+fn cmd_rdeps(
+    cache: &DyldCache<LittleEndian>,
+    dylib: &str,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    // Load commands may name a cached dylib by any of its aliases, so paths are compared by
+    // the image they resolve to.
+    let bases: HashMap<&str, u64> = cache
+        .images()
+        .filter_map(|image| Some((image.path().ok()?, image.info().address.get(LittleEndian))))
+        .collect();
+    let target = find_image(cache, dylib)
+        .ok()
+        .map(|image| image.info().address.get(LittleEndian));
+    if target.is_none() {
+        eprintln!(
+            "{} is not in the cache, matching load commands by path",
+            dylib
+        );
+    }
+    let references = |path: &str| {
+        path == dylib
+            || path.rsplit('/').next() == Some(dylib)
+            || target.is_some_and(|base| bases.get(path) == Some(&base))
+    };
+
+    let mut dependents = Vec::new();
+    for (path, image) in images_by_path(cache, None)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        for linked in dylibs::linked_dylibs(&file)? {
+            if references(&linked.path) {
+                dependents.push((path.clone(), linked.kind));
+            }
+        }
+    }
+
+    if format == OutputFormat::Json {
+        let items: Vec<_> = dependents
+            .iter()
+            .map(|(path, kind)| {
+                Json::object()
+                    .field("image", path.as_str())
+                    .field("kind", kind.to_string())
+            })
+            .collect();
+        println!("{}", Json::from(items).pretty());
+    } else {
+        for (path, kind) in &dependents {
+            match kind {
+                dylibs::LinkKind::Load => println!("{}", path),
+                kind => println!("{} ({})", path, kind),
+            }
+        }
+    }
+    eprintln!("{} images link against {}", dependents.len(), dylib);
+    Ok(())
+}
+
+fn cmd_imports(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        let linked = dylibs::linked_dylibs(&file)?;
+        let (source, imports) = dylibs::bound_imports(cache, &file)?;
+
+        if format == OutputFormat::Json {
+            let items: Vec<_> = imports
+                .iter()
+                .map(|import| {
+                    // JSON numbers are unsigned here, so the negative special ordinals are
+                    // only named by their `dylib` placeholder.
+                    let ordinal = u64::try_from(import.provider.ordinal()).ok();
+                    Json::object()
+                        .field("name", import.name.as_str())
+                        .field("ordinal", ordinal)
+                        .field("dylib", provider_name(import.provider, &linked))
+                        .field("weak", import.weak)
+                })
+                .collect();
+            images.push(
+                Json::object()
+                    .field("image", path)
+                    .field("source", source.to_string())
+                    .field("imports", items),
+            );
+            continue;
+        }
+
+        println!("{} ({})", path, source);
+        let width = imports.iter().map(|i| i.name.len()).max().unwrap_or(0);
+        for import in &imports {
+            println!(
+                "  {:<width$} #{:<3} {}{}",
+                import.name,
+                import.provider.ordinal(),
+                provider_name(import.provider, &linked),
+                if import.weak { " (weak)" } else { "" },
+                width = width
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
+
+fn cmd_exports(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+    demangle: bool,
+    symbol_filter: &symbolfilter::SymbolFilter,
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        let linked = dylibs::linked_dylibs(&file)?;
+        // Re-exports name their dylib by library ordinal, 1 for the first linked dylib.
+        let dylib = |ordinal: u64| {
+            (ordinal as usize)
+                .checked_sub(1)
+                .and_then(|i| linked.get(i))
+                .map(|d| d.path.as_str())
+        };
+        let mut entries = exports::image_exports(cache, &image)?;
+        entries.retain(|entry| symbol_filter.keeps(&entry.name));
+
+        if format == OutputFormat::Json {
+            let items: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    let mut item = Json::object()
+                        .field("name", entry.name.as_str())
+                        .field("address", entry.address())
+                        .field("flags", entry.flags)
+                        .field("kind", entry.flag_names());
+                    match &entry.kind {
+                        exports::ExportKind::Regular { address } if entry.is_absolute() => {
+                            item = item.field("value", *address);
+                        }
+                        exports::ExportKind::Reexport { ordinal, import } => {
+                            item = item
+                                .field("ordinal", *ordinal)
+                                .field("dylib", dylib(*ordinal))
+                                .field("import", import.as_deref());
+                        }
+                        exports::ExportKind::StubAndResolver { resolver, .. } => {
+                            item = item.field("resolver", *resolver);
+                        }
+                        exports::ExportKind::Regular { .. } => {}
+                    }
+                    if demangle {
+                        item = item.field("demangled", symbolize::demangle(&entry.name));
+                    }
+                    item
+                })
+                .collect();
+            images.push(Json::object().field("image", path).field("exports", items));
+            continue;
+        }
+
+        println!("{}", path);
+        let rows: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                let address = match entry.address() {
+                    Some(address) => fmt.format(address).to_string(),
+                    None => "-".to_string(),
+                };
+                let detail = match &entry.kind {
+                    exports::ExportKind::Regular { address } if entry.is_absolute() => {
+                        format!(" = 0x{:X}", address)
+                    }
+                    exports::ExportKind::Regular { .. } => String::new(),
+                    exports::ExportKind::Reexport { ordinal, import } => format!(
+                        " -> {} from #{} {}",
+                        import.as_deref().unwrap_or(&entry.name),
+                        ordinal,
+                        dylib(*ordinal).unwrap_or("(unknown ordinal)")
+                    ),
+                    exports::ExportKind::StubAndResolver { resolver, .. } => {
+                        format!(" resolver {}", fmt.format(*resolver))
+                    }
+                };
+                let name = if demangle {
+                    symbolize::with_demangled(&entry.name)
+                } else {
+                    entry.name.as_str().into()
+                };
+                (address, entry.flag_names().join(","), name, detail)
+            })
+            .collect();
+        let width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+        for (address, flags, name, detail) in rows {
+            println!(
+                "{:<width$} {:<14} {}{}",
+                address,
+                flags,
+                name,
+                detail,
+                width = width
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
+
+/// Kind, visibility and `segment,section` of a symbol, for the columns of `symbols`.
+fn symbol_columns<'data>(
+    obj: &object::File<'data>,
+    symbol: &object::Symbol<'data, '_>,
+) -> (&'static str, &'static str, String) {
+    let kind = if symbol.is_undefined() {
+        "undefined"
+    } else {
+        match symbol.kind() {
+            object::SymbolKind::Text => "function",
+            object::SymbolKind::Data => "data",
+            object::SymbolKind::Tls => "tls",
+            _ => "other",
+        }
+    };
+    // Private externs (N_PEXT) are only visible within their linkage unit.
+    let scope = match symbol.scope() {
+        object::SymbolScope::Dynamic => "external",
+        object::SymbolScope::Linkage => "private-ext",
+        object::SymbolScope::Compilation => "local",
+        _ => "-",
+    };
+    let section = match symbol.section() {
+        object::SymbolSection::Section(index) => obj
+            .section_by_index(index)
+            .ok()
+            .map(|section| {
+                format!(
+                    "{},{}",
+                    section.segment_name().ok().flatten().unwrap_or(""),
+                    section.name().unwrap_or("")
+                )
+            })
+            .unwrap_or_else(|| "-".to_string()),
+        object::SymbolSection::Absolute => "*ABS*".to_string(),
+        _ => "-".to_string(),
+    };
+    (kind, scope, section)
+}
+
+fn cmd_lookup(
+    index: &CacheIndex,
+    patterns: &[String],
+    module: Option<&str>,
+    public_only: bool,
+    regex: bool,
+    demangle: bool,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let matcher = NameMatcher::new(patterns, regex)?;
+    // With --demangle, Swift and C++ symbols also match by their demangled form.
+    let matches_name = |name: &str| matcher.matches_symbol(name, demangle);
+    let symbols = symbolize::Symbolizer::new(index);
+    let mut seen = HashSet::new();
+    let images: Vec<usize> = (0..index.len())
+        .filter(|&i| {
+            let entry = index.image(i);
+            // Aliases share the symbols of the image listed first.
+            seen.insert(entry.base)
+                && module.is_none_or(|m| entry.path == m || entry.name() == m)
+                && (!public_only || is_public_framework(&entry.path))
+        })
+        .collect();
+    // Reading the symbol tables and export tries of every image is the slow part, so images
+    // are searched in parallel and their matches printed in cache order.
+    let matches: Vec<_> = images
+        .par_iter()
+        .map(|&i| lookup_image(&symbols, i, &matches_name, public_only, demangle, fmt))
+        .collect();
+    let mut found = 0;
+    for lines in matches {
+        for line in lines? {
+            println!("{}", line);
+            found += 1;
+        }
+    }
+    if found == 0 {
+        return Err("No symbols matched".into());
+    }
+    Ok(())
+}
+
+/// Lines of `lookup` for the symbols of an image whose name `matches`.
+fn lookup_image(
+    symbols: &symbolize::Symbolizer,
+    i: usize,
+    matches: &(dyn Fn(&str) -> bool + Sync),
+    public_only: bool,
+    demangle: bool,
+    fmt: &AddressFormat,
+) -> Result<Vec<String>, String> {
+    let index = symbols.index();
+    let cache = index.cache();
+    let entry = index.image(i);
+    let table = symbols.symbols(i);
+    // Public API is what public frameworks export.
+    let exported: Option<HashSet<String>> = if public_only {
+        let image = cache.images().nth(i).ok_or("Image index out of range")?;
+        let entries = exports::image_exports(cache, &image).map_err(|e| e.to_string())?;
+        Some(entries.into_iter().map(|entry| entry.name).collect())
+    } else {
+        None
+    };
+    let matches: Vec<_> = table
+        .iter()
+        .filter(|(_, name)| exported.as_ref().is_none_or(|e| e.contains(name)))
+        .filter(|(_, name)| matches(name))
+        .collect();
+    if matches.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (starts, source) = symbols.function_starts(i);
+    let mut lines = Vec::new();
+    for &(start, ref name) in matches {
+        // The function ends at the next symbol or function start, or the end of its section.
+        let next_symbol = table.get(table.partition_point(|s| s.0 <= start));
+        let next_start = starts.get(starts.partition_point(|&a| a <= start)).copied();
+        let end = [
+            next_symbol.map(|s| s.0),
+            next_start,
+            entry.section_at(start).map(|s| s.end),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(start);
+        let source = (next_start == Some(end)).then_some(source);
+        let name = if demangle {
+            symbolize::with_demangled(name)
+        } else {
+            name.into()
+        };
+        lines.push(format!(
+            "{} {} {}{}",
+            fmt.format_range(start, end),
+            name,
+            entry.path,
+            unwind_note(source)
+        ));
+    }
+    Ok(lines)
+}
+
+/// Flags function ends taken from unwind info, which may cover several functions.
+fn unwind_note(source: Option<linkedit::StartsSource>) -> &'static str {
+    match source {
+        Some(linkedit::StartsSource::UnwindInfo) => " (end from __unwind_info)",
+        _ => "",
+    }
+}
+
+fn cmd_overlaps(
+    cache: &DyldCache<LittleEndian>,
+    start: u64,
+    end: u64,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    if start >= end {
+        return Err(format!("Invalid range 0x{:X}-0x{:X}", start, end).into());
+    }
+
+    for image in cache.images() {
+        let Ok(obj) = image.parse_object() else {
+            continue;
+        };
+
+        let hits: Vec<_> = obj
+            .segments()
+            .filter(|segment| {
+                let base = segment.address();
+                base < end && start < base + segment.size()
+            })
+            .collect();
+        if hits.is_empty() {
+            continue;
+        }
+
+        println!("{}", image.path().unwrap_or(""));
+        for segment in hits {
+            let base = segment.address();
+            println!(
+                "  {:16} {}",
+                segment.name().ok().flatten().unwrap_or(""),
+                fmt.format_range(base, base + segment.size())
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cmd_a2l(
+    index: &CacheIndex,
+    addr: u64,
+    labels: &labels::Labels,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    match resolve(index, addr) {
+        Some(location) => {
+            println!("{} {}", fmt.format(addr), location);
+            if let Some(label) = labels.at(addr) {
+                let offset = addr - label.start;
+                print!("  label {}", label.name);
+                if offset != 0 {
+                    print!("+0x{:X}", offset);
+                }
+                match &label.comment {
+                    Some(comment) => println!(" ; {}", comment),
+                    None => println!(),
+                }
+            }
+            Ok(())
+        }
+        None => Err(format!("Address 0x{:X} not found in dyld cache", addr).into()),
+    }
+}
+
+fn cmd_symbolicate(
+    index: &CacheIndex,
+    addrs: &[u64],
+    labels: &labels::Labels,
+    demangle: bool,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let symbols = symbolize::Symbolizer::new(index)
+        .with_labels(labels)
+        .with_demangling(demangle);
+    let mut results = Vec::new();
+    let mut symbolicate = |addr: u64| {
+        let addr = canonical_address(cache, addr);
+        let image = index.image_at(addr).map(|(_, image)| image.path.as_str());
+        let symbol = symbols.lookup(addr);
+        if format == OutputFormat::Json {
+            let mut item = Json::object()
+                .field("address", addr)
+                .field("image", image)
+                .field("symbol", symbol.map(|(name, _)| name))
+                .field("offset", symbol.map(|(_, offset)| offset))
+                .field("location", resolve(index, addr).map(|l| l.to_string()));
+            if demangle {
+                item = item.field(
+                    "demangled",
+                    symbol.and_then(|(name, _)| symbolize::demangle(name)),
+                );
+            }
+            results.push(item);
+            return;
+        }
+        let description = match (image, symbols.name(addr)) {
+            (Some(image), Some(name)) => format!("{} {}", image, name),
+            _ => resolve(index, addr)
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| "<not in cache>".to_string()),
+        };
+        println!("{} {}", fmt.format(addr), description);
+    };
+    if addrs.is_empty() {
+        for line in std::io::stdin().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_u64(&line) {
+                Ok(addr) => symbolicate(addr),
+                Err(e) => eprintln!("{}: {}", line.trim(), e),
+            }
+        }
+    } else {
+        addrs.iter().for_each(|&addr| symbolicate(addr));
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(results).pretty());
+    }
+    Ok(())
+}
+
+fn cmd_slide(
+    index: &CacheIndex,
+    module: &str,
+    base: u64,
+    addrs: &[u64],
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let image = find_image(cache, module)?;
+    let slide = base.wrapping_sub(image.info().address.get(LittleEndian));
+    println!("slide 0x{:X}", slide);
+    if slide % emu::page_size(cache) != 0 {
+        eprintln!("Slide is not page aligned; is the base address the Mach-O header?");
+    }
+
+    let symbols = symbolize::Symbolizer::new(index);
+    let symbolicate = |addr: u64| {
+        let unslid = canonical_address(cache, addr).wrapping_sub(slide);
+        let name = symbols
+            .name(unslid)
+            .or_else(|| resolve(index, unslid).map(|location| location.to_string()))
+            .unwrap_or_else(|| "<not in cache>".to_string());
+        println!("0x{:X} {} {}", addr, fmt.format(unslid), name);
+    };
+    if !addrs.is_empty() {
+        addrs.iter().for_each(|&addr| symbolicate(addr));
+        return Ok(());
+    }
+    for line in std::io::stdin().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_u64(&line) {
+            Ok(addr) => symbolicate(addr),
+            Err(e) => eprintln!("{}: {}", line.trim(), e),
+        }
+    }
+    Ok(())
+}
+
+fn cmd_translate(
+    from: (&CacheIndex, &AddressFormat),
+    to: (&CacheIndex, &AddressFormat),
+    addr: u64,
+) -> Result<(), Box<dyn Error>> {
+    let (from_index, from_fmt) = from;
+    let (to_index, to_fmt) = to;
+    let addr = canonical_address(from_index.cache(), addr);
+    let (_, image) = from_index
+        .image_at(addr)
+        .ok_or_else(|| format!("Address 0x{:X} is not inside an image", addr))?;
+    let from_symbols = symbolize::Symbolizer::new(from_index);
+    let (name, offset) = from_symbols
+        .lookup(addr)
+        .ok_or_else(|| format!("No symbol found for 0x{:X}", addr))?;
+
+    let to_symbols = symbolize::Symbolizer::new(to_index);
+    let (_, start) = to_symbols
+        .find(name, Some(&image.path))
+        .ok_or_else(|| format!("{} not found in {} in the target cache", name, image.path))?;
+    let label = if offset == 0 {
+        name.to_string()
+    } else {
+        format!("{}+0x{:X}", name, offset)
+    };
+    println!(
+        "{} -> {} {} {}",
+        from_fmt.format(addr),
+        to_fmt.format(start + offset),
+        label,
+        image.path
+    );
+    if offset != 0 {
+        eprintln!("Offsets inside a function only carry over if its code did not change");
+    }
+    Ok(())
+}
+
+fn cmd_port(
+    index: &CacheIndex,
+    anchor_path: &str,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let text = std::fs::read_to_string(anchor_path)
+        .map_err(|e| format!("Failed to read {}: {}", anchor_path, e))?;
+    let anchors = anchors::parse(&text)?;
+    let matches_module = |path: &str, module: &Option<String>| {
+        module
+            .as_deref()
+            .is_none_or(|m| path == m || path.rsplit('/').next() == Some(m))
+    };
+
+    // Scan for all signature anchors at once, in the images any of them asks for.
+    let mut sig_anchors = Vec::new();
+    let mut signatures = Vec::new();
+    for (i, anchor) in anchors.iter().enumerate() {
+        if let anchors::Locator::Signature(pattern) = &anchor.locator {
+            sig_anchors.push(i);
+            signatures.push(signatures::Signature {
+                name: anchor.name.clone(),
+                pattern: pattern.clone(),
+            });
+        }
+    }
+    let mut sig_matches: Vec<Vec<(u64, String)>> = vec![Vec::new(); anchors.len()];
+    if !signatures.is_empty() {
+        let scanner = signatures::Scanner::new(&signatures);
+        let align = match cache.architecture() {
+            object::Architecture::Aarch64 | object::Architecture::Arm => 4,
+            _ => 1,
+        };
+        let mut images = BTreeMap::new();
+        for &i in &sig_anchors {
+            // An unknown module leaves the anchor unresolved rather than failing the batch.
+            if let Ok(found) = images_by_path(cache, anchors[i].module.as_deref()) {
+                images.extend(found);
+            }
+        }
+        for (path, image) in &images {
+            for (s, addr) in signatures::scan_image(cache, image, &scanner, align)? {
+                let i = sig_anchors[s];
+                if matches_module(path, &anchors[i].module) {
+                    sig_matches[i].push((addr, path.clone()));
+                }
+            }
+        }
+    }
+
+    let symbols = symbolize::Symbolizer::new(index);
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for (i, anchor) in anchors.iter().enumerate() {
+        let resolved = match &anchor.locator {
+            anchors::Locator::Symbol { symbol, offset } => symbols
+                .find(symbol, anchor.module.as_deref())
+                .map(|(image, addr)| (addr + offset, index.image(image).path.clone()))
+                .ok_or_else(|| format!("symbol {} not found", symbol)),
+            anchors::Locator::Signature(_) => match sig_matches[i].as_slice() {
+                [found] => Ok(found.clone()),
+                [] => Err("signature not found".to_string()),
+                found => Err(format!("signature is ambiguous ({} matches)", found.len())),
+            },
+        };
+        if resolved.is_err() {
+            failed += 1;
+        }
+        match format {
+            OutputFormat::Text => match &resolved {
+                Ok((addr, path)) => println!("{} {} {}", fmt.format(*addr), anchor.name, path),
+                Err(e) => eprintln!("{}: {}", anchor.name, e),
+            },
+            OutputFormat::Json => {
+                let (addr, path, error) = match resolved {
+                    Ok((addr, path)) => (Some(addr), Some(path), None),
+                    Err(e) => (None, None, Some(e)),
+                };
+                results.push(
+                    Json::object()
+                        .field("name", anchor.name.as_str())
+                        .field("address", addr)
+                        .field("image", path)
+                        .field("error", error),
+                );
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(results).pretty());
+    }
+    eprintln!(
+        "{} of {} anchors resolved",
+        anchors.len() - failed,
+        anchors.len()
+    );
+    if failed > 0 {
+        return Err(format!("{} anchors could not be resolved", failed).into());
+    }
+    Ok(())
+}
+
+fn cmd_surface(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    for (path, image) in images_by_path(cache, module)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        let linked = dylibs::linked_dylibs(&file)?;
+        let imports = dylibs::import_providers(&file);
+
+        let mut counts: BTreeMap<dylibs::Provider, usize> = BTreeMap::new();
+        for &provider in &imports {
+            *counts.entry(provider).or_default() += 1;
+        }
+
+        println!("{}", path);
+        println!(
+            "  exports       {}",
+            exports::image_exports(cache, &image)?.len()
+        );
+        println!("  objc classes  {}", objc::class_count(&image)?);
+        println!("  imports       {}", imports.len());
+        println!("  linked dylibs {}", linked.len());
+        for (i, dylib) in linked.iter().enumerate() {
+            let count = counts.remove(&dylibs::Provider::Dylib(i)).unwrap_or(0);
+            if dylib.kind == dylibs::LinkKind::Load {
+                println!("    {:>6}  {}", count, dylib.path);
+            } else {
+                println!("    {:>6}  {} ({})", count, dylib.path, dylib.kind);
+            }
+        }
+        // Imports that do not name a linked dylib.
+        for (provider, count) in counts {
+            println!("    {:>6}  {}", count, provider_name(provider, &linked));
+        }
+    }
+    Ok(())
+}
+
+/// The path of the dylib an import comes from, or a placeholder for special library ordinals.
+fn provider_name(provider: dylibs::Provider, linked: &[dylibs::LinkedDylib]) -> String {
+    match provider {
+        dylibs::Provider::Dylib(i) => match linked.get(i) {
+            Some(dylib) => dylib.path.clone(),
+            None => format!("<invalid ordinal {}>", i + 1),
+        },
+        dylibs::Provider::This => "<self>".to_string(),
+        dylibs::Provider::MainExecutable => "<main executable>".to_string(),
+        dylibs::Provider::FlatLookup => "<flat lookup>".to_string(),
+        dylibs::Provider::WeakLookup => "<weak lookup>".to_string(),
+    }
+}
+
+fn cmd_languages(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    kind: Option<languages::Kind>,
+) -> Result<(), Box<dyn Error>> {
+    let reader = objc::ObjcReader::new(cache);
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let languages = languages::image_languages(cache, &reader, &image)?;
+        let image_kind = languages.kind();
+        if kind.is_some_and(|k| k != image_kind) {
+            continue;
+        }
+        *counts.entry(image_kind.to_string()).or_default() += 1;
+        let cxx = if languages.cxx { "c++" } else { "" };
+        println!("{:<10} {:<3} {}", image_kind, cxx, path);
+    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+    if !summary.is_empty() {
+        eprintln!("{}", summary.join(", "));
+    }
+    Ok(())
+}
+
+struct StringFilter<'a> {
+    section: Option<&'a str>,
+    all_sections: bool,
+    min_length: usize,
+    printable: f64,
+}
+
+fn cmd_strings(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    filter: &StringFilter,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut images = Vec::new();
+    let mut total = 0;
+    for (path, image) in images_by_path(cache, module)? {
+        let found: Vec<_> = strings::scan_strings(cache, &image, filter.all_sections)?
+            .into_iter()
+            .filter(|s| filter.section.is_none_or(|section| s.section == section))
+            .filter(|s| s.value.chars().count() >= filter.min_length)
+            .filter(|s| strings::printable_ratio(&s.value) >= filter.printable)
+            .collect();
+        if found.is_empty() {
+            continue;
+        }
+        total += found.len();
+        if format == OutputFormat::Json {
+            let found: Vec<_> = found
+                .into_iter()
+                .map(|s| {
+                    Json::object()
+                        .field("address", s.address)
+                        .field("section", s.section)
+                        .field("value", s.value)
+                })
+                .collect();
+            images.push(Json::object().field("image", path).field("strings", found));
+            continue;
+        }
+        println!("{}", path);
+        for s in found {
+            println!(
+                "  {} {:<16} {}",
+                fmt.format(s.address),
+                s.section,
+                strings::escape(&s.value)
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    eprintln!("{} strings", total);
+    Ok(())
+}
+
+fn cmd_oslog(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    contains: Option<&str>,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    for (path, image) in images_by_path(cache, module)? {
+        let strings: Vec<_> = strings::image_strings(cache, &image)?
+            .into_iter()
+            .filter(|s| s.section == "__oslogstring")
+            .filter(|s| contains.is_none_or(|c| s.value.contains(c)))
+            .collect();
+        if strings.is_empty() {
+            continue;
+        }
+        println!("{}", path);
+        for s in strings {
+            println!("  {} {}", fmt.format(s.address), strings::escape(&s.value));
+        }
+    }
+    Ok(())
+}
+
+fn cmd_grep(
+    cache: &DyldCache<LittleEndian>,
+    (needle, substring): (&str, bool),
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    // Aliases are left out by `images_by_path`, so each image is searched and reported once.
+    let mut images = Vec::new();
+    let mut total = 0;
+    for (path, image) in images_by_path(cache, module)? {
+        let found = strings::find_strings(cache, &image, needle, substring)?;
+        if found.is_empty() {
+            continue;
+        }
+        total += 1;
+        if format == OutputFormat::Json {
+            let matches: Vec<_> = found
+                .into_iter()
+                .map(|s| {
+                    Json::object()
+                        .field("address", s.address)
+                        .field("section", s.section)
+                        .field("value", s.value)
+                })
+                .collect();
+            images.push(
+                Json::object()
+                    .field("image", path)
+                    .field("matches", matches),
+            );
+            continue;
+        }
+        println!("{}", path);
+        for s in found {
+            println!(
+                "  {} {:<16} {}",
+                fmt.format(s.address),
+                s.section,
+                strings::escape(&s.value)
+            );
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    eprintln!("{} images contain the string", total);
+    Ok(())
+}
+
+fn cmd_search_string(
+    cache: &DyldCache<LittleEndian>,
+    index: &CacheIndex,
+    needle: &str,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    if needle.is_empty() {
+        return Err("The string to search for is empty".into());
+    }
+    let needle = needle.as_bytes();
+    let hits = search_mappings(cache, object::macho::VM_PROT_READ, needle.len(), |data| {
+        strings::find_bytes(data, needle)
+    });
+    print_hits(index, &hits, module, fmt, format);
+    Ok(())
+}
+
+fn cmd_search_bytes(
+    cache: &DyldCache<LittleEndian>,
+    index: &CacheIndex,
+    pattern: &str,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let pattern = signatures::parse_pattern(pattern)?;
+    // Instructions are 4-byte aligned on ARM; x86 code can start anywhere.
+    let align = match cache.architecture() {
+        object::Architecture::Aarch64 | object::Architecture::Arm => 4,
+        _ => 1,
+    };
+    let hits = search_mappings(
+        cache,
+        object::macho::VM_PROT_EXECUTE,
+        pattern.len(),
+        |data| signatures::find_pattern(data, &pattern, align),
+    );
+    print_hits(index, &hits, module, fmt, format);
+    Ok(())
+}
+
+fn cmd_xrefs(
+    cache: &DyldCache<LittleEndian>,
+    index: &CacheIndex,
+    addr: u64,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let addr = canonical_address(cache, addr);
+    if format == OutputFormat::Text {
+        match resolve(index, addr) {
+            Some(location) => eprintln!("References to {} ({})", fmt.format(addr), location),
+            None => eprintln!("References to {}", fmt.format(addr)),
+        }
+    }
+    let hits = slide::references(cache, addr)?;
+    print_hits(index, &hits, module, fmt, format);
+    Ok(())
+}
+
+fn cmd_hooks(
+    index: &CacheIndex,
+    function: &str,
+    module: Option<&str>,
+    demangle: bool,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let symbols = symbolize::Symbolizer::new(index).with_demangling(demangle);
+    let (image, target) = match parse_u64(function) {
+        Ok(addr) => {
+            let addr = canonical_address(cache, addr);
+            let (image, _) = index
+                .image_at(addr)
+                .ok_or_else(|| format!("0x{:X} is not inside an image", addr))?;
+            (image, addr)
+        }
+        Err(_) => symbols
+            .find(function, module)
+            .ok_or_else(|| format!("Symbol {} not found", function))?,
+    };
+    let name = symbols.name(target);
+    let selector = hooks::method_selector(cache, image, target);
+    let points = hooks::hook_points(index, image, target)?;
+    if patchtable::patch_sites(cache, image, target).is_err() {
+        eprintln!("note: the cache has no readable patch table");
+    }
+
+    let describe = |addr: u64| {
+        symbols
+            .name(addr)
+            .map(|name| match index.image_at(addr) {
+                Some((_, image)) => format!("{} {}", image.name(), name),
+                None => name,
+            })
+            .or_else(|| resolve(index, addr).map(|l| l.to_string()))
+            .unwrap_or_else(|| "<not in any image>".to_string())
+    };
+
+    if format == OutputFormat::Json {
+        let items: Vec<_> = points
+            .iter()
+            .map(|p| {
+                Json::object()
+                    .field("kind", p.kind.name())
+                    .field("address", p.address)
+                    .field(
+                        "image",
+                        index.image_at(p.address).map(|(_, i)| i.path.as_str()),
+                    )
+                    .field("symbol", symbols.name(p.address))
+                    .field("location", resolve(index, p.address).map(|l| l.to_string()))
+                    .field("via", p.via)
+            })
+            .collect();
+        let report = Json::object()
+            .field("function", name.as_deref())
+            .field("address", target)
+            .field("image", index.image(image).path.as_str())
+            .field("selector", selector.as_deref())
+            .field("points", items);
+        println!("{}", report.pretty());
+        return Ok(());
+    }
+
+    println!(
+        "{} {} ({})",
+        fmt.format(target),
+        name.as_deref().unwrap_or("<unnamed>"),
+        index.image(image).path
+    );
+    if let Some(selector) = &selector {
+        println!("implements {}", selector);
+    }
+    let mut kinds: Vec<hooks::HookKind> = points.iter().map(|p| p.kind).collect();
+    kinds.dedup();
+    for kind in kinds {
+        let of_kind: Vec<_> = points.iter().filter(|p| p.kind == kind).collect();
+        println!();
+        println!("{} ({}): {}", kind.name(), of_kind.len(), kind.summary());
+        for point in of_kind {
+            match point.via {
+                Some(via) => println!(
+                    "  {}  {}  via {}",
+                    fmt.format(point.address),
+                    describe(point.address),
+                    fmt.format(via)
+                ),
+                None => println!(
+                    "  {}  {}",
+                    fmt.format(point.address),
+                    describe(point.address)
+                ),
+            }
+        }
+    }
+    if points.is_empty() {
+        eprintln!("No hook points found");
+    }
+    Ok(())
+}
+
+/// Search the mappings with any of the `prot` bits in their initial protection, returning the
+/// sorted addresses of the offsets `find` reports. Mappings run to hundreds of megabytes, so
+/// they are searched in parallel chunks, each overlapping the next by `len - 1` bytes so that a
+/// match of `len` bytes straddling a boundary is found, and found once.
+fn search_mappings<F>(cache: &DyldCache<LittleEndian>, prot: u32, len: usize, find: F) -> Vec<u64>
+where
+    F: Fn(&[u8]) -> Vec<usize> + Sync,
+{
+    // A multiple of the instruction size, so that chunks keep the alignment of their mapping.
+    const CHUNK: usize = 16 << 20;
+    let mut chunks = Vec::new();
+    for mapping in cache.mappings() {
+        if mapping.init_prot() & prot == 0 {
+            continue;
+        }
+        let Ok(data) = mapping.data() else {
+            continue;
+        };
+        let data = &data[..std::cmp::min(data.len(), mapping.size() as usize)];
+        for start in (0..data.len()).step_by(CHUNK) {
+            let end = std::cmp::min(data.len(), start + CHUNK + len.saturating_sub(1));
+            chunks.push((mapping.address() + start as u64, &data[start..end]));
+        }
+    }
+    let mut hits: Vec<u64> = chunks
+        .par_iter()
+        .flat_map_iter(|&(address, data)| {
+            find(data)
+                .into_iter()
+                .map(move |offset| address + offset as u64)
+        })
+        .collect();
+    hits.sort_unstable();
+    hits.dedup();
+    hits
+}
+
+/// Print search hits with the image and section containing them, keeping only those inside
+/// `module` when it is given.
+fn print_hits(
+    index: &CacheIndex,
+    hits: &[u64],
+    module: Option<&str>,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) {
+    let mut json = Vec::new();
+    let mut total = 0;
+    for &addr in hits {
+        let location = resolve(index, addr);
+        let (image, section) = match &location {
+            Some(resolve::Location::Image {
+                path,
+                segment,
+                section,
+                ..
+            }) => (
+                Some(path.as_str()),
+                Some(match section {
+                    Some(section) => format!("{},{}", segment, section),
+                    None => segment.clone(),
+                }),
+            ),
+            _ => (None, None),
+        };
+        if let Some(module) = module
+            && !image.is_some_and(|p| p == module || p.rsplit('/').next() == Some(module))
+        {
+            continue;
+        }
+        total += 1;
+        if format == OutputFormat::Json {
+            json.push(
+                Json::object()
+                    .field("address", addr)
+                    .field("image", image)
+                    .field("section", section)
+                    .field("location", location.as_ref().map(|l| l.to_string())),
+            );
+            continue;
+        }
+        match location {
+            Some(location) => println!("{} {}", fmt.format(addr), location),
+            None => println!("{} <not in any image>", fmt.format(addr)),
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(json).pretty());
+    }
+    eprintln!("{} occurrences found", total);
+}
+
+fn cmd_shim(
+    cache: &DyldCache<LittleEndian>,
+    module: &str,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let image = find_image(cache, module)?;
+    let shim = shim::Shim::new(cache, &image)?;
+    shim.write(Path::new(output))?;
+    eprintln!(
+        "Wrote {} exported symbols, {} re-exported symbols, {} aliases and {} re-exported \
+         libraries of {} to {}",
+        shim.exported.len(),
+        shim.reexported.len(),
+        shim.aliases.len(),
+        shim.reexported_libraries.len(),
+        shim.install_name,
+        output
+    );
+    Ok(())
+}
+
+fn cmd_umbrellas(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let matches =
+        |path: &str| module.is_none_or(|m| path == m || path.rsplit('/').next() == Some(m));
+    // (umbrella name, member image) pairs, install path -> paths it re-exports, and file name
+    // -> install paths, to find the image an umbrella name refers to.
+    let mut membership: Vec<(String, String)> = Vec::new();
+    let mut reexports: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut by_name: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (path, image) in images_by_path(cache, None)? {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?;
+        let reexported = dylibs::linked_dylibs(&file)?
+            .into_iter()
+            .filter(|d| d.kind == dylibs::LinkKind::Reexport)
+            .map(|d| d.path)
+            .collect();
+        let name = path.rsplit('/').next().unwrap_or("");
+        by_name
+            .entry(name.to_string())
+            .or_default()
+            .push(path.clone());
+        reexports.insert(path.clone(), reexported);
+
+        let relationships = dylibs::relationships(&file)?;
+        if let Some(umbrella) = &relationships.umbrella {
+            membership.push((umbrella.clone(), path.clone()));
+        }
+        if !matches(&path) {
+            continue;
+        }
+        let rows = [
+            ("umbrella", relationships.umbrella.into_iter().collect()),
+            ("sub-umbrella", relationships.sub_umbrellas),
+            ("sub-library", relationships.sub_libraries),
+            ("client", relationships.clients),
+            ("rpath", relationships.rpaths),
+        ];
+        if rows.iter().all(|(_, values)| values.is_empty()) {
+            continue;
+        }
+        println!("{}", path);
+        for (label, values) in &rows {
+            for value in values {
+                println!("  {:<12} {}", label, value);
+            }
+        }
+    }
+
+    // Several images can share the umbrella's name (e.g. a framework and its iOSSupport
+    // twin): a member belongs to the one re-exporting it, or to the first of them.
+    let mut members: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (umbrella, path) in membership {
+        let candidates = by_name
+            .get(&umbrella)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let owner = candidates
+            .iter()
+            .find(|c| reexports.get(*c).is_some_and(|r| r.contains(&path)))
+            .or(candidates.first())
+            .cloned()
+            .unwrap_or(umbrella);
+        members.entry(owner).or_default().push(path);
+    }
+
+    let mut header = false;
+    for (umbrella, paths) in &members {
+        if !matches(umbrella) && !paths.iter().any(|p| matches(p)) {
+            continue;
+        }
+        if !header {
+            println!();
+            println!("Umbrellas:");
+            header = true;
+        }
+        println!("{}", umbrella);
+        let reexported = reexports.get(umbrella);
+        for path in paths {
+            let note = if reexported.is_some_and(|r| r.contains(path)) {
+                " (re-exported)"
+            } else {
+                ""
+            };
+            println!("  {}{}", path, note);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_simulate_load(
+    cache: &DyldCache<LittleEndian>,
+    executable: &str,
+) -> Result<(), Box<dyn Error>> {
+    let file =
+        File::open(executable).map_err(|e| format!("Failed to open {}: {}", executable, e))?;
+    let data = unsafe { Mmap::map(&file)? };
+    let slice = simulate::executable_slice(&data, cache)?;
+    let exe = MachOFile64::<LittleEndian>::parse(slice)
+        .map_err(|e| format!("Failed to parse {}: {}", executable, e))?;
+    let simulation = simulate::simulate(cache, &exe, executable)?;
+
+    let via = |parent: &Option<String>| parent.as_deref().unwrap_or(executable).to_string();
+    println!("Loaded ({}):", simulation.loads.len());
+    for load in &simulation.loads {
+        let location = if load.in_cache { "" } else { " (on disk)" };
+        println!(
+            "  {}{} [{} from {}]",
+            load.path,
+            location,
+            load.kind,
+            via(&load.parent)
+        );
+    }
+    if !simulation.missing.is_empty() {
+        println!("Unsatisfied dependencies ({}):", simulation.missing.len());
+        for missing in &simulation.missing {
+            let note = if missing.kind == dylibs::LinkKind::Weak {
+                " (weak, ignored by dyld)"
+            } else {
+                ""
+            };
+            println!(
+                "  {} [{} from {}]{}",
+                missing.path,
+                missing.kind,
+                via(&missing.parent),
+                note
+            );
+        }
+    }
+    if !simulation.missing_symbols.is_empty() {
+        println!("Unresolved symbols ({}):", simulation.missing_symbols.len());
+        for symbol in &simulation.missing_symbols {
+            let note = if symbol.weak { " (weak import)" } else { "" };
+            println!("  {} from {}{}", symbol.name, symbol.dylib, note);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_audit_deps(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let cached: HashSet<&str> = cache.images().filter_map(|i| i.path().ok()).collect();
+    let (mut dangling, mut on_disk) = (0, 0);
+    let images = images_by_path(cache, module)?;
+    for (path, image) in &images {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+        let rpaths = dylibs::relationships(&file)?.rpaths;
+        let dir = simulate::parent_dir(path);
+        let mut header = false;
+        for dylib in dylibs::linked_dylibs(&file)? {
+            // Without an executable, @executable_path cannot be resolved.
+            if dylib.path.starts_with("@executable_path") {
+                continue;
+            }
+            let candidates = simulate::expand(&dylib.path, &rpaths, "", &dir);
+            if candidates.iter().any(|c| cached.contains(c.as_str())) {
+                continue;
+            }
+            let status = if candidates.iter().any(|c| Path::new(c).exists()) {
+                on_disk += 1;
+                "on disk"
+            } else {
+                dangling += 1;
+                match dylib.kind {
+                    dylibs::LinkKind::Reexport => "missing, broken re-export",
+                    dylibs::LinkKind::Weak => "missing, weak",
+                    _ => "missing",
+                }
+            };
+            if !header {
+                println!("{}", path);
+                header = true;
+            }
+            println!("  {:<8} {} ({})", dylib.kind, dylib.path, status);
+        }
+    }
+    eprintln!(
+        "{} dangling and {} on-disk references in {} images",
+        dangling,
+        on_disk,
+        images.len()
+    );
+    Ok(())
+}
+
+fn cmd_signatures(
+    cache: &DyldCache<LittleEndian>,
+    signature_path: &str,
+    modules: &[String],
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let text = std::fs::read_to_string(signature_path)
+        .map_err(|e| format!("Failed to read {}: {}", signature_path, e))?;
+    let signatures = signatures::parse(&text)?;
+    let scanner = signatures::Scanner::new(&signatures);
+    // Instructions are 4-byte aligned on ARM; x86 code can start anywhere.
+    let align = match cache.architecture() {
+        object::Architecture::Aarch64 | object::Architecture::Arm => 4,
+        _ => 1,
+    };
+
+    let mut images = BTreeMap::new();
+    if modules.is_empty() {
+        images = images_by_path(cache, None)?;
+    } else {
+        for module in modules {
+            images.extend(images_by_path(cache, Some(module))?);
+        }
+    }
+
+    let mut total = 0;
+    for (path, image) in &images {
+        let mut matches = signatures::scan_image(cache, image, &scanner, align)?;
+        matches.sort_by_key(|&(_, addr)| addr);
+        for (i, addr) in matches {
+            total += 1;
+            println!("{} {} {}", fmt.format(addr), signatures[i].name, path);
+        }
+    }
+    eprintln!(
+        "{} matches for {} signatures in {} images",
+        total,
+        signatures.len(),
+        images.len()
+    );
+    Ok(())
+}
+
+fn cmd_duplicates(
+    index: &CacheIndex,
+    modules: &[String],
+    min_size: u64,
+    normalize: bool,
+    demangle: bool,
+    fmt: &AddressFormat,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let images: Vec<usize> = if modules.is_empty() {
+        (0..index.len()).collect()
+    } else {
+        modules
+            .iter()
+            .map(|module| image_index(index, module))
+            .collect::<Result<_, _>>()?
+    };
+    let symbols = symbolize::Symbolizer::new(index).with_demangling(demangle);
+    let groups = duplicates::find_duplicates(&symbols, &images, min_size, normalize);
+    let redundant: u64 = groups.iter().map(|g| g.redundant_bytes()).sum();
+
+    if format == OutputFormat::Json {
+        let items: Vec<_> = groups
+            .iter()
+            .map(|group| {
+                let functions: Vec<_> = group
+                    .functions
+                    .iter()
+                    .map(|f| {
+                        Json::object()
+                            .field("address", f.address)
+                            .field("image", index.image(f.image).path.as_str())
+                            .field("symbol", symbols.name(f.address))
+                    })
+                    .collect();
+                Json::object()
+                    .field("size", group.size)
+                    .field("redundant_bytes", group.redundant_bytes())
+                    .field("functions", functions)
+            })
+            .collect();
+        println!("{}", Json::from(items).pretty());
+    } else {
+        for group in &groups {
+            println!(
+                "{} bytes, {} copies in {} images",
+                group.size,
+                group.functions.len(),
+                group.images()
+            );
+            for f in &group.functions {
+                println!(
+                    "  {}  {}  {}",
+                    fmt.format(f.address),
+                    index.image(f.image).name(),
+                    symbols.name(f.address).as_deref().unwrap_or("<unnamed>")
+                );
+            }
+        }
+    }
+    eprintln!(
+        "{} groups of identical functions, {} redundant bytes",
+        groups.len(),
+        redundant
+    );
+    Ok(())
+}
+
+/// Magic at the start of binary function start exports.
+const FUNCTION_STARTS_MAGIC: &[u8; 8] = b"DSCFNST2";
+
+fn cmd_function_starts(
+    cache: &DyldCache<LittleEndian>,
+    output: &str,
+    format: StartsFormat,
+    module: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let images = images_by_path(cache, module)?;
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output, e))?;
+    let mut out = BufWriter::new(file);
+    match format {
+        StartsFormat::Csv => writeln!(out, "image,address,offset,source")?,
+        StartsFormat::Binary => {
+            out.write_all(FUNCTION_STARTS_MAGIC)?;
+            out.write_all(&(images.len() as u32).to_le_bytes())?;
+        }
+        StartsFormat::Json => {}
+    }
+    let mut entries = Vec::new();
+
+    let mut total = 0;
+    let mut from_unwind = 0;
+    for (path, image) in &images {
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+        let (starts, source) = linkedit::function_boundaries(cache, &file)?;
+        let base = image.info().address.get(LittleEndian);
+        total += starts.len();
+        if source == linkedit::StartsSource::UnwindInfo {
+            from_unwind += 1;
+        }
+        match format {
+            StartsFormat::Csv => {
+                for start in starts {
+                    writeln!(
+                        out,
+                        "{},0x{:X},0x{:X},{}",
+                        path,
+                        start,
+                        start - base,
+                        source.name()
+                    )?;
+                }
+            }
+            StartsFormat::Binary => {
+                out.write_all(&(path.len() as u32).to_le_bytes())?;
+                out.write_all(path.as_bytes())?;
+                out.write_all(&base.to_le_bytes())?;
+                out.write_all(&[(source == linkedit::StartsSource::UnwindInfo) as u8])?;
+                out.write_all(&(starts.len() as u32).to_le_bytes())?;
+                for start in starts {
+                    let offset = u32::try_from(start - base).map_err(|_| {
+                        format!("{}: function 0x{:X} too far from base", path, start)
+                    })?;
+                    out.write_all(&offset.to_le_bytes())?;
+                }
+            }
+            StartsFormat::Json => entries.push(
+                Json::object()
+                    .field("image", path.as_str())
+                    .field("base", base)
+                    .field("source", source.name())
+                    .field(
+                        "offsets",
+                        starts.iter().map(|s| s - base).collect::<Vec<_>>(),
+                    ),
+            ),
+        }
+    }
+    if format == StartsFormat::Json {
+        writeln!(out, "{}", Json::Array(entries))?;
+    }
+    out.flush()?;
+    eprintln!(
+        "Wrote {} function starts of {} images to {}",
+        total,
+        images.len(),
+        output
+    );
+    if from_unwind > 0 {
+        eprintln!(
+            "{} images have no LC_FUNCTION_STARTS; their starts come from __unwind_info",
+            from_unwind
+        );
+    }
+    Ok(())
+}
+
+fn cmd_index(index: &CacheIndex, output: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(output).map_err(|e| format!("Failed to create {}: {}", output, e))?;
+    let mut out = BufWriter::new(file);
+    let counts = indexfile::write(index, &mut out)?;
+    out.flush()?;
+    eprintln!(
+        "Wrote {} images, {} sections and {} symbols to {}",
+        counts.images, counts.sections, counts.symbols, output
+    );
+    Ok(())
+}
+
+fn cmd_check(cache: &DyldCache<LittleEndian>, module: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let images = images_by_path(cache, module)?;
+    let mut flagged = 0;
+    for (path, image) in &images {
+        let warnings = match check::check_image(cache, image) {
+            Ok(warnings) => warnings,
+            Err(e) => vec![format!("check failed: {}", e)],
+        };
+        if !warnings.is_empty() {
+            flagged += 1;
+        }
+        for warning in warnings {
+            eprintln!("warning: {}: {}", path, warning);
+        }
+    }
+    eprintln!("{} of {} images with discrepancies", flagged, images.len());
+    Ok(())
+}
+
+/// How `blob` reports an extraction.
+#[derive(Clone, Copy)]
+struct BlobMode {
+    /// Trace each step, and write the trace beside the blob.
+    explain: bool,
+    /// Only print the planned layout.
+    dry_run: bool,
+}
+
+fn cmd_blob(
+    cache: &DyldCache<LittleEndian>,
+    module: &str,
+    output: &str,
+    options: extract::ExtractOptions,
+    mode: BlobMode,
+) -> Result<(), Box<dyn Error>> {
+    let image = find_image(cache, module)?;
+    let (blob, trace) = extract_blob(cache, &image, options, mode.explain)?;
+    match mode.dry_run {
+        true => {
+            print_plan(&blob, output, mode.explain, &[]);
+            Ok(())
+        }
+        false => save_blob(&blob, trace, output),
+    }
+}
+
+/// Most blobs being written at once. Extraction is the CPU heavy part; more writers would
+/// only compete for the disk.
+const BLOB_WRITERS: usize = 4;
+
+/// Extract every image, or those whose install path matches a glob in `filters` or `regex`,
+/// to `<root>/<install path>.bin` and `.json`, carrying on past failures.
+fn cmd_blob_all(
+    cache: &DyldCache<LittleEndian>,
+    root: &str,
+    selection: (&[String], Option<&str>),
+    options: extract::ExtractOptions,
+    mode: BlobMode,
+    jobs: Option<usize>,
+    throttle: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (filters, regex) = selection;
+    let regex = regex.map(regex::Regex::new).transpose()?;
+    let selected = |path: &str| {
+        (filters.is_empty() && regex.is_none())
+            || filters.iter().any(|f| wildcard_match(f, path))
+            || regex.as_ref().is_some_and(|r| r.is_match(path))
+    };
+    let mut images = images_by_path(cache, None)?;
+    // Other install paths of the same Mach-O, by the path the image is extracted to.
+    let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+    let canonical: HashMap<u64, &String> = images
+        .iter()
+        .map(|(path, image)| (image.info().address.get(LittleEndian), path))
+        .collect();
+    for image in cache.images() {
+        let path = image.path()?;
+        if let Some(&target) = canonical.get(&image.info().address.get(LittleEndian))
+            && target != path
+        {
+            aliases
+                .entry(target.clone())
+                .or_default()
+                .push(path.to_string());
+        }
+    }
+    images.retain(|path, _| {
+        selected(path)
+            || aliases
+                .get(path)
+                .is_some_and(|a| a.iter().any(|p| selected(p)))
+    });
+    if images.is_empty() {
+        return Err("No image matches the filters".into());
+    }
+    let images: Vec<_> = images.into_iter().collect();
+    let no_aliases = Vec::new();
+    let aliases_of = |path: &str| aliases.get(path).unwrap_or(&no_aliases);
+
+    let jobs = jobs
+        .unwrap_or_else(|| {
+            if throttle {
+                1
+            } else {
+                std::thread::available_parallelism().map_or(1, |n| n.get())
+            }
+        })
+        .max(1);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    // Blobs waiting for a writer are bounded, so a slow disk does not pile them up in memory.
+    // Throttled runs read ahead by a single image.
+    let queued = if throttle { 1 } else { jobs };
+    let (sender, receiver) = mpsc::sync_channel::<(&str, extract::Extracted, Explain)>(queued);
+    let receiver = Mutex::new(receiver);
+    let failures = Mutex::new(Vec::new());
+    let linked = Mutex::new(0usize);
+    let fail = |path: &str, e: String| {
+        eprintln!("{}: {}", path, e);
+        failures.lock().unwrap().push((path.to_string(), e));
+    };
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(BLOB_WRITERS) {
+            scope.spawn(|| {
+                loop {
+                    let job = receiver.lock().unwrap().recv();
+                    let Ok((path, blob, trace)) = job else {
+                        break;
+                    };
+                    let prefix = Path::new(root).join(path.trim_start_matches('/'));
+                    let aliases = aliases_of(path);
+                    if mode.dry_run {
+                        let links: Vec<String> = aliases
+                            .iter()
+                            .map(|a| Path::new(root).join(a.trim_start_matches('/')))
+                            .map(|a| a.to_string_lossy().into_owned())
+                            .collect();
+                        print_plan(&blob, &prefix.to_string_lossy(), mode.explain, &links);
+                        *linked.lock().unwrap() += aliases.len();
+                        continue;
+                    }
+                    let result = prefix
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .map_err(|e| e.into())
+                        .and_then(|_| {
+                            let prefix = prefix.to_str().ok_or("Output path is not valid UTF-8")?;
+                            save_blob(&blob, trace, prefix)
+                        });
+                    if let Err(e) = result {
+                        fail(path, e.to_string());
+                        continue;
+                    }
+                    for alias in aliases {
+                        match link_alias(Path::new(root), path, alias) {
+                            Ok(()) => *linked.lock().unwrap() += 1,
+                            Err(e) => fail(alias, e.to_string()),
+                        }
+                    }
+                }
+            });
+        }
+        // The cache is only read, so every worker shares the same mapping.
+        pool.install(|| {
+            images
+                .par_iter()
+                .for_each_with(sender, |sender, (path, image)| {
+                    match extract_blob(cache, image, options, mode.explain) {
+                        // Writers only stop once every sender is gone.
+                        Ok((blob, trace)) => sender.send((path.as_str(), blob, trace)).unwrap(),
+                        Err(e) => fail(path, e.to_string()),
+                    }
+                })
+        });
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    let linked = linked.into_inner().unwrap();
+    // A failed alias is only a failed link: its image was written.
+    let extracted = images
+        .iter()
+        .filter(|(path, _)| !failures.iter().any(|(failed, _)| failed == path))
+        .count();
+    eprintln!(
+        "{} {} of {} images to {}, {} {} aliases",
+        if mode.dry_run {
+            "Would extract"
+        } else {
+            "Extracted"
+        },
+        extracted,
+        images.len(),
+        root,
+        if mode.dry_run { "would link" } else { "linked" },
+        linked
+    );
+    if failures.is_empty() {
+        return Ok(());
+    }
+    failures.sort();
+    eprintln!("Failed:");
+    for (path, e) in &failures {
+        eprintln!("  {}: {}", path, e);
+    }
+    Err(format!("{} images or aliases could not be written", failures.len()).into())
+}
+
+fn extract_blob(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    options: extract::ExtractOptions,
+    explain: bool,
+) -> Result<(extract::Extracted, Explain), Box<dyn Error>> {
+    let mut trace = Explain::new(explain);
+    let extracted = extract::extract(cache, image, &options, &mut trace)?;
+    Ok((extracted, trace))
+}
+
+/// Print what [`save_blob`] would write to `output`: the segments at their offsets in the
+/// blob, the segments left out, the total size and the files, with the prefixes of the
+/// `aliases` linked to them.
+fn print_plan(extracted: &extract::Extracted, output: &str, explain: bool, aliases: &[String]) {
+    let metadata = &extracted.metadata;
+    let field = |entry: &Json, key: &str| entry.get(key).and_then(Json::as_u64).unwrap_or(0);
+    let name = |entry: &Json| {
+        entry
+            .get("name")
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    for warning in &extracted.warnings {
+        eprintln!("warning: {}: {}", output, warning.message);
+    }
+    // Built whole, as images extracted in parallel are printed from several threads.
+    let mut plan = format!(
+        "{}: 0x{:X} bytes at 0x{:X}\n  {:<12} {:<10} {:<18} {:<10} segment\n",
+        metadata.get("image").and_then(Json::as_str).unwrap_or(""),
+        extracted.data.len(),
+        extracted.base,
+        "offset",
+        "size",
+        "vmaddr",
+        "zero-fill"
+    );
+    for segment in metadata
+        .get("segments")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+    {
+        let (vmsize, filesize) = (field(segment, "vmsize"), field(segment, "filesize"));
+        plan += &format!(
+            "  {:<12} {:<10} {:<18} {:<10} {}\n",
+            format!("0x{:X}", field(segment, "offset")),
+            format!("0x{:X}", vmsize),
+            format!("0x{:X}", field(segment, "vmaddr")),
+            format!("0x{:X}", vmsize.saturating_sub(filesize)),
+            name(segment)
+        );
+    }
+    for segment in metadata
+        .get("skipped")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+    {
+        let vmaddr = field(segment, "vmaddr");
+        plan += &format!(
+            "  skip {} 0x{:X}-0x{:X}, {}\n",
+            name(segment),
+            vmaddr,
+            vmaddr + field(segment, "vmsize"),
+            segment.get("reason").and_then(Json::as_str).unwrap_or("")
+        );
+    }
+    let mut files = vec![format!("{}.bin", output), format!("{}.json", output)];
+    if explain {
+        files.push(format!("{}.explain.json", output));
+    }
+    plan += &format!("  would write {}", files.join(", "));
+    for alias in aliases {
+        plan += &format!("\n  would link {}.* to them", alias);
+    }
+    println!("{}", plan);
+}
+
+/// Files [`save_blob`] may write beside each other, by suffix.
+const BLOB_FILES: [&str; 3] = [".bin", ".json", ".explain.json"];
+
+/// Link the files of the blob extracted to `<root>/<path>` from `<root>/<alias>`, with
+/// relative symlinks so the tree can be moved, or copies where there are no symlinks.
+fn link_alias(root: &Path, path: &str, alias: &str) -> Result<(), Box<dyn Error>> {
+    let (path, alias) = (path.trim_start_matches('/'), alias.trim_start_matches('/'));
+    let link_prefix = root.join(alias);
+    if let Some(parent) = link_prefix.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    for suffix in BLOB_FILES {
+        let target = root.join(format!("{}{}", path, suffix));
+        if !target.exists() {
+            continue;
+        }
+        let link = root.join(format!("{}{}", alias, suffix));
+        // Left from an earlier run.
+        if link.symlink_metadata().is_ok() {
+            std::fs::remove_file(&link)
+                .map_err(|e| format!("Failed to replace {}: {}", link.display(), e))?;
+        }
+        #[cfg(unix)]
+        let result = {
+            let up = "../".repeat(alias.matches('/').count());
+            std::os::unix::fs::symlink(format!("{}{}{}", up, path, suffix), &link)
+        };
+        #[cfg(not(unix))]
+        let result = std::fs::copy(&target, &link).map(|_| ());
+        result.map_err(|e| format!("Failed to link {}: {}", link.display(), e))?;
+    }
+    Ok(())
+}
+
+fn save_blob(
+    extracted: &extract::Extracted,
+    trace: Explain,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let bin_path = format!("{}.bin", output);
+    let json_path = format!("{}.json", output);
+    for warning in &extracted.warnings {
+        eprintln!("warning: {}: {}", output, warning.message);
+    }
+    std::fs::write(&bin_path, &extracted.data)
+        .map_err(|e| format!("Failed to write {}: {}", bin_path, e))?;
+    std::fs::write(&json_path, extracted.metadata.pretty() + "\n")
+        .map_err(|e| format!("Failed to write {}: {}", json_path, e))?;
+    eprintln!(
+        "Wrote {} (0x{:X} bytes at 0x{:X}) and {}",
+        bin_path,
+        extracted.data.len(),
+        extracted.base,
+        json_path
+    );
+    trace.write(&format!("{}.explain.json", output))
+}
+
+fn cmd_emu_export(
+    cache: &DyldCache<LittleEndian>,
+    output: &str,
+    modules: &[String],
+    range: Option<(u64, u64)>,
+    auth: Option<AuthMode>,
+) -> Result<(), Box<dyn Error>> {
+    if modules.is_empty() && range.is_none() {
+        return Err("Specify at least one --module or an address range".into());
+    }
+
+    let mut regions = emu::image_regions(cache, modules)?;
+    if let Some((start, end)) = range {
+        if start >= end {
+            return Err(format!("Invalid range 0x{:X}-0x{:X}", start, end).into());
+        }
+        regions.extend(emu::range_regions(cache, start, end));
+    }
+    let regions = emu::merge(regions);
+
+    let rebased = emu::write(cache, &regions, Path::new(output), auth)?;
+    for region in &regions {
+        eprintln!(
+            "0x{:X}-0x{:X} {}",
+            region.start,
+            region.end,
+            emu::prot_string(region.prot)
+        );
+    }
+    if auth.is_some() {
+        eprintln!("Rewrote {} slid pointers", rebased);
+    }
+    eprintln!("Wrote {} regions to {}", regions.len(), output);
+    Ok(())
+}
+
+fn cmd_exports_diff(
+    old: (&DyldCache<LittleEndian>, &str, &AddressFormat),
+    new: (&DyldCache<LittleEndian>, &str, &AddressFormat),
+    symbol_filter: &symbolfilter::SymbolFilter,
+    colors: Colors,
+) -> Result<(), Box<dyn Error>> {
+    let (old_cache, old_module, old_fmt) = old;
+    let (new_cache, new_module, new_fmt) = new;
+    let old_image = find_image(old_cache, old_module)?;
+    let new_image = find_image(new_cache, new_module)?;
+    let old_base = old_image.info().address.get(LittleEndian);
+    let new_base = new_image.info().address.get(LittleEndian);
+    let mut old_exports = exports::image_exports(old_cache, &old_image)?;
+    let mut new_exports = exports::image_exports(new_cache, &new_image)?;
+    old_exports.retain(|entry| symbol_filter.keeps(&entry.name));
+    new_exports.retain(|entry| symbol_filter.keeps(&entry.name));
+
+    let heading = |sign, path: Result<&str, _>| {
+        let line = format!("{} {}", sign, path.unwrap_or(""));
+        println!("{}", colors.paint(Style::Heading, line));
+    };
+    heading("---", old_image.path());
+    heading("+++", new_image.path());
+
+    let describe =
+        |entry: &exports::ExportEntry, base: u64, fmt: &AddressFormat| match entry.address() {
+            Some(addr) => format!("{} ({})", fmt.format(addr), entry.target(base)),
+            None => entry.target(base),
+        };
+
+    let (mut added, mut removed, mut moved) = (0, 0, 0);
+    for change in exports::diff(&old_exports, old_base, &new_exports, new_base) {
+        match change {
+            exports::ExportChange::Added(entry) => {
+                added += 1;
+                let line = format!("+ {} {}", entry.name, describe(entry, new_base, new_fmt));
+                println!("{}", colors.paint(Style::Added, line));
+            }
+            exports::ExportChange::Removed(entry) => {
+                removed += 1;
+                let line = format!("- {} {}", entry.name, describe(entry, old_base, old_fmt));
+                println!("{}", colors.paint(Style::Removed, line));
+            }
+            exports::ExportChange::Moved { old, new } => {
+                moved += 1;
+                let line = format!(
+                    "~ {} {} -> {}",
+                    old.name,
+                    describe(old, old_base, old_fmt),
+                    describe(new, new_base, new_fmt)
+                );
+                println!("{}", colors.paint(Style::Changed, line));
+            }
+        }
+    }
+    eprintln!(
+        "{} exports vs {}: {} added, {} removed, {} moved",
+        old_exports.len(),
+        new_exports.len(),
+        added,
+        removed,
+        moved
+    );
+    Ok(())
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    Images {
-        path: String,
-    },
-    Sections {
-        path: String,
-        #[arg(short, long)]
-        module: Option<String>,
-    },
-    Symbols {
-        path: String,
-        #[arg(short, long)]
-        module: Option<String>,
-    },
-    Dump {
-        path: String,
-        #[arg(value_parser = parse_u64)]
-        addr: u64,
-        #[arg(default_value_t = 256, value_parser = parse_u64)]
-        size: u64,
-    },
+fn cmd_objc_diff(
+    old_cache: &DyldCache<LittleEndian>,
+    new_cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    group: Group,
+    colors: Colors,
+) -> Result<(), Box<dyn Error>> {
+    let old_images = images_by_path(old_cache, module)?;
+    let new_images = images_by_path(new_cache, module)?;
+    let old_reader = objc::ObjcReader::new(old_cache);
+    let new_reader = objc::ObjcReader::new(new_cache);
+
+    let paths: BTreeSet<&String> = old_images.keys().chain(new_images.keys()).collect();
+    let (mut added, mut removed) = (0, 0);
+    for (unit, paths) in group.paths(paths) {
+        let metadata = |reader: &objc::ObjcReader, image: Option<&DyldCacheImage<LittleEndian>>| {
+            image
+                .map(|image| reader.image_metadata(image))
+                .transpose()
+                .map(Option::unwrap_or_default)
+        };
+        let (mut old_items, mut new_items) = (BTreeSet::new(), BTreeSet::new());
+        for path in &paths {
+            old_items.extend(metadata(&old_reader, old_images.get(*path))?.items());
+            new_items.extend(metadata(&new_reader, new_images.get(*path))?.items());
+        }
+
+        // Owners (classes, categories, protocols) that appear or disappear entirely are
+        // reported once, without listing each of their methods.
+        let owners = |items: &BTreeSet<(String, u8, String)>| -> HashSet<String> {
+            items
+                .iter()
+                .filter(|i| i.1 == 0)
+                .map(|i| i.0.clone())
+                .collect()
+        };
+        let (old_owners, new_owners) = (owners(&old_items), owners(&new_items));
+
+        let mut changes: Vec<(&(String, u8, String), char)> = Vec::new();
+        for item in new_items.difference(&old_items) {
+            if item.1 == 0 || old_owners.contains(&item.0) {
+                changes.push((item, '+'));
+            }
+        }
+        for item in old_items.difference(&new_items) {
+            if item.1 == 0 || new_owners.contains(&item.0) {
+                changes.push((item, '-'));
+            }
+        }
+        if changes.is_empty() {
+            continue;
+        }
+        changes.sort();
+
+        let heading = match (
+            paths.iter().any(|p| old_images.contains_key(*p)),
+            paths.iter().any(|p| new_images.contains_key(*p)),
+        ) {
+            (false, _) => format!("{} (new {})", unit, group.noun()),
+            (_, false) => format!("{} (removed {})", unit, group.noun()),
+            _ => unit.to_string(),
+        };
+        println!("{}", colors.paint(Style::Heading, heading));
+        for ((_, _, description), sign) in changes {
+            if sign == '+' {
+                added += 1;
+            } else {
+                removed += 1;
+            }
+            let line = format!("{} {}", sign, description);
+            println!("  {}", colors.paint(sign_style(sign), line));
+        }
+    }
+    eprintln!("{} added, {} removed", added, removed);
+    Ok(())
 }
 
-fn parse_u64(input: &str) -> Result<u64, String> {
-    let input = input.trim();
-    if input.to_ascii_lowercase().starts_with("0x") {
-        u64::from_str_radix(&input[2..], 16).map_err(|e| format!("Invalid hex: {}", e))
+/// Style of a `+` or `-` diff line.
+fn sign_style(sign: char) -> Style {
+    if sign == '+' {
+        Style::Added
     } else {
-        input
-            .parse::<u64>()
-            .map_err(|e| format!("Invalid number: {}", e))
+        Style::Removed
     }
 }
 
-fn with_dyld_cache<F>(path: &str, action: F) -> Result<(), Box<dyn Error>>
-where
-    F: FnOnce(&DyldCache<LittleEndian>) -> Result<(), Box<dyn Error>>,
-{
-    let main_file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
-    let main_mmap = unsafe { Mmap::map(&main_file)? };
-    let suffixes = DyldCache::<LittleEndian>::subcache_suffixes(&*main_mmap)?;
+fn cmd_objc_ivar_offset(
+    index: &CacheIndex,
+    class: &str,
+    ivar: &str,
+    module: Option<&str>,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let reader = objc::ObjcReader::new(cache);
+    let symbol = format!("_OBJC_IVAR_$_{}.{}", class, ivar);
+
+    // Class metadata covers ivars whose offset symbol is not exported; fall back to the
+    // symbol for classes whose metadata cannot be read.
+    let mut variable = None;
+    for image in images_by_path(cache, module)?.values() {
+        variable = reader.ivar_offset_variable(image, class, ivar)?;
+        if variable.is_some() {
+            break;
+        }
+    }
+    let variable = match variable {
+        Some(addr) => addr,
+        None => symbolize::Symbolizer::new(index)
+            .find(&symbol, module)
+            .map(|(_, addr)| addr)
+            .ok_or_else(|| format!("Ivar {} not found in class {}", ivar, class))?,
+    };
+    let offset = vm::read_u32(cache, variable)
+        .ok_or_else(|| format!("Offset variable at 0x{:X} is not mapped", variable))?;
+    println!(
+        "{} {} = 0x{:X} ({})",
+        fmt.format(variable),
+        symbol,
+        offset,
+        offset
+    );
+    Ok(())
+}
+
+fn cmd_objc_impmap(
+    index: &CacheIndex,
+    module: Option<&str>,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let reader = objc::ObjcReader::new(cache);
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let imps = reader.image_metadata(&image)?.implementations();
+        if imps.is_empty() {
+            continue;
+        }
+        match format {
+            OutputFormat::Text => {
+                for (imp, name) in &imps {
+                    println!("{} {}", fmt.format(*imp), name);
+                }
+            }
+            OutputFormat::Json => {
+                // Offsets let the map be applied to a slid cache from the image's load address.
+                let base = image.info().address.get(LittleEndian);
+                let methods: Vec<_> = imps
+                    .into_iter()
+                    .map(|(imp, name)| {
+                        Json::object()
+                            .field("address", imp)
+                            .field("offset", imp.wrapping_sub(base))
+                            .field("name", name)
+                    })
+                    .collect();
+                images.push(
+                    Json::object()
+                        .field("image", path)
+                        .field("base", base)
+                        .field("methods", methods),
+                );
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
 
-    let mut subcache_mmaps = Vec::new();
-    for suffix in suffixes {
-        let sub_path = format!("{}{}", path, suffix);
-        let sub_file = File::open(&sub_path)?;
-        let sub_mmap = unsafe { Mmap::map(&sub_file)? };
-        subcache_mmaps.push(sub_mmap);
+fn cmd_objc_selectors(
+    cache: &DyldCache<LittleEndian>,
+    wordlist: bool,
+    by_frequency: bool,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut selectors = objc::selectors(cache)?;
+    if wordlist {
+        // The table holds each selector once, but a wordlist promises it whatever the source.
+        let mut seen = HashSet::new();
+        selectors.retain(|(_, name)| seen.insert(name.clone()));
+    }
+    let counts = match by_frequency {
+        true => Some(selector_frequencies(cache)?),
+        false => None,
+    };
+    let count = |name: &str| {
+        counts
+            .as_ref()
+            .and_then(|counts| counts.get(name).copied())
+            .unwrap_or(0)
+    };
+    if counts.is_some() {
+        selectors.sort_by(|(_, a), (_, b)| count(b).cmp(&count(a)).then_with(|| a.cmp(b)));
     }
 
-    let subcache_data: Vec<&[u8]> = subcache_mmaps.iter().map(|m| &**m).collect();
-    let cache = DyldCache::<LittleEndian>::parse(&*main_mmap, &subcache_data)?;
+    match format {
+        OutputFormat::Text if wordlist => {
+            for (_, name) in &selectors {
+                println!("{}", name);
+            }
+        }
+        OutputFormat::Text => {
+            for (address, name) in &selectors {
+                match counts {
+                    Some(_) => println!("{} {} {}", fmt.format(*address), name, count(name)),
+                    None => println!("{} {}", fmt.format(*address), name),
+                }
+            }
+        }
+        OutputFormat::Json if wordlist => {
+            let names: Vec<Json> = selectors
+                .iter()
+                .map(|(_, name)| Json::from(name.as_str()))
+                .collect();
+            println!("{}", Json::from(names).pretty());
+        }
+        OutputFormat::Json => {
+            let items: Vec<_> = selectors
+                .iter()
+                .map(|(address, name)| {
+                    let item = Json::object()
+                        .field("address", *address)
+                        .field("name", name.as_str());
+                    match counts {
+                        Some(_) => item.field("methods", count(name)),
+                        None => item,
+                    }
+                })
+                .collect();
+            println!("{}", Json::from(items).pretty());
+        }
+    }
+    eprintln!("{} selectors", selectors.len());
+    Ok(())
+}
 
-    action(&cache)
+/// How many methods of the classes and categories of every image implement each selector.
+/// Images are read in parallel, as parsing the ObjC metadata of the whole cache is slow.
+fn selector_frequencies(
+    cache: &DyldCache<LittleEndian>,
+) -> Result<HashMap<String, usize>, Box<dyn Error>> {
+    let images: Vec<_> = images_by_path(cache, None)?.into_values().collect();
+    let per_image: Vec<HashMap<String, usize>> = images
+        .par_iter()
+        .map(|image| {
+            let mut counts = HashMap::new();
+            let Ok(metadata) = objc::ObjcReader::new(cache).image_metadata(image) else {
+                return counts;
+            };
+            for class in metadata.classes.iter().chain(&metadata.categories) {
+                for method in class.instance_methods.iter().chain(&class.class_methods) {
+                    *counts.entry(method.selector.clone()).or_insert(0) += 1;
+                }
+            }
+            counts
+        })
+        .collect();
+    let mut counts = HashMap::new();
+    for image in per_image {
+        for (selector, n) in image {
+            *counts.entry(selector).or_insert(0) += n;
+        }
+    }
+    Ok(counts)
 }
 
-fn cmd_images(cache: &DyldCache<LittleEndian>) -> Result<(), Box<dyn Error>> {
-    for image in cache.images() {
-        println!("{}", image.path().unwrap_or(""));
+fn cmd_objc_classes(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let reader = objc::ObjcReader::new(cache);
+    let methods_json = |methods: &[objc::ObjcMethod]| -> Vec<Json> {
+        methods
+            .iter()
+            .map(|method| {
+                Json::object()
+                    .field("selector", method.selector.as_str())
+                    .field("address", method.imp)
+            })
+            .collect()
+    };
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let metadata = reader.image_metadata(&image)?;
+        if metadata.classes.is_empty() && metadata.categories.is_empty() {
+            continue;
+        }
+
+        if format == OutputFormat::Json {
+            let classes = |list: &[objc::ObjcClass]| -> Vec<Json> {
+                list.iter()
+                    .map(|class| {
+                        let ivars: Vec<_> = class
+                            .ivars
+                            .iter()
+                            .map(|ivar| {
+                                Json::object()
+                                    .field("name", ivar.name.as_str())
+                                    .field("offset", ivar.offset)
+                                    .field("size", ivar.size)
+                            })
+                            .collect();
+                        Json::object()
+                            .field("name", class.name.as_str())
+                            .field("superclass", class.superclass.as_deref())
+                            .field("instance_size", class.instance_size)
+                            .field("protocols", class.protocols.clone())
+                            .field("ivars", ivars)
+                            .field("instance_methods", methods_json(&class.instance_methods))
+                            .field("class_methods", methods_json(&class.class_methods))
+                    })
+                    .collect()
+            };
+            images.push(
+                Json::object()
+                    .field("image", path.as_str())
+                    .field("classes", classes(&metadata.classes))
+                    .field("categories", classes(&metadata.categories)),
+            );
+            continue;
+        }
+
+        println!("// {}", path);
+        for class in metadata.classes.iter().chain(&metadata.categories) {
+            let protocols = protocol_suffix(&class.protocols);
+            match &class.superclass {
+                Some(superclass) => {
+                    println!("@interface {} : {}{}", class.name, superclass, protocols)
+                }
+                None => println!("@interface {}{}", class.name, protocols),
+            }
+            if !class.ivars.is_empty() {
+                println!("{{");
+                for ivar in &class.ivars {
+                    match ivar.offset {
+                        Some(offset) => println!("    {}; // 0x{:X}", ivar.name, offset),
+                        None => println!("    {};", ivar.name),
+                    }
+                }
+                println!("}}");
+            }
+            for (sign, methods) in [('+', &class.class_methods), ('-', &class.instance_methods)] {
+                for method in methods {
+                    println!(
+                        "{} {}; // {}",
+                        sign,
+                        method.selector,
+                        fmt.format(method.imp)
+                    );
+                }
+            }
+            println!("@end\n");
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
     }
     Ok(())
 }
 
-fn cmd_sections(
+/// ` <A, B>` for adopted protocols, as in an `@interface` line.
+fn protocol_suffix(protocols: &[String]) -> String {
+    if protocols.is_empty() {
+        String::new()
+    } else {
+        format!(" <{}>", protocols.join(", "))
+    }
+}
+
+fn cmd_objc_protocols(
     cache: &DyldCache<LittleEndian>,
-    filter_module: Option<&str>,
+    module: Option<&str>,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn Error>> {
-    for image in cache.images() {
-        let image_path = image.path().unwrap_or("");
+    let reader = objc::ObjcReader::new(cache);
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let protocols = reader.image_metadata(&image)?.protocols;
+        if protocols.is_empty() {
+            continue;
+        }
+
+        if format == OutputFormat::Json {
+            let selectors = |methods: &[objc::ObjcMethod]| -> Vec<String> {
+                methods.iter().map(|m| m.selector.clone()).collect()
+            };
+            let items: Vec<_> = protocols
+                .iter()
+                .map(|protocol| {
+                    Json::object()
+                        .field("name", protocol.name.as_str())
+                        .field("protocols", protocol.protocols.clone())
+                        .field("instance_methods", selectors(&protocol.instance_methods))
+                        .field("class_methods", selectors(&protocol.class_methods))
+                        .field(
+                            "optional_instance_methods",
+                            selectors(&protocol.optional_instance_methods),
+                        )
+                        .field(
+                            "optional_class_methods",
+                            selectors(&protocol.optional_class_methods),
+                        )
+                })
+                .collect();
+            images.push(
+                Json::object()
+                    .field("image", path.as_str())
+                    .field("protocols", items),
+            );
+            continue;
+        }
+
+        println!("// {}", path);
+        for protocol in &protocols {
+            println!(
+                "@protocol {}{}",
+                protocol.name,
+                protocol_suffix(&protocol.protocols)
+            );
+            let print = |sign: char, methods: &[objc::ObjcMethod]| {
+                for method in methods {
+                    println!("{} {};", sign, method.selector);
+                }
+            };
+            print('+', &protocol.class_methods);
+            print('-', &protocol.instance_methods);
+            if !protocol.optional_class_methods.is_empty()
+                || !protocol.optional_instance_methods.is_empty()
+            {
+                println!("@optional");
+                print('+', &protocol.optional_class_methods);
+                print('-', &protocol.optional_instance_methods);
+            }
+            println!("@end\n");
+        }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
+    Ok(())
+}
 
-        if let Some(filter) = filter_module {
-            if image_path != filter {
+fn cmd_objc_headers(
+    cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    let reader = objc::ObjcReader::new(cache);
+    let (mut written, mut images) = (0, 0);
+    for (path, image) in images_by_path(cache, module)? {
+        let metadata = match reader.image_metadata(&image) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
                 continue;
             }
+        };
+        let headers = headers::image_headers(&path, &metadata);
+        if headers.is_empty() {
+            continue;
+        }
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        let dir = Path::new(output).join(name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        for header in &headers {
+            let file = dir.join(&header.file_name);
+            std::fs::write(&file, &header.text)
+                .map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
         }
+        written += headers.len();
+        images += 1;
+    }
+    eprintln!(
+        "Wrote {} headers for {} images to {}",
+        written, images, output
+    );
+    Ok(())
+}
 
-        println!("{}", image_path);
-        if let Ok(obj) = image.parse_object() {
-            for section in obj.sections() {
-                let base = section.address();
-                let end = base + section.size();
-                println!(
-                    "  {:16} 0x{:X}-0x{:X}",
-                    section.name().unwrap_or(""),
-                    base,
-                    end
+fn cmd_objc_categories(
+    index: &CacheIndex,
+    module: Option<&str>,
+    format: OutputFormat,
+    fmt: &AddressFormat,
+) -> Result<(), Box<dyn Error>> {
+    let cache = index.cache();
+    let reader = objc::ObjcReader::new(cache);
+    let mut images = Vec::new();
+    for (path, image) in images_by_path(cache, module)? {
+        let categories = reader.image_metadata(&image)?.categories;
+        if categories.is_empty() {
+            continue;
+        }
+        if format == OutputFormat::Text {
+            println!("// {}", path);
+        }
+
+        let mut items = Vec::new();
+        for category in &categories {
+            // Categories are named `Class(Category)`.
+            let (class, name) = category
+                .name
+                .split_once('(')
+                .map(|(class, name)| (class, name.trim_end_matches(')')))
+                .unwrap_or(("?", category.name.as_str()));
+            let class_image = category
+                .extended_class
+                .and_then(|addr| index.image_at(addr))
+                .map(|(_, image)| image.path.as_str());
+
+            if format == OutputFormat::Json {
+                let methods = |methods: &[objc::ObjcMethod]| -> Vec<Json> {
+                    methods
+                        .iter()
+                        .map(|method| {
+                            Json::object()
+                                .field("selector", method.selector.as_str())
+                                .field("address", method.imp)
+                        })
+                        .collect()
+                };
+                items.push(
+                    Json::object()
+                        .field("name", name)
+                        .field("class", class)
+                        .field("class_address", category.extended_class)
+                        .field("class_image", class_image)
+                        .field("protocols", category.protocols.clone())
+                        .field("instance_methods", methods(&category.instance_methods))
+                        .field("class_methods", methods(&category.class_methods)),
                 );
+                continue;
+            }
+
+            println!(
+                "@interface {} ({}){}",
+                class,
+                name,
+                protocol_suffix(&category.protocols)
+            );
+            match (category.extended_class, class_image) {
+                (Some(addr), Some(image)) => {
+                    println!("// extends {} in {}", fmt.format(addr), image)
+                }
+                (Some(addr), None) => println!("// extends {}", fmt.format(addr)),
+                (None, _) => println!("// extends a class that is not bound"),
             }
+            for (sign, methods) in [
+                ('+', &category.class_methods),
+                ('-', &category.instance_methods),
+            ] {
+                for method in methods {
+                    println!(
+                        "{} {}; // {}",
+                        sign,
+                        method.selector,
+                        fmt.format(method.imp)
+                    );
+                }
+            }
+            println!("@end\n");
+        }
+        if format == OutputFormat::Json {
+            images.push(
+                Json::object()
+                    .field("image", path.as_str())
+                    .field("categories", items),
+            );
         }
     }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(images).pretty());
+    }
     Ok(())
 }
 
-fn cmd_symbols(
+fn cmd_objc_find(
     cache: &DyldCache<LittleEndian>,
-    filter_module: Option<&str>,
+    query: &objc::ClassQuery,
+    module: Option<&str>,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn Error>> {
-    for image in cache.images() {
-        let image_path = image.path().unwrap_or("");
-
-        if let Some(filter) = filter_module {
-            if image_path != filter {
+    if query.is_empty() {
+        return Err("Pass at least one of --ivar, --size or --selector".into());
+    }
+    let reader = objc::ObjcReader::new(cache);
+    let mut matches = Vec::new();
+    let mut count = 0;
+    for (path, image) in images_by_path(cache, module)? {
+        for class in reader.image_metadata(&image)?.classes {
+            if !query.matches(&class) {
+                continue;
+            }
+            count += 1;
+            let size = class.instance_size.unwrap_or(0);
+            if format == OutputFormat::Json {
+                let ivars: Vec<_> = class
+                    .ivars
+                    .iter()
+                    .map(|ivar| {
+                        Json::object()
+                            .field("name", ivar.name.as_str())
+                            .field("offset", ivar.offset)
+                            .field("size", ivar.size)
+                    })
+                    .collect();
+                matches.push(
+                    Json::object()
+                        .field("image", path.as_str())
+                        .field("class", class.name)
+                        .field("instance_size", size)
+                        .field("ivars", ivars),
+                );
                 continue;
             }
+            println!("{} (0x{:X} bytes) {}", class.name, size, path);
+            // Offsets of the ivars searched for, to check them against the memory dump.
+            for ivar in class.ivars.iter().filter(|i| query.ivars.contains(&i.name)) {
+                match ivar.offset {
+                    Some(offset) => println!("  0x{:<4X} {}", offset, ivar.name),
+                    None => println!("  ?      {}", ivar.name),
+                }
+            }
         }
+    }
+    if format == OutputFormat::Json {
+        println!("{}", Json::from(matches).pretty());
+    }
+    eprintln!("{} classes match", count);
+    Ok(())
+}
 
-        println!("{}", image_path);
-        if let Ok(obj) = image.parse_object() {
-            for symbol in obj.symbols() {
-                println!("0x{:X} {}", symbol.address(), symbol.name().unwrap_or(""))
+fn cmd_strings_diff(
+    old_cache: &DyldCache<LittleEndian>,
+    new_cache: &DyldCache<LittleEndian>,
+    module: Option<&str>,
+    section: Option<&str>,
+    group: Group,
+    colors: Colors,
+) -> Result<(), Box<dyn Error>> {
+    let old_images = images_by_path(old_cache, module)?;
+    let new_images = images_by_path(new_cache, module)?;
+
+    let strings = |cache,
+                   image: Option<&DyldCacheImage<LittleEndian>>|
+     -> Result<BTreeSet<String>, Box<dyn Error>> {
+        Ok(match image {
+            Some(image) => strings::image_strings(cache, image)?
+                .into_iter()
+                .filter(|s| section.is_none_or(|name| s.section == name))
+                .map(|s| s.value)
+                .collect(),
+            None => BTreeSet::new(),
+        })
+    };
+
+    let paths: BTreeSet<&String> = old_images.keys().chain(new_images.keys()).collect();
+    let (mut added, mut removed) = (0, 0);
+    for (unit, paths) in group.paths(paths) {
+        let (mut old_strings, mut new_strings) = (BTreeSet::new(), BTreeSet::new());
+        for path in &paths {
+            old_strings.extend(strings(old_cache, old_images.get(*path))?);
+            new_strings.extend(strings(new_cache, new_images.get(*path))?);
+        }
+        let mut changes: Vec<(&String, char)> = new_strings
+            .difference(&old_strings)
+            .map(|s| (s, '+'))
+            .chain(old_strings.difference(&new_strings).map(|s| (s, '-')))
+            .collect();
+        if changes.is_empty() {
+            continue;
+        }
+        changes.sort();
+
+        let heading = match (
+            paths.iter().any(|p| old_images.contains_key(*p)),
+            paths.iter().any(|p| new_images.contains_key(*p)),
+        ) {
+            (false, _) => format!("{} (new {})", unit, group.noun()),
+            (_, false) => format!("{} (removed {})", unit, group.noun()),
+            _ => unit.to_string(),
+        };
+        println!("{}", colors.paint(Style::Heading, heading));
+        for (value, sign) in changes {
+            if sign == '+' {
+                added += 1;
+            } else {
+                removed += 1;
+            }
+            let line = format!("{} {}", sign, strings::escape(value));
+            println!("  {}", colors.paint(sign_style(sign), line));
+        }
+    }
+    eprintln!("{} added, {} removed", added, removed);
+    Ok(())
+}
+
+/// What `disasm` disassembles.
+enum DisasmTarget<'a> {
+    /// A function by symbol name, looked up in one image or in all of them.
+    Function {
+        name: &'a str,
+        module: Option<&'a str>,
+    },
+    /// Code at an address: `count` instructions, or up to the next function start.
+    Address { addr: u64, count: Option<u64> },
+}
+
+fn cmd_disasm<'c>(
+    index: &CacheIndex<'c>,
+    target: DisasmTarget,
+    output: Option<&str>,
+    labels: &labels::Labels,
+    demangle: bool,
+    fmt: &AddressFormat<'_, 'c>,
+) -> Result<(), Box<dyn Error>> {
+    if index.cache().architecture() != object::Architecture::Aarch64 {
+        return Err("Disassembly is only supported for arm64 caches".into());
+    }
+    let symbols = symbolize::Symbolizer::new(index)
+        .with_labels(labels)
+        .with_demangling(demangle);
+    let disassembler = disasm::Disassembler::new(index, &symbols, fmt);
+    let (image, name, start, end, source) = match target {
+        DisasmTarget::Function { name, module } => {
+            let (image, start) = symbols
+                .find(name, module)
+                .ok_or_else(|| format!("Symbol {} not found", name))?;
+            let (end, source) = disassembler
+                .function_end(image, start)
+                .ok_or_else(|| format!("{} at 0x{:X} is not inside a section", name, start))?;
+            let name = if demangle {
+                symbolize::with_demangled(name).into_owned()
+            } else {
+                name.to_string()
+            };
+            (Some(image), Some(name), start, end, source)
+        }
+        DisasmTarget::Address { addr, count } => {
+            if addr % 4 != 0 {
+                return Err(format!("0x{:X} is not 4-byte aligned", addr).into());
+            }
+            let image = index.image_at(addr).map(|(i, _)| i);
+            let (end, source) = match count {
+                Some(count) => (addr.saturating_add(count.saturating_mul(4)), None),
+                None => image
+                    .and_then(|image| disassembler.function_end(image, addr))
+                    .ok_or_else(|| {
+                        format!(
+                            "0x{:X} is not inside a section of an image; give an instruction count",
+                            addr
+                        )
+                    })?,
+            };
+            (image, symbols.name(addr), addr, end, source)
+        }
+    };
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    if let Some(image) = image {
+        writeln!(out, "; {}", index.image(image).path)?;
+    }
+    match name {
+        Some(name) => writeln!(
+            out,
+            "; {} {}{}",
+            name,
+            fmt.format_range(start, end),
+            unwind_note(source)
+        )?,
+        None => writeln!(
+            out,
+            "; {}{}",
+            fmt.format_range(start, end),
+            unwind_note(source)
+        )?,
+    }
+    disassembler.write(&mut out, start, end)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// A pointer found by `dump --annotate`: the address it is stored at and its target.
+struct DumpPointer {
+    address: u64,
+    target: u64,
+    image: Option<String>,
+    /// `segment,section` of the target in its image.
+    section: Option<String>,
+    symbol: Option<String>,
+    location: Option<String>,
+}
+
+impl DumpPointer {
+    /// `image segment,section symbol`, or the location when no symbol covers the target.
+    fn describe(&self) -> String {
+        match (&self.image, &self.symbol) {
+            (Some(image), Some(symbol)) => {
+                let name = image.rsplit('/').next().unwrap_or(image);
+                match &self.section {
+                    Some(section) => format!("-> {} {} {}", name, section, symbol),
+                    None => format!("-> {} {}", name, symbol),
+                }
             }
+            _ => format!(
+                "-> {}",
+                self.location.as_deref().unwrap_or("<not in any image>")
+            ),
+        }
+    }
+}
+
+/// Decode the aligned 8-byte values of `bytes`, read at `vmaddr`, whose targets are mapped by
+/// the cache. Values are decoded as rebased pointers, and runtime pointer bits are stripped.
+fn dump_pointers(
+    cache: &DyldCache<LittleEndian>,
+    symbols: &symbolize::Symbolizer,
+    vmaddr: u64,
+    bytes: &[u8],
+) -> Vec<DumpPointer> {
+    let reader = slide::PointerReader::new(cache);
+    let index = symbols.index();
+    let skip = (vmaddr.next_multiple_of(8) - vmaddr) as usize;
+    bytes
+        .get(skip..)
+        .unwrap_or_default()
+        .chunks_exact(8)
+        .enumerate()
+        .filter_map(|(i, value)| {
+            let address = vmaddr + (skip + i * 8) as u64;
+            let raw = u64::from_le_bytes(value.try_into().unwrap());
+            let target = reader.decode(address, raw);
+            let target = resolve::strip_pointer_bits(cache, target).unwrap_or(target);
+            cache.data_and_offset_for_address(target)?;
+            let image = index.image_at(target).map(|(_, image)| image);
+            Some(DumpPointer {
+                address,
+                target,
+                image: image.map(|image| image.path.clone()),
+                section: image
+                    .and_then(|image| image.section_at(target))
+                    .map(|s| format!("{},{}", s.segment, s.name)),
+                symbol: symbols.name(target),
+                location: resolve(index, target).map(|l| l.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Write the bytes mapped from `vmaddr` to `output`, or to stdout for `-`. The region may cross
+/// mappings and subcache files; it stops where mapped memory does, with a warning.
+fn cmd_dump_raw(
+    cache: &DyldCache<LittleEndian>,
+    vmaddr: u64,
+    size: u64,
+    output: &str,
+) -> Result<(), Box<dyn Error>> {
+    cache
+        .data_and_offset_for_address(vmaddr)
+        .ok_or_else(|| format!("Address 0x{:X} not found in dyld cache", vmaddr))?;
+    if output == "-" && std::io::stdout().is_terminal() {
+        return Err(
+            "Refusing to write raw bytes to a terminal; redirect stdout or give a file".into(),
+        );
+    }
+    let bytes = vm::read_mapped(cache, vmaddr, size)?;
+    let end = vmaddr + bytes.len() as u64;
+
+    let suffixes = DyldCache::<LittleEndian>::subcache_suffixes(cache.data())?;
+    let pieces: Vec<_> = vm::mappings(cache)
+        .into_iter()
+        .filter(|m| m.address < end && vmaddr < m.address + m.size)
+        .collect();
+    if pieces.len() > 1 {
+        eprintln!("The region spans {} mappings:", pieces.len());
+        for m in &pieces {
+            eprintln!(
+                "  0x{:X}-0x{:X} {} in the {} file",
+                m.address.max(vmaddr),
+                (m.address + m.size).min(end),
+                m.name(),
+                cache_file_name(&suffixes, m.file)
+            );
         }
     }
+    if (bytes.len() as u64) < size {
+        eprintln!(
+            "warning: only 0x{:X} of 0x{:X} bytes are mapped from 0x{:X}, the output stops at 0x{:X}",
+            bytes.len(),
+            size,
+            vmaddr,
+            end
+        );
+    }
+
+    if output == "-" {
+        let mut out = std::io::stdout().lock();
+        out.write_all(&bytes)?;
+        out.flush()?;
+    } else {
+        std::fs::write(output, &bytes).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+        eprintln!("Wrote {} bytes to {}", bytes.len(), output);
+    }
     Ok(())
 }
 
+/// Rows per page of `dump --follow` without `--page-size`.
+const FOLLOW_PAGE_ROWS: usize = 32;
+/// Bytes read at a time past the requested range by `dump --follow`.
+const FOLLOW_CHUNK: u64 = 0x1000;
+/// Largest text or JSON dump made without `--no-limit` or paging.
+const MAX_DUMP_SIZE: u64 = 1 << 20;
+
+/// How `dump` prints its text output.
+#[derive(Clone, Copy)]
+struct DumpScreen {
+    colors: Colors,
+    /// Rows shown before pausing, when writing to a terminal.
+    page_size: Option<usize>,
+    /// Carry on past the requested range while the reader asks for more.
+    follow: bool,
+}
+
 fn cmd_dump(
     cache: &DyldCache<LittleEndian>,
     vmaddr: u64,
     size: usize,
+    labels: &labels::Labels,
+    annotate: Option<&symbolize::Symbolizer>,
+    format: OutputFormat,
+    screen: DumpScreen,
 ) -> Result<(), Box<dyn Error>> {
-    match cache.data_and_offset_for_address(vmaddr) {
-        Some((data, offset)) => {
-            let off = offset as usize;
-            if off >= data.len() {
-                return Err(format!(
-                    "Calculated offset {} is out of range (data len {})",
-                    off,
-                    data.len()
-                )
-                .into());
-            }
+    let (_, offset) = cache
+        .data_and_offset_for_address(vmaddr)
+        .ok_or_else(|| format!("Address 0x{:X} not found in dyld cache", vmaddr))?;
 
-            let end = std::cmp::min(data.len(), off + size);
-            let bytes = &data[off..end];
+    let bytes = vm::read_mapped(cache, vmaddr, size as u64)?;
 
-            eprintln!("Mapped to file offset 0x{:X}", off);
-            eprintln!(
-                "Found VM address 0x{:X}, {} bytes available",
+    eprintln!("Mapped to file offset 0x{:X}", offset);
+    eprintln!(
+        "Found VM address 0x{:X}, {} bytes available",
+        vmaddr,
+        bytes.len()
+    );
+    if format == OutputFormat::Text {
+        let rows = screen
+            .page_size
+            .or(screen.follow.then_some(FOLLOW_PAGE_ROWS));
+        let mut pager = pager::Pager::new(rows);
+        if screen.follow && !pager.is_paging() {
+            return Err("--follow needs a terminal on stdin and stdout".into());
+        }
+        let (mut start, mut bytes) = (vmaddr, bytes);
+        loop {
+            let pointers = annotate.map(|symbols| dump_pointers(cache, symbols, start, &bytes));
+            let more = print_dump_rows(
                 vmaddr,
-                bytes.len()
-            );
-            print_hex_dump(vmaddr, bytes);
-            Ok(())
+                start,
+                &bytes,
+                labels,
+                pointers.as_deref(),
+                screen.colors,
+                &mut pager,
+            )?;
+            if !more || !screen.follow {
+                break;
+            }
+            start += bytes.len() as u64;
+            bytes = vm::read_mapped(cache, start, FOLLOW_CHUNK).unwrap_or_default();
+            if bytes.is_empty() {
+                eprintln!("End of mapped memory at 0x{:X}", start);
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let end = vmaddr + bytes.len() as u64;
+    let pointers = annotate.map(|symbols| dump_pointers(cache, symbols, vmaddr, &bytes));
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let labels: Vec<_> = labels
+        .overlapping(vmaddr, end)
+        .map(|l| {
+            Json::object()
+                .field("name", l.name.as_str())
+                .field("start", l.start)
+                .field("end", l.end)
+                .field("comment", l.comment.as_deref())
+        })
+        .collect();
+    let mut dump = Json::object()
+        .field("address", vmaddr)
+        .field("file_offset", offset)
+        .field("size", bytes.len())
+        .field("bytes", hex)
+        .field("labels", labels);
+    if let Some(pointers) = pointers {
+        let pointers: Vec<_> = pointers
+            .into_iter()
+            .map(|p| {
+                Json::object()
+                    .field("address", p.address)
+                    .field("target", p.target)
+                    .field("image", p.image)
+                    .field("section", p.section)
+                    .field("symbol", p.symbol)
+                    .field("location", p.location)
+            })
+            .collect();
+        dump = dump.field("pointers", pointers);
+    }
+    println!("{}", dump.pretty());
+    Ok(())
+}
+
+/// Print `bytes`, read at `start` by a dump beginning at `vmaddr`, as hex rows noting the labels
+/// starting in each row, and on the first row of the dump those it starts in. With `pointers`,
+/// each row holds one aligned 8-byte value followed by its target. Returns false when the
+/// reader quit the pager.
+fn print_dump_rows(
+    vmaddr: u64,
+    start: u64,
+    bytes: &[u8],
+    labels: &labels::Labels,
+    pointers: Option<&[DumpPointer]>,
+    colors: Colors,
+    pager: &mut pager::Pager,
+) -> Result<bool, Box<dyn Error>> {
+    let end = start + bytes.len() as u64;
+    let mut addr = start;
+    while addr < end {
+        let row_end = match pointers {
+            Some(_) => (addr + 1).next_multiple_of(8).min(end),
+            None => (addr + 16).min(end),
+        };
+        let row = &bytes[(addr - start) as usize..(row_end - start) as usize];
+        let starting = labels
+            .overlapping(addr, row_end)
+            .filter(|l| l.start >= addr || addr == vmaddr);
+        let note = match pointers {
+            Some(pointers) => {
+                let mut notes: Vec<String> = pointers
+                    .iter()
+                    .filter(|p| p.address == addr && row.len() == 8)
+                    .map(DumpPointer::describe)
+                    .collect();
+                notes.extend(starting.map(|l| format!("[{}]", l.name)));
+                notes.join(" ")
+            }
+            None => starting
+                .map(|l| l.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        if !pager.row()? {
+            return Ok(false);
+        }
+        if note.is_empty() {
+            print_hex_dump(addr, row, colors);
+        } else {
+            print_annotated_hex(addr, row, &note, colors);
+        }
+        addr = row_end;
+    }
+    Ok(true)
+}
+
+fn cmd_layout(
+    cache: &DyldCache<LittleEndian>,
+    module: &str,
+    colors: Colors,
+) -> Result<(), Box<dyn Error>> {
+    let image = find_image(cache, module)?;
+    let base = image.info().address.get(LittleEndian);
+    let data = layout::header_data(cache, base)?;
+    // Malformed commands are still shown, as far as they can be decoded.
+    if let Err(e) = layout::validate(&data) {
+        eprintln!("warning: {}", e);
+    }
+
+    println!("; {}", image.path().unwrap_or(""));
+    for (i, item) in layout::annotate(&data).iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{}",
+            colors.paint(
+                Style::Heading,
+                format!("{} @ 0x{:X}", item.name, base + item.offset as u64)
+            )
+        );
+        for field in &item.fields {
+            let bytes = &data[field.offset..field.offset + field.size];
+            let note = format!("{:<22} {}", field.name, field.value);
+            print_annotated_hex(base + field.offset as u64, bytes, note.trim_end(), colors);
         }
-        None => Err(format!("Address 0x{:X} not found in dyld cache", vmaddr).into()),
     }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    if cli.format == OutputFormat::Json && !cli.command.prints_json() {
+        return Err("This command has no JSON output; --format json is not supported".into());
+    }
+    let colors = Colors::stdout(cli.color);
+    let symbol_filter = match &cli.symbol_filter {
+        Some(path) => symbolfilter::SymbolFilter::load(path)?,
+        None => symbolfilter::SymbolFilter::default(),
+    };
 
     match &cli.command {
-        Commands::Images { path } => with_dyld_cache(path, |cache| cmd_images(cache)),
-        Commands::Sections { path, module } => {
-            with_dyld_cache(path, |cache| cmd_sections(cache, module.as_deref()))
+        Commands::Info { path } => with_cache_header(path, &cli, cmd_info),
+        Commands::Caches => cmd_caches(),
+        Commands::Map { path } => with_dyld_cache(path, &cli, |cache| cmd_map(cache, cli.format)),
+        Commands::Images {
+            path,
+            verbose,
+            exports_symbol,
+            has_section,
+        } => match kernel::fileset_entries(Path::new(path))? {
+            Some(entries) => cmd_fileset_images(path, &entries, cli.format),
+            // The plain list of install paths is all in the main file's header.
+            None if !*verbose
+                && cli.format == OutputFormat::Text
+                && cli.group == Group::Image
+                && exports_symbol.is_empty()
+                && has_section.is_empty() =>
+            {
+                with_cache_header(path, &cli, |cache| {
+                    for (_, path) in cache.images()? {
+                        println!("{}", path);
+                    }
+                    Ok(())
+                })
+            }
+            None => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let selected = select_images(&index, exports_symbol, has_section)?;
+                match cli.group {
+                    Group::Image => cmd_images(&index, *verbose, &selected, cli.format),
+                    Group::Framework => cmd_images_grouped(&index, &selected, cli.format),
+                }
+            }),
+        },
+        Commands::Sections {
+            path,
+            module,
+            verbose,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_sections(
+                cache,
+                module.as_deref(),
+                &fmt,
+                cli.format,
+                cli.show_errors,
+                *verbose,
+            )
+        }),
+        Commands::Overlaps { path, start, end } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_overlaps(cache, *start, *end, &fmt)
+        }),
+        Commands::Lookup {
+            path,
+            patterns,
+            module,
+            public_only,
+            regex,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_lookup(
+                &index,
+                patterns,
+                module.as_deref(),
+                *public_only,
+                *regex,
+                cli.demangle,
+                &fmt,
+            )
+        }),
+        Commands::A2l { path, addr } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            let labels = load_labels(cache, &cli.labels)?;
+            cmd_a2l(&index, canonical_address(cache, *addr), &labels, &fmt)
+        }),
+        Commands::Symbolicate { path, addrs } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            let labels = load_labels(cache, &cli.labels)?;
+            cmd_symbolicate(&index, addrs, &labels, cli.demangle, cli.format, &fmt)
+        }),
+        Commands::Slide {
+            path,
+            module,
+            base,
+            addrs,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_slide(&index, module, canonical_address(cache, *base), addrs, &fmt)
+        }),
+        Commands::Translate { addr, from, to } => with_dyld_cache(from, &cli, |from_cache| {
+            with_dyld_cache(to, &cli, |to_cache| {
+                let (from_index, to_index) =
+                    (CacheIndex::new(from_cache), CacheIndex::new(to_cache));
+                let from_fmt = AddressFormat::new(&from_index, cli.relative);
+                let to_fmt = AddressFormat::new(&to_index, cli.relative);
+                cmd_translate((&from_index, &from_fmt), (&to_index, &to_fmt), *addr)
+            })
+        }),
+        Commands::Port { path, anchors } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_port(&index, anchors, cli.format, &fmt)
+        }),
+        Commands::Surface { path, module } => {
+            with_dyld_cache(path, &cli, |cache| cmd_surface(cache, module.as_deref()))
         }
-        Commands::Dump { path, addr, size } => {
-            with_dyld_cache(path, |cache| cmd_dump(cache, *addr, *size as usize))
+        Commands::Languages { path, module, kind } => with_dyld_cache(path, &cli, |cache| {
+            cmd_languages(cache, module.as_deref(), *kind)
+        }),
+        Commands::Oslog {
+            path,
+            module,
+            contains,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_oslog(cache, module.as_deref(), contains.as_deref(), &fmt)
+        }),
+        Commands::Grep {
+            path,
+            string,
+            substring,
+            module,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_grep(
+                cache,
+                (string, *substring),
+                module.as_deref(),
+                &fmt,
+                cli.format,
+            )
+        }),
+        Commands::SearchString {
+            path,
+            needle,
+            module,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_search_string(cache, &index, needle, module.as_deref(), &fmt, cli.format)
+        }),
+        Commands::SearchBytes {
+            path,
+            pattern,
+            module,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_search_bytes(cache, &index, pattern, module.as_deref(), &fmt, cli.format)
+        }),
+        Commands::Xrefs { path, addr, module } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_xrefs(cache, &index, *addr, module.as_deref(), &fmt, cli.format)
+        }),
+        Commands::Hooks {
+            path,
+            function,
+            module,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_hooks(
+                &index,
+                function,
+                module.as_deref(),
+                cli.demangle,
+                &fmt,
+                cli.format,
+            )
+        }),
+        Commands::Umbrellas { path, module } => {
+            with_dyld_cache(path, &cli, |cache| cmd_umbrellas(cache, module.as_deref()))
+        }
+        Commands::Shim {
+            path,
+            module,
+            output,
+        } => with_dyld_cache(path, &cli, |cache| cmd_shim(cache, module, output)),
+        Commands::SimulateLoad { path, executable } => {
+            with_dyld_cache(path, &cli, |cache| cmd_simulate_load(cache, executable))
+        }
+        Commands::AuditDeps { path, module } => {
+            with_dyld_cache(path, &cli, |cache| cmd_audit_deps(cache, module.as_deref()))
+        }
+        Commands::Signatures {
+            path,
+            signatures,
+            module,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_signatures(cache, signatures, module, &fmt)
+        }),
+        Commands::Duplicates {
+            path,
+            module,
+            min_size,
+            normalize,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_duplicates(
+                &index,
+                module,
+                *min_size,
+                *normalize,
+                cli.demangle,
+                &fmt,
+                cli.format,
+            )
+        }),
+        Commands::FunctionStarts {
+            path,
+            output,
+            encoding,
+            module,
+        } => {
+            let encoding = encoding.unwrap_or(match cli.format {
+                OutputFormat::Json => StartsFormat::Json,
+                OutputFormat::Text => StartsFormat::Csv,
+            });
+            with_dyld_cache(path, &cli, |cache| {
+                cmd_function_starts(cache, output, encoding, module.as_deref())
+            })
+        }
+        Commands::Index { path, output } => with_dyld_cache(path, &cli, |cache| {
+            cmd_index(&CacheIndex::new(cache), output)
+        }),
+        Commands::Check { path, module } => {
+            with_dyld_cache(path, &cli, |cache| cmd_check(cache, module.as_deref()))
+        }
+        Commands::Blob {
+            path,
+            module,
+            output,
+            all: _,
+            filter,
+            regex,
+            auth,
+            explain,
+            provenance,
+            text_only,
+            rebase_info,
+            fix_selectors,
+            strict,
+            faithful,
+            dry_run,
+            jobs,
+            io_throttle,
+        } => with_dyld_cache(path, &cli, |cache| {
+            if *io_throttle && let Err(e) = throttle::lower_priority() {
+                eprintln!("warning: {}", e);
+            }
+            let mut options = extract::ExtractOptions::default();
+            options.auth = *auth;
+            options.provenance = *provenance;
+            options.text_only = *text_only;
+            options.rebase_info = *rebase_info;
+            options.fix_selectors = *fix_selectors;
+            options.faithful = *faithful;
+            options.strict = *strict;
+            let mode = BlobMode {
+                explain: *explain,
+                dry_run: *dry_run,
+            };
+            match module {
+                Some(module) => cmd_blob(cache, module, output, options, mode),
+                None => cmd_blob_all(
+                    cache,
+                    output,
+                    (filter, regex.as_deref()),
+                    options,
+                    mode,
+                    *jobs,
+                    *io_throttle,
+                ),
+            }
+        }),
+        Commands::EmuExport {
+            path,
+            output,
+            module,
+            start,
+            end,
+            auth,
+        } => with_dyld_cache(path, &cli, |cache| {
+            cmd_emu_export(cache, output, module, start.zip(*end), *auth)
+        }),
+        Commands::ExportsDiff {
+            old_path,
+            old_module,
+            new_path,
+            new_module,
+        } => with_dyld_cache(old_path, &cli, |old_cache| {
+            with_dyld_cache(new_path, &cli, |new_cache| {
+                let old_index = CacheIndex::new(old_cache);
+                let new_index = CacheIndex::new(new_cache);
+                let old_fmt = AddressFormat::new(&old_index, cli.relative);
+                let new_fmt = AddressFormat::new(&new_index, cli.relative);
+                cmd_exports_diff(
+                    (old_cache, old_module, &old_fmt),
+                    (
+                        new_cache,
+                        new_module.as_deref().unwrap_or(old_module),
+                        &new_fmt,
+                    ),
+                    &symbol_filter,
+                    colors,
+                )
+            })
+        }),
+        Commands::ObjcDiff {
+            old_path,
+            new_path,
+            module,
+        } => with_dyld_cache(old_path, &cli, |old_cache| {
+            with_dyld_cache(new_path, &cli, |new_cache| {
+                cmd_objc_diff(old_cache, new_cache, module.as_deref(), cli.group, colors)
+            })
+        }),
+        Commands::Objc { command } => match command {
+            ObjcCommands::IvarOffset {
+                path,
+                class,
+                ivar,
+                module,
+            } => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let fmt = AddressFormat::new(&index, cli.relative);
+                cmd_objc_ivar_offset(&index, class, ivar, module.as_deref(), &fmt)
+            }),
+            ObjcCommands::Impmap { path, module } => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let fmt = AddressFormat::new(&index, cli.relative);
+                cmd_objc_impmap(&index, module.as_deref(), cli.format, &fmt)
+            }),
+            ObjcCommands::Selectors {
+                path,
+                wordlist,
+                by_frequency,
+            } => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let fmt = AddressFormat::new(&index, cli.relative);
+                cmd_objc_selectors(cache, *wordlist, *by_frequency, cli.format, &fmt)
+            }),
+            ObjcCommands::Protocols { path, module } => with_dyld_cache(path, &cli, |cache| {
+                cmd_objc_protocols(cache, module.as_deref(), cli.format)
+            }),
+            ObjcCommands::Categories { path, module } => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let fmt = AddressFormat::new(&index, cli.relative);
+                cmd_objc_categories(&index, module.as_deref(), cli.format, &fmt)
+            }),
+            ObjcCommands::Headers {
+                path,
+                output,
+                module,
+            } => with_dyld_cache(path, &cli, |cache| {
+                cmd_objc_headers(cache, module.as_deref(), output)
+            }),
+            ObjcCommands::Classes { path, module } => with_dyld_cache(path, &cli, |cache| {
+                let index = CacheIndex::new(cache);
+                let fmt = AddressFormat::new(&index, cli.relative);
+                cmd_objc_classes(cache, module.as_deref(), cli.format, &fmt)
+            }),
+            ObjcCommands::Find {
+                path,
+                ivar,
+                size,
+                selector,
+                module,
+            } => with_dyld_cache(path, &cli, |cache| {
+                let query = objc::ClassQuery {
+                    ivars: ivar.clone(),
+                    size: *size,
+                    selectors: selector.clone(),
+                };
+                cmd_objc_find(cache, &query, module.as_deref(), cli.format)
+            }),
+        },
+        Commands::Strings {
+            path,
+            module,
+            section,
+            all_sections,
+            min_length,
+            printable,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            let filter = StringFilter {
+                section: section.as_deref(),
+                all_sections: *all_sections,
+                min_length: *min_length,
+                printable: *printable,
+            };
+            cmd_strings(cache, module.as_deref(), &filter, &fmt, cli.format)
+        }),
+        Commands::StringsDiff {
+            old_path,
+            new_path,
+            module,
+            section,
+        } => with_dyld_cache(old_path, &cli, |old_cache| {
+            with_dyld_cache(new_path, &cli, |new_cache| {
+                cmd_strings_diff(
+                    old_cache,
+                    new_cache,
+                    module.as_deref(),
+                    section.as_deref(),
+                    cli.group,
+                    colors,
+                )
+            })
+        }),
+        Commands::Disasm {
+            path,
+            addr,
+            count,
+            function,
+            module,
+            output,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            let labels = load_labels(cache, &cli.labels)?;
+            let target = match (function, addr) {
+                (Some(name), _) => DisasmTarget::Function {
+                    name,
+                    module: module.as_deref(),
+                },
+                (None, addr) => DisasmTarget::Address {
+                    addr: canonical_address(cache, addr.ok_or("No address given")?),
+                    count: *count,
+                },
+            };
+            cmd_disasm(
+                &index,
+                target,
+                output.as_deref(),
+                &labels,
+                cli.demangle,
+                &fmt,
+            )
+        }),
+        Commands::Dump {
+            path,
+            addr,
+            size,
+            file_offset,
+            annotate,
+            output,
+            page_size,
+            follow,
+            no_limit,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let (vmaddr, size) = match parse_u64(addr) {
+                Ok(addr) => {
+                    let size = size.as_deref().map_or(Ok(256), parse_u64)?;
+                    if *file_offset {
+                        let vmaddr = vm::file_offset_to_address(cache, addr).ok_or_else(|| {
+                            format!("File offset 0x{:X} is not mapped by the main cache", addr)
+                        })?;
+                        eprintln!("File offset 0x{:X} is VM address 0x{:X}", addr, vmaddr);
+                        (vmaddr, size)
+                    } else {
+                        (canonical_address(cache, addr), size)
+                    }
+                }
+                Err(_) if *file_offset => {
+                    return Err(format!("{} is not a file offset", addr).into());
+                }
+                Err(_) => {
+                    let section = size
+                        .as_deref()
+                        .ok_or("Give the segment,section of the image to dump")?;
+                    let section = find_section(&index, addr, section)?;
+                    (section.start, section.end - section.start)
+                }
+            };
+            if let Some(output) = output {
+                return cmd_dump_raw(cache, vmaddr, size, output);
+            }
+            if *follow && cli.format == OutputFormat::Json {
+                return Err("--follow only pages through text output".into());
+            }
+            let paged = cli.format == OutputFormat::Text
+                && (*follow || pager::Pager::new(*page_size).is_paging());
+            if size > MAX_DUMP_SIZE && !*no_limit && !paged {
+                return Err(format!(
+                    "Refusing to print {} bytes (over 1 MiB); pass --no-limit, page through them \
+                     with --page-size, or write the raw bytes with -o",
+                    size
+                )
+                .into());
+            }
+            let labels = load_labels(cache, &cli.labels)?;
+            let symbols = symbolize::Symbolizer::new(&index)
+                .with_labels(&labels)
+                .with_demangling(cli.demangle);
+            cmd_dump(
+                cache,
+                vmaddr,
+                size as usize,
+                &labels,
+                annotate.then_some(&symbols),
+                cli.format,
+                DumpScreen {
+                    colors,
+                    page_size: *page_size,
+                    follow: *follow,
+                },
+            )
+        }),
+        Commands::Segments { path, module } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_segments(cache, module.as_deref(), &fmt, cli.format)
+        }),
+        Commands::Layout { path, module } => {
+            with_dyld_cache(path, &cli, |cache| cmd_layout(cache, module, colors))
         }
-        Commands::Symbols { path, module } => {
-            with_dyld_cache(path, |cache| cmd_symbols(cache, module.as_deref()))
+        Commands::Exports { path, module } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_exports(
+                cache,
+                module.as_deref(),
+                &fmt,
+                cli.format,
+                cli.demangle,
+                &symbol_filter,
+            )
+        }),
+        Commands::Deps { path, module } => with_dyld_cache(path, &cli, |cache| {
+            cmd_deps(cache, module.as_deref(), cli.format)
+        }),
+        Commands::Rdeps { path, dylib } => {
+            with_dyld_cache(path, &cli, |cache| cmd_rdeps(cache, dylib, cli.format))
         }
+        Commands::Imports { path, module } => with_dyld_cache(path, &cli, |cache| {
+            cmd_imports(cache, module.as_deref(), cli.format)
+        }),
+        Commands::Symbols {
+            path,
+            module,
+            public_only,
+        } => with_dyld_cache(path, &cli, |cache| {
+            let index = CacheIndex::new(cache);
+            let fmt = AddressFormat::new(&index, cli.relative);
+            cmd_symbols(
+                cache,
+                module.as_deref(),
+                &fmt,
+                cli.format,
+                cli.show_errors,
+                cli.demangle,
+                (*public_only, &symbol_filter),
+            )
+        }),
     }
 }
@@ -0,0 +1,113 @@
+//! Name patterns given on the command line: shell-style wildcards (see `wildcard_match`) or,
+//! with `--regex`, regular expressions matched anywhere in the name.
+
+use regex::Regex;
+
+use crate::symbolize;
+use crate::utils::wildcard_match;
+
+pub enum NameMatcher {
+    Wildcards(Vec<String>),
+    Regexes(Vec<Regex>),
+}
+
+impl NameMatcher {
+    pub fn new(patterns: &[String], regex: bool) -> Result<Self, regex::Error> {
+        Ok(if regex {
+            NameMatcher::Regexes(
+                patterns
+                    .iter()
+                    .map(|p| Regex::new(p))
+                    .collect::<Result<_, _>>()?,
+            )
+        } else {
+            NameMatcher::Wildcards(patterns.to_vec())
+        })
+    }
+
+    /// Whether any of the patterns matches `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            NameMatcher::Wildcards(patterns) => patterns.iter().any(|p| wildcard_match(p, text)),
+            NameMatcher::Regexes(regexes) => regexes.iter().any(|r| r.is_match(text)),
+        }
+    }
+
+    /// Whether any of the patterns matches the symbol `name` or, with `demangle`, the
+    /// demangled form of a Swift or C++ symbol.
+    pub fn matches_symbol(&self, name: &str, demangle: bool) -> bool {
+        self.is_match(name)
+            || (demangle && symbolize::demangle(name).is_some_and(|d| self.is_match(&d)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn matcher(patterns: &[&str], regex: bool) -> NameMatcher {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        NameMatcher::new(&patterns, regex).unwrap()
+    }
+
+    #[test]
+    fn regexes_match_anywhere() {
+        let m = matcher(&["^/usr/lib/swift/libswift(Core|Foundation)"], true);
+        assert!(m.is_match("/usr/lib/swift/libswiftCore.dylib"));
+        assert!(m.is_match("/usr/lib/swift/libswiftFoundation.dylib"));
+        assert!(!m.is_match("/usr/lib/swift/libswiftDispatch.dylib"));
+        let m = matcher(&["_CF.*Copy", r"\d{2}$"], true);
+        assert!(m.is_match("__CFStringCopyDescription"));
+        assert!(m.is_match("_objc_msgSend$42"));
+        assert!(!m.is_match("_CFRelease"));
+    }
+
+    #[test]
+    fn wildcards_match_whole_names() {
+        let m = matcher(&["_objc_*", "*Copy"], false);
+        assert!(m.is_match("_objc_msgSend"));
+        assert!(m.is_match("_CFStringCopy"));
+        assert!(!m.is_match("__objc_msgSend"));
+    }
+
+    #[test]
+    fn demangled_names_match_with_demangle() {
+        let swift = "_$s4main3fooyySiF";
+        let cpp = "__ZN4llvm5TwineC2Ev";
+        for regex in [false, true] {
+            let pattern = if regex { r"main\.foo\(" } else { "main.foo(*" };
+            let m = matcher(&[pattern], regex);
+            assert!(m.matches_symbol(swift, true));
+            assert!(!m.matches_symbol(swift, false));
+            let pattern = if regex {
+                "^llvm::Twine::"
+            } else {
+                "llvm::Twine::*"
+            };
+            let m = matcher(&[pattern], regex);
+            assert!(m.matches_symbol(cpp, true));
+            assert!(!m.matches_symbol(cpp, false));
+        }
+        // Mangled names still match with --demangle.
+        let m = matcher(&["_$s4main*"], false);
+        assert!(m.matches_symbol(swift, true));
+    }
+
+    #[test]
+    fn invalid_regexes_are_errors() {
+        for pattern in ["(", "[a-", "a{2,1}", "*"] {
+            assert!(NameMatcher::new(&[pattern.to_string()], true).is_err());
+        }
+    }
+
+    #[test]
+    fn nested_quantifiers_run_in_linear_time() {
+        // Exponential for a backtracking matcher.
+        let m = matcher(&["^(a+)+$", "(a|aa)*b"], true);
+        let text = "a".repeat(10_000) + "!";
+        let start = Instant::now();
+        assert!(!m.is_match(&text));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,783 @@
+use object::LittleEndian;
+use object::macho::DyldCacheHeader;
+use object::read::macho::{DyldCache, DyldCacheImage};
+use object::{Object, ObjectSection};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::mem::offset_of;
+
+use crate::slide::PointerReader;
+use crate::vm;
+
+/// `class_t::data` carries flags in its low bits.
+const CLASS_DATA_MASK: u64 = 0x0000_7FFF_FFFF_FFF8;
+/// `FAST_IS_SWIFT_LEGACY | FAST_IS_SWIFT_STABLE` in `class_t::data`.
+const CLASS_IS_SWIFT: u64 = 0b11;
+const METHOD_LIST_SMALL: u32 = 0x8000_0000;
+/// Relative method names are offsets from the cache-wide selector base, not selector references.
+const METHOD_LIST_DIRECT_SELECTORS: u32 = 0x4000_0000;
+const METHOD_LIST_ENTSIZE_MASK: u32 = 0xFFFC;
+/// A tagged `baseMethods` pointer refers to a list of method lists (preattached categories).
+const LIST_OF_LISTS: u64 = 1;
+/// Upper bound on list counts, so that garbage data does not send us into a long loop.
+const MAX_LIST_COUNT: u32 = 0x10000;
+
+pub struct ObjcMethod {
+    pub selector: String,
+    /// Type encoding of the return value and arguments, e.g. `v24@0:8@16`.
+    pub types: Option<String>,
+    /// Address of the implementation, 0 for protocol methods.
+    pub imp: u64,
+}
+
+/// The header of a `method_list_t`.
+pub struct MethodList {
+    pub address: u64,
+    pub flags: u32,
+    pub count: u64,
+    pub entsize: u64,
+}
+
+impl MethodList {
+    /// Read the header at `addr`, checking the entry size against the list format.
+    fn parse(cache: &DyldCache<LittleEndian>, addr: u64) -> Option<Self> {
+        let flags = vm::read_u32(cache, addr)?;
+        let count = vm::read_u32(cache, addr + 4)?;
+        let entsize = (flags & METHOD_LIST_ENTSIZE_MASK) as u64;
+        let small = flags & METHOD_LIST_SMALL != 0;
+        if entsize < if small { 12 } else { 24 } || count > MAX_LIST_COUNT {
+            return None;
+        }
+        Some(MethodList {
+            address: addr,
+            flags,
+            count: count as u64,
+            entsize,
+        })
+    }
+
+    /// Entries hold 32-bit offsets relative to each field instead of pointers.
+    pub fn is_small(&self) -> bool {
+        self.flags & METHOD_LIST_SMALL != 0
+    }
+
+    /// Names of small entries are offsets from the cache-wide selector base instead of offsets
+    /// to selector references.
+    pub fn has_direct_selectors(&self) -> bool {
+        self.flags & METHOD_LIST_DIRECT_SELECTORS != 0
+    }
+
+    /// The flags with direct selectors turned off, for names pointing at selector references.
+    pub fn flags_without_direct_selectors(&self) -> u32 {
+        self.flags & !METHOD_LIST_DIRECT_SELECTORS
+    }
+
+    /// Address of the `index`th entry.
+    pub fn entry(&self, index: u64) -> u64 {
+        self.address + 8 + index * self.entsize
+    }
+}
+
+pub struct ObjcIvar {
+    pub name: String,
+    /// Address of the variable holding the ivar's offset (`OBJC_IVAR_$_Class.ivar`).
+    pub offset_variable: u64,
+    /// Current value of the offset variable, when it is mapped.
+    pub offset: Option<u32>,
+    pub size: u32,
+    /// Type encoding, e.g. `@"NSString"`.
+    pub encoding: Option<String>,
+}
+
+pub struct ObjcProperty {
+    pub name: String,
+    /// Attribute string, e.g. `T@"NSString",C,N,V_name`.
+    pub attributes: String,
+}
+
+pub struct ObjcClass {
+    pub name: String,
+    /// Name of the superclass, `None` for root classes and categories.
+    pub superclass: Option<String>,
+    pub instance_methods: Vec<ObjcMethod>,
+    pub class_methods: Vec<ObjcMethod>,
+    /// `instanceSize` of the class, including its superclasses. `None` for categories.
+    pub instance_size: Option<u32>,
+    /// Ivars the class itself declares, without those of its superclasses.
+    pub ivars: Vec<ObjcIvar>,
+    /// Protocols the class or category adopts itself.
+    pub protocols: Vec<String>,
+    pub properties: Vec<ObjcProperty>,
+    pub class_properties: Vec<ObjcProperty>,
+    /// For categories, the address of the `class_t` they extend. `None` for classes, and for
+    /// categories whose class is not bound (e.g. a missing weak import).
+    pub extended_class: Option<u64>,
+}
+
+pub struct ObjcProtocol {
+    pub name: String,
+    /// Protocols this protocol incorporates.
+    pub protocols: Vec<String>,
+    /// Required and `@optional` instance and class methods. Their types are the extended
+    /// encodings, naming classes of objects, when the protocol has them.
+    pub instance_methods: Vec<ObjcMethod>,
+    pub class_methods: Vec<ObjcMethod>,
+    pub optional_instance_methods: Vec<ObjcMethod>,
+    pub optional_class_methods: Vec<ObjcMethod>,
+    pub properties: Vec<ObjcProperty>,
+}
+
+/// Criteria for identifying a class from an object seen in memory. Every given criterion must
+/// match.
+#[derive(Default)]
+pub struct ClassQuery {
+    /// Ivar names the class declares.
+    pub ivars: Vec<String>,
+    /// Inclusive range of `instanceSize`.
+    pub size: Option<(u32, u32)>,
+    /// Selectors the class implements itself, as instance or class methods.
+    pub selectors: Vec<String>,
+}
+
+impl ClassQuery {
+    pub fn is_empty(&self) -> bool {
+        self.ivars.is_empty() && self.size.is_none() && self.selectors.is_empty()
+    }
+
+    pub fn matches(&self, class: &ObjcClass) -> bool {
+        let Some(size) = class.instance_size else {
+            return false;
+        };
+        self.size
+            .is_none_or(|(min, max)| (min..=max).contains(&size))
+            && self
+                .ivars
+                .iter()
+                .all(|name| class.ivars.iter().any(|ivar| &ivar.name == name))
+            && self.selectors.iter().all(|selector| {
+                class
+                    .instance_methods
+                    .iter()
+                    .chain(&class.class_methods)
+                    .any(|method| &method.selector == selector)
+            })
+    }
+}
+
+/// ObjC metadata defined by one image.
+#[derive(Default)]
+pub struct ObjcMetadata {
+    pub classes: Vec<ObjcClass>,
+    /// Categories, named `Class(Category)`.
+    pub categories: Vec<ObjcClass>,
+    pub protocols: Vec<ObjcProtocol>,
+}
+
+impl ObjcMetadata {
+    /// Flatten the metadata into comparable items: (owner, kind, description), where kind 0 is
+    /// the class, category or protocol itself and kind 1 is one of its methods.
+    pub fn items(&self) -> BTreeSet<(String, u8, String)> {
+        let mut items = BTreeSet::new();
+        for (kind, list) in [("class", &self.classes), ("category", &self.categories)] {
+            for class in list {
+                items.insert((class.name.clone(), 0, format!("{} {}", kind, class.name)));
+                for method in &class.instance_methods {
+                    items.insert((
+                        class.name.clone(),
+                        1,
+                        format!("-[{} {}]", class.name, method.selector),
+                    ));
+                }
+                for method in &class.class_methods {
+                    items.insert((
+                        class.name.clone(),
+                        1,
+                        format!("+[{} {}]", class.name, method.selector),
+                    ));
+                }
+            }
+        }
+        for protocol in &self.protocols {
+            items.insert((
+                protocol.name.clone(),
+                0,
+                format!("protocol {}", protocol.name),
+            ));
+        }
+        items
+    }
+
+    /// Every method implementation of the classes and categories, as (IMP, `-[Class selector]`)
+    /// pairs sorted by address.
+    pub fn implementations(&self) -> Vec<(u64, String)> {
+        let mut imps = Vec::new();
+        for class in self.classes.iter().chain(&self.categories) {
+            for (sign, methods) in [('-', &class.instance_methods), ('+', &class.class_methods)] {
+                for method in methods {
+                    if method.imp != 0 {
+                        imps.push((
+                            method.imp,
+                            format!("{}[{} {}]", sign, class.name, method.selector),
+                        ));
+                    }
+                }
+            }
+        }
+        imps.sort();
+        imps
+    }
+}
+
+pub struct ObjcReader<'a> {
+    pointers: PointerReader<'a>,
+    /// Base address for selectors of relative method lists with direct selectors.
+    selector_base: Option<u64>,
+}
+
+impl<'a> ObjcReader<'a> {
+    pub fn new(cache: &'a DyldCache<'a, LittleEndian>) -> Self {
+        ObjcReader {
+            pointers: PointerReader::new(cache),
+            selector_base: selector_base(cache),
+        }
+    }
+
+    /// Collect the classes, categories and protocols an image defines.
+    pub fn image_metadata(
+        &self,
+        image: &DyldCacheImage<LittleEndian>,
+    ) -> Result<ObjcMetadata, Box<dyn Error>> {
+        let file = image.parse_object()?;
+        let mut metadata = ObjcMetadata::default();
+
+        for section in file.sections() {
+            let Ok(name) = section.name() else {
+                continue;
+            };
+            let start = section.address();
+            let pointers =
+                (0..section.size() / 8).filter_map(|i| self.pointers.read(start + i * 8));
+            match name {
+                "__objc_classlist" => {
+                    metadata
+                        .classes
+                        .extend(pointers.filter_map(|p| self.class(p)));
+                }
+                // Categories on Swift stub classes are listed apart, in __objc_catlist2.
+                "__objc_catlist" | "__objc_catlist2" => {
+                    metadata
+                        .categories
+                        .extend(pointers.filter_map(|p| self.category(p)));
+                }
+                "__objc_protolist" => {
+                    metadata
+                        .protocols
+                        .extend(pointers.filter_map(|p| self.protocol(p)));
+                }
+                _ => {}
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// The method lists of the classes, metaclasses, categories and protocols an image defines,
+    /// with lists of lists expanded, each once.
+    pub fn method_lists(
+        &self,
+        image: &DyldCacheImage<LittleEndian>,
+    ) -> Result<Vec<MethodList>, Box<dyn Error>> {
+        let file = image.parse_object()?;
+        let mut addresses = Vec::new();
+        for section in file.sections() {
+            let Ok(name) = section.name() else {
+                continue;
+            };
+            let start = section.address();
+            let pointers =
+                (0..section.size() / 8).filter_map(|i| self.pointers.read(start + i * 8));
+            let (offsets, metaclass): (&[u64], bool) = match name {
+                "__objc_classlist" => (&[32], true),
+                "__objc_catlist" | "__objc_catlist2" => (&[16, 24], false),
+                "__objc_protolist" => (&[24, 32, 40, 48], false),
+                _ => continue,
+            };
+            for addr in pointers {
+                if metaclass {
+                    let classes = [Some(addr), self.pointers.read(addr).filter(|&isa| isa != 0)];
+                    for class in classes.into_iter().flatten() {
+                        if let Some(ro) = self.pointers.read(class + 32) {
+                            addresses.extend(self.pointers.read((ro & CLASS_DATA_MASK) + 32));
+                        }
+                    }
+                } else {
+                    addresses.extend(offsets.iter().filter_map(|&o| self.pointers.read(addr + o)));
+                }
+            }
+        }
+
+        let mut lists = Vec::new();
+        for addr in addresses {
+            match addr & LIST_OF_LISTS {
+                0 => lists.push(addr),
+                _ => lists.extend(self.lists_of(addr & !LIST_OF_LISTS)),
+            }
+        }
+        let cache = self.pointers.cache();
+        lists.sort_unstable();
+        lists.dedup();
+        Ok(lists
+            .into_iter()
+            .filter(|&addr| addr != 0)
+            .filter_map(|addr| MethodList::parse(cache, addr))
+            .collect())
+    }
+
+    /// Base address of the offsets of method lists with direct selectors, when the cache has one.
+    pub fn selector_base(&self) -> Option<u64> {
+        self.selector_base
+    }
+
+    /// Number of classes an image defines that are plain ObjC and that are Swift classes.
+    pub fn class_languages(
+        &self,
+        image: &DyldCacheImage<LittleEndian>,
+    ) -> Result<(u64, u64), Box<dyn Error>> {
+        let file = image.parse_object()?;
+        let (mut objc, mut swift) = (0, 0);
+        for section in file.sections() {
+            if section.name() != Ok("__objc_classlist") {
+                continue;
+            }
+            let start = section.address();
+            for i in 0..section.size() / 8 {
+                let Some(data) = self
+                    .pointers
+                    .read(start + i * 8)
+                    .and_then(|class| self.pointers.read(class + 32))
+                else {
+                    continue;
+                };
+                if data & CLASS_IS_SWIFT != 0 {
+                    swift += 1;
+                } else {
+                    objc += 1;
+                }
+            }
+        }
+        Ok((objc, swift))
+    }
+
+    /// Address of the variable holding the offset of `ivar` in `class`, looked up in the
+    /// `ivar_list_t` of the classes the image defines.
+    pub fn ivar_offset_variable(
+        &self,
+        image: &DyldCacheImage<LittleEndian>,
+        class: &str,
+        ivar: &str,
+    ) -> Result<Option<u64>, Box<dyn Error>> {
+        let file = image.parse_object()?;
+        for section in file.sections() {
+            if section.name() != Ok("__objc_classlist") {
+                continue;
+            }
+            let start = section.address();
+            for addr in (0..section.size() / 8).filter_map(|i| self.pointers.read(start + i * 8)) {
+                let Some(ro) = self.pointers.read(addr + 32).map(|d| d & CLASS_DATA_MASK) else {
+                    continue;
+                };
+                if self
+                    .pointers
+                    .read(ro + 24)
+                    .and_then(|n| self.string_at(n))
+                    .as_deref()
+                    == Some(class)
+                {
+                    let ivars = self.ivar_list(self.pointers.read(ro + 48).unwrap_or(0));
+                    return Ok(ivars
+                        .into_iter()
+                        .find(|i| i.name == ivar)
+                        .map(|i| i.offset_variable));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Entries of an `ivar_list_t`.
+    fn ivar_list(&self, addr: u64) -> Vec<ObjcIvar> {
+        if addr == 0 {
+            return Vec::new();
+        }
+        let cache = self.pointers.cache();
+        let (Some(entsize), Some(count)) =
+            (vm::read_u32(cache, addr), vm::read_u32(cache, addr + 4))
+        else {
+            return Vec::new();
+        };
+        let entsize = (entsize & !3) as u64;
+        if entsize < 32 || count > MAX_LIST_COUNT {
+            return Vec::new();
+        }
+        (0..count as u64)
+            .filter_map(|i| {
+                let entry = addr + 8 + i * entsize;
+                let offset_variable = self.pointers.read(entry)?;
+                Some(ObjcIvar {
+                    name: self.string_at(self.pointers.read(entry + 8)?)?,
+                    offset_variable,
+                    offset: vm::read_u32(cache, offset_variable),
+                    size: vm::read_u32(cache, entry + 28).unwrap_or(0),
+                    encoding: self
+                        .pointers
+                        .read(entry + 16)
+                        .and_then(|types| self.string_at(types)),
+                })
+            })
+            .collect()
+    }
+
+    /// Entries of a `property_list_t`.
+    fn property_list(&self, addr: u64) -> Vec<ObjcProperty> {
+        if addr == 0 {
+            return Vec::new();
+        }
+        let cache = self.pointers.cache();
+        let (Some(entsize), Some(count)) =
+            (vm::read_u32(cache, addr), vm::read_u32(cache, addr + 4))
+        else {
+            return Vec::new();
+        };
+        if entsize < 16 || count > MAX_LIST_COUNT {
+            return Vec::new();
+        }
+        (0..count as u64)
+            .filter_map(|i| {
+                let entry = addr + 8 + i * entsize as u64;
+                Some(ObjcProperty {
+                    name: self.string_at(self.pointers.read(entry)?)?,
+                    attributes: self.string_at(self.pointers.read(entry + 8)?)?,
+                })
+            })
+            .collect()
+    }
+
+    fn string_at(&self, addr: u64) -> Option<String> {
+        vm::read_cstr(self.pointers.cache(), addr).map(str::to_string)
+    }
+
+    /// Name of the `class_t` at `addr`, from its `class_ro_t`.
+    fn class_name(&self, addr: u64) -> Option<String> {
+        if addr == 0 {
+            return None;
+        }
+        let ro = self.pointers.read(addr + 32)? & CLASS_DATA_MASK;
+        self.string_at(self.pointers.read(ro + 24)?)
+    }
+
+    /// Parse a `class_t` and its metaclass.
+    fn class(&self, addr: u64) -> Option<ObjcClass> {
+        let ro = self.pointers.read(addr + 32)? & CLASS_DATA_MASK;
+        let name = self.string_at(self.pointers.read(ro + 24)?)?;
+        let instance_methods = self.method_list(self.pointers.read(ro + 32)?);
+
+        let meta_ro = self
+            .pointers
+            .read(addr)
+            .filter(|&isa| isa != 0)
+            .and_then(|isa| self.pointers.read(isa + 32))
+            .map(|meta| meta & CLASS_DATA_MASK);
+        let meta_list = |offset: u64| meta_ro.and_then(|ro| self.pointers.read(ro + offset));
+        let class_methods = meta_list(32)
+            .map(|list| self.method_list(list))
+            .unwrap_or_default();
+
+        Some(ObjcClass {
+            name,
+            superclass: self
+                .pointers
+                .read(addr + 8)
+                .and_then(|superclass| self.class_name(superclass)),
+            instance_methods,
+            class_methods,
+            instance_size: vm::read_u32(self.pointers.cache(), ro + 8),
+            ivars: self.ivar_list(self.pointers.read(ro + 48).unwrap_or(0)),
+            protocols: self.protocol_list(self.pointers.read(ro + 40).unwrap_or(0)),
+            properties: self.property_list(self.pointers.read(ro + 64).unwrap_or(0)),
+            class_properties: self.property_list(meta_list(64).unwrap_or(0)),
+            extended_class: None,
+        })
+    }
+
+    /// Parse a `category_t`.
+    fn category(&self, addr: u64) -> Option<ObjcClass> {
+        let name = self.string_at(self.pointers.read(addr)?)?;
+        let extended_class = self.pointers.read(addr + 8).filter(|&cls| cls != 0);
+        let class = extended_class
+            .and_then(|cls| self.class_name(cls))
+            .unwrap_or_else(|| "?".to_string());
+        Some(ObjcClass {
+            name: format!("{}({})", class, name),
+            superclass: None,
+            instance_methods: self.method_list(self.pointers.read(addr + 16)?),
+            class_methods: self.method_list(self.pointers.read(addr + 24)?),
+            instance_size: None,
+            ivars: Vec::new(),
+            protocols: self.protocol_list(self.pointers.read(addr + 32).unwrap_or(0)),
+            properties: self.property_list(self.pointers.read(addr + 40).unwrap_or(0)),
+            class_properties: self.property_list(self.pointers.read(addr + 48).unwrap_or(0)),
+            extended_class,
+        })
+    }
+
+    /// Parse a `protocol_t`. Its method lists hold selectors without implementations.
+    fn protocol(&self, addr: u64) -> Option<ObjcProtocol> {
+        let methods = |offset: u64| -> Vec<ObjcMethod> {
+            self.pointers
+                .read(addr + offset)
+                .map(|list| self.method_list(list))
+                .unwrap_or_default()
+        };
+        let mut protocol = ObjcProtocol {
+            name: self.string_at(self.pointers.read(addr + 8)?)?,
+            protocols: self.protocol_list(self.pointers.read(addr + 16).unwrap_or(0)),
+            instance_methods: methods(24),
+            class_methods: methods(32),
+            optional_instance_methods: methods(40),
+            optional_class_methods: methods(48),
+            properties: self.property_list(self.pointers.read(addr + 56).unwrap_or(0)),
+        };
+
+        // `extendedMethodTypes` follows `size` and `flags` in protocols large enough to hold
+        // it, one string per method in the order of the four lists.
+        let size = vm::read_u32(self.pointers.cache(), addr + 64).unwrap_or(0);
+        if let Some(types) = self
+            .pointers
+            .read(addr + 72)
+            .filter(|&types| size >= 80 && types != 0)
+        {
+            let lists = [
+                &mut protocol.instance_methods,
+                &mut protocol.class_methods,
+                &mut protocol.optional_instance_methods,
+                &mut protocol.optional_class_methods,
+            ];
+            for (i, method) in lists.into_iter().flatten().enumerate() {
+                if let Some(extended) = self
+                    .pointers
+                    .read(types + i as u64 * 8)
+                    .and_then(|s| self.string_at(s))
+                {
+                    method.types = Some(extended);
+                }
+            }
+        }
+        Some(protocol)
+    }
+
+    /// Names of the protocols of a `protocol_list_t`: a 64-bit count and protocol pointers.
+    fn protocol_list(&self, addr: u64) -> Vec<String> {
+        if addr == 0 {
+            return Vec::new();
+        }
+        // The count is a plain integer, not a pointer to decode.
+        let Some(count) = vm::read_bytes(self.pointers.cache(), addr, 8)
+            .ok()
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+            .filter(|&n| n <= MAX_LIST_COUNT as u64)
+        else {
+            return Vec::new();
+        };
+        (0..count)
+            .filter_map(|i| {
+                let protocol = self.pointers.read(addr + 8 + i * 8)?;
+                self.string_at(self.pointers.read(protocol + 8)?)
+            })
+            .collect()
+    }
+
+    /// Methods of a `method_list_t`, or of every list in a list of lists.
+    fn method_list(&self, addr: u64) -> Vec<ObjcMethod> {
+        if addr == 0 {
+            return Vec::new();
+        }
+        if addr & LIST_OF_LISTS != 0 {
+            return self.list_of_lists(addr & !LIST_OF_LISTS);
+        }
+
+        let cache = self.pointers.cache();
+        let Some(list) = MethodList::parse(cache, addr) else {
+            return Vec::new();
+        };
+        let (flags, small) = (list.flags, list.is_small());
+
+        (0..list.count)
+            .filter_map(|i| {
+                let entry = list.entry(i);
+                let (name, types, imp) = if !small {
+                    (
+                        self.pointers.read(entry)?,
+                        self.pointers.read(entry + 8),
+                        self.pointers.read(entry + 16)?,
+                    )
+                } else {
+                    // Small methods hold offsets relative to each field.
+                    let relative = |field: u64| {
+                        let offset = vm::read_u32(cache, field)? as i32 as i64;
+                        Some(field.wrapping_add_signed(offset))
+                    };
+                    let name = if flags & METHOD_LIST_DIRECT_SELECTORS != 0 {
+                        let offset = vm::read_u32(cache, entry)? as i32 as i64;
+                        self.selector_base?.wrapping_add_signed(offset)
+                    } else {
+                        self.pointers.read(relative(entry)?)?
+                    };
+                    (name, relative(entry + 4), relative(entry + 8)?)
+                };
+                Some(ObjcMethod {
+                    selector: self.string_at(name)?,
+                    types: types.and_then(|types| self.string_at(types)),
+                    imp,
+                })
+            })
+            .collect()
+    }
+
+    /// Methods of every list of a `relative_list_list_t`.
+    fn list_of_lists(&self, addr: u64) -> Vec<ObjcMethod> {
+        self.lists_of(addr)
+            .into_iter()
+            .flat_map(|list| self.method_list(list))
+            .collect()
+    }
+
+    /// Addresses of the lists of a `relative_list_list_t`: entries are 64-bit values holding a
+    /// 16-bit image index and a signed 48-bit offset from the entry to a list.
+    fn lists_of(&self, addr: u64) -> Vec<u64> {
+        let cache = self.pointers.cache();
+        let (Some(entsize), Some(count)) =
+            (vm::read_u32(cache, addr), vm::read_u32(cache, addr + 4))
+        else {
+            return Vec::new();
+        };
+        if entsize < 8 || count > MAX_LIST_COUNT {
+            return Vec::new();
+        }
+        (0..count as u64)
+            .filter_map(|i| {
+                let entry = addr + 8 + i * entsize as u64;
+                vm::read_bytes(cache, entry, 8)
+                    .ok()
+                    .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as i64 >> 16)
+                    .map(|offset| entry.wrapping_add_signed(offset))
+            })
+            .collect()
+    }
+}
+
+/// Number of classes an image defines, from the size of its class lists.
+pub fn class_count(image: &DyldCacheImage<LittleEndian>) -> Result<u64, Box<dyn Error>> {
+    Ok(image
+        .parse_object()?
+        .sections()
+        .filter(|s| s.name() == Ok("__objc_classlist"))
+        .map(|s| s.size() / 8)
+        .sum())
+}
+
+/// `relativeMethodSelectorBaseAddressOffset` from the ObjC optimization header, which newer
+/// caches describe in the cache header.
+fn selector_base(cache: &DyldCache<LittleEndian>) -> Option<u64> {
+    let (base, opts) = objc_opts(cache)?;
+    let bytes = vm::read_bytes(cache, opts + 48, 8).ok()?;
+    let offset = u64::from_le_bytes(bytes.try_into().ok()?);
+    (offset != 0).then(|| base + offset)
+}
+
+/// Address of the first mapping, which the offsets of the ObjC optimization header are
+/// relative to, and address of the header, when the cache header points at one.
+fn objc_opts(cache: &DyldCache<LittleEndian>) -> Option<(u64, u64)> {
+    let e = LittleEndian;
+    let header = DyldCacheHeader::<LittleEndian>::parse(cache.data()).ok()?;
+    if offset_of!(DyldCacheHeader<LittleEndian>, objc_opts_size)
+        >= header.mapping_offset.get(e) as usize
+        || header.objc_opts_offset.get(e) == 0
+    {
+        return None;
+    }
+    let base = cache.mappings().next()?.address();
+    Some((base, base + header.objc_opts_offset.get(e)))
+}
+
+/// Address of the selector hash table, from the ObjC optimization header or, in caches that
+/// predate it, from the `objc_opt_t` in libobjc's `__objc_opt_ro` section.
+fn selector_table(cache: &DyldCache<LittleEndian>) -> Result<u64, Box<dyn Error>> {
+    if let Some((base, opts)) = objc_opts(cache) {
+        let bytes = vm::read_bytes(cache, opts + 24, 8)?;
+        let offset = u64::from_le_bytes(bytes.try_into().unwrap());
+        if offset == 0 {
+            return Err("The ObjC optimization header has no selector table".into());
+        }
+        return Ok(base + offset);
+    }
+    let image = cache
+        .images()
+        .find(|image| image.path() == Ok("/usr/lib/libobjc.A.dylib"))
+        .ok_or("Cache has no ObjC optimization header and no libobjc")?;
+    let section = image
+        .parse_object()?
+        .sections()
+        .find(|s| s.name() == Ok("__objc_opt_ro"))
+        .map(|s| s.address())
+        .ok_or("libobjc has no __objc_opt_ro section")?;
+    // objc_opt_t: version, flags, then selopt_offset relative to the structure.
+    let offset = vm::read_u32(cache, section + 8).ok_or("__objc_opt_ro is not mapped")? as i32;
+    if offset == 0 {
+        return Err("objc_opt_t has no selector table".into());
+    }
+    Ok(section.wrapping_add_signed(offset as i64))
+}
+
+/// Every selector uniqued by the cache builder, as (address, name) pairs sorted by address,
+/// from the selector perfect hash table (`objc_stringhash_t`).
+pub fn selectors(cache: &DyldCache<LittleEndian>) -> Result<Vec<(u64, String)>, Box<dyn Error>> {
+    let table = selector_table(cache)?;
+    let field = |index: u64| {
+        vm::read_u32(cache, table + index * 4)
+            .ok_or_else(|| format!("Selector table at 0x{:X} is not mapped", table))
+    };
+    let capacity = field(0)?;
+    let mask = field(3)?;
+    if capacity > 0x100_0000 {
+        return Err(format!("Implausible selector table capacity {}", capacity).into());
+    }
+    // Older tables leave the rounded sizes zero.
+    let tab_size = match field(4)? {
+        0 => mask as u64 + 1,
+        size => size as u64,
+    };
+    let check_size = match field(5)? {
+        0 => capacity as u64,
+        size => size as u64,
+    };
+    // capacity, occupied, shift, mask, the two rounded sizes, salt and scramble[256].
+    let offsets = table + 32 + 256 * 4 + tab_size + check_size;
+    let data = vm::read_bytes(cache, offsets, capacity as u64 * 4)?;
+
+    let mut selectors: Vec<_> = data
+        .chunks_exact(4)
+        .filter_map(|chunk| {
+            // Offsets are relative to the table. Empty slots hold 0 or, in older tables, the
+            // offset of a zero field, which reads as an empty string.
+            let offset = i32::from_le_bytes(chunk.try_into().unwrap());
+            if offset == 0 {
+                return None;
+            }
+            let address = table.wrapping_add_signed(offset as i64);
+            let name = vm::read_cstr(cache, address).filter(|name| !name.is_empty())?;
+            Some((address, name.to_string()))
+        })
+        .collect();
+    selectors.sort();
+    selectors.dedup();
+    Ok(selectors)
+}
@@ -0,0 +1,69 @@
+use object::LittleEndian;
+use object::macho::DyldCacheHeader;
+use object::read::macho::{DyldCache, DyldCacheImage};
+use std::mem::offset_of;
+
+use crate::cachetype::CacheKind;
+
+/// `dylibsExpectedOnDisk` in the header flags, after the 8-bit format version.
+const FLAG_DYLIBS_EXPECTED_ON_DISK: u32 = 1 << 8;
+/// The only dylib dyld lets a root replace in a production cache, below the root of the
+/// cache's runtime (e.g. `/System/DriverKit`).
+const PRODUCTION_OVERRIDABLE: &str = "/usr/lib/system/libdispatch.dylib";
+
+/// Which cached dylibs dyld will replace with a file at the same path on disk (a "root").
+///
+/// When the cache records that its dylibs are expected on disk (older macOS), dyld compares the
+/// modification time and inode of each file with the values stored in the cache and loads the
+/// file instead when they differ. Otherwise the cache is self-contained: development caches
+/// accept roots for every dylib, production caches only for libdispatch.
+pub struct OverridePolicy {
+    pub expected_on_disk: bool,
+    pub production: bool,
+}
+
+impl OverridePolicy {
+    pub fn new(cache: &DyldCache<LittleEndian>) -> Self {
+        let e = LittleEndian;
+        let Ok(header) = DyldCacheHeader::<LittleEndian>::parse(cache.data()) else {
+            return OverridePolicy {
+                expected_on_disk: false,
+                production: false,
+            };
+        };
+        // Fields past the end of an old header overlap the mapping table.
+        let has = |offset: usize| offset < header.mapping_offset.get(e) as usize;
+
+        let expected_on_disk = has(offset_of!(DyldCacheHeader<LittleEndian>, flags))
+            && header.flags.get(e) & FLAG_DYLIBS_EXPECTED_ON_DISK != 0;
+        let production = CacheKind::new(cache).is_production();
+        OverridePolicy {
+            expected_on_disk,
+            production,
+        }
+    }
+
+    pub fn is_overridable(&self, path: &str) -> bool {
+        self.expected_on_disk || !self.production || path.ends_with(PRODUCTION_OVERRIDABLE)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match (self.expected_on_disk, self.production) {
+            (true, _) => {
+                "dylibs expected on disk; files whose mtime/inode differ replace the cache"
+            }
+            (false, false) => "development cache; every dylib can be replaced by a root",
+            (false, true) => "production cache; only libdispatch can be replaced by a root",
+        }
+    }
+}
+
+/// Modification time and inode recorded for an image, checked against the file on disk when
+/// dylibs are expected on disk.
+pub fn disk_identity(image: &DyldCacheImage<LittleEndian>) -> (u64, u64) {
+    let info = image.info();
+    (
+        info.mod_time.get(LittleEndian),
+        info.inode.get(LittleEndian),
+    )
+}
@@ -0,0 +1,83 @@
+//! Pausing long output between screens, like `more`, when a person is reading it.
+
+use std::io::{self, IsTerminal, Write};
+
+/// Counts printed rows and waits for a key once a screen is full.
+pub struct Pager {
+    rows: Option<usize>,
+    printed: usize,
+}
+
+impl Pager {
+    /// Pause every `rows` rows when stdin and stdout are both terminals, and never otherwise, so
+    /// piped output is left alone.
+    pub fn new(rows: Option<usize>) -> Self {
+        let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+        Pager {
+            rows: rows.filter(|&rows| rows > 0 && interactive),
+            printed: 0,
+        }
+    }
+
+    pub fn is_paging(&self) -> bool {
+        self.rows.is_some()
+    }
+
+    /// Count a row about to be printed, first waiting for space or enter when the screen is
+    /// full. Returns false when the reader quit with `q`, escape or end of input.
+    pub fn row(&mut self) -> io::Result<bool> {
+        let Some(rows) = self.rows else {
+            return Ok(true);
+        };
+        if self.printed == rows {
+            self.printed = 0;
+            io::stdout().flush()?;
+            eprint!("--More-- (space or enter for the next page, q to quit)");
+            io::stderr().flush()?;
+            let key = read_key()?;
+            // Erase the prompt so that it does not stay between pages.
+            eprint!("\r\x1b[K");
+            if matches!(key, None | Some(b'q' | b'Q' | 0x1B)) {
+                return Ok(false);
+            }
+        }
+        self.printed += 1;
+        Ok(true)
+    }
+}
+
+/// Read a single key press from the terminal on stdin, without waiting for enter.
+#[cfg(unix)]
+fn read_key() -> io::Result<Option<u8>> {
+    use std::io::Read;
+
+    // SAFETY: termios is plain data, filled in by tcgetattr before use.
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: stdin is a terminal (checked by `Pager::new`) and `saved` is a valid termios.
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut raw = saved;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 1;
+    raw.c_cc[libc::VTIME] = 0;
+    // SAFETY: as above; the saved settings are restored before returning.
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut key = [0u8];
+    let read = io::stdin().lock().read(&mut key);
+    // SAFETY: restores the settings read above.
+    unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved) };
+    Ok((read? == 1).then_some(key[0]))
+}
+
+/// Read a line from stdin, the first key of which is returned.
+#[cfg(not(unix))]
+fn read_key() -> io::Result<Option<u8>> {
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.bytes().next().unwrap_or(b'\n')))
+}
@@ -0,0 +1,280 @@
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use object::macho::{
+    BIND_IMMEDIATE_MASK, BIND_OPCODE_ADD_ADDR_ULEB, BIND_OPCODE_DO_BIND,
+    BIND_OPCODE_DO_BIND_ADD_ADDR_IMM_SCALED, BIND_OPCODE_DO_BIND_ADD_ADDR_ULEB,
+    BIND_OPCODE_DO_BIND_ULEB_TIMES_SKIPPING_ULEB, BIND_OPCODE_DONE, BIND_OPCODE_MASK,
+    BIND_OPCODE_SET_ADDEND_SLEB, BIND_OPCODE_SET_DYLIB_ORDINAL_IMM,
+    BIND_OPCODE_SET_DYLIB_ORDINAL_ULEB, BIND_OPCODE_SET_DYLIB_SPECIAL_IMM,
+    BIND_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB, BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM,
+    BIND_OPCODE_SET_TYPE_IMM, BIND_OPCODE_THREADED,
+    BIND_SUBOPCODE_THREADED_SET_BIND_ORDINAL_TABLE_SIZE_ULEB, BIND_SYMBOL_FLAGS_WEAK_IMPORT,
+};
+
+use super::cursor::Cursor;
+
+/// A symbol bound by name from another image.
+pub struct BoundSymbol {
+    pub name: String,
+    /// Library ordinal: N for the Nth linked dylib, 0 for the image itself, and -1, -2 and
+    /// -3 for the main executable, a flat lookup and a weak lookup.
+    pub ordinal: i64,
+    /// Weak imports may be missing at run time.
+    pub weak: bool,
+}
+
+/// Decode the imports table of `LC_DYLD_CHAINED_FIXUPS` data, in table order.
+pub fn chained_imports(data: &[u8]) -> Result<Vec<BoundSymbol>, String> {
+    let field = |index: usize| read_u32(data, index * 4);
+    let imports_offset = field(2)? as usize;
+    let symbols_offset = field(3)? as usize;
+    let count = field(4)? as usize;
+    let format = field(5)?;
+    if field(6)? != 0 {
+        return Err("Compressed chained fixup symbols are not supported".to_string());
+    }
+    let symbols = data
+        .get(symbols_offset..)
+        .ok_or_else(|| format!("Symbol pool offset 0x{:X} is out of bounds", symbols_offset))?;
+    // DYLD_CHAINED_IMPORT, DYLD_CHAINED_IMPORT_ADDEND and DYLD_CHAINED_IMPORT_ADDEND64.
+    let stride = match format {
+        1 => 4,
+        2 => 8,
+        3 => 16,
+        _ => return Err(format!("Unknown chained fixup imports format {}", format)),
+    };
+    // The count is checked against the data before anything is allocated for it.
+    count
+        .checked_mul(stride)
+        .and_then(|size| imports_offset.checked_add(size))
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| {
+            format!(
+                "{} imports at 0x{:X} do not fit in 0x{:X} bytes",
+                count,
+                imports_offset,
+                data.len()
+            )
+        })?;
+
+    let mut imports = Vec::with_capacity(count);
+    for i in 0..count {
+        let offset = imports_offset + i * stride;
+        let (ordinal, weak, name_offset) = match format {
+            1 | 2 => {
+                let raw = read_u32(data, offset)?;
+                let ordinal = (raw & 0xFF) as u8;
+                let ordinal = if ordinal > 0xF0 {
+                    ordinal as i8 as i64
+                } else {
+                    ordinal as i64
+                };
+                (ordinal, raw & 0x100 != 0, (raw >> 9) as usize)
+            }
+            _ => {
+                let raw = read_u32(data, offset)?;
+                let ordinal = (raw & 0xFFFF) as u16;
+                let ordinal = if ordinal > 0xFFF0 {
+                    ordinal as i16 as i64
+                } else {
+                    ordinal as i64
+                };
+                (
+                    ordinal,
+                    raw & 0x1_0000 != 0,
+                    read_u32(data, offset + 4)? as usize,
+                )
+            }
+        };
+        let name = Cursor::new(symbols, name_offset).cstr()?;
+        imports.push(BoundSymbol {
+            name: name.to_string(),
+            ordinal,
+            weak,
+        });
+    }
+    Ok(imports)
+}
+
+/// Decode the symbols bound by `LC_DYLD_INFO` bind or lazy bind opcodes, each distinct symbol
+/// and ordinal once, in the order they are first bound.
+pub fn bind_opcodes(data: &[u8]) -> Result<Vec<BoundSymbol>, String> {
+    let mut cursor = Cursor::new(data, 0);
+    let mut ordinal = 0i64;
+    let mut name = "";
+    let mut weak = false;
+    let mut seen = BTreeSet::new();
+    let mut symbols = Vec::new();
+    // Lazy bind opcodes end each entry with BIND_OPCODE_DONE, so the whole buffer is read.
+    while cursor.position() < data.len() {
+        let byte = cursor.u8()?;
+        let immediate = byte & BIND_IMMEDIATE_MASK;
+        let mut bind = false;
+        match byte & BIND_OPCODE_MASK {
+            BIND_OPCODE_DONE | BIND_OPCODE_SET_TYPE_IMM => {}
+            BIND_OPCODE_SET_DYLIB_ORDINAL_IMM => ordinal = immediate as i64,
+            BIND_OPCODE_SET_DYLIB_ORDINAL_ULEB => ordinal = cursor.uleb()? as i64,
+            BIND_OPCODE_SET_DYLIB_SPECIAL_IMM => {
+                // Sign-extend the immediate: 0 is the image itself, negatives are special.
+                ordinal = if immediate == 0 {
+                    0
+                } else {
+                    (immediate | BIND_OPCODE_MASK) as i8 as i64
+                };
+            }
+            BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM => {
+                weak = immediate & BIND_SYMBOL_FLAGS_WEAK_IMPORT != 0;
+                name = cursor.cstr()?;
+            }
+            // Addresses are not needed, only skipped. An SLEB128 is as long as a ULEB128.
+            BIND_OPCODE_SET_ADDEND_SLEB
+            | BIND_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB
+            | BIND_OPCODE_ADD_ADDR_ULEB => {
+                cursor.uleb()?;
+            }
+            BIND_OPCODE_DO_BIND | BIND_OPCODE_DO_BIND_ADD_ADDR_IMM_SCALED => bind = true,
+            BIND_OPCODE_DO_BIND_ADD_ADDR_ULEB => {
+                cursor.uleb()?;
+                bind = true;
+            }
+            BIND_OPCODE_DO_BIND_ULEB_TIMES_SKIPPING_ULEB => {
+                cursor.uleb()?;
+                cursor.uleb()?;
+                bind = true;
+            }
+            BIND_OPCODE_THREADED => {
+                if immediate == BIND_SUBOPCODE_THREADED_SET_BIND_ORDINAL_TABLE_SIZE_ULEB {
+                    cursor.uleb()?;
+                }
+            }
+            opcode => {
+                return Err(format!(
+                    "Unknown bind opcode 0x{:02X} at 0x{:X}",
+                    opcode,
+                    cursor.position() - 1
+                ));
+            }
+        }
+        if bind && seen.insert((name, ordinal)) {
+            symbols.push(BoundSymbol {
+                name: name.to_string(),
+                ordinal,
+                weak,
+            });
+        }
+    }
+    Ok(symbols)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("Unexpected end of data at 0x{:X}", offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `dyld_chained_fixups_header` followed by the imports table and the symbol pool.
+    fn fixups(format: u32, count: u32, imports: &[u8], symbols: &[u8]) -> Vec<u8> {
+        let imports_offset = 28;
+        let symbols_offset = imports_offset + imports.len() as u32;
+        let mut data = Vec::new();
+        for field in [0, 0, imports_offset, symbols_offset, count, format, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(imports);
+        data.extend_from_slice(symbols);
+        data
+    }
+
+    #[test]
+    fn chained_imports_formats() {
+        let symbols = b"\0_malloc\0_objc_msgSend\0";
+        // Ordinal 1, then weak with the flat lookup ordinal (0xFE).
+        let import = |ordinal: u32, weak: bool, name: u32| ordinal | (weak as u32) << 8 | name << 9;
+        let mut table = Vec::new();
+        table.extend_from_slice(&import(1, false, 1).to_le_bytes());
+        table.extend_from_slice(&import(0xFE, true, 9).to_le_bytes());
+        let imports = chained_imports(&fixups(1, 2, &table, symbols)).unwrap();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(
+            (
+                imports[0].name.as_str(),
+                imports[0].ordinal,
+                imports[0].weak
+            ),
+            ("_malloc", 1, false)
+        );
+        assert_eq!(
+            (
+                imports[1].name.as_str(),
+                imports[1].ordinal,
+                imports[1].weak
+            ),
+            ("_objc_msgSend", -2, true)
+        );
+
+        // DYLD_CHAINED_IMPORT_ADDEND64: 16-bit ordinal, weak bit 16 and a separate name offset.
+        let mut table = Vec::new();
+        table.extend_from_slice(&(0xFFFFu32 | 0x1_0000).to_le_bytes());
+        table.extend_from_slice(&9u32.to_le_bytes());
+        table.extend_from_slice(&0u64.to_le_bytes());
+        let imports = chained_imports(&fixups(3, 1, &table, symbols)).unwrap();
+        assert_eq!(
+            (
+                imports[0].name.as_str(),
+                imports[0].ordinal,
+                imports[0].weak
+            ),
+            ("_objc_msgSend", -1, true)
+        );
+    }
+
+    #[test]
+    fn malformed_chained_imports() {
+        // A header claiming 0x7FFFFFFF imports with no table must not allocate for them.
+        assert!(chained_imports(&fixups(1, 0x7FFF_FFFF, &[], &[])).is_err());
+        assert!(chained_imports(&fixups(4, 0, &[], &[])).is_err());
+        assert!(chained_imports(&fixups(1, 1, &u32::MAX.to_le_bytes(), b"\0")).is_err());
+        assert!(chained_imports(&[0; 20]).is_err());
+        let mut compressed = fixups(1, 0, &[], &[]);
+        compressed[24] = 1;
+        assert!(chained_imports(&compressed).is_err());
+    }
+
+    #[test]
+    fn bind_opcodes_round_trip() {
+        let mut data = vec![BIND_OPCODE_SET_DYLIB_ORDINAL_IMM | 2];
+        data.push(BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM);
+        data.extend_from_slice(b"_free\0");
+        data.extend_from_slice(&[BIND_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 2, 0x90, 0x01]);
+        data.push(BIND_OPCODE_DO_BIND);
+        // The same symbol again is reported once.
+        data.extend_from_slice(&[BIND_OPCODE_DO_BIND_ULEB_TIMES_SKIPPING_ULEB, 3, 8]);
+        data.push(BIND_OPCODE_SET_DYLIB_SPECIAL_IMM | 0x0E);
+        data.push(BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM | BIND_SYMBOL_FLAGS_WEAK_IMPORT);
+        data.extend_from_slice(b"_weak\0");
+        data.extend_from_slice(&[BIND_OPCODE_SET_ADDEND_SLEB, 0x7F]);
+        data.push(BIND_OPCODE_DO_BIND_ADD_ADDR_IMM_SCALED | 1);
+        data.push(BIND_OPCODE_DONE);
+
+        let symbols = bind_opcodes(&data).unwrap();
+        let decoded: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.ordinal, s.weak))
+            .collect();
+        assert_eq!(decoded, [("_free", 2, false), ("_weak", -2, true)]);
+    }
+
+    #[test]
+    fn malformed_bind_opcodes() {
+        // Unknown opcode, unterminated name and truncated ULEB128.
+        assert!(bind_opcodes(&[0xE0]).is_err());
+        assert!(bind_opcodes(&[BIND_OPCODE_SET_SYMBOL_TRAILING_FLAGS_IMM, b'_']).is_err());
+        assert!(bind_opcodes(&[BIND_OPCODE_ADD_ADDR_ULEB, 0x80]).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::format;
+use alloc::string::String;
+
+/// A forward reader for the ULEB128/C-string encodings used throughout `__LINKEDIT`.
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8], pos: usize) -> Self {
+        Cursor { data, pos }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| format!("Unexpected end of data at 0x{:X}", self.pos))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub fn uleb(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7F) as u64) << shift;
+            }
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    pub fn cstr(&mut self) -> Result<&'a str, String> {
+        let rest = self.data.get(self.pos..).unwrap_or(&[]);
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| format!("Unterminated string at 0x{:X}", self.pos))?;
+        let s = core::str::from_utf8(&rest[..len])
+            .map_err(|_| format!("Invalid UTF-8 string at 0x{:X}", self.pos))?;
+        self.pos += len + 1;
+        Ok(s)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), String> {
+        if self
+            .pos
+            .checked_add(len)
+            .is_none_or(|end| end > self.data.len())
+        {
+            return Err(format!("Unexpected end of data at 0x{:X}", self.pos));
+        }
+        self.pos += len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ulebs_and_strings() {
+        let data = b"\x00\x7F\xE5\x8E\x26\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01abc\0\0";
+        let mut cursor = Cursor::new(data, 0);
+        assert_eq!(cursor.uleb(), Ok(0));
+        assert_eq!(cursor.uleb(), Ok(0x7F));
+        assert_eq!(cursor.uleb(), Ok(624_485));
+        assert_eq!(cursor.uleb(), Ok(u64::MAX));
+        assert_eq!(cursor.cstr(), Ok("abc"));
+        assert_eq!(cursor.cstr(), Ok(""));
+        assert_eq!(cursor.position(), data.len());
+        assert!(cursor.u8().is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Cursor::new(b"\x80\x80", 0).uleb().is_err());
+        assert!(Cursor::new(b"abc", 0).cstr().is_err());
+        assert!(Cursor::new(b"\xFF\0", 0).cstr().is_err());
+        assert!(Cursor::new(b"abc", 5).u8().is_err());
+        let mut cursor = Cursor::new(b"abc", 1);
+        assert!(cursor.skip(3).is_err());
+        assert!(cursor.skip(usize::MAX).is_err());
+        assert_eq!(cursor.skip(2), Ok(()));
+        assert_eq!(cursor.position(), 3);
+    }
+}
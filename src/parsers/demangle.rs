@@ -0,0 +1,2482 @@
+//! A demangler for Swift symbol names (`$s`, `_$s`, `$S` and `_$S` manglings), covering the
+//! entities, types, metadata and thunks found in system libraries. It builds the node tree of
+//! the Swift runtime's demangler and prints it the way `swift demangle` does; symbols using a
+//! mangling it does not know are left alone. Coverage is what the reference corpus in the tests
+//! exercises: functions, initializers, accessors, generics, closures, conformances, witnesses,
+//! the common thunks and metadata. Manglings outside it may print differently from the real
+//! tool.
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const PREFIXES: [&str; 4] = ["_$s", "$s", "_$S", "$S"];
+const MAX_SYMBOL: usize = 4096;
+const MAX_REPEAT: u64 = 2048;
+const MAX_DEPTH: usize = 256;
+const MAX_OUTPUT: usize = 16384;
+
+/// Demangle a Swift symbol, e.g. `_$s4main3fooyySiF` to `main.foo(Swift.Int) -> ()`. `None`
+/// when `symbol` is not a Swift symbol or uses an unsupported mangling.
+pub fn demangle(symbol: &str) -> Option<String> {
+    let text = PREFIXES
+        .iter()
+        .find_map(|prefix| symbol.strip_prefix(prefix))?;
+    if text.is_empty() || text.len() > MAX_SYMBOL {
+        return None;
+    }
+    let global = Demangler::new(text.as_bytes()).global()?;
+    let mut printer = Printer {
+        out: String::new(),
+        depth: 0,
+    };
+    printer.print(&global, false)?;
+    Some(printer.out)
+}
+
+/// Whether `symbol` has a Swift mangling prefix, whether or not it can be demangled.
+pub fn is_swift(symbol: &str) -> bool {
+    PREFIXES.iter().any(|prefix| symbol.starts_with(prefix))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Global,
+    Suffix,
+    Module,
+    Identifier,
+    Type,
+    TypeMangling,
+    Class,
+    Structure,
+    Enum,
+    Protocol,
+    TypeAlias,
+    Extension,
+    BoundGenericClass,
+    BoundGenericStructure,
+    BoundGenericEnum,
+    BoundGenericTypeAlias,
+    TypeList,
+    Tuple,
+    TupleElement,
+    TupleElementName,
+    VariadicMarker,
+    EmptyList,
+    FirstElementMarker,
+    LabelList,
+    FunctionType,
+    NoEscapeFunctionType,
+    ThinFunctionType,
+    CFunctionPointer,
+    ObjCBlock,
+    AutoClosureType,
+    ArgumentTuple,
+    ReturnType,
+    ThrowsAnnotation,
+    TypedThrowsAnnotation,
+    AsyncAnnotation,
+    ConcurrentFunctionType,
+    GlobalActorFunctionType,
+    IsolatedAnyFunctionType,
+    SendingResultFunctionType,
+    Function,
+    Variable,
+    Subscript,
+    Static,
+    /// An accessor of a variable or subscript; the text is the accessor name.
+    Accessor,
+    Allocator,
+    Constructor,
+    Deallocator,
+    Destructor,
+    IVarInitializer,
+    IVarDestroyer,
+    Initializer,
+    ExplicitClosure,
+    ImplicitClosure,
+    DefaultArgumentInitializer,
+    Number,
+    PrivateDeclName,
+    LocalDeclName,
+    /// A declaration related to an imported one; the text is the kind of relation.
+    RelatedEntityDeclName,
+    /// An operator name; the text includes the fixity, e.g. `== infix`.
+    Operator,
+    DependentGenericParamType,
+    DependentGenericSignature,
+    DependentGenericParamCount,
+    ConformanceRequirement,
+    SameTypeRequirement,
+    LayoutRequirement,
+    DependentGenericType,
+    DependentMemberType,
+    DependentAssociatedTypeRef,
+    Metatype,
+    ExistentialMetatype,
+    ProtocolList,
+    ProtocolListWithAnyObject,
+    ProtocolListWithClass,
+    /// A type with a modifier such as `inout`; the text is printed before the type.
+    Modifier,
+    DynamicSelf,
+    BuiltinTypeName,
+    OpaqueReturnType,
+    ProtocolConformance,
+    /// A description such as "type metadata for "; `{N}` in the text prints the Nth child,
+    /// otherwise the children are printed after the text.
+    Described,
+    /// Like `Described`, for attributes of the entity printed after them.
+    Attribute,
+    /// A partial apply forwarder, the parent of the entities it forwards to.
+    PartialApply,
+    /// A specialization with its generic arguments, printed before the entity.
+    Specialization,
+}
+
+struct Node {
+    kind: Kind,
+    text: String,
+    index: u64,
+    children: Vec<Rc<Node>>,
+}
+
+type NodeRef = Rc<Node>;
+
+fn make(kind: Kind, text: &str, index: u64, children: Vec<NodeRef>) -> NodeRef {
+    Rc::new(Node {
+        kind,
+        text: text.to_string(),
+        index,
+        children,
+    })
+}
+
+fn leaf(kind: Kind) -> NodeRef {
+    make(kind, "", 0, Vec::new())
+}
+
+fn text(kind: Kind, text: &str) -> NodeRef {
+    make(kind, text, 0, Vec::new())
+}
+
+fn number(kind: Kind, index: u64) -> NodeRef {
+    make(kind, "", index, Vec::new())
+}
+
+fn parent(kind: Kind, children: Vec<NodeRef>) -> NodeRef {
+    make(kind, "", 0, children)
+}
+
+fn described(kind: Kind, text: &str, children: Vec<NodeRef>) -> NodeRef {
+    make(kind, text, 0, children)
+}
+
+fn ty(child: NodeRef) -> NodeRef {
+    parent(Kind::Type, vec![child])
+}
+
+fn generic_param(depth: u64, index: u64) -> NodeRef {
+    let mut name = String::new();
+    let mut i = index;
+    loop {
+        name.push((b'A' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            break;
+        }
+    }
+    if depth != 0 {
+        name.push_str(&depth.to_string());
+    }
+    text(Kind::DependentGenericParamType, &name)
+}
+
+fn is_context(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Module
+            | Kind::Class
+            | Kind::Structure
+            | Kind::Enum
+            | Kind::Protocol
+            | Kind::TypeAlias
+            | Kind::Extension
+            | Kind::Function
+            | Kind::Variable
+            | Kind::Subscript
+            | Kind::Static
+            | Kind::Accessor
+            | Kind::Allocator
+            | Kind::Constructor
+            | Kind::Deallocator
+            | Kind::Destructor
+            | Kind::IVarInitializer
+            | Kind::IVarDestroyer
+            | Kind::Initializer
+            | Kind::ExplicitClosure
+            | Kind::ImplicitClosure
+            | Kind::DefaultArgumentInitializer
+    )
+}
+
+fn is_entity(kind: Kind) -> bool {
+    kind == Kind::Type || is_context(kind)
+}
+
+fn is_decl_name(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Identifier
+            | Kind::LocalDeclName
+            | Kind::PrivateDeclName
+            | Kind::RelatedEntityDeclName
+            | Kind::Operator
+    )
+}
+
+fn is_requirement(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::ConformanceRequirement | Kind::SameTypeRequirement | Kind::LayoutRequirement
+    )
+}
+
+fn is_function_attr(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::Attribute | Kind::PartialApply | Kind::Specialization
+    )
+}
+
+/// Whether the generic arguments of a bound generic type apply to `kind` rather than only to
+/// its parents.
+fn consumes_generic_args(kind: Kind) -> bool {
+    !matches!(
+        kind,
+        Kind::Variable
+            | Kind::Subscript
+            | Kind::ImplicitClosure
+            | Kind::ExplicitClosure
+            | Kind::DefaultArgumentInitializer
+            | Kind::Initializer
+    )
+}
+
+/// A stack machine over the mangled text: each operator pops the nodes it applies to and
+/// pushes its result, and substitutions refer back to earlier nodes by index.
+struct Demangler<'a> {
+    text: &'a [u8],
+    pos: usize,
+    stack: Vec<NodeRef>,
+    substitutions: Vec<NodeRef>,
+    words: Vec<&'a str>,
+}
+
+impl<'a> Demangler<'a> {
+    fn new(text: &'a [u8]) -> Self {
+        Demangler {
+            text,
+            pos: 0,
+            stack: Vec::new(),
+            substitutions: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> u8 {
+        self.text.get(self.pos).copied().unwrap_or(0)
+    }
+
+    fn next(&mut self) -> u8 {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn next_if(&mut self, c: u8) -> bool {
+        let matched = self.peek() == c;
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn push_back(&mut self) {
+        self.pos -= 1;
+    }
+
+    fn pop_if(&mut self, pred: impl Fn(Kind) -> bool) -> Option<NodeRef> {
+        match self.stack.last() {
+            Some(node) if pred(node.kind) => self.stack.pop(),
+            _ => None,
+        }
+    }
+
+    fn pop_kind(&mut self, kind: Kind) -> Option<NodeRef> {
+        self.pop_if(|k| k == kind)
+    }
+
+    fn pop_type(&mut self) -> Option<NodeRef> {
+        self.pop_kind(Kind::Type)
+    }
+
+    fn pop_type_child(&mut self) -> Option<NodeRef> {
+        self.pop_type()?.children.first().cloned()
+    }
+
+    /// A module, which is pushed as an identifier.
+    fn pop_module(&mut self) -> Option<NodeRef> {
+        if let Some(identifier) = self.pop_kind(Kind::Identifier) {
+            return Some(text(Kind::Module, &identifier.text));
+        }
+        self.pop_kind(Kind::Module)
+    }
+
+    fn pop_context(&mut self) -> Option<NodeRef> {
+        if let Some(module) = self.pop_module() {
+            return Some(module);
+        }
+        if let Some(ty) = self.pop_type() {
+            let child = ty.children.first()?;
+            return is_context(child.kind).then(|| child.clone());
+        }
+        self.pop_if(is_context)
+    }
+
+    /// A protocol type, or the name and context of one.
+    fn pop_protocol(&mut self) -> Option<NodeRef> {
+        if let Some(ty) = self.pop_type() {
+            return (ty.children.first()?.kind == Kind::Protocol).then_some(ty);
+        }
+        let name = self.pop_if(is_decl_name)?;
+        let context = self.pop_context()?;
+        Some(ty(parent(Kind::Protocol, vec![context, name])))
+    }
+
+    fn pop_conformance(&mut self) -> Option<NodeRef> {
+        let signature = self.pop_kind(Kind::DependentGenericSignature);
+        let module = self.pop_module()?;
+        let protocol = self.pop_protocol()?;
+        let mut conforming = self.pop_type()?;
+        if let Some(signature) = signature {
+            conforming = ty(parent(
+                Kind::DependentGenericType,
+                vec![signature, conforming],
+            ));
+        }
+        Some(parent(
+            Kind::ProtocolConformance,
+            vec![conforming, protocol, module],
+        ))
+    }
+
+    /// An associated type name with an optional protocol.
+    fn pop_assoc_type_name(&mut self) -> Option<NodeRef> {
+        let protocol = match self.pop_type() {
+            Some(ty) if ty.children.first()?.kind == Kind::Protocol => Some(ty),
+            Some(_) => return None,
+            None => None,
+        };
+        let name = self.pop_kind(Kind::Identifier)?;
+        Some(make(
+            Kind::DependentAssociatedTypeRef,
+            &name.text,
+            0,
+            protocol.into_iter().collect(),
+        ))
+    }
+
+    fn type_list(&mut self) -> Option<Vec<NodeRef>> {
+        let mut types = Vec::new();
+        if self.pop_kind(Kind::EmptyList).is_none() {
+            loop {
+                let first = self.pop_kind(Kind::FirstElementMarker).is_some();
+                types.push(self.pop_type()?);
+                if first {
+                    break;
+                }
+            }
+            types.reverse();
+        }
+        Some(types)
+    }
+
+    /// The digits at the current position.
+    fn natural(&mut self) -> Option<u64> {
+        if !self.peek().is_ascii_digit() {
+            return None;
+        }
+        let mut value = 0u64;
+        while self.peek().is_ascii_digit() {
+            value = value
+                .checked_mul(10)?
+                .checked_add((self.next() - b'0') as u64)?;
+        }
+        Some(value)
+    }
+
+    /// `_` for 0, or `N_` for N + 1.
+    fn index(&mut self) -> Option<u64> {
+        if self.next_if(b'_') {
+            return Some(0);
+        }
+        let value = self.natural()?;
+        if self.next_if(b'_') {
+            value.checked_add(1)
+        } else {
+            None
+        }
+    }
+
+    fn global(mut self) -> Option<NodeRef> {
+        while self.pos < self.text.len() {
+            let node = self.operator()?;
+            self.stack.push(node);
+        }
+        // Attributes apply to what follows them; a partial apply forwarder becomes the
+        // parent of the rest.
+        let mut frames = vec![(leaf(Kind::Global), Vec::new())];
+        while let Some(attribute) = self.pop_if(is_function_attr) {
+            if attribute.kind == Kind::PartialApply {
+                frames.push((attribute, Vec::new()));
+            } else {
+                frames.last_mut()?.1.push(attribute);
+            }
+        }
+        for node in self.stack.drain(..) {
+            let node = if node.kind == Kind::Type {
+                node.children.first()?.clone()
+            } else {
+                node
+            };
+            frames.last_mut()?.1.push(node);
+        }
+        let mut result = None;
+        while let Some((node, mut children)) = frames.pop() {
+            children.extend(result.take());
+            if children.is_empty() && node.kind == Kind::Global {
+                return None;
+            }
+            result = Some(make(node.kind, &node.text, node.index, children));
+        }
+        result
+    }
+
+    fn operator(&mut self) -> Option<NodeRef> {
+        let node = match self.next() {
+            b'A' => return self.multi_substitutions(),
+            b'B' => self.builtin_type()?,
+            b'C' => self.nominal(Kind::Class)?,
+            b'D' => parent(Kind::TypeMangling, vec![self.pop_type()?]),
+            b'E' => self.extension()?,
+            b'F' => self.plain_function()?,
+            b'G' => self.bound_generic()?,
+            b'K' => leaf(Kind::ThrowsAnnotation),
+            b'L' => self.local_identifier()?,
+            b'M' => self.metatype()?,
+            b'N' => described(
+                Kind::Described,
+                "type metadata for ",
+                vec![self.pop_type()?],
+            ),
+            b'O' => self.nominal(Kind::Enum)?,
+            b'P' => self.nominal(Kind::Protocol)?,
+            b'Q' => self.archetype()?,
+            b'R' => self.generic_requirement()?,
+            b'S' => self.standard_substitution()?,
+            b'T' => self.thunk()?,
+            b'V' => self.nominal(Kind::Structure)?,
+            b'W' => self.witness()?,
+            b'X' => self.special_type()?,
+            b'Y' => self.type_annotation()?,
+            b'Z' => parent(Kind::Static, vec![self.pop_if(is_entity)?]),
+            b'a' => self.nominal(Kind::TypeAlias)?,
+            b'c' => self.function_type(Kind::FunctionType)?,
+            b'd' => leaf(Kind::VariadicMarker),
+            b'f' => self.function_entity()?,
+            b'h' => self.modifier("__shared ")?,
+            b'i' => self.subscript()?,
+            b'l' => self.generic_signature(false)?,
+            b'm' => ty(parent(Kind::Metatype, vec![self.pop_type()?])),
+            b'n' => self.modifier("__owned ")?,
+            b'o' => self.operator_identifier()?,
+            b'p' => ty(self.protocol_list()?),
+            b'q' => ty(self.generic_param_index()?),
+            b'r' => self.generic_signature(true)?,
+            b's' => text(Kind::Module, "Swift"),
+            b't' => self.tuple()?,
+            b'u' => self.generic_type()?,
+            b'v' => self.variable()?,
+            b'x' => ty(generic_param(0, 0)),
+            b'y' => leaf(Kind::EmptyList),
+            b'z' => self.modifier("inout ")?,
+            b'_' => leaf(Kind::FirstElementMarker),
+            b'.' => {
+                // Everything from a `.` on is a suffix added after mangling, e.g. `.cold.1`.
+                self.push_back();
+                let suffix = core::str::from_utf8(&self.text[self.pos..]).ok()?;
+                self.pos = self.text.len();
+                text(Kind::Suffix, suffix)
+            }
+            b'0'..=b'9' => {
+                self.push_back();
+                self.identifier()?
+            }
+            _ => return None,
+        };
+        Some(node)
+    }
+
+    fn identifier(&mut self) -> Option<NodeRef> {
+        let mut has_word_substitutions = false;
+        if self.next_if(b'0') {
+            if self.peek() == b'0' {
+                // Punycode-encoded Unicode names.
+                return None;
+            }
+            has_word_substitutions = true;
+        }
+        let mut identifier = String::new();
+        loop {
+            // Lowercase letters refer to earlier words and continue; uppercase ends the list.
+            while has_word_substitutions && self.peek().is_ascii_alphabetic() {
+                let c = self.next();
+                let word = if c.is_ascii_lowercase() {
+                    c - b'a'
+                } else {
+                    has_word_substitutions = false;
+                    c - b'A'
+                };
+                identifier.push_str(self.words.get(word as usize)?);
+            }
+            if self.next_if(b'0') {
+                break;
+            }
+            let length = self.natural()? as usize;
+            if length == 0 {
+                return None;
+            }
+            let start = self.pos;
+            let end = start.checked_add(length)?;
+            let part = core::str::from_utf8(self.text.get(start..end)?).ok()?;
+            identifier.push_str(part);
+            self.record_words(start, end);
+            self.pos = end;
+            if !has_word_substitutions {
+                break;
+            }
+        }
+        if identifier.is_empty() {
+            return None;
+        }
+        let node = text(Kind::Identifier, &identifier);
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    /// Remember the words of an identifier for later word substitutions: runs of at least two
+    /// characters that start at a non-digit and end before `_`, the end, or an uppercase
+    /// letter following a non-uppercase one.
+    fn record_words(&mut self, start: usize, end: usize) {
+        let text = self.text;
+        let bytes = &text[start..end];
+        let mut word_start = None;
+        for i in 0..=bytes.len() {
+            let c = bytes.get(i).copied().unwrap_or(0);
+            if let Some(from) = word_start {
+                let prev: u8 = bytes[i - 1];
+                if c == b'_' || c == 0 || (!prev.is_ascii_uppercase() && c.is_ascii_uppercase()) {
+                    if i - from >= 2 && self.words.len() < 26 {
+                        let word = &text[start + from..start + i];
+                        self.words.push(core::str::from_utf8(word).unwrap_or(""));
+                    }
+                    word_start = None;
+                }
+            }
+            if word_start.is_none() && !c.is_ascii_digit() && c != b'_' && c != 0 {
+                word_start = Some(i);
+            }
+        }
+    }
+
+    fn multi_substitutions(&mut self) -> Option<NodeRef> {
+        let mut repeat = 1;
+        loop {
+            let c = self.next();
+            if c.is_ascii_lowercase() {
+                // More substitutions follow.
+                let node = self.substitutions.get((c - b'a') as usize)?.clone();
+                for _ in 0..repeat {
+                    self.stack.push(node.clone());
+                }
+                repeat = 1;
+            } else if c.is_ascii_uppercase() {
+                let node = self.substitutions.get((c - b'A') as usize)?.clone();
+                for _ in 1..repeat {
+                    self.stack.push(node.clone());
+                }
+                return Some(node);
+            } else if c == b'_' {
+                return self.substitutions.get(repeat as usize + 27).cloned();
+            } else {
+                self.push_back();
+                repeat = self.natural()?;
+                if repeat > MAX_REPEAT {
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn standard_substitution(&mut self) -> Option<NodeRef> {
+        match self.next() {
+            b'o' => return Some(text(Kind::Module, "__C")),
+            b'C' => return Some(text(Kind::Module, "__C_Synthesized")),
+            b'g' => {
+                let wrapped = self.pop_type()?;
+                let node = ty(parent(
+                    Kind::BoundGenericEnum,
+                    vec![
+                        swift_type(Kind::Enum, "Optional"),
+                        parent(Kind::TypeList, vec![wrapped]),
+                    ],
+                ));
+                self.substitutions.push(node.clone());
+                return Some(node);
+            }
+            _ => self.push_back(),
+        }
+        let repeat = self.natural().unwrap_or(1);
+        if repeat > MAX_REPEAT {
+            return None;
+        }
+        let (kind, name) = if self.next_if(b'c') {
+            concurrency_type(self.next())?
+        } else {
+            standard_type(self.next())?
+        };
+        let node = swift_type(kind, name);
+        for _ in 1..repeat {
+            self.stack.push(node.clone());
+        }
+        Some(node)
+    }
+
+    fn nominal(&mut self, kind: Kind) -> Option<NodeRef> {
+        let name = self.pop_if(is_decl_name)?;
+        let context = self.pop_context()?;
+        let node = ty(parent(kind, vec![context, name]));
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn extension(&mut self) -> Option<NodeRef> {
+        let signature = self.pop_kind(Kind::DependentGenericSignature);
+        let module = self.pop_module()?;
+        let extended = self.pop_type_child()?;
+        let mut children = vec![module, extended];
+        children.extend(signature);
+        Some(parent(Kind::Extension, children))
+    }
+
+    /// `<nominal> y <args> [_ <args>...] G`: arguments for the type, then for its parents,
+    /// innermost first.
+    fn bound_generic(&mut self) -> Option<NodeRef> {
+        let mut lists = Vec::new();
+        loop {
+            let mut types = Vec::new();
+            while let Some(arg) = self.pop_type() {
+                types.push(arg);
+            }
+            types.reverse();
+            lists.push(parent(Kind::TypeList, types));
+            if self.pop_kind(Kind::EmptyList).is_some() {
+                break;
+            }
+            self.pop_kind(Kind::FirstElementMarker)?;
+        }
+        let nominal = self.pop_type_child()?;
+        let node = ty(bind_generic_args(nominal, &lists, 0)?);
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn builtin_type(&mut self) -> Option<NodeRef> {
+        let name = match self.next() {
+            b'b' => "Builtin.BridgeObject".to_string(),
+            b'B' => "Builtin.UnsafeValueBuffer".to_string(),
+            b'c' => "Builtin.RawUnsafeContinuation".to_string(),
+            b'D' => "Builtin.DefaultActorStorage".to_string(),
+            b'd' => "Builtin.NonDefaultDistributedActorStorage".to_string(),
+            b'e' => "Builtin.Executor".to_string(),
+            b'f' => format!("Builtin.FPIEEE{}", self.index()?.checked_sub(1)?),
+            b'I' => "Builtin.IntLiteral".to_string(),
+            b'i' => format!("Builtin.Int{}", self.index()?.checked_sub(1)?),
+            b'j' => "Builtin.Job".to_string(),
+            b'O' => "Builtin.UnknownObject".to_string(),
+            b'o' => "Builtin.NativeObject".to_string(),
+            b'p' => "Builtin.RawPointer".to_string(),
+            b't' => "Builtin.SILToken".to_string(),
+            b'w' => "Builtin.Word".to_string(),
+            _ => return None,
+        };
+        let node = ty(text(Kind::BuiltinTypeName, &name));
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn modifier(&mut self, modifier: &str) -> Option<NodeRef> {
+        let inner = self.pop_type()?;
+        Some(ty(described(Kind::Modifier, modifier, vec![inner])))
+    }
+
+    fn tuple(&mut self) -> Option<NodeRef> {
+        let mut elements = Vec::new();
+        if self.pop_kind(Kind::EmptyList).is_none() {
+            loop {
+                let first = self.pop_kind(Kind::FirstElementMarker).is_some();
+                let mut children = Vec::new();
+                children.extend(self.pop_kind(Kind::VariadicMarker));
+                if let Some(label) = self.pop_kind(Kind::Identifier) {
+                    children.push(text(Kind::TupleElementName, &label.text));
+                }
+                children.push(self.pop_type()?);
+                elements.push(parent(Kind::TupleElement, children));
+                if first {
+                    break;
+                }
+            }
+            elements.reverse();
+        }
+        Some(ty(parent(Kind::Tuple, elements)))
+    }
+
+    /// Parameters or results of a function type; `y` is an empty list.
+    fn pop_function_params(&mut self) -> Option<NodeRef> {
+        if self.pop_kind(Kind::EmptyList).is_some() {
+            return Some(ty(parent(Kind::Tuple, Vec::new())));
+        }
+        self.pop_type()
+    }
+
+    /// `<result> <params> [annotations] c`, and the `X` function types.
+    fn function_type(&mut self, kind: Kind) -> Option<NodeRef> {
+        let mut children = Vec::new();
+        children.extend(self.pop_kind(Kind::SendingResultFunctionType));
+        children.extend(self.pop_kind(Kind::GlobalActorFunctionType));
+        children.extend(self.pop_kind(Kind::IsolatedAnyFunctionType));
+        children.extend(
+            self.pop_if(|k| k == Kind::ThrowsAnnotation || k == Kind::TypedThrowsAnnotation),
+        );
+        children.extend(self.pop_kind(Kind::ConcurrentFunctionType));
+        children.extend(self.pop_kind(Kind::AsyncAnnotation));
+        let params = self.pop_function_params()?;
+        let result = self.pop_function_params()?;
+        children.push(parent(Kind::ArgumentTuple, vec![params]));
+        children.push(parent(Kind::ReturnType, vec![result]));
+        Some(ty(parent(kind, children)))
+    }
+
+    /// Argument labels of a function-typed entity, one identifier or `_` per parameter, or
+    /// `y` for none. `None` when the type takes no parameters.
+    fn pop_labels(&mut self, entity_type: &NodeRef) -> Option<Option<NodeRef>> {
+        if self.pop_kind(Kind::EmptyList).is_some() {
+            return Some(Some(parent(Kind::LabelList, Vec::new())));
+        }
+        let mut function = entity_type.children.first()?;
+        if function.kind == Kind::DependentGenericType {
+            function = function.children.get(1)?.children.first()?;
+        }
+        if !matches!(
+            function.kind,
+            Kind::FunctionType | Kind::NoEscapeFunctionType
+        ) {
+            return Some(None);
+        }
+        let arguments = function
+            .children
+            .iter()
+            .find(|c| c.kind == Kind::ArgumentTuple)?;
+        let params = arguments.children.first()?.children.first()?;
+        let count = if params.kind == Kind::Tuple {
+            params.children.len()
+        } else {
+            1
+        };
+        if count == 0 {
+            return Some(None);
+        }
+        let mut labels = Vec::new();
+        for _ in 0..count {
+            labels.push(self.pop_if(|k| k == Kind::Identifier || k == Kind::FirstElementMarker)?);
+        }
+        if labels.iter().all(|l| l.kind == Kind::FirstElementMarker) {
+            labels.clear();
+        }
+        labels.reverse();
+        Some(Some(parent(Kind::LabelList, labels)))
+    }
+
+    fn type_annotation(&mut self) -> Option<NodeRef> {
+        let node = match self.next() {
+            b'a' => leaf(Kind::AsyncAnnotation),
+            b'A' => leaf(Kind::IsolatedAnyFunctionType),
+            b'b' => leaf(Kind::ConcurrentFunctionType),
+            b'c' => parent(Kind::GlobalActorFunctionType, vec![self.pop_type()?]),
+            b'K' => parent(Kind::TypedThrowsAnnotation, vec![self.pop_type()?]),
+            b'T' => leaf(Kind::SendingResultFunctionType),
+            b'i' => self.modifier("isolated ")?,
+            b't' => self.modifier("_const ")?,
+            b'u' => self.modifier("sending ")?,
+            _ => return None,
+        };
+        Some(node)
+    }
+
+    fn special_type(&mut self) -> Option<NodeRef> {
+        let node = match self.next() {
+            b'E' => self.function_type(Kind::NoEscapeFunctionType)?,
+            b'A' | b'K' => self.function_type(Kind::AutoClosureType)?,
+            b'f' => self.function_type(Kind::ThinFunctionType)?,
+            b'B' | b'L' => self.function_type(Kind::ObjCBlock)?,
+            b'C' => self.function_type(Kind::CFunctionPointer)?,
+            b'o' => self.modifier("unowned ")?,
+            b'u' => self.modifier("unowned(unsafe) ")?,
+            b'w' => self.modifier("weak ")?,
+            b'D' => {
+                self.pop_type()?;
+                ty(leaf(Kind::DynamicSelf))
+            }
+            b'M' | b'm' => {
+                // The representation (thin, thick or @objc) is not printed.
+                let kind = if self.text[self.pos - 1] == b'M' {
+                    Kind::Metatype
+                } else {
+                    Kind::ExistentialMetatype
+                };
+                if !matches!(self.next(), b't' | b'T' | b'o') {
+                    return None;
+                }
+                ty(parent(kind, vec![self.pop_type()?]))
+            }
+            b'p' => ty(parent(Kind::ExistentialMetatype, vec![self.pop_type()?])),
+            b'c' => {
+                let superclass = self.pop_type()?;
+                let protocols = self.protocol_list()?;
+                ty(parent(
+                    Kind::ProtocolListWithClass,
+                    vec![protocols, superclass],
+                ))
+            }
+            b'l' => {
+                let protocols = self.protocol_list()?;
+                ty(parent(Kind::ProtocolListWithAnyObject, vec![protocols]))
+            }
+            _ => return None,
+        };
+        Some(node)
+    }
+
+    fn protocol_list(&mut self) -> Option<NodeRef> {
+        let mut protocols = Vec::new();
+        if self.pop_kind(Kind::EmptyList).is_none() {
+            loop {
+                let first = self.pop_kind(Kind::FirstElementMarker).is_some();
+                protocols.push(self.pop_protocol()?);
+                if first {
+                    break;
+                }
+            }
+            protocols.reverse();
+        }
+        Some(parent(
+            Kind::ProtocolList,
+            vec![parent(Kind::TypeList, protocols)],
+        ))
+    }
+
+    fn generic_param_index(&mut self) -> Option<NodeRef> {
+        if self.next_if(b'd') {
+            let depth = self.index()?.checked_add(1)?;
+            let index = self.index()?;
+            return Some(generic_param(depth, index));
+        }
+        if self.next_if(b'z') {
+            return Some(generic_param(0, 0));
+        }
+        Some(generic_param(0, self.index()?.checked_add(1)?))
+    }
+
+    fn generic_signature(&mut self, has_param_counts: bool) -> Option<NodeRef> {
+        let mut children = Vec::new();
+        if has_param_counts {
+            while !self.next_if(b'l') {
+                let count = if self.next_if(b'z') {
+                    0
+                } else {
+                    self.index()?.checked_add(1)?
+                };
+                children.push(number(Kind::DependentGenericParamCount, count));
+            }
+        } else {
+            children.push(number(Kind::DependentGenericParamCount, 1));
+        }
+        let mut requirements = Vec::new();
+        while let Some(requirement) = self.pop_if(is_requirement) {
+            requirements.push(requirement);
+        }
+        requirements.reverse();
+        children.append(&mut requirements);
+        Some(parent(Kind::DependentGenericSignature, children))
+    }
+
+    fn generic_requirement(&mut self) -> Option<NodeRef> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Constraint {
+            Protocol,
+            BaseClass,
+            SameType,
+            Layout,
+        }
+        #[derive(Clone, Copy, PartialEq)]
+        enum Subject {
+            Generic,
+            Assoc,
+            Substitution,
+        }
+        let (constraint, subject) = match self.next() {
+            b'c' => (Constraint::BaseClass, Subject::Assoc),
+            b'b' => (Constraint::BaseClass, Subject::Generic),
+            b'B' => (Constraint::BaseClass, Subject::Substitution),
+            b't' => (Constraint::SameType, Subject::Assoc),
+            b's' => (Constraint::SameType, Subject::Generic),
+            b'S' => (Constraint::SameType, Subject::Substitution),
+            b'm' => (Constraint::Layout, Subject::Assoc),
+            b'l' => (Constraint::Layout, Subject::Generic),
+            b'L' => (Constraint::Layout, Subject::Substitution),
+            b'p' => (Constraint::Protocol, Subject::Assoc),
+            b'Q' => (Constraint::Protocol, Subject::Substitution),
+            // Compound associated type paths, packs, values and inverses.
+            b'C' | b'T' | b'M' | b'P' | b'v' | b'V' | b'h' | b'i' | b'I' => return None,
+            _ => {
+                self.push_back();
+                (Constraint::Protocol, Subject::Generic)
+            }
+        };
+        let subject = match subject {
+            Subject::Generic => ty(self.generic_param_index()?),
+            Subject::Assoc => {
+                let base = ty(self.generic_param_index()?);
+                let member = self.member_type(base)?;
+                self.substitutions.push(member.clone());
+                member
+            }
+            Subject::Substitution => self.pop_type()?,
+        };
+        let requirement = match constraint {
+            Constraint::Protocol => parent(
+                Kind::ConformanceRequirement,
+                vec![subject, self.pop_protocol()?],
+            ),
+            Constraint::BaseClass => parent(
+                Kind::ConformanceRequirement,
+                vec![subject, self.pop_type()?],
+            ),
+            Constraint::SameType => {
+                parent(Kind::SameTypeRequirement, vec![subject, self.pop_type()?])
+            }
+            Constraint::Layout => {
+                let layout = match self.next() {
+                    b'C' => "AnyObject",
+                    b'D' => "_NativeClass",
+                    b'N' => "_NativeRefCountedObject",
+                    b'R' => "_RefCountedObject",
+                    b'T' => "_Trivial",
+                    b'U' => "_UnknownLayout",
+                    _ => return None,
+                };
+                parent(
+                    Kind::LayoutRequirement,
+                    vec![subject, text(Kind::Identifier, layout)],
+                )
+            }
+        };
+        Some(requirement)
+    }
+
+    fn generic_type(&mut self) -> Option<NodeRef> {
+        let inner = self.pop_type()?;
+        let signature = self.pop_kind(Kind::DependentGenericSignature)?;
+        Some(ty(parent(
+            Kind::DependentGenericType,
+            vec![signature, inner],
+        )))
+    }
+
+    /// `<base>.<name>`, with the name popped before the base.
+    fn member_type(&mut self, base: NodeRef) -> Option<NodeRef> {
+        let name = self.pop_assoc_type_name()?;
+        Some(ty(parent(Kind::DependentMemberType, vec![base, name])))
+    }
+
+    fn archetype(&mut self) -> Option<NodeRef> {
+        let node = match self.next() {
+            b'y' => {
+                let base = ty(self.generic_param_index()?);
+                self.member_type(base)?
+            }
+            b'z' => self.member_type(ty(generic_param(0, 0)))?,
+            b'x' => {
+                let name = self.pop_assoc_type_name()?;
+                let base = self.pop_type()?;
+                ty(parent(Kind::DependentMemberType, vec![base, name]))
+            }
+            b'r' => return Some(ty(leaf(Kind::OpaqueReturnType))),
+            b'R' => {
+                self.index()?;
+                return Some(ty(leaf(Kind::OpaqueReturnType)));
+            }
+            _ => return None,
+        };
+        self.substitutions.push(node.clone());
+        Some(node)
+    }
+
+    fn local_identifier(&mut self) -> Option<NodeRef> {
+        if self.next_if(b'L') {
+            let discriminator = self.pop_kind(Kind::Identifier)?;
+            let name = self.pop_if(is_decl_name)?;
+            return Some(parent(Kind::PrivateDeclName, vec![discriminator, name]));
+        }
+        if self.next_if(b'l') {
+            let discriminator = self.pop_kind(Kind::Identifier)?;
+            return Some(parent(Kind::PrivateDeclName, vec![discriminator]));
+        }
+        if matches!(self.peek(), b'a'..=b'j' | b'A'..=b'J') {
+            let relation = self.next() as char;
+            let name = self.stack.pop()?;
+            return Some(make(
+                Kind::RelatedEntityDeclName,
+                &relation.to_string(),
+                0,
+                vec![name],
+            ));
+        }
+        let discriminator = number(Kind::Number, self.index()?);
+        let name = self.pop_if(is_decl_name)?;
+        Some(parent(Kind::LocalDeclName, vec![discriminator, name]))
+    }
+
+    fn operator_identifier(&mut self) -> Option<NodeRef> {
+        const TABLE: &[u8; 26] = b"& @/= >    <*!|+?%-~   ^ .";
+        let name = self.pop_kind(Kind::Identifier)?;
+        let mut op = String::new();
+        for c in name.text.chars() {
+            if !c.is_ascii() {
+                op.push(c);
+                continue;
+            }
+            if !c.is_ascii_lowercase() {
+                return None;
+            }
+            let mapped = TABLE[(c as u8 - b'a') as usize];
+            if mapped == b' ' {
+                return None;
+            }
+            op.push(mapped as char);
+        }
+        op.push_str(match self.next() {
+            b'i' => " infix",
+            b'p' => " prefix",
+            b'P' => " postfix",
+            _ => return None,
+        });
+        Some(text(Kind::Operator, &op))
+    }
+
+    fn plain_function(&mut self) -> Option<NodeRef> {
+        let signature = self.pop_kind(Kind::DependentGenericSignature);
+        let mut function_type = self.function_type(Kind::FunctionType)?;
+        let labels = self.pop_labels(&function_type)?;
+        if let Some(signature) = signature {
+            function_type = ty(parent(
+                Kind::DependentGenericType,
+                vec![signature, function_type],
+            ));
+        }
+        let name = self.pop_if(is_decl_name)?;
+        let context = self.pop_context()?;
+        let mut children = vec![context, name];
+        children.extend(labels);
+        children.push(function_type);
+        Some(parent(Kind::Function, children))
+    }
+
+    fn variable(&mut self) -> Option<NodeRef> {
+        let variable_type = self.pop_type()?;
+        let labels = self.pop_labels(&variable_type)?;
+        let name = self.pop_if(is_decl_name)?;
+        let context = self.pop_context()?;
+        let mut children = vec![context, name];
+        children.extend(labels);
+        children.push(variable_type);
+        self.accessor(parent(Kind::Variable, children))
+    }
+
+    fn subscript(&mut self) -> Option<NodeRef> {
+        let private_name = self.pop_kind(Kind::PrivateDeclName);
+        let subscript_type = self.pop_type()?;
+        let labels = self.pop_labels(&subscript_type)?;
+        let context = self.pop_context()?;
+        let mut children = vec![context];
+        children.extend(labels);
+        children.push(subscript_type);
+        children.extend(private_name);
+        self.accessor(parent(Kind::Subscript, children))
+    }
+
+    fn accessor(&mut self, storage: NodeRef) -> Option<NodeRef> {
+        let name = match self.next() {
+            // The variable or subscript itself.
+            b'p' => return Some(storage),
+            b'g' | b'G' => "getter",
+            b's' => "setter",
+            b'm' => "materializeForSet",
+            b'w' => "willset",
+            b'W' => "didset",
+            b'r' => "read",
+            b'M' => "modify",
+            b'i' => "init",
+            b'x' => "modify2",
+            b'y' => "read2",
+            b'a' => match self.next() {
+                b'O' => "owningMutableAddressor",
+                b'o' => "nativeOwningMutableAddressor",
+                b'p' => "nativePinningMutableAddressor",
+                b'u' => "unsafeMutableAddressor",
+                _ => return None,
+            },
+            b'l' => match self.next() {
+                b'O' => "owningAddressor",
+                b'o' => "nativeOwningAddressor",
+                b'p' => "nativePinningAddressor",
+                b'u' => "unsafeAddressor",
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(described(Kind::Accessor, name, vec![storage]))
+    }
+
+    fn function_entity(&mut self) -> Option<NodeRef> {
+        let kind = match self.next() {
+            b'D' => Kind::Deallocator,
+            b'd' => Kind::Destructor,
+            b'E' => Kind::IVarDestroyer,
+            b'e' => Kind::IVarInitializer,
+            b'i' => Kind::Initializer,
+            b'C' => Kind::Allocator,
+            b'c' => Kind::Constructor,
+            b'U' => Kind::ExplicitClosure,
+            b'u' => Kind::ImplicitClosure,
+            b'A' => Kind::DefaultArgumentInitializer,
+            _ => return None,
+        };
+        let mut children = Vec::new();
+        match kind {
+            Kind::Allocator | Kind::Constructor => {
+                let private_name = self.pop_kind(Kind::PrivateDeclName);
+                let function_type = self.pop_type()?;
+                children.extend(self.pop_labels(&function_type)?);
+                children.push(function_type);
+                children.extend(private_name);
+            }
+            Kind::ExplicitClosure | Kind::ImplicitClosure => {
+                children.push(number(Kind::Number, self.index()?));
+                children.push(self.pop_type()?);
+            }
+            Kind::DefaultArgumentInitializer => {
+                children.push(number(Kind::Number, self.index()?));
+            }
+            _ => {}
+        }
+        children.insert(0, self.pop_context()?);
+        Some(parent(kind, children))
+    }
+
+    fn metatype(&mut self) -> Option<NodeRef> {
+        let description = match self.next() {
+            b'a' => "type metadata accessor for ",
+            b'B' => "reflection metadata builtin descriptor ",
+            b'D' => "demangling cache variable for type metadata for ",
+            b'f' => "full type metadata for ",
+            b'F' => "reflection metadata field descriptor ",
+            b'i' => "type metadata instantiation function for ",
+            b'I' => "type metadata instantiation cache for ",
+            b'l' => "type metadata singleton initialization cache for ",
+            b'L' => "lazy cache variable for type metadata for ",
+            b'm' => "metaclass for ",
+            b'n' => "nominal type descriptor for ",
+            b'o' => "class metadata base offset for ",
+            b'P' => "generic type metadata pattern for ",
+            b'r' => "type metadata completion function for ",
+            b's' => "ObjC resilient class stub for ",
+            b't' => "full ObjC resilient class stub for ",
+            b'u' => "method lookup function for ",
+            b'U' => "ObjC metadata update function for ",
+            b'A' => {
+                return Some(described(
+                    Kind::Described,
+                    "reflection metadata associated type descriptor ",
+                    vec![self.pop_conformance()?],
+                ));
+            }
+            b'c' => {
+                return Some(described(
+                    Kind::Described,
+                    "protocol conformance descriptor for ",
+                    vec![self.pop_conformance()?],
+                ));
+            }
+            b'p' => {
+                return Some(described(
+                    Kind::Described,
+                    "protocol descriptor for ",
+                    vec![self.pop_protocol()?],
+                ));
+            }
+            b'V' => {
+                return Some(described(
+                    Kind::Described,
+                    "property descriptor for ",
+                    vec![self.pop_if(is_entity)?],
+                ));
+            }
+            b'X' => {
+                let (description, context) = match self.next() {
+                    b'E' => ("extension descriptor ", self.pop_context()?),
+                    b'M' => ("module descriptor ", self.pop_module()?),
+                    b'X' => ("anonymous descriptor ", self.pop_context()?),
+                    _ => return None,
+                };
+                return Some(described(Kind::Described, description, vec![context]));
+            }
+            _ => return None,
+        };
+        Some(described(
+            Kind::Described,
+            description,
+            vec![self.pop_type()?],
+        ))
+    }
+
+    fn witness(&mut self) -> Option<NodeRef> {
+        let description = match self.next() {
+            b'P' => "protocol witness table for ",
+            b'p' => "protocol witness table pattern for ",
+            b'G' => "generic protocol witness table for ",
+            b'I' => "instantiation function for generic protocol witness table for ",
+            b'r' => "resilient protocol witness table for ",
+            b'a' => "protocol witness table accessor for ",
+            b'V' => {
+                return Some(described(
+                    Kind::Described,
+                    "value witness table for ",
+                    vec![self.pop_type()?],
+                ));
+            }
+            b'v' => {
+                let description = match self.next() {
+                    b'd' => "direct field offset for ",
+                    b'i' => "indirect field offset for ",
+                    _ => return None,
+                };
+                return Some(described(
+                    Kind::Described,
+                    description,
+                    vec![self.pop_if(is_entity)?],
+                ));
+            }
+            b'C' => {
+                return Some(described(
+                    Kind::Described,
+                    "enum case for ",
+                    vec![self.pop_if(is_entity)?],
+                ));
+            }
+            b'S' => {
+                return Some(described(
+                    Kind::Described,
+                    "protocol self-conformance witness table for ",
+                    vec![self.pop_protocol()?],
+                ));
+            }
+            c @ (b'l' | b'L') => {
+                let description = if c == b'l' {
+                    "lazy protocol witness table accessor for type {0} and conformance {1}"
+                } else {
+                    "lazy protocol witness table cache variable for type {0} and conformance {1}"
+                };
+                let conformance = self.pop_conformance()?;
+                let conforming = self.pop_type()?;
+                return Some(described(
+                    Kind::Described,
+                    description,
+                    vec![conforming, conformance],
+                ));
+            }
+            b't' => {
+                let name = self.pop_if(is_decl_name)?;
+                let conformance = self.pop_conformance()?;
+                return Some(described(
+                    Kind::Described,
+                    "associated type metadata accessor for {1} in {0}",
+                    vec![conformance, name],
+                ));
+            }
+            b'b' => {
+                let protocol = self.pop_type()?;
+                let conformance = self.pop_conformance()?;
+                return Some(described(
+                    Kind::Described,
+                    "base witness table accessor for {1} in {0}",
+                    vec![conformance, protocol],
+                ));
+            }
+            _ => return None,
+        };
+        Some(described(
+            Kind::Described,
+            description,
+            vec![self.pop_conformance()?],
+        ))
+    }
+
+    fn thunk(&mut self) -> Option<NodeRef> {
+        let attribute = match self.next() {
+            b'A' => return Some(text(Kind::PartialApply, "partial apply forwarder")),
+            b'a' => return Some(text(Kind::PartialApply, "partial apply ObjC forwarder")),
+            b'O' => "@nonobjc ",
+            b'o' => "@objc ",
+            b'D' => "dynamic ",
+            b'd' => "super ",
+            b'm' => "merged ",
+            b'u' => "async function pointer to ",
+            c @ (b'Q' | b'Y') => {
+                let description = if c == b'Q' {
+                    "({0}) await resume partial function for "
+                } else {
+                    "({0}) suspend resume partial function for "
+                };
+                return Some(described(
+                    Kind::Attribute,
+                    description,
+                    vec![number(Kind::Number, self.index()?)],
+                ));
+            }
+            c @ (b'j' | b'q' | b'c') => {
+                let description = match c {
+                    b'j' => "dispatch thunk of ",
+                    b'q' => "method descriptor for ",
+                    _ => "curry thunk of ",
+                };
+                return Some(described(
+                    Kind::Described,
+                    description,
+                    vec![self.pop_if(is_entity)?],
+                ));
+            }
+            b'L' => {
+                return Some(described(
+                    Kind::Described,
+                    "protocol requirements base descriptor for ",
+                    vec![self.pop_protocol()?],
+                ));
+            }
+            b'l' => {
+                return Some(described(
+                    Kind::Described,
+                    "associated type descriptor for ",
+                    vec![self.pop_assoc_type_name()?],
+                ));
+            }
+            b'W' => {
+                let entity = self.pop_if(is_entity)?;
+                let conformance = self.pop_conformance()?;
+                return Some(described(
+                    Kind::Described,
+                    "protocol witness for {1} in conformance {0}",
+                    vec![conformance, entity],
+                ));
+            }
+            b'g' => return self.generic_specialization("generic specialization"),
+            b'i' => return self.generic_specialization("inlined generic function"),
+            _ => return None,
+        };
+        Some(text(Kind::Attribute, attribute))
+    }
+
+    /// `<type list> Tg [m] [q] [a] <pass>`.
+    fn generic_specialization(&mut self, description: &str) -> Option<NodeRef> {
+        self.next_if(b'm');
+        let serialized = self.next_if(b'q');
+        self.next_if(b'a');
+        if !self.next().is_ascii_digit() {
+            return None;
+        }
+        let mut children = Vec::new();
+        if serialized {
+            children.push(text(Kind::Identifier, "serialized"));
+        }
+        children.extend(self.type_list()?);
+        Some(described(Kind::Specialization, description, children))
+    }
+}
+
+/// Apply generic arguments to `nominal` and, from `lists[index + 1]` on, to its parents.
+fn bind_generic_args(nominal: NodeRef, lists: &[NodeRef], index: usize) -> Option<NodeRef> {
+    let args = lists.get(index)?;
+    let consumes = consumes_generic_args(nominal.kind);
+    let next = if consumes { index + 1 } else { index };
+    let mut nominal = nominal;
+    if next < lists.len() {
+        let context = nominal.children.first()?.clone();
+        let bound_parent = if context.kind == Kind::Extension {
+            let extended = context.children.get(1)?.clone();
+            let mut children = vec![
+                context.children.first()?.clone(),
+                bind_generic_args(extended, lists, next)?,
+            ];
+            children.extend(context.children.get(2).cloned());
+            parent(Kind::Extension, children)
+        } else {
+            bind_generic_args(context, lists, next)?
+        };
+        let mut children = vec![bound_parent];
+        children.extend(nominal.children[1..].iter().cloned());
+        nominal = make(nominal.kind, &nominal.text, nominal.index, children);
+    }
+    if !consumes || args.children.is_empty() {
+        return Some(nominal);
+    }
+    let kind = match nominal.kind {
+        Kind::Class => Kind::BoundGenericClass,
+        Kind::Structure => Kind::BoundGenericStructure,
+        Kind::Enum => Kind::BoundGenericEnum,
+        Kind::TypeAlias => Kind::BoundGenericTypeAlias,
+        _ => return None,
+    };
+    Some(parent(kind, vec![ty(nominal), args.clone()]))
+}
+
+fn swift_type(kind: Kind, name: &str) -> NodeRef {
+    ty(parent(
+        kind,
+        vec![text(Kind::Module, "Swift"), text(Kind::Identifier, name)],
+    ))
+}
+
+/// Standard library types with one-letter substitutions after `S`.
+fn standard_type(c: u8) -> Option<(Kind, &'static str)> {
+    let structure = |name| Some((Kind::Structure, name));
+    let protocol = |name| Some((Kind::Protocol, name));
+    match c {
+        b'A' => structure("AutoreleasingUnsafeMutablePointer"),
+        b'a' => structure("Array"),
+        b'b' => structure("Bool"),
+        b'D' => structure("Dictionary"),
+        b'd' => structure("Double"),
+        b'f' => structure("Float"),
+        b'h' => structure("Set"),
+        b'I' => structure("DefaultIndices"),
+        b'i' => structure("Int"),
+        b'J' => structure("Character"),
+        b'N' => structure("ClosedRange"),
+        b'n' => structure("Range"),
+        b'O' => structure("ObjectIdentifier"),
+        b'P' => structure("UnsafePointer"),
+        b'p' => structure("UnsafeMutablePointer"),
+        b'R' => structure("UnsafeBufferPointer"),
+        b'r' => structure("UnsafeMutableBufferPointer"),
+        b'S' => structure("String"),
+        b's' => structure("Substring"),
+        b'u' => structure("UInt"),
+        b'V' => structure("UnsafeRawPointer"),
+        b'v' => structure("UnsafeMutableRawPointer"),
+        b'W' => structure("UnsafeRawBufferPointer"),
+        b'w' => structure("UnsafeMutableRawBufferPointer"),
+        b'q' => Some((Kind::Enum, "Optional")),
+        b'B' => protocol("BinaryFloatingPoint"),
+        b'E' => protocol("Encodable"),
+        b'e' => protocol("Decodable"),
+        b'F' => protocol("FloatingPoint"),
+        b'G' => protocol("RandomNumberGenerator"),
+        b'H' => protocol("Hashable"),
+        b'j' => protocol("Numeric"),
+        b'K' => protocol("BidirectionalCollection"),
+        b'k' => protocol("RandomAccessCollection"),
+        b'L' => protocol("Comparable"),
+        b'l' => protocol("Collection"),
+        b'M' => protocol("MutableCollection"),
+        b'm' => protocol("RangeReplaceableCollection"),
+        b'Q' => protocol("Equatable"),
+        b'T' => protocol("Sequence"),
+        b't' => protocol("IteratorProtocol"),
+        b'U' => protocol("UnsignedInteger"),
+        b'X' => protocol("RangeExpression"),
+        b'x' => protocol("Strideable"),
+        b'Y' => protocol("RawRepresentable"),
+        b'y' => protocol("StringProtocol"),
+        b'Z' => protocol("SignedInteger"),
+        b'z' => protocol("BinaryInteger"),
+        _ => None,
+    }
+}
+
+/// Concurrency types with substitutions after `Sc`.
+fn concurrency_type(c: u8) -> Option<(Kind, &'static str)> {
+    let structure = |name| Some((Kind::Structure, name));
+    let protocol = |name| Some((Kind::Protocol, name));
+    match c {
+        b'A' => protocol("Actor"),
+        b'C' => structure("CheckedContinuation"),
+        b'c' => structure("UnsafeContinuation"),
+        b'E' => structure("CancellationError"),
+        b'e' => structure("UnownedSerialExecutor"),
+        b'F' => protocol("Executor"),
+        b'f' => protocol("SerialExecutor"),
+        b'G' => structure("TaskGroup"),
+        b'g' => structure("ThrowingTaskGroup"),
+        b'h' => protocol("TaskExecutor"),
+        b'I' => protocol("AsyncIteratorProtocol"),
+        b'i' => protocol("AsyncSequence"),
+        b'J' => structure("UnownedJob"),
+        b'M' => Some((Kind::Class, "MainActor")),
+        b'P' => structure("TaskPriority"),
+        b'S' => structure("AsyncStream"),
+        b's' => structure("AsyncThrowingStream"),
+        b'T' => structure("Task"),
+        b't' => structure("UnsafeCurrentTask"),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TypePrinting {
+    NoType,
+    WithColon,
+    FunctionStyle,
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn children(&mut self, node: &Node, separator: &str) -> Option<()> {
+        for (i, child) in node.children.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(separator);
+            }
+            self.print(child, false)?;
+        }
+        Some(())
+    }
+
+    fn child_of(node: &Node, kind: Kind) -> Option<&NodeRef> {
+        node.children.iter().find(|c| c.kind == kind)
+    }
+
+    /// Print `node`; `as_prefix` prints a context in front of a name. Returns a context that
+    /// could not be printed as a prefix, to print after the name.
+    fn print(&mut self, node: &NodeRef, as_prefix: bool) -> Option<Option<NodeRef>> {
+        if self.depth > MAX_DEPTH || self.out.len() > MAX_OUTPUT {
+            return None;
+        }
+        self.depth += 1;
+        let result = self.print_node(node, as_prefix);
+        self.depth -= 1;
+        result
+    }
+
+    fn print_node(&mut self, node: &NodeRef, as_prefix: bool) -> Option<Option<NodeRef>> {
+        use TypePrinting::*;
+        match node.kind {
+            Kind::Global => self.children(node, "")?,
+            Kind::Suffix => {
+                self.out.push_str(" with unmangled suffix \"");
+                self.out.push_str(&node.text);
+                self.out.push('"');
+            }
+            Kind::Module | Kind::Identifier | Kind::BuiltinTypeName | Kind::TupleElementName => {
+                self.out.push_str(&node.text)
+            }
+            Kind::DependentGenericParamType | Kind::Operator => self.out.push_str(&node.text),
+            Kind::DependentAssociatedTypeRef => {
+                if let Some(protocol) = node.children.first() {
+                    self.print(protocol, false)?;
+                    self.out.push('.');
+                }
+                self.out.push_str(&node.text);
+            }
+            Kind::RelatedEntityDeclName => {
+                self.out.push_str("related decl '");
+                self.out.push_str(&node.text);
+                self.out.push_str("' for ");
+                self.print(node.children.first()?, false)?;
+            }
+            Kind::Number => self.out.push_str(&node.index.to_string()),
+            Kind::Type | Kind::TypeMangling => {
+                return self.print(node.children.first()?, as_prefix);
+            }
+            Kind::Class | Kind::Structure | Kind::Enum | Kind::Protocol | Kind::TypeAlias => {
+                return self.entity(node, as_prefix, NoType, true, "", None, None);
+            }
+            Kind::Function => {
+                return self.entity(node, as_prefix, FunctionStyle, true, "", None, None);
+            }
+            Kind::Variable => {
+                return self.entity(node, as_prefix, WithColon, true, "", None, None);
+            }
+            Kind::Subscript => {
+                return self.entity(
+                    node,
+                    as_prefix,
+                    FunctionStyle,
+                    false,
+                    "",
+                    None,
+                    Some("subscript"),
+                );
+            }
+            Kind::Accessor => {
+                let storage = node.children.first()?;
+                return match storage.kind {
+                    Kind::Variable => {
+                        self.entity(storage, as_prefix, WithColon, true, &node.text, None, None)
+                    }
+                    Kind::Subscript => self.entity(
+                        storage,
+                        as_prefix,
+                        WithColon,
+                        false,
+                        &node.text,
+                        None,
+                        Some("subscript"),
+                    ),
+                    _ => None,
+                };
+            }
+            Kind::Static => {
+                self.out.push_str("static ");
+                self.print(node.children.first()?, false)?;
+            }
+            Kind::Allocator => {
+                // Only classes separate allocation from initialization.
+                let name = match node.children.first() {
+                    Some(context) if context.kind == Kind::Class => "__allocating_init",
+                    _ => "init",
+                };
+                return self.entity(node, as_prefix, FunctionStyle, false, name, None, None);
+            }
+            Kind::Constructor => {
+                return self.entity(node, as_prefix, FunctionStyle, false, "init", None, None);
+            }
+            Kind::Deallocator => {
+                return self.entity(
+                    node,
+                    as_prefix,
+                    NoType,
+                    false,
+                    "__deallocating_deinit",
+                    None,
+                    None,
+                );
+            }
+            Kind::Destructor => {
+                return self.entity(node, as_prefix, NoType, false, "deinit", None, None);
+            }
+            Kind::IVarInitializer => {
+                return self.entity(
+                    node,
+                    as_prefix,
+                    NoType,
+                    false,
+                    "__ivar_initializer",
+                    None,
+                    None,
+                );
+            }
+            Kind::IVarDestroyer => {
+                return self.entity(
+                    node,
+                    as_prefix,
+                    NoType,
+                    false,
+                    "__ivar_destroyer",
+                    None,
+                    None,
+                );
+            }
+            Kind::Initializer => {
+                return self.entity(
+                    node,
+                    as_prefix,
+                    NoType,
+                    false,
+                    "variable initialization expression",
+                    None,
+                    None,
+                );
+            }
+            Kind::ExplicitClosure | Kind::ImplicitClosure => {
+                let name = if node.kind == Kind::ExplicitClosure {
+                    "closure #"
+                } else {
+                    "implicit closure #"
+                };
+                let index = node.children.get(1)?.index.saturating_add(1);
+                return self.entity(
+                    node,
+                    as_prefix,
+                    FunctionStyle,
+                    false,
+                    name,
+                    Some(index),
+                    None,
+                );
+            }
+            Kind::DefaultArgumentInitializer => {
+                let index = node.children.get(1)?.index;
+                return self.entity(
+                    node,
+                    as_prefix,
+                    NoType,
+                    false,
+                    "default argument ",
+                    Some(index),
+                    None,
+                );
+            }
+            Kind::Extension => {
+                self.out.push_str("(extension in ");
+                self.print(node.children.first()?, true)?;
+                self.out.push_str("):");
+                self.print(node.children.get(1)?, false)?;
+                if let Some(signature) = node.children.get(2) {
+                    self.print(signature, false)?;
+                }
+            }
+            Kind::PrivateDeclName => {
+                let name = node.children.get(1)?;
+                self.out.push('(');
+                self.print(name, false)?;
+                self.out.push_str(" in ");
+                self.out.push_str(&node.children[0].text);
+                self.out.push(')');
+            }
+            Kind::LocalDeclName => {
+                self.print(node.children.get(1)?, false)?;
+                self.out.push_str(" #");
+                self.out
+                    .push_str(&(node.children[0].index.saturating_add(1)).to_string());
+            }
+            Kind::BoundGenericClass
+            | Kind::BoundGenericStructure
+            | Kind::BoundGenericEnum
+            | Kind::BoundGenericTypeAlias => self.bound_generic(node)?,
+            Kind::TypeList => self.children(node, ", ")?,
+            Kind::Tuple => {
+                self.out.push('(');
+                self.children(node, ", ")?;
+                self.out.push(')');
+            }
+            Kind::TupleElement => {
+                if let Some(label) = Self::child_of(node, Kind::TupleElementName) {
+                    self.out.push_str(&label.text);
+                    self.out.push_str(": ");
+                }
+                self.print(Self::child_of(node, Kind::Type)?, false)?;
+                if Self::child_of(node, Kind::VariadicMarker).is_some() {
+                    self.out.push_str("...");
+                }
+            }
+            Kind::FunctionType
+            | Kind::NoEscapeFunctionType
+            | Kind::ThinFunctionType
+            | Kind::CFunctionPointer
+            | Kind::ObjCBlock
+            | Kind::AutoClosureType => self.function_type(None, node)?,
+            Kind::ReturnType => {
+                self.out.push_str(" -> ");
+                self.children(node, "")?;
+            }
+            Kind::ThrowsAnnotation => self.out.push_str(" throws"),
+            Kind::TypedThrowsAnnotation => {
+                self.out.push_str(" throws(");
+                self.children(node, "")?;
+                self.out.push(')');
+            }
+            Kind::GlobalActorFunctionType => {
+                self.out.push('@');
+                self.children(node, "")?;
+                self.out.push(' ');
+            }
+            Kind::IsolatedAnyFunctionType => self.out.push_str("@isolated(any) "),
+            Kind::DependentGenericSignature => self.generic_signature(node)?,
+            Kind::ConformanceRequirement | Kind::LayoutRequirement => {
+                self.print(node.children.first()?, false)?;
+                self.out.push_str(": ");
+                self.print(node.children.get(1)?, false)?;
+            }
+            Kind::SameTypeRequirement => {
+                self.print(node.children.first()?, false)?;
+                self.out.push_str(" == ");
+                self.print(node.children.get(1)?, false)?;
+            }
+            Kind::DependentGenericType => {
+                let signature = node.children.first()?;
+                let inner = node.children.get(1)?;
+                self.print(signature, false)?;
+                if need_space_before_type(inner) {
+                    self.out.push(' ');
+                }
+                self.print(inner, false)?;
+            }
+            Kind::DependentMemberType => {
+                self.print(node.children.first()?, false)?;
+                self.out.push('.');
+                self.print(node.children.get(1)?, false)?;
+            }
+            Kind::Metatype => {
+                let inner = node.children.first()?;
+                self.with_parens(inner)?;
+                if is_existential(inner) {
+                    self.out.push_str(".Protocol");
+                } else {
+                    self.out.push_str(".Type");
+                }
+            }
+            Kind::ExistentialMetatype => {
+                self.print(node.children.first()?, false)?;
+                self.out.push_str(".Type");
+            }
+            Kind::ProtocolList => {
+                let protocols = node.children.first()?;
+                if protocols.children.is_empty() {
+                    self.out.push_str("Any");
+                } else {
+                    self.children(protocols, " & ")?;
+                }
+            }
+            Kind::ProtocolListWithAnyObject => {
+                let protocols = node.children.first()?.children.first()?;
+                if !protocols.children.is_empty() {
+                    self.children(protocols, " & ")?;
+                    self.out.push_str(" & ");
+                }
+                self.out.push_str("Swift.AnyObject");
+            }
+            Kind::ProtocolListWithClass => {
+                self.print(node.children.get(1)?, false)?;
+                self.out.push_str(" & ");
+                let protocols = node.children.first()?.children.first()?;
+                self.children(protocols, " & ")?;
+            }
+            Kind::Modifier => {
+                self.out.push_str(&node.text);
+                self.print(node.children.first()?, false)?;
+            }
+            Kind::DynamicSelf => self.out.push_str("Self"),
+            Kind::OpaqueReturnType => self.out.push_str("some"),
+            Kind::ProtocolConformance => {
+                self.print(node.children.first()?, false)?;
+                self.out.push_str(" : ");
+                self.print(node.children.get(1)?, false)?;
+                self.out.push_str(" in ");
+                self.print(node.children.get(2)?, false)?;
+            }
+            Kind::Described | Kind::Attribute => self.described(node)?,
+            Kind::PartialApply => {
+                self.out.push_str(&node.text);
+                if !node.children.is_empty() {
+                    self.out.push_str(" for ");
+                    self.children(node, "")?;
+                }
+            }
+            Kind::Specialization => {
+                self.out.push_str(&node.text);
+                self.out.push_str(" <");
+                self.children(node, ", ")?;
+                self.out.push_str("> of ");
+            }
+            Kind::ArgumentTuple
+            | Kind::LabelList
+            | Kind::EmptyList
+            | Kind::FirstElementMarker
+            | Kind::VariadicMarker
+            | Kind::AsyncAnnotation
+            | Kind::ConcurrentFunctionType
+            | Kind::SendingResultFunctionType
+            | Kind::DependentGenericParamCount => {}
+        }
+        Some(None)
+    }
+
+    /// Print a `Described` node, substituting `{N}` with the Nth child and appending the
+    /// children not referenced by the text.
+    fn described(&mut self, node: &Node) -> Option<()> {
+        let mut used = false;
+        let mut rest = node.text.as_str();
+        while let Some(open) = rest.find('{') {
+            self.out.push_str(&rest[..open]);
+            let close = rest[open..].find('}')? + open;
+            let index: usize = rest[open + 1..close].parse().ok()?;
+            self.print(node.children.get(index)?, false)?;
+            used = true;
+            rest = &rest[close + 1..];
+        }
+        self.out.push_str(rest);
+        if !used {
+            self.children(node, "")?;
+        }
+        Some(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn entity(
+        &mut self,
+        entity: &NodeRef,
+        as_prefix: bool,
+        mut type_printing: TypePrinting,
+        has_name: bool,
+        extra_name: &str,
+        extra_index: Option<u64>,
+        overwrite_name: Option<&str>,
+    ) -> Option<Option<NodeRef>> {
+        // The context is printed in front ("<context>.<name>") or after ("<name> in
+        // <context>") for multi-word and local names.
+        let mut multi_word = extra_name.contains(' ');
+        if has_name && entity.children.get(1)?.kind == Kind::LocalDeclName {
+            multi_word = true;
+        }
+        if as_prefix && (type_printing != TypePrinting::NoType || multi_word) {
+            return Some(Some(entity.clone()));
+        }
+        let context = entity.children.first()?;
+        let mut postfix = None;
+        if multi_word {
+            postfix = Some(context.clone());
+        } else {
+            let length = self.out.len();
+            postfix = self.print(context, true)?.or(postfix);
+            if self.out.len() != length {
+                self.out.push('.');
+            }
+        }
+
+        let mut extra_name = extra_name;
+        let mut extra_index = extra_index;
+        if has_name || overwrite_name.is_some() {
+            if !extra_name.is_empty() && multi_word {
+                self.out.push_str(extra_name);
+                if let Some(index) = extra_index {
+                    self.out.push_str(&index.to_string());
+                }
+                self.out.push_str(" of ");
+                extra_name = "";
+                extra_index = None;
+            }
+            let length = self.out.len();
+            match overwrite_name {
+                Some(name) => self.out.push_str(name),
+                None => {
+                    self.print(entity.children.get(1)?, false)?;
+                }
+            }
+            if self.out.len() != length && !extra_name.is_empty() {
+                self.out.push('.');
+            }
+        }
+        if !extra_name.is_empty() {
+            self.out.push_str(extra_name);
+            if let Some(index) = extra_index {
+                self.out.push_str(&index.to_string());
+            }
+        }
+        if type_printing != TypePrinting::NoType {
+            let entity_type = Self::child_of(entity, Kind::Type)?
+                .children
+                .first()?
+                .clone();
+            if type_printing == TypePrinting::FunctionStyle {
+                let mut t = &entity_type;
+                while t.kind == Kind::DependentGenericType {
+                    t = t.children.get(1)?.children.first()?;
+                }
+                if !is_function_type(t.kind) {
+                    type_printing = TypePrinting::WithColon;
+                }
+            }
+            if type_printing == TypePrinting::WithColon {
+                self.out.push_str(" : ");
+            } else if multi_word || need_space_before_type(&entity_type) {
+                self.out.push(' ');
+            }
+            self.entity_type(entity, &entity_type)?;
+        }
+        if !as_prefix && let Some(context) = postfix.take() {
+            if matches!(
+                entity.kind,
+                Kind::DefaultArgumentInitializer | Kind::Initializer
+            ) {
+                self.out.push_str(" of ");
+            } else {
+                self.out.push_str(" in ");
+            }
+            self.print(&context, false)?;
+        }
+        Some(postfix)
+    }
+
+    fn entity_type(&mut self, entity: &Node, entity_type: &NodeRef) -> Option<()> {
+        let Some(labels) = Self::child_of(entity, Kind::LabelList) else {
+            self.print(entity_type, false)?;
+            return Some(());
+        };
+        let mut function = entity_type;
+        if entity_type.kind == Kind::DependentGenericType {
+            self.print(entity_type.children.first()?, false)?;
+            let inner = entity_type.children.get(1)?;
+            if need_space_before_type(inner) {
+                self.out.push(' ');
+            }
+            function = inner.children.first()?;
+        }
+        self.function_type(Some(labels), function)
+    }
+
+    fn function_type(&mut self, labels: Option<&NodeRef>, node: &NodeRef) -> Option<()> {
+        if node.children.len() < 2 {
+            return None;
+        }
+        match node.kind {
+            Kind::AutoClosureType => self.out.push_str("@autoclosure "),
+            Kind::ThinFunctionType => self.out.push_str("@convention(thin) "),
+            Kind::CFunctionPointer => self.out.push_str("@convention(c) "),
+            Kind::ObjCBlock => self.out.push_str("@convention(block) "),
+            _ => {}
+        }
+        let mut throws = None;
+        let mut sendable = false;
+        let mut is_async = false;
+        let mut sending_result = false;
+        for child in &node.children[..node.children.len() - 2] {
+            match child.kind {
+                Kind::GlobalActorFunctionType | Kind::IsolatedAnyFunctionType => {
+                    self.print(child, false)?;
+                }
+                Kind::ThrowsAnnotation | Kind::TypedThrowsAnnotation => throws = Some(child),
+                Kind::ConcurrentFunctionType => sendable = true,
+                Kind::AsyncAnnotation => is_async = true,
+                Kind::SendingResultFunctionType => sending_result = true,
+                _ => {}
+            }
+        }
+        if sendable {
+            self.out.push_str("@Sendable ");
+        }
+        let arguments = &node.children[node.children.len() - 2];
+        self.parameters(labels, arguments)?;
+        if is_async {
+            self.out.push_str(" async");
+        }
+        if let Some(throws) = throws {
+            self.print(throws, false)?;
+        }
+        let result = node.children.last()?;
+        if sending_result {
+            self.out.push_str(" -> sending ");
+            self.children(result, "")?;
+        } else {
+            self.print(result, false)?;
+        }
+        Some(())
+    }
+
+    fn parameters(&mut self, labels: Option<&NodeRef>, arguments: &Node) -> Option<()> {
+        if arguments.kind != Kind::ArgumentTuple {
+            return None;
+        }
+        let params = arguments.children.first()?.children.first()?;
+        if params.kind != Kind::Tuple {
+            self.out.push('(');
+            self.print(params, false)?;
+            self.out.push(')');
+            return Some(());
+        }
+        let labels = labels.filter(|l| !l.children.is_empty());
+        self.out.push('(');
+        for (i, param) in params.children.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            if let Some(labels) = labels {
+                match labels.children.get(i) {
+                    Some(label) if label.kind == Kind::Identifier => self.out.push_str(&label.text),
+                    _ => self.out.push('_'),
+                }
+                self.out.push_str(": ");
+            }
+            self.print(param, false)?;
+        }
+        self.out.push(')');
+        Some(())
+    }
+
+    fn generic_signature(&mut self, node: &Node) -> Option<()> {
+        self.out.push('<');
+        let mut depth = 0;
+        while depth < node.children.len()
+            && node.children[depth].kind == Kind::DependentGenericParamCount
+        {
+            if depth != 0 {
+                self.out.push_str("><");
+            }
+            for index in 0..node.children[depth].index.min(128) {
+                if index != 0 {
+                    self.out.push_str(", ");
+                }
+                self.out.push_str(&generic_param(depth as u64, index).text);
+            }
+            depth += 1;
+        }
+        if depth != node.children.len() {
+            self.out.push_str(" where ");
+            for (i, requirement) in node.children[depth..].iter().enumerate() {
+                if i > 0 {
+                    self.out.push_str(", ");
+                }
+                self.print(requirement, false)?;
+            }
+        }
+        self.out.push('>');
+        Some(())
+    }
+
+    /// Print a bound generic type, with sugar for optionals, arrays and dictionaries.
+    fn bound_generic(&mut self, node: &Node) -> Option<()> {
+        let nominal = node.children.first()?;
+        let args = node.children.get(1)?;
+        let unbound = nominal.children.first()?;
+        let in_swift = unbound
+            .children
+            .first()
+            .is_some_and(|m| m.kind == Kind::Module && m.text == "Swift");
+        let name = unbound.children.get(1).map(|n| n.text.as_str());
+        match (in_swift, node.kind, name, args.children.len()) {
+            (true, Kind::BoundGenericEnum, Some("Optional"), 1) => {
+                self.with_parens(args.children.first()?)?;
+                self.out.push('?');
+            }
+            (true, Kind::BoundGenericStructure, Some("Array"), 1) => {
+                self.out.push('[');
+                self.print(args.children.first()?, false)?;
+                self.out.push(']');
+            }
+            (true, Kind::BoundGenericStructure, Some("Dictionary"), 2) => {
+                self.out.push('[');
+                self.print(args.children.first()?, false)?;
+                self.out.push_str(" : ");
+                self.print(args.children.get(1)?, false)?;
+                self.out.push(']');
+            }
+            _ => {
+                self.print(nominal, false)?;
+                self.out.push('<');
+                self.children(args, ", ")?;
+                self.out.push('>');
+            }
+        }
+        Some(())
+    }
+
+    fn with_parens(&mut self, node: &NodeRef) -> Option<()> {
+        let parens = !is_simple_type(node);
+        if parens {
+            self.out.push('(');
+        }
+        self.print(node, false)?;
+        if parens {
+            self.out.push(')');
+        }
+        Some(())
+    }
+}
+
+fn is_function_type(kind: Kind) -> bool {
+    matches!(
+        kind,
+        Kind::FunctionType
+            | Kind::NoEscapeFunctionType
+            | Kind::ThinFunctionType
+            | Kind::CFunctionPointer
+            | Kind::ObjCBlock
+            | Kind::AutoClosureType
+    )
+}
+
+fn need_space_before_type(node: &Node) -> bool {
+    match node.kind {
+        Kind::Type => node
+            .children
+            .first()
+            .is_none_or(|c| need_space_before_type(c)),
+        Kind::FunctionType | Kind::NoEscapeFunctionType | Kind::DependentGenericType => false,
+        _ => true,
+    }
+}
+
+fn is_simple_type(node: &Node) -> bool {
+    match node.kind {
+        Kind::Type => node.children.first().is_some_and(|c| is_simple_type(c)),
+        Kind::ProtocolList => node
+            .children
+            .first()
+            .is_some_and(|list| list.children.len() <= 1),
+        Kind::ProtocolListWithAnyObject => node
+            .children
+            .first()
+            .and_then(|list| list.children.first())
+            .is_some_and(|protocols| protocols.children.is_empty()),
+        Kind::Modifier | Kind::ProtocolListWithClass | Kind::DependentGenericType => false,
+        kind => !is_function_type(kind),
+    }
+}
+
+fn is_existential(node: &Node) -> bool {
+    match node.kind {
+        Kind::Type => node.children.first().is_some_and(|c| is_existential(c)),
+        Kind::Protocol
+        | Kind::ProtocolList
+        | Kind::ProtocolListWithAnyObject
+        | Kind::ProtocolListWithClass => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Symbols and their demangling by `swift demangle`. There is no Swift toolchain in CI, so
+    /// new entries are checked by hand against the real tool.
+    const REFERENCE: &[(&str, &str)] = &[
+        ("_$s4main3fooyySiF", "main.foo(Swift.Int) -> ()"),
+        (
+            "$sSTsE3mapySayqd__Gqd__7ElementQzKXEKlF",
+            "(extension in Swift):Swift.Sequence.map<A>((A.Element) throws -> A1) throws -> [A1]",
+        ),
+        (
+            "$s4main3bar1xSiSgSi_tF",
+            "main.bar(x: Swift.Int) -> Swift.Int?",
+        ),
+        ("$s4main1fyyYaKF", "main.f() async throws -> ()"),
+        ("$s4main1CCACycfc", "main.C.init() -> main.C"),
+        ("$s4main1AV1xSivg", "main.A.x.getter : Swift.Int"),
+        ("$s4main1AV1xSivs", "main.A.x.setter : Swift.Int"),
+        ("_$s4main3fooyySiFTO", "@nonobjc main.foo(Swift.Int) -> ()"),
+        ("$sSSN", "type metadata for Swift.String"),
+        ("$s4test3FooCMa", "type metadata accessor for test.Foo"),
+        ("$sSo6CGRectVMa", "type metadata accessor for __C.CGRect"),
+        (
+            "$sSo8NSObjectCMn",
+            "nominal type descriptor for __C.NSObject",
+        ),
+        ("$s4main1PMp", "protocol descriptor for main.P"),
+        (
+            "$s4main1AVAA1PAAMc",
+            "protocol conformance descriptor for main.A : main.P in main",
+        ),
+        ("$sSaySiGD", "[Swift.Int]"),
+        ("$sSDySSypGD", "[Swift.String : Any]"),
+        ("$sSiSgD", "Swift.Int?"),
+        // Functions, labels and initializers.
+        ("$s4main3fooyyF", "main.foo() -> ()"),
+        ("$s4main3fooSiyF", "main.foo() -> Swift.Int"),
+        ("$s4main3fooyS2iF", "main.foo(Swift.Int) -> Swift.Int"),
+        (
+            "$s4main4funcyySi_SStF",
+            "main.func(Swift.Int, Swift.String) -> ()",
+        ),
+        (
+            "$s4main3foo3bar3bazySi_SitF",
+            "main.foo(bar: Swift.Int, baz: Swift.Int) -> ()",
+        ),
+        (
+            "$s4main3foo_1yySi_SitF",
+            "main.foo(_: Swift.Int, y: Swift.Int) -> ()",
+        ),
+        (
+            "$ss5print_9separator10terminatoryypd_S2StF",
+            "Swift.print(_: Any..., separator: Swift.String, terminator: Swift.String) -> ()",
+        ),
+        ("$s4main3fooyySiz_tF", "main.foo(inout Swift.Int) -> ()"),
+        ("$s4main3fooyySayypGF", "main.foo([Any]) -> ()"),
+        ("$s4main3fooyyyycF", "main.foo(() -> ()) -> ()"),
+        ("$sSa6appendyyxnF", "Swift.Array.append(__owned A) -> ()"),
+        ("$s4main3fooyyYaF", "main.foo() async -> ()"),
+        ("$s4main3fooyyKF", "main.foo() throws -> ()"),
+        ("$s4main1CC3baryyFZ", "static main.C.bar() -> ()"),
+        ("$s4main1EO1ayA2CmF", "main.E.a(main.E.Type) -> main.E"),
+        ("$s4main1AVyACSicfC", "main.A.init(Swift.Int) -> main.A"),
+        (
+            "$sSS7cStringSSSPys4Int8VG_tcfC",
+            "Swift.String.init(cString: Swift.UnsafePointer<Swift.Int8>) -> Swift.String",
+        ),
+        (
+            "$s4main1CC1xACSi_tcfC",
+            "main.C.__allocating_init(x: Swift.Int) -> main.C",
+        ),
+        ("$s4main1CCfd", "main.C.deinit"),
+        ("$s4main1CCfD", "main.C.__deallocating_deinit"),
+        (
+            "$sSo8NSObjectC4mainE3fooyyF",
+            "(extension in main):__C.NSObject.foo() -> ()",
+        ),
+        // Accessors.
+        ("$sSS5countSivg", "Swift.String.count.getter : Swift.Int"),
+        ("$s4main1AV1xSivM", "main.A.x.modify : Swift.Int"),
+        ("$s4main1AV1xSivr", "main.A.x.read : Swift.Int"),
+        ("$s4main1AV1xSivW", "main.A.x.didset : Swift.Int"),
+        ("$s4main1CC1xSivM", "main.C.x.modify : Swift.Int"),
+        // Generics.
+        ("$s4main3fooyxxlF", "main.foo<A>(A) -> A"),
+        (
+            "$s4main3fooyxxSQRzlF",
+            "main.foo<A where A: Swift.Equatable>(A) -> A",
+        ),
+        ("$s4main1AVyxGD", "main.A<A>"),
+        ("$s4main1AVySiGD", "main.A<Swift.Int>"),
+        ("$sSay4main1AVGD", "[main.A]"),
+        ("$sSDySiSSGD", "[Swift.Int : Swift.String]"),
+        ("$sSPySiGD", "Swift.UnsafePointer<Swift.Int>"),
+        ("$sSpySiGD", "Swift.UnsafeMutablePointer<Swift.Int>"),
+        ("$sSRySiGD", "Swift.UnsafeBufferPointer<Swift.Int>"),
+        (
+            "$s4main3fooyyxlFSi_Tg5",
+            "generic specialization <Swift.Int> of main.foo<A>(A) -> ()",
+        ),
+        // Tuples and function types.
+        ("$sSi_SStD", "(Swift.Int, Swift.String)"),
+        ("$sSS_SitD", "(Swift.String, Swift.Int)"),
+        ("$sSiSScD", "(Swift.String) -> Swift.Int"),
+        ("$sS2icD", "(Swift.Int) -> Swift.Int"),
+        ("$sSVD", "Swift.UnsafeRawPointer"),
+        ("$sSvD", "Swift.UnsafeMutableRawPointer"),
+        ("$sSo8NSObjectCD", "__C.NSObject"),
+        // Closures and default arguments.
+        (
+            "$s4main3fooyyFyycfU_",
+            "closure #1 () -> () in main.foo() -> ()",
+        ),
+        (
+            "$s4main3foo1xySi_tFfA_",
+            "default argument 0 of main.foo(x: Swift.Int) -> ()",
+        ),
+        (
+            "$s4main1AV5valueSivpfi",
+            "variable initialization expression of main.A.value : Swift.Int",
+        ),
+        // Conformances and witnesses.
+        (
+            "$sSayxGSlsMc",
+            "protocol conformance descriptor for [A] : Swift.Collection in Swift",
+        ),
+        (
+            "$s4main1AVyxGAA1PAAMc",
+            "protocol conformance descriptor for main.A<A> : main.P in main",
+        ),
+        (
+            "$s4main1AV4test1PAAMc",
+            "protocol conformance descriptor for main.A : test.P in main",
+        ),
+        (
+            "$s4main1AVAA1PAAWP",
+            "protocol witness table for main.A : main.P in main",
+        ),
+        (
+            "$sSSSHsWP",
+            "protocol witness table for Swift.String : Swift.Hashable in Swift",
+        ),
+        (
+            "$s4main1AVAA1PA2aDP1fyyFTW",
+            "protocol witness for main.P.f() -> () in conformance main.A : main.P in main",
+        ),
+        (
+            "$s4main1PTL",
+            "protocol requirements base descriptor for main.P",
+        ),
+        ("$sSQMp", "protocol descriptor for Swift.Equatable"),
+        ("$sSHMp", "protocol descriptor for Swift.Hashable"),
+        ("$ss5ErrorMp", "protocol descriptor for Swift.Error"),
+        // Thunks.
+        (
+            "$s4main3fooyyFTA",
+            "partial apply forwarder for main.foo() -> ()",
+        ),
+        ("$s4main1PP1fyyFTj", "dispatch thunk of main.P.f() -> ()"),
+        (
+            "$s4main3FooC3baryyFTq",
+            "method descriptor for main.Foo.bar() -> ()",
+        ),
+        ("$s4main1CC3fooyyFTo", "@objc main.C.foo() -> ()"),
+        // Metadata.
+        ("$sSiN", "type metadata for Swift.Int"),
+        ("$sypN", "type metadata for Any"),
+        ("$sytN", "type metadata for ()"),
+        ("$ss6UInt32VN", "type metadata for Swift.UInt32"),
+        ("$sSqMa", "type metadata accessor for Swift.Optional"),
+        ("$s4main1AVMn", "nominal type descriptor for main.A"),
+        ("$s4main1AVMf", "full type metadata for main.A"),
+        ("$s4main1AVWV", "value witness table for main.A"),
+        (
+            "$s4main1AV1xSivpMV",
+            "property descriptor for main.A.x : Swift.Int",
+        ),
+        ("$s4main1CCMm", "metaclass for main.C"),
+        ("$s4main1CCMo", "class metadata base offset for main.C"),
+        ("$s4main1CCMu", "method lookup function for main.C"),
+    ];
+
+    #[test]
+    fn demangles_like_swift() {
+        for (symbol, expected) in REFERENCE {
+            assert_eq!(demangle(symbol).as_deref(), Some(*expected), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn malformed_symbols() {
+        let long = format!("$s4main{}", "3foo".repeat(MAX_SYMBOL));
+        let deep = format!("$sSi{}D", "Sg".repeat(2000));
+        for symbol in [
+            "_Z3foov",
+            "$s",
+            "$sX",
+            "$s4mai",
+            "$s99999999999999999999main",
+            "$s4main3fooyySiFTzTz",
+            &long,
+            &deep,
+        ] {
+            assert_eq!(demangle(symbol), None, "{}", symbol);
+        }
+        assert!(is_swift("$s4mai"));
+        assert!(!is_swift("_Z3foov"));
+    }
+}
@@ -0,0 +1,12 @@
+//! Parsers for cache data structures that work on raw buffers and only need `alloc`, so that
+//! they can be used without `std` (build with `--no-default-features`), e.g. by an on-device
+//! agent that reads the memory itself.
+
+pub mod bind;
+pub mod cursor;
+pub mod demangle;
+pub mod pattern;
+pub mod rebase;
+pub mod starts;
+pub mod trie;
+pub mod unwind;
@@ -0,0 +1,201 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A named byte pattern; `None` bytes match anything.
+pub struct Signature {
+    pub name: String,
+    pub pattern: Vec<Option<u8>>,
+}
+
+/// Parse a signature list. Each non-empty line is a name followed by hex bytes, with `??` (or
+/// `?`) as a wildcard byte; `#` starts a comment:
+///
+/// ```text
+/// # name            pattern
+/// _objc_msgSend     10 00 40 F9 ?? ?? ?? ?? 1F 20 03 D5
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Signature>, String> {
+    let mut signatures = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next() else {
+            continue;
+        };
+        let pattern = parse_pattern(&fields.collect::<Vec<_>>().join(" "))
+            .map_err(|e| format!("Line {}: {} for {}", lineno + 1, e, name))?;
+        signatures.push(Signature {
+            name: name.to_string(),
+            pattern,
+        });
+    }
+    Ok(signatures)
+}
+
+/// Parse a pattern of whitespace-separated hex bytes, with `??` (or `?`) as a wildcard byte.
+pub fn parse_pattern(text: &str) -> Result<Vec<Option<u8>>, String> {
+    let pattern = text
+        .split_whitespace()
+        .map(|byte| match byte {
+            "?" | "??" => Ok(None),
+            _ => u8::from_str_radix(byte, 16)
+                .map(Some)
+                .map_err(|_| format!("invalid pattern byte '{}'", byte)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if pattern.iter().all(Option::is_none) {
+        return Err("pattern has no fixed bytes".to_string());
+    }
+    Ok(pattern)
+}
+
+/// Scans data for many signatures at once. Signatures are bucketed by their first byte, so
+/// that each position only tries the patterns that can start there.
+pub struct Scanner<'s> {
+    signatures: &'s [Signature],
+    by_first: Vec<Vec<usize>>,
+    wildcard_first: Vec<usize>,
+}
+
+impl<'s> Scanner<'s> {
+    pub fn new(signatures: &'s [Signature]) -> Self {
+        let mut by_first = vec![Vec::new(); 256];
+        let mut wildcard_first = Vec::new();
+        for (i, signature) in signatures.iter().enumerate() {
+            match signature.pattern.first() {
+                Some(Some(byte)) => by_first[*byte as usize].push(i),
+                _ => wildcard_first.push(i),
+            }
+        }
+        Scanner {
+            signatures,
+            by_first,
+            wildcard_first,
+        }
+    }
+
+    /// Return (signature index, offset) for every match at an offset that is a multiple of
+    /// `align`.
+    pub fn scan(&self, data: &[u8], align: usize) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        for offset in (0..data.len()).step_by(align) {
+            let candidates = self.by_first[data[offset] as usize]
+                .iter()
+                .chain(&self.wildcard_first);
+            for &i in candidates {
+                let pattern = &self.signatures[i].pattern;
+                let Some(window) = data.get(offset..offset + pattern.len()) else {
+                    continue;
+                };
+                if pattern
+                    .iter()
+                    .zip(window)
+                    .all(|(p, b)| p.is_none_or(|p| p == *b))
+                {
+                    matches.push((i, offset));
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Offsets of every match of a single pattern in `data` at a multiple of `align`. The data is
+/// searched for one of the pattern's fixed bytes, preferring one that is uncommon in code, and
+/// the rest of the pattern is only compared where it occurs.
+pub fn find_pattern(data: &[u8], pattern: &[Option<u8>], align: usize) -> Vec<usize> {
+    let mut matches = Vec::new();
+    // 0x00 and 0xFF fill padding and immediates, so they make poor anchors.
+    let Some((anchor, byte)) = pattern
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| b.map(|b| (i, b)))
+        .min_by_key(|&(_, b)| matches!(b, 0x00 | 0xFF))
+    else {
+        return matches;
+    };
+    let Some(last) = data.len().checked_sub(pattern.len()) else {
+        return matches;
+    };
+    let align = align.max(1);
+    let mut pos = anchor;
+    while pos <= last + anchor {
+        let Some(skip) = data[pos..=last + anchor].iter().position(|&b| b == byte) else {
+            break;
+        };
+        let offset = pos + skip - anchor;
+        if offset % align == 0
+            && pattern
+                .iter()
+                .zip(&data[offset..offset + pattern.len()])
+                .all(|(p, b)| p.is_none_or(|p| p == *b))
+        {
+            matches.push(offset);
+        }
+        pos += skip + 1;
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signatures() {
+        let text = "# name pattern\n\n_objc_msgSend  10 00 40 F9 ?? ? 1f  # trailing\n_b 00\n";
+        let signatures = parse(text).unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].name, "_objc_msgSend");
+        assert_eq!(
+            signatures[0].pattern,
+            [
+                Some(0x10),
+                Some(0x00),
+                Some(0x40),
+                Some(0xF9),
+                None,
+                None,
+                Some(0x1F)
+            ]
+        );
+        assert_eq!(signatures[1].pattern, [Some(0x00)]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            parse("a 00\nb 0G").err().as_deref(),
+            Some("Line 2: invalid pattern byte '0G' for b")
+        );
+        for pattern in ["", "?? ??", "100", "-1", "0x10"] {
+            assert!(parse_pattern(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn scanner_agrees_with_find_pattern() {
+        let data = [
+            0x00, 0xAA, 0xBB, 0x00, 0xAA, 0xCC, 0x00, 0xAA, 0xBB, 0x00, 0xFF, 0xAA,
+        ];
+        let signatures = parse("ab AA BB\nwild ?? AA\nff FF AA 00").unwrap();
+        let scanner = Scanner::new(&signatures);
+        for align in [1, 2, 3] {
+            let mut matches = scanner.scan(&data, align);
+            matches.sort();
+            for (i, signature) in signatures.iter().enumerate() {
+                let expected: Vec<usize> =
+                    matches.iter().filter(|m| m.0 == i).map(|m| m.1).collect();
+                assert_eq!(find_pattern(&data, &signature.pattern, align), expected);
+            }
+        }
+        assert_eq!(find_pattern(&data, &signatures[0].pattern, 1), [1, 7]);
+        assert_eq!(
+            find_pattern(&data, &signatures[1].pattern, 1),
+            [0, 3, 6, 10]
+        );
+        assert_eq!(find_pattern(&data[..1], &signatures[0].pattern, 1), []);
+    }
+}
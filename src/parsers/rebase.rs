@@ -0,0 +1,286 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use object::macho::{
+    REBASE_IMMEDIATE_MASK, REBASE_OPCODE_ADD_ADDR_IMM_SCALED, REBASE_OPCODE_ADD_ADDR_ULEB,
+    REBASE_OPCODE_DO_REBASE_ADD_ADDR_ULEB, REBASE_OPCODE_DO_REBASE_IMM_TIMES,
+    REBASE_OPCODE_DO_REBASE_ULEB_TIMES, REBASE_OPCODE_DO_REBASE_ULEB_TIMES_SKIPPING_ULEB,
+    REBASE_OPCODE_DONE, REBASE_OPCODE_MASK, REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB,
+    REBASE_OPCODE_SET_TYPE_IMM, REBASE_TYPE_POINTER,
+};
+
+use super::cursor::Cursor;
+
+const POINTER_SIZE: u64 = 8;
+
+/// Encode 64-bit pointer locations, as segment indices and offsets in the segments, into the
+/// rebase opcodes of `LC_DYLD_INFO`. Runs of adjacent pointers and of pointers a fixed stride
+/// apart take a single opcode. The stream is padded to a multiple of 8 bytes. The opcodes only
+/// have room for segment indices up to 15.
+pub fn encode(locations: &[(u8, u64)]) -> Result<Vec<u8>, String> {
+    let mut locations = locations.to_vec();
+    locations.sort_unstable();
+    locations.dedup();
+
+    let mut out = vec![REBASE_OPCODE_SET_TYPE_IMM | REBASE_TYPE_POINTER];
+    // Where the next rebase would land without moving, as a segment and offset.
+    let mut cursor: Option<(u8, u64)> = None;
+    let mut i = 0;
+    while i < locations.len() {
+        let (segment, offset) = locations[i];
+        match cursor {
+            Some((current, at)) if current == segment && at <= offset => {
+                let delta = offset - at;
+                if delta % POINTER_SIZE == 0 && delta / POINTER_SIZE <= REBASE_IMMEDIATE_MASK as u64
+                {
+                    if delta > 0 {
+                        out.push(REBASE_OPCODE_ADD_ADDR_IMM_SCALED | (delta / POINTER_SIZE) as u8);
+                    }
+                } else {
+                    out.push(REBASE_OPCODE_ADD_ADDR_ULEB);
+                    uleb128(&mut out, delta);
+                }
+            }
+            _ => {
+                if segment > REBASE_IMMEDIATE_MASK {
+                    return Err(format!(
+                        "Segment index {} does not fit rebase opcodes",
+                        segment
+                    ));
+                }
+                out.push(REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | segment);
+                uleb128(&mut out, offset);
+            }
+        }
+
+        // Locations a constant stride apart from this one, in the same segment.
+        let run = |stride: u64| {
+            locations[i..]
+                .iter()
+                .enumerate()
+                .take_while(|&(n, &(s, o))| s == segment && o == offset + n as u64 * stride)
+                .count()
+        };
+        let adjacent = run(POINTER_SIZE);
+        let stride = locations
+            .get(i + 1)
+            .filter(|&&(s, o)| s == segment && o - offset > POINTER_SIZE)
+            .map(|&(_, o)| o - offset);
+        let (count, stride) = match stride.map(|stride| (run(stride), stride)) {
+            Some((count, stride)) if adjacent == 1 && count > 2 => (count, stride),
+            _ => (adjacent, POINTER_SIZE),
+        };
+        if stride != POINTER_SIZE {
+            out.push(REBASE_OPCODE_DO_REBASE_ULEB_TIMES_SKIPPING_ULEB);
+            uleb128(&mut out, count as u64);
+            uleb128(&mut out, stride - POINTER_SIZE);
+        } else if count <= REBASE_IMMEDIATE_MASK as usize {
+            out.push(REBASE_OPCODE_DO_REBASE_IMM_TIMES | count as u8);
+        } else {
+            out.push(REBASE_OPCODE_DO_REBASE_ULEB_TIMES);
+            uleb128(&mut out, count as u64);
+        }
+        cursor = Some((segment, offset + count as u64 * stride));
+        i += count;
+    }
+    out.resize(
+        (out.len() + 1).next_multiple_of(POINTER_SIZE as usize),
+        REBASE_OPCODE_DONE,
+    );
+    Ok(out)
+}
+
+/// Decode rebase opcodes into the segment indices and offsets of the pointers they rebase,
+/// given the sizes of the image's segments. Only pointer rebases are supported. Pointers
+/// outside their segment are errors, and so are more pointers than the segments have room
+/// for, which bounds the work a crafted stream can cause.
+pub fn decode(data: &[u8], segment_sizes: &[u64]) -> Result<Vec<(u8, u64)>, String> {
+    let slots = segment_sizes.iter().fold(0u64, |total, size| {
+        total.saturating_add(size / POINTER_SIZE)
+    });
+    let mut cursor = Cursor::new(data, 0);
+    let mut locations = Vec::new();
+    let mut segment = 0u8;
+    let mut offset = 0u64;
+    let mut rebase = |segment: u8, offset: u64| -> Result<(), String> {
+        let size = *segment_sizes
+            .get(segment as usize)
+            .ok_or_else(|| format!("Rebase of segment {} out of range", segment))?;
+        if offset
+            .checked_add(POINTER_SIZE)
+            .is_none_or(|end| end > size)
+        {
+            return Err(format!(
+                "Rebase at 0x{:X} past the end of segment {}",
+                offset, segment
+            ));
+        }
+        if locations.len() as u64 >= slots {
+            return Err("Rebase opcodes rebase more pointers than the segments hold".into());
+        }
+        locations.push((segment, offset));
+        Ok(())
+    };
+    while cursor.position() < data.len() {
+        let at = cursor.position();
+        let byte = cursor.u8()?;
+        let immediate = byte & REBASE_IMMEDIATE_MASK;
+        let advance = |offset: u64, delta: u64| {
+            offset
+                .checked_add(delta)
+                .ok_or_else(|| format!("Rebase offset overflows at 0x{:X}", at))
+        };
+        match byte & REBASE_OPCODE_MASK {
+            REBASE_OPCODE_DONE => break,
+            REBASE_OPCODE_SET_TYPE_IMM => {
+                if immediate != REBASE_TYPE_POINTER {
+                    return Err(format!("Unsupported rebase type {}", immediate));
+                }
+            }
+            REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB => {
+                segment = immediate;
+                offset = cursor.uleb()?;
+            }
+            REBASE_OPCODE_ADD_ADDR_ULEB => offset = advance(offset, cursor.uleb()?)?,
+            REBASE_OPCODE_ADD_ADDR_IMM_SCALED => {
+                offset = advance(offset, immediate as u64 * POINTER_SIZE)?;
+            }
+            REBASE_OPCODE_DO_REBASE_IMM_TIMES | REBASE_OPCODE_DO_REBASE_ULEB_TIMES => {
+                let count = match byte & REBASE_OPCODE_MASK {
+                    REBASE_OPCODE_DO_REBASE_IMM_TIMES => immediate as u64,
+                    _ => cursor.uleb()?,
+                };
+                for _ in 0..count {
+                    rebase(segment, offset)?;
+                    offset = advance(offset, POINTER_SIZE)?;
+                }
+            }
+            REBASE_OPCODE_DO_REBASE_ADD_ADDR_ULEB => {
+                rebase(segment, offset)?;
+                let skip = cursor.uleb()?;
+                offset = advance(advance(offset, POINTER_SIZE)?, skip)?;
+            }
+            REBASE_OPCODE_DO_REBASE_ULEB_TIMES_SKIPPING_ULEB => {
+                let count = cursor.uleb()?;
+                let skip = cursor.uleb()?;
+                for _ in 0..count {
+                    rebase(segment, offset)?;
+                    offset = advance(advance(offset, POINTER_SIZE)?, skip)?;
+                }
+            }
+            opcode => {
+                return Err(format!(
+                    "Unknown rebase opcode 0x{:02X} at 0x{:X}",
+                    opcode, at
+                ));
+            }
+        }
+    }
+    Ok(locations)
+}
+
+fn uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [u64; 3] = [0x4000, 0x10_0000, 0x8000];
+
+    #[test]
+    fn round_trip() {
+        let mut locations = vec![(0, 0x10), (0, 0x3FF8), (2, 0x100)];
+        // A run of adjacent pointers, too long for an immediate count.
+        locations.extend((0..100).map(|i| (1, 0x1000 + i * 8)));
+        // Pointers a fixed stride apart.
+        locations.extend((0..20).map(|i| (1, 0x8000 + i * 0x28)));
+        // Gaps too wide for a scaled immediate.
+        locations.extend([(1, 0x9_0000), (1, 0x9_0008), (1, 0xF_FFF8)]);
+        locations.sort_unstable();
+
+        let encoded = encode(&locations).unwrap();
+        assert_eq!(encoded.len() % 8, 0);
+        assert_eq!(decode(&encoded, &SIZES).unwrap(), locations);
+        // Unsorted and duplicate input encodes the same.
+        let mut shuffled = locations.clone();
+        shuffled.reverse();
+        shuffled.push(locations[5]);
+        assert_eq!(encode(&shuffled).unwrap(), encoded);
+    }
+
+    #[test]
+    fn decodes_opcodes() {
+        let opcodes = [
+            0x11, // pointer type
+            0x21, 0x10, // segment 1, offset 0x10
+            0x52, // 2 pointers
+            0x41, // skip a pointer
+            0x70, 0x08, // a pointer, then skip 8 bytes
+            0x80, 0x02, 0x10, // 2 pointers 0x18 apart
+            0x30, 0x08, // skip 8 bytes
+            0x60, 0x01, // 1 pointer
+            0x00,
+        ];
+        assert_eq!(
+            decode(&opcodes, &SIZES).unwrap(),
+            [
+                (1, 0x10),
+                (1, 0x18),
+                (1, 0x28),
+                (1, 0x38),
+                (1, 0x50),
+                (1, 0x70)
+            ]
+        );
+        assert_eq!(decode(&[], &SIZES).unwrap(), []);
+    }
+
+    #[test]
+    fn malformed() {
+        let cases: [&[u8]; 8] = [
+            // Unsupported type, unknown opcode.
+            &[0x12],
+            &[0x90],
+            // Segment out of range, offset past the end of the segment.
+            &[0x23, 0x00, 0x51],
+            &[0x20, 0x80, 0x80, 0x01, 0x51],
+            // Truncated ULEB.
+            &[0x20, 0x80],
+            // Offsets past 2^64.
+            &[
+                0x20, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x41,
+            ],
+            &[
+                0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x30, 0x01,
+            ],
+            // A run past the end of the segment.
+            &[0x20, 0x00, 0x60, 0x81, 0x10],
+        ];
+        for opcodes in cases {
+            assert!(decode(opcodes, &SIZES).is_err(), "{:02X?}", opcodes);
+        }
+        assert!(encode(&[(16, 0)]).is_err());
+    }
+
+    #[test]
+    fn counts_are_bounded_by_the_segments() {
+        // Rebasing the same 15 pointers over and over stays inside the segment.
+        let mut opcodes = Vec::new();
+        for _ in 0..0x2000 {
+            opcodes.extend([0x20, 0x00, 0x5F]);
+        }
+        assert!(decode(&opcodes, &[0x100]).is_err());
+        assert_eq!(decode(&opcodes[..6], &[0x100]).unwrap().len(), 30);
+    }
+}
@@ -0,0 +1,81 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::cursor::Cursor;
+
+/// Decode `LC_FUNCTION_STARTS` data: ULEB128 deltas from `base` (the address of `__TEXT`),
+/// ended by a zero delta. Addresses are returned in ascending order.
+pub fn decode(data: &[u8], base: u64) -> Result<Vec<u64>, String> {
+    let mut address = base;
+    let mut cursor = Cursor::new(data, 0);
+    let mut starts = Vec::new();
+    while cursor.position() < data.len() {
+        let delta = cursor.uleb()?;
+        if delta == 0 {
+            break;
+        }
+        address = address.checked_add(delta).ok_or_else(|| {
+            format!(
+                "Function start delta 0x{:X} at 0x{:X} overflows",
+                delta,
+                cursor.position()
+            )
+        })?;
+        starts.push(address);
+    }
+    Ok(starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uleb(mut value: u64, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    #[test]
+    fn decodes_deltas() {
+        // Functions at +0x3F0 and +0x3F0+0x1C, then the terminator and padding.
+        let data = [0xF0, 0x07, 0x1C, 0x00, 0x00, 0x00];
+        assert_eq!(decode(&data, 0x1000).unwrap(), [0x13F0, 0x140C]);
+        assert!(decode(&[], 0x1000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let starts = [
+            0x1_8000_4000u64,
+            0x1_8000_4010,
+            0x1_8000_8000,
+            0x1_9000_0000,
+        ];
+        let mut data = Vec::new();
+        let mut previous = 0x1_8000_0000;
+        for &start in &starts {
+            uleb(start - previous, &mut data);
+            previous = start;
+        }
+        data.push(0);
+        assert_eq!(decode(&data, 0x1_8000_0000).unwrap(), starts);
+    }
+
+    #[test]
+    fn malformed() {
+        // Truncated ULEB128.
+        assert!(decode(&[0x80, 0x80], 0).is_err());
+        // A delta past the end of the address space.
+        let mut data = Vec::new();
+        uleb(u64::MAX, &mut data);
+        assert!(decode(&data, 0x1000).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use object::macho::{
+    EXPORT_SYMBOL_FLAGS_KIND_ABSOLUTE, EXPORT_SYMBOL_FLAGS_KIND_MASK,
+    EXPORT_SYMBOL_FLAGS_KIND_THREAD_LOCAL, EXPORT_SYMBOL_FLAGS_REEXPORT,
+    EXPORT_SYMBOL_FLAGS_STUB_AND_RESOLVER, EXPORT_SYMBOL_FLAGS_WEAK_DEFINITION,
+};
+
+use super::cursor::Cursor;
+
+pub enum ExportKind {
+    Regular {
+        address: u64,
+    },
+    Reexport {
+        ordinal: u64,
+        import: Option<String>,
+    },
+    StubAndResolver {
+        stub: u64,
+        resolver: u64,
+    },
+}
+
+pub struct ExportEntry {
+    pub name: String,
+    pub flags: u64,
+    pub kind: ExportKind,
+}
+
+/// Parse a serialized export trie. `base` is added to regular and stub addresses.
+pub fn parse_trie(data: &[u8], base: u64) -> Result<Vec<ExportEntry>, String> {
+    let mut exports = Vec::new();
+    let mut stack = vec![(0usize, Vec::new())];
+    let mut visited = 0usize;
+
+    while let Some((offset, prefix)) = stack.pop() {
+        // A well-formed trie never has more nodes than bytes; bail out on cycles.
+        visited += 1;
+        if visited > data.len() + 1 {
+            return Err("Export trie contains a cycle".into());
+        }
+
+        let mut cursor = Cursor::new(data, offset);
+        let terminal_size = cursor.uleb()? as usize;
+        if terminal_size != 0 {
+            let mut terminal = Cursor::new(data, cursor.position());
+            let flags = terminal.uleb()?;
+            let kind = if flags & EXPORT_SYMBOL_FLAGS_REEXPORT as u64 != 0 {
+                let ordinal = terminal.uleb()?;
+                let import = terminal.cstr()?;
+                ExportKind::Reexport {
+                    ordinal,
+                    import: (!import.is_empty()).then(|| import.to_string()),
+                }
+            } else if flags & EXPORT_SYMBOL_FLAGS_STUB_AND_RESOLVER as u64 != 0 {
+                let stub = terminal.uleb()?;
+                let resolver = terminal.uleb()?;
+                ExportKind::StubAndResolver {
+                    stub: base.wrapping_add(stub),
+                    resolver: base.wrapping_add(resolver),
+                }
+            } else {
+                let value = terminal.uleb()?;
+                let absolute = flags & EXPORT_SYMBOL_FLAGS_KIND_MASK as u64
+                    == EXPORT_SYMBOL_FLAGS_KIND_ABSOLUTE as u64;
+                ExportKind::Regular {
+                    address: if absolute {
+                        value
+                    } else {
+                        base.wrapping_add(value)
+                    },
+                }
+            };
+            exports.push(ExportEntry {
+                name: String::from_utf8_lossy(&prefix).into_owned(),
+                flags,
+                kind,
+            });
+        }
+        cursor.skip(terminal_size)?;
+
+        let children = cursor.u8()?;
+        let mut edges = Vec::with_capacity(children as usize);
+        for _ in 0..children {
+            let label = cursor.cstr()?;
+            let child = cursor.uleb()? as usize;
+            if child >= data.len() {
+                return Err(format!(
+                    "Export trie child offset 0x{:X} out of range",
+                    child
+                ));
+            }
+            let mut name = prefix.clone();
+            name.extend_from_slice(label.as_bytes());
+            edges.push((child, name));
+        }
+        // Push in reverse so that children are visited in trie order.
+        stack.extend(edges.into_iter().rev());
+    }
+    Ok(exports)
+}
+
+impl ExportEntry {
+    /// The address the export resolves to inside the image, if it has one.
+    pub fn address(&self) -> Option<u64> {
+        match self.kind {
+            ExportKind::Regular { .. } if self.is_absolute() => None,
+            ExportKind::Regular { address } => Some(address),
+            ExportKind::StubAndResolver { stub, .. } => Some(stub),
+            ExportKind::Reexport { .. } => None,
+        }
+    }
+
+    /// Describe the export target with addresses relative to `base`, so that the same image in
+    /// two different caches (or two different images) can be compared.
+    pub fn target(&self, base: u64) -> String {
+        let mut target = match &self.kind {
+            ExportKind::Regular { address } if self.is_absolute() => {
+                format!("absolute 0x{:X}", address)
+            }
+            ExportKind::Regular { address } => format!("+0x{:X}", address.wrapping_sub(base)),
+            ExportKind::StubAndResolver { stub, resolver } => format!(
+                "stub +0x{:X} resolver +0x{:X}",
+                stub.wrapping_sub(base),
+                resolver.wrapping_sub(base)
+            ),
+            ExportKind::Reexport { ordinal, import } => format!(
+                "re-export of {} from dylib #{}",
+                import.as_deref().unwrap_or(&self.name),
+                ordinal
+            ),
+        };
+        if self.flags & EXPORT_SYMBOL_FLAGS_KIND_MASK as u64
+            == EXPORT_SYMBOL_FLAGS_KIND_THREAD_LOCAL as u64
+        {
+            target.push_str(" [tls]");
+        }
+        if self.flags & EXPORT_SYMBOL_FLAGS_WEAK_DEFINITION as u64 != 0 {
+            target.push_str(" [weak]");
+        }
+        target
+    }
+
+    /// Names of the kind and flags of the export: `regular`, `absolute`, `tls`, `re-export`
+    /// or `resolver` (stub and resolver), then `weak` for weak definitions.
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        let kind = self.flags & EXPORT_SYMBOL_FLAGS_KIND_MASK as u64;
+        let mut names = vec![match self.kind {
+            ExportKind::Reexport { .. } => "re-export",
+            ExportKind::StubAndResolver { .. } => "resolver",
+            ExportKind::Regular { .. } if kind == EXPORT_SYMBOL_FLAGS_KIND_ABSOLUTE as u64 => {
+                "absolute"
+            }
+            ExportKind::Regular { .. } if kind == EXPORT_SYMBOL_FLAGS_KIND_THREAD_LOCAL as u64 => {
+                "tls"
+            }
+            ExportKind::Regular { .. } => "regular",
+        }];
+        if self.flags & EXPORT_SYMBOL_FLAGS_WEAK_DEFINITION as u64 != 0 {
+            names.push("weak");
+        }
+        names
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.flags & EXPORT_SYMBOL_FLAGS_KIND_MASK as u64
+            == EXPORT_SYMBOL_FLAGS_KIND_ABSOLUTE as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A trie node padded to 24 bytes, so that node `i` sits at `24 * i`.
+    fn node(terminal: &[u8], children: &[(&str, u8)]) -> Vec<u8> {
+        let mut node = vec![terminal.len() as u8];
+        node.extend_from_slice(terminal);
+        node.push(children.len() as u8);
+        for (label, child) in children {
+            node.extend_from_slice(label.as_bytes());
+            node.extend_from_slice(&[0, child * 24]);
+        }
+        node.resize(24, 0);
+        node
+    }
+
+    fn sample() -> Vec<u8> {
+        [
+            node(&[], &[("_f", 1)]),
+            node(&[], &[("oo", 2), ("bar", 3), ("tls", 5)]),
+            // Regular, at 0x1000.
+            node(&[0x00, 0x80, 0x20], &[]),
+            // Re-export of _baz from the second dylib.
+            node(&[0x08, 0x02, b'_', b'b', b'a', b'z', 0], &[("Stub", 4)]),
+            // Stub and resolver, weak.
+            node(&[0x14, 0x20, 0x30], &[]),
+            // Thread local.
+            node(&[0x01, 0x40], &[]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn parses_exports() {
+        let base = 0x1_8000_0000;
+        let exports = parse_trie(&sample(), base).unwrap();
+        let names: Vec<&str> = exports.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["_foo", "_fbar", "_fbarStub", "_ftls"]);
+        let targets: Vec<String> = exports.iter().map(|e| e.target(base)).collect();
+        assert_eq!(
+            targets,
+            [
+                "+0x1000",
+                "re-export of _baz from dylib #2",
+                "stub +0x20 resolver +0x30 [weak]",
+                "+0x40 [tls]",
+            ]
+        );
+        let addresses: Vec<Option<u64>> = exports.iter().map(ExportEntry::address).collect();
+        assert_eq!(
+            addresses,
+            [
+                Some(base + 0x1000),
+                None,
+                Some(base + 0x20),
+                Some(base + 0x40)
+            ]
+        );
+        assert_eq!(exports[2].flag_names(), ["resolver", "weak"]);
+    }
+
+    #[test]
+    fn malformed() {
+        // A child pointing back at the root.
+        let cycle = node(&[], &[("a", 0)]);
+        assert!(parse_trie(&cycle, 0).is_err());
+        let out_of_range = node(&[], &[("a", 2)]);
+        assert!(parse_trie(&out_of_range, 0).is_err());
+        let sample = sample();
+        for len in [0, 1, 30, 48 + 2] {
+            assert!(parse_trie(&sample[..len], 0).is_err(), "{}", len);
+        }
+        // A terminal size running past the end of the data.
+        let huge = [
+            0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00,
+        ];
+        assert!(parse_trie(&huge, 0).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Kinds of second-level `__unwind_info` pages.
+const UNWIND_SECOND_LEVEL_REGULAR: u32 = 2;
+const UNWIND_SECOND_LEVEL_COMPRESSED: u32 = 3;
+
+/// Decode the function addresses covered by `__unwind_info` entries, relative to `base` (the
+/// address of the Mach-O header), in ascending order.
+///
+/// Each entry starts a run of code sharing one unwind encoding. The linker merges adjacent
+/// functions with the same encoding and no LSDA into one entry, so the result is a subset of
+/// the real function starts.
+pub fn function_starts(data: &[u8], base: u64) -> Result<Vec<u64>, String> {
+    let field = |index: usize| read_u32(data, index * 4);
+    let version = field(0)?;
+    if version != 1 {
+        return Err(format!("Unsupported __unwind_info version {}", version));
+    }
+    let index_offset = field(5)? as usize;
+    let index_count = field(6)? as usize;
+
+    let mut starts = Vec::new();
+    // The last index entry only marks the end of the covered range.
+    for i in 0..index_count.saturating_sub(1) {
+        let entry = index_offset + i * 12;
+        let first = read_u32(data, entry)?;
+        let page = read_u32(data, entry + 4)? as usize;
+        if page == 0 {
+            continue;
+        }
+        let header = |offset: usize| read_u16(data, page + offset);
+        match read_u32(data, page)? {
+            UNWIND_SECOND_LEVEL_REGULAR => {
+                let entries = page + header(4)? as usize;
+                for j in 0..header(6)? as usize {
+                    starts.push(read_u32(data, entries + j * 8)?);
+                }
+            }
+            UNWIND_SECOND_LEVEL_COMPRESSED => {
+                let entries = page + header(4)? as usize;
+                for j in 0..header(6)? as usize {
+                    // The low 24 bits are the offset from the first function of the page.
+                    let offset = read_u32(data, entries + j * 4)? & 0x00FF_FFFF;
+                    starts.push(
+                        first
+                            .checked_add(offset)
+                            .ok_or_else(|| "Compressed unwind entry overflows".to_string())?,
+                    );
+                }
+            }
+            kind => {
+                return Err(format!(
+                    "Unknown second-level unwind page kind {} at 0x{:X}",
+                    kind, page
+                ));
+            }
+        }
+    }
+    starts.sort_unstable();
+    starts.dedup();
+    Ok(starts
+        .into_iter()
+        .map(|offset| base.wrapping_add(offset as u64))
+        .collect())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("Unexpected end of data at 0x{:X}", offset))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("Unexpected end of data at 0x{:X}", offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn words(out: &mut Vec<u8>, words: &[u32]) {
+        for word in words {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// `__unwind_info` with a regular page for the functions at 0x1000 and 0x1040, a compressed
+    /// page for those at 0x2000, 0x2010 and 0x2100, and the end of the range at 0x3000.
+    fn sample() -> Vec<u8> {
+        let mut data = Vec::new();
+        words(&mut data, &[1, 0, 0, 0, 0, 28, 3]);
+        // Index at 28: (first function, page, LSDA index).
+        words(&mut data, &[0x1000, 64, 0, 0x2000, 96, 0, 0x3000, 0, 0]);
+        // Regular page at 64: kind, then entries at +8, 2 of them.
+        words(&mut data, &[2, 8 | 2 << 16, 0x1000, 0, 0x1040, 0]);
+        data.resize(96, 0);
+        // Compressed page at 96: kind, entries at +12, 3 of them, no encodings.
+        words(
+            &mut data,
+            &[3, 12 | 3 << 16, 0, 0x0100_0000, 0x0200_0010, 0x0100_0100],
+        );
+        data
+    }
+
+    #[test]
+    fn decodes_regular_and_compressed_pages() {
+        let starts = function_starts(&sample(), 0x1_8000_0000).unwrap();
+        let expected: Vec<u64> = [0x1000, 0x1040, 0x2000, 0x2010, 0x2100]
+            .iter()
+            .map(|offset| 0x1_8000_0000 + offset)
+            .collect();
+        assert_eq!(starts, expected);
+    }
+
+    #[test]
+    fn malformed() {
+        let sample = sample();
+        for len in [0, 20, 40, 70, 110] {
+            assert!(function_starts(&sample[..len], 0).is_err(), "{}", len);
+        }
+        let mut version = sample.clone();
+        version[0] = 2;
+        assert!(function_starts(&version, 0).is_err());
+        let mut kind = sample.clone();
+        kind[64] = 7;
+        assert!(function_starts(&kind, 0).is_err());
+        // A compressed entry past the end of the address space.
+        let mut overflow = sample.clone();
+        overflow[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(function_starts(&overflow, 0).is_err());
+        let mut entries = vec![0; 28];
+        entries[..4].copy_from_slice(&1u32.to_le_bytes());
+        entries[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(function_starts(&entries, 0).is_err());
+    }
+}
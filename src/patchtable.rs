@@ -0,0 +1,197 @@
+//! Uses of exported functions recorded in the dyld patch table: the locations dyld rewrites
+//! when a root or an interposing library replaces a function of a cache dylib.
+
+use object::LittleEndian;
+use object::macho::DyldCacheHeader;
+use object::read::macho::DyldCache;
+use std::error::Error;
+use std::mem::offset_of;
+
+use crate::vm;
+
+/// Sizes of the table entries. The v1 table records cache offsets, later ones offsets in the
+/// client dylibs, plus uniqued GOT uses from v3 on.
+const V1_IMAGE: u64 = 8;
+const V1_EXPORT: u64 = 16;
+const V1_LOCATION: u64 = 8;
+const V2_IMAGE: u64 = 16;
+const V2_EXPORT: u64 = 8;
+const V2_CLIENT: u64 = 12;
+const V2_CLIENT_EXPORT: u64 = 12;
+const V2_LOCATION: u64 = 8;
+const V3_GOT_CLIENT: u64 = 8;
+const V3_GOT_LOCATION: u64 = 16;
+
+/// A location dyld patches.
+pub struct PatchSite {
+    pub address: u64,
+    /// A uniqued GOT slot shared by the clients, rather than a use in one client dylib.
+    pub got: bool,
+}
+
+/// The patched uses of the function at `implementation` exported by the image at `image` (an
+/// index in the cache image list). Empty when the image does not export it as patchable, and
+/// an error when the cache has no patch table.
+pub fn patch_sites(
+    cache: &DyldCache<LittleEndian>,
+    image: usize,
+    implementation: u64,
+) -> Result<Vec<PatchSite>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let header = DyldCacheHeader::<LittleEndian>::parse(cache.data())?;
+    if offset_of!(DyldCacheHeader<LittleEndian>, patch_info_size)
+        >= header.mapping_offset.get(e) as usize
+        || header.patch_info_addr.get(e) == 0
+    {
+        return Err("The cache has no patch table".into());
+    }
+    let info = vm::read_bytes(
+        cache,
+        header.patch_info_addr.get(e),
+        header.patch_info_size.get(e),
+    )?;
+    let field = |offset: usize| -> Result<u64, Box<dyn Error>> {
+        let bytes = info
+            .get(offset..offset + 8)
+            .ok_or("Patch table header is truncated")?;
+        Ok(u64::from_le_bytes(bytes.try_into()?))
+    };
+    // The v1 header starts with an address instead of a version.
+    let version = field(0)? as u32;
+    if !(2..=4).contains(&version) {
+        return patch_sites_v1(cache, image, implementation, &field);
+    }
+
+    let images: Vec<u64> = cache.images().map(|i| i.info().address.get(e)).collect();
+    let base = *images.get(image).ok_or("Image index out of range")?;
+    let entry = read_u32s(cache, field(8)? + image as u64 * V2_IMAGE, 4)?;
+    let (clients_start, clients_count) = (entry[0] as u64, entry[1] as u64);
+    let (exports_start, exports_count) = (entry[2] as u64, entry[3] as u64);
+
+    let exports = read_u32s(
+        cache,
+        field(24)? + exports_start * V2_EXPORT,
+        exports_count * 2,
+    )?;
+    let Some(export) = exports
+        .chunks(2)
+        .position(|export| base + export[0] as u64 == implementation)
+    else {
+        return Ok(Vec::new());
+    };
+    let export = exports_start + export as u64;
+
+    let mut sites = Vec::new();
+    let clients = read_u32s(
+        cache,
+        field(40)? + clients_start * V2_CLIENT,
+        clients_count * 3,
+    )?;
+    for client in clients.chunks(3) {
+        let client_base = *images
+            .get(client[0] as usize)
+            .ok_or("Patch table client index out of range")?;
+        for (start, count) in export_uses(
+            cache,
+            field(56)?,
+            client[1] as u64,
+            client[2] as u64,
+            export,
+        )? {
+            let locations = read_u32s(cache, field(72)? + start * V2_LOCATION, count * 2)?;
+            sites.extend(locations.chunks(2).map(|l| PatchSite {
+                address: client_base + l[0] as u64,
+                got: false,
+            }));
+        }
+    }
+
+    if version >= 3 {
+        let cache_base = cache_base(cache)?;
+        let got = read_u32s(cache, field(104)? + image as u64 * V3_GOT_CLIENT, 2)?;
+        for (start, count) in export_uses(cache, field(120)?, got[0] as u64, got[1] as u64, export)?
+        {
+            let locations = vm::read_bytes(
+                cache,
+                field(136)? + start * V3_GOT_LOCATION,
+                count * V3_GOT_LOCATION,
+            )?;
+            for location in locations.chunks(V3_GOT_LOCATION as usize) {
+                let offset = u64::from_le_bytes(location[..8].try_into()?);
+                sites.push(PatchSite {
+                    address: cache_base + offset,
+                    got: true,
+                });
+            }
+        }
+    }
+    sites.sort_by_key(|s| s.address);
+    sites.dedup_by_key(|s| s.address);
+    Ok(sites)
+}
+
+/// Ranges of the locations using `export`, from the `count` client exports at index `start` of
+/// the table at `table`.
+fn export_uses(
+    cache: &DyldCache<LittleEndian>,
+    table: u64,
+    start: u64,
+    count: u64,
+    export: u64,
+) -> Result<Vec<(u64, u64)>, Box<dyn Error>> {
+    let exports = read_u32s(cache, table + start * V2_CLIENT_EXPORT, count * 3)?;
+    Ok(exports
+        .chunks(3)
+        .filter(|e| e[0] as u64 == export)
+        .map(|e| (e[1] as u64, e[2] as u64))
+        .collect())
+}
+
+/// Caches from iOS 13 to 15 record exports and uses as cache offsets.
+fn patch_sites_v1(
+    cache: &DyldCache<LittleEndian>,
+    image: usize,
+    implementation: u64,
+    field: &dyn Fn(usize) -> Result<u64, Box<dyn Error>>,
+) -> Result<Vec<PatchSite>, Box<dyn Error>> {
+    let base = cache_base(cache)?;
+    let entry = read_u32s(cache, field(0)? + image as u64 * V1_IMAGE, 2)?;
+    let (start, count) = (entry[0] as u64, entry[1] as u64);
+    let exports = read_u32s(cache, field(16)? + start * V1_EXPORT, count * 4)?;
+    let mut sites = Vec::new();
+    for export in exports
+        .chunks(4)
+        .filter(|e| base + e[0] as u64 == implementation)
+    {
+        let (start, count) = (export[1] as u64, export[2] as u64);
+        let locations = read_u32s(cache, field(32)? + start * V1_LOCATION, count * 2)?;
+        sites.extend(locations.chunks(2).map(|l| PatchSite {
+            address: base + l[0] as u64,
+            got: false,
+        }));
+    }
+    sites.sort_by_key(|s| s.address);
+    sites.dedup_by_key(|s| s.address);
+    Ok(sites)
+}
+
+/// Address cache offsets are relative to, that of the cache header.
+fn cache_base(cache: &DyldCache<LittleEndian>) -> Result<u64, Box<dyn Error>> {
+    Ok(cache
+        .mappings()
+        .next()
+        .ok_or("Cache has no mappings")?
+        .address())
+}
+
+fn read_u32s(
+    cache: &DyldCache<LittleEndian>,
+    addr: u64,
+    count: u64,
+) -> Result<Vec<u32>, Box<dyn Error>> {
+    let bytes = vm::read_bytes(cache, addr, count * 4)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .collect())
+}
@@ -0,0 +1,432 @@
+use object::macho::{self, DyldCacheHeader};
+use object::read::macho::{DyldCache, DyldCacheImage, MachHeader};
+use object::{Architecture, LittleEndian};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::mem::offset_of;
+
+use crate::index::CacheIndex;
+use crate::vm::read_u32;
+
+/// Where an address lives inside the cache.
+pub enum Location {
+    /// Inside a segment of an image.
+    Image {
+        path: String,
+        segment: String,
+        section: Option<String>,
+        /// Offset from the start of the section, or of the segment when no section matches.
+        offset: u64,
+        note: Option<&'static str>,
+    },
+    /// Inside a region generated by the cache builder rather than by any image.
+    Builder(String),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Image {
+                path,
+                segment,
+                section,
+                offset,
+                note,
+            } => {
+                match section {
+                    Some(section) => write!(f, "{} {},{}+0x{:X}", path, segment, section, offset)?,
+                    None => write!(f, "{} {}+0x{:X}", path, segment, offset)?,
+                }
+                if let Some(note) = note {
+                    write!(f, " ({})", note)?;
+                }
+                Ok(())
+            }
+            Location::Builder(label) => write!(f, "<{}>", label),
+        }
+    }
+}
+
+/// Find an image by install path or by file name.
+pub fn find_image<'data, 'cache>(
+    cache: &'cache DyldCache<'data, LittleEndian>,
+    module: &str,
+) -> Result<DyldCacheImage<'data, 'cache, LittleEndian>, Box<dyn Error>> {
+    cache
+        .images()
+        .find(|image| {
+            image
+                .path()
+                .is_ok_and(|p| p == module || p.rsplit('/').next() == Some(module))
+        })
+        .ok_or_else(|| format!("Image {} not found in dyld cache", module).into())
+}
+
+/// Map install paths to images, either the single image matching `module` or every image
+/// except aliases of an image already listed.
+pub fn images_by_path<'data, 'cache>(
+    cache: &'cache DyldCache<'data, LittleEndian>,
+    module: Option<&str>,
+) -> Result<BTreeMap<String, DyldCacheImage<'data, 'cache, LittleEndian>>, Box<dyn Error>> {
+    let mut map = BTreeMap::new();
+    if let Some(module) = module {
+        let image = find_image(cache, module)?;
+        map.insert(image.path()?.to_string(), image);
+        return Ok(map);
+    }
+    let mut seen = HashSet::new();
+    for image in cache.images() {
+        if seen.insert(image.info().address.get(LittleEndian)) {
+            map.insert(image.path()?.to_string(), image);
+        }
+    }
+    Ok(map)
+}
+
+pub fn resolve(index: &CacheIndex, addr: u64) -> Option<Location> {
+    image_location(index, addr).or_else(|| {
+        shared_linkedit(index, addr)
+            .or_else(|| builder_region(index, addr))
+            .map(Location::Builder)
+    })
+}
+
+fn image_location(index: &CacheIndex, addr: u64) -> Option<Location> {
+    let (_, image) = index.image_at(addr)?;
+    let segment = image.segment_at(addr)?;
+    let section = image.section_at(addr).filter(|s| s.segment == segment.name);
+    let (section, offset) = match section {
+        Some(s) => (Some(s.name.clone()), addr - s.start),
+        None => (None, addr - segment.start),
+    };
+    let note = (segment.name == "__OBJC_RO").then_some("objc optimization data");
+
+    Some(Location::Image {
+        path: image.path.clone(),
+        segment: segment.name.clone(),
+        section,
+        offset,
+        note,
+    })
+}
+
+/// Every image maps the same `__LINKEDIT` region(s), so they are not attributed to one image.
+fn shared_linkedit(index: &CacheIndex, addr: u64) -> Option<String> {
+    (0..index.len())
+        .flat_map(|i| index.image(i).segments.iter())
+        .any(|s| s.name == "__LINKEDIT" && addr >= s.start && addr < s.end)
+        .then(|| "shared __LINKEDIT (symbol tables, export tries)".to_string())
+}
+
+/// Formats addresses either as absolute VM addresses or, with `relative`, as
+/// `<image file name>+0x<offset from the image load address>`. Addresses outside every image
+/// are shown relative to the shared region base as `cache+0x<offset>`.
+pub struct AddressFormat<'i, 'c> {
+    relative: bool,
+    region_base: u64,
+    index: &'i CacheIndex<'c>,
+}
+
+impl<'i, 'c> AddressFormat<'i, 'c> {
+    pub fn new(index: &'i CacheIndex<'c>, relative: bool) -> Self {
+        AddressFormat {
+            relative,
+            region_base: shared_region_base(index.cache()),
+            index,
+        }
+    }
+
+    pub fn format(&self, addr: u64) -> String {
+        if !self.relative {
+            return format!("0x{:X}", addr);
+        }
+        match self.index.image_at(addr) {
+            Some((_, image)) => format!("{}+0x{:X}", image.name(), addr - image.base),
+            None if addr >= self.region_base => format!("cache+0x{:X}", addr - self.region_base),
+            None => format!("0x{:X}", addr),
+        }
+    }
+
+    /// Format `[start, end)`; in relative mode both ends are offsets from the image of `start`.
+    pub fn format_range(&self, start: u64, end: u64) -> String {
+        if !self.relative {
+            return format!("0x{:X}-0x{:X}", start, end);
+        }
+        match self.index.image_at(start) {
+            Some((_, image)) => format!(
+                "{}+0x{:X}-0x{:X}",
+                image.name(),
+                start - image.base,
+                end - image.base
+            ),
+            None => format!("{}-{}", self.format(start), self.format(end)),
+        }
+    }
+}
+
+/// The address the shared region is mapped at when not slid. Old headers do not record it, so
+/// fall back to the per-platform default for the cache architecture.
+pub fn shared_region_base(cache: &DyldCache<LittleEndian>) -> u64 {
+    let e = LittleEndian;
+    if let Ok(header) = DyldCacheHeader::<LittleEndian>::parse(cache.data())
+        && offset_of!(DyldCacheHeader<LittleEndian>, shared_region_start)
+            < header.mapping_offset.get(e) as usize
+        && header.shared_region_start.get(e) != 0
+    {
+        return header.shared_region_start.get(e);
+    }
+    match cache.architecture() {
+        Architecture::Aarch64 => 0x1_8000_0000,
+        Architecture::X86_64 => 0x7FFF_8000_0000,
+        Architecture::I386 => 0x9000_0000,
+        Architecture::Arm => 0x1A00_0000,
+        _ => cache.mappings().next().map_or(0, |m| m.address()),
+    }
+}
+
+/// Strip pointer authentication and top-byte tag bits from an address copied from a runtime
+/// arm64e pointer. Bits above those needed to address the cache, slid as far as it may be,
+/// cannot be part of the address. Returns `None` when there is nothing to strip.
+pub fn strip_pointer_bits(cache: &DyldCache<LittleEndian>, addr: u64) -> Option<u64> {
+    if cache.architecture() != Architecture::Aarch64 {
+        return None;
+    }
+    let e = LittleEndian;
+    let max_slide = DyldCacheHeader::<LittleEndian>::parse(cache.data())
+        .ok()
+        .filter(|h| {
+            offset_of!(DyldCacheHeader<LittleEndian>, max_slide) < h.mapping_offset.get(e) as usize
+        })
+        .map_or(0, |h| h.max_slide.get(e));
+    let end = cache.mappings().map(|m| m.address() + m.size()).max()?;
+    let bits = 64 - (end - 1 + max_slide).leading_zeros();
+    let stripped = addr & ((1 << bits) - 1);
+    (stripped != addr).then_some(stripped)
+}
+
+/// Label addresses that belong to cache-builder generated content: tables described by the
+/// cache header, legacy branch pools, and mappings that no image claims (stub islands,
+/// uniqued GOTs and other optimization data).
+fn builder_region(index: &CacheIndex, addr: u64) -> Option<String> {
+    let cache = index.cache();
+    let data = cache.data();
+    let header = DyldCacheHeader::<LittleEndian>::parse(data).ok()?;
+    let base = cache.mappings().next()?.address();
+
+    for (label, start, size) in header_regions(header, base) {
+        if size != 0 && addr >= start && addr < start + size {
+            return Some(label.to_string());
+        }
+    }
+
+    if let Some(label) = branch_pool(cache, header, addr) {
+        return Some(label);
+    }
+
+    let mapping = cache
+        .mappings()
+        .find(|m| addr >= m.address() && addr < m.address() + m.size())?;
+    if mapping.init_prot() & macho::VM_PROT_EXECUTE != 0 {
+        return Some(match decode_stub(cache, addr) {
+            Some(StubTarget::Direct(target)) => match image_location(index, target) {
+                Some(location) => format!("stub island for {}", location),
+                None => format!("stub island for 0x{:X}", target),
+            },
+            Some(StubTarget::Got(slot)) => format!("stub island via GOT slot 0x{:X}", slot),
+            None => "cache-builder text (stub islands)".to_string(),
+        });
+    }
+    Some(format!(
+        "cache-builder data in mapping 0x{:X}-0x{:X} (uniqued GOTs, optimization tables)",
+        mapping.address(),
+        mapping.address() + mapping.size()
+    ))
+}
+
+fn header_regions(
+    header: &DyldCacheHeader<LittleEndian>,
+    base: u64,
+) -> Vec<(&'static str, u64, u64)> {
+    let e = LittleEndian;
+    // Fields are only meaningful when the header is large enough to contain them.
+    let header_size = header.mapping_offset.get(e) as usize;
+    let has = |offset: usize| offset < header_size;
+    type H = DyldCacheHeader<LittleEndian>;
+
+    let mut regions = vec![("dyld cache header", base, header_size as u64)];
+    if has(offset_of!(H, patch_info_size)) {
+        regions.push((
+            "dyld patch table",
+            header.patch_info_addr.get(e),
+            header.patch_info_size.get(e),
+        ));
+        regions.push((
+            "program launch closures",
+            header.prog_closures_addr.get(e),
+            header.prog_closures_size.get(e),
+        ));
+        regions.push((
+            "program launch closures trie",
+            header.prog_closures_trie_addr.get(e),
+            header.prog_closures_trie_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, other_trie_size)) {
+        regions.push((
+            "dylibs image array",
+            header.dylibs_image_array_addr.get(e),
+            header.dylibs_image_array_size.get(e),
+        ));
+        regions.push((
+            "dylibs trie",
+            header.dylibs_trie_addr.get(e),
+            header.dylibs_trie_size.get(e),
+        ));
+        regions.push((
+            "other image array",
+            header.other_image_array_addr.get(e),
+            header.other_image_array_size.get(e),
+        ));
+        regions.push((
+            "other images trie",
+            header.other_trie_addr.get(e),
+            header.other_trie_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, programs_pbl_set_pool_size)) {
+        regions.push((
+            "program prebuilt loader sets",
+            header.programs_pbl_set_pool_addr.get(e),
+            header.programs_pbl_set_pool_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, swift_opts_size)) {
+        regions.push((
+            "swift optimizations",
+            base + header.swift_opts_offset.get(e),
+            header.swift_opts_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, rosetta_read_write_size)) {
+        regions.push((
+            "rosetta read-only area",
+            header.rosetta_read_only_addr.get(e),
+            header.rosetta_read_only_size.get(e),
+        ));
+        regions.push((
+            "rosetta read-write area",
+            header.rosetta_read_write_addr.get(e),
+            header.rosetta_read_write_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, objc_opts_size)) {
+        regions.push((
+            "objc optimizations",
+            base + header.objc_opts_offset.get(e),
+            header.objc_opts_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, cache_atlas_size)) {
+        regions.push((
+            "cache atlas",
+            base + header.cache_atlas_offset.get(e),
+            header.cache_atlas_size.get(e),
+        ));
+    }
+    if has(offset_of!(H, dynamic_data_max_size)) {
+        regions.push((
+            "dyld dynamic data",
+            base + header.dynamic_data_offset.get(e),
+            header.dynamic_data_max_size.get(e),
+        ));
+    }
+    regions
+}
+
+/// Older caches (iOS 11 era) place branch islands in pools listed by the header.
+fn branch_pool(
+    cache: &DyldCache<LittleEndian>,
+    header: &DyldCacheHeader<LittleEndian>,
+    addr: u64,
+) -> Option<String> {
+    let e = LittleEndian;
+    let count = header.branch_pools_count.get(e) as usize;
+    if count == 0 {
+        return None;
+    }
+
+    let data = cache.data();
+    let offset = header.branch_pools_offset.get(e) as usize;
+    for (index, chunk) in data
+        .get(offset..offset + count * 8)?
+        .chunks_exact(8)
+        .enumerate()
+    {
+        let pool = u64::from_le_bytes(chunk.try_into().ok()?);
+        if addr < pool {
+            continue;
+        }
+        let Some((pool_data, pool_offset)) = cache.data_and_offset_for_address(pool) else {
+            continue;
+        };
+        let size = macho::MachHeader64::<LittleEndian>::parse(pool_data, pool_offset)
+            .ok()
+            .and_then(|mh| {
+                let mut commands = mh.load_commands(e, pool_data, pool_offset).ok()?;
+                let mut size = 0;
+                while let Ok(Some(command)) = commands.next() {
+                    if let Ok(Some((segment, _))) = command.segment_64() {
+                        size += segment.vmsize.get(e);
+                    }
+                }
+                Some(size)
+            })
+            .unwrap_or(0);
+        if addr < pool + size {
+            return Some(format!("branch island pool {}", index));
+        }
+    }
+    None
+}
+
+enum StubTarget {
+    /// `adrp; add; br` stubs that branch straight to their target.
+    Direct(u64),
+    /// `adrp; ldr; br` stubs that load their target from a GOT slot.
+    Got(u64),
+}
+
+fn adrp_page(insn: u32, pc: u64) -> Option<(u32, u64)> {
+    if insn & 0x9F00_0000 != 0x9000_0000 {
+        return None;
+    }
+    let immlo = ((insn >> 29) & 0x3) as u64;
+    let immhi = ((insn >> 5) & 0x7FFFF) as u64;
+    let imm = (((immhi << 2) | immlo) << 43) as i64 >> 31;
+    Some((insn & 0x1F, (pc & !0xFFF).wrapping_add(imm as u64)))
+}
+
+fn decode_stub(cache: &DyldCache<LittleEndian>, addr: u64) -> Option<StubTarget> {
+    let aligned = addr & !3;
+    // The address may point anywhere inside the stub, so try each possible start.
+    for back in 0..4u64 {
+        let start = aligned.checked_sub(back * 4)?;
+        let Some((reg, page)) = read_u32(cache, start).and_then(|i| adrp_page(i, start)) else {
+            continue;
+        };
+        let second = read_u32(cache, start + 4)?;
+        let rn = (second >> 5) & 0x1F;
+        let imm12 = ((second >> 10) & 0xFFF) as u64;
+        if rn != reg {
+            continue;
+        }
+        if second & 0xFFC0_0000 == 0x9100_0000 {
+            return Some(StubTarget::Direct(page + imm12));
+        }
+        if second & 0xFFC0_0000 == 0xF940_0000 {
+            return Some(StubTarget::Got(page + imm12 * 8));
+        }
+    }
+    None
+}
@@ -0,0 +1,198 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::kernel;
+
+const CACHE_PREFIX: &str = "dyld_shared_cache_";
+
+/// Separate caches shipped by the OS, each for its own runtime.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    /// The cache of the OS itself.
+    System,
+    /// Dylibs for DriverKit drivers, with install paths under `/System/DriverKit`.
+    DriverKit,
+    /// Dylibs for ExclaveKit, the userspace of secure exclaves.
+    ExclaveKit,
+}
+
+impl Family {
+    pub fn name(self) -> &'static str {
+        match self {
+            Family::System => "system",
+            Family::DriverKit => "driverkit",
+            Family::ExclaveKit => "exclavekit",
+        }
+    }
+}
+
+/// Directories the OS keeps its caches in, newest layout first within each family. macOS
+/// moved its cache into the OS cryptex in macOS 13, iOS in iOS 16.
+const LOCATIONS: &[(Family, &str)] = &[
+    (
+        Family::System,
+        "/System/Volumes/Preboot/Cryptexes/OS/System/Library/dyld",
+    ),
+    (Family::System, "/System/Library/dyld"),
+    (
+        Family::System,
+        "/private/preboot/Cryptexes/OS/System/Library/Caches/com.apple.dyld",
+    ),
+    (Family::System, "/System/Library/Caches/com.apple.dyld"),
+    (Family::DriverKit, "/System/DriverKit/System/Library/dyld"),
+    (Family::ExclaveKit, "/System/ExclaveKit/System/Library/dyld"),
+    (
+        Family::ExclaveKit,
+        "/System/ExclaveKit/System/Library/Caches/com.apple.dyld",
+    ),
+];
+
+/// Standard cache directories that exist on this host and contain a main cache file.
+pub fn discover() -> Vec<(Family, PathBuf)> {
+    LOCATIONS
+        .iter()
+        .filter(|(_, dir)| !main_caches(Path::new(dir)).is_empty())
+        .map(|&(family, dir)| (family, PathBuf::from(dir)))
+        .collect()
+}
+
+/// Main cache files of a directory with their architecture, sorted by path. Subcaches and
+/// symbol files have an extension.
+pub fn main_caches(dir: &Path) -> Vec<(PathBuf, String)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut caches: Vec<_> = entries
+        .filter_map(|entry| {
+            let file = entry.ok()?.path();
+            let name = file.file_name()?.to_str()?;
+            if !name.starts_with(CACHE_PREFIX) || name.contains('.') {
+                return None;
+            }
+            let arch = cache_arch(&file)?;
+            Some((file, arch))
+        })
+        .collect();
+    caches.sort();
+    caches
+}
+
+/// A cache file picked for a path given on the command line.
+pub struct Selection {
+    pub path: PathBuf,
+    pub arch: String,
+    /// Why this file was picked, when it was chosen among several in a directory.
+    pub reason: Option<String>,
+    /// The other main cache files of the directory.
+    pub alternatives: Vec<(PathBuf, String)>,
+}
+
+/// Architecture of a cache file from its magic, e.g. `arm64e` or `x86_64h`.
+pub fn cache_arch(path: &Path) -> Option<String> {
+    let mut magic = [0u8; 16];
+    File::open(path).ok()?.read_exact(&mut magic).ok()?;
+    let magic = std::str::from_utf8(&magic).ok()?.trim_end_matches('\0');
+    let arch = magic.strip_prefix("dyld_v")?.split_whitespace().nth(1)?;
+    Some(arch.to_string())
+}
+
+/// Cache architectures the host can run, best first.
+fn host_preference() -> &'static [&'static str] {
+    match std::env::consts::ARCH {
+        "aarch64" => &["arm64e", "arm64"],
+        "x86_64" => &["x86_64h", "x86_64"],
+        _ => &[],
+    }
+}
+
+/// Resolve `path` to a main cache file. A directory is searched for main cache files; `arch`
+/// picks one of them, otherwise the best one for the host is used. For a file, `arch` must
+/// match the file. `@system`, `@driverkit` and `@exclavekit` stand for the first standard
+/// directory of that family found on this host.
+pub fn select(path: &str, arch: Option<&str>) -> Result<Selection, Box<dyn Error>> {
+    let discovered;
+    let path = match path.strip_prefix('@') {
+        Some(family) => {
+            discovered = discover()
+                .into_iter()
+                .find(|(f, _)| f.name() == family)
+                .map(|(_, dir)| dir)
+                .ok_or_else(|| format!("No {} cache found on this host", family))?;
+            discovered.as_path()
+        }
+        None => Path::new(path),
+    };
+    if !path.is_dir() {
+        if let Some(kind) = kernel::identify_file(path) {
+            return Err(format!("{} {}", path.display(), kind.advice()).into());
+        }
+        let found = cache_arch(path);
+        if let Some(arch) = arch {
+            match &found {
+                None => {
+                    return Err(format!(
+                        "{} has an unrecognized cache magic, expected a cache for {}",
+                        path.display(),
+                        arch
+                    )
+                    .into());
+                }
+                Some(found) if found != arch => {
+                    return Err(format!(
+                        "{} is a cache for {}, not {}",
+                        path.display(),
+                        found,
+                        arch
+                    )
+                    .into());
+                }
+                Some(_) => {}
+            }
+        }
+        return Ok(Selection {
+            path: path.to_path_buf(),
+            arch: found.unwrap_or_default(),
+            reason: None,
+            alternatives: Vec::new(),
+        });
+    }
+
+    let mut candidates = main_caches(path);
+    if candidates.is_empty() {
+        return Err(format!("No dyld shared cache found in {}", path.display()).into());
+    }
+
+    let (pos, reason) = match arch {
+        Some(arch) => {
+            let pos = candidates.iter().position(|c| c.1 == arch).ok_or_else(|| {
+                let available: Vec<_> = candidates.iter().map(|c| c.1.as_str()).collect();
+                format!(
+                    "No {} cache in {} (found {})",
+                    arch,
+                    path.display(),
+                    available.join(", ")
+                )
+            })?;
+            (pos, format!("requested with --arch {}", arch))
+        }
+        None => host_preference()
+            .iter()
+            .find_map(|&preferred| candidates.iter().position(|c| c.1 == preferred))
+            .map(|pos| {
+                (
+                    pos,
+                    format!("best match for host {}", std::env::consts::ARCH),
+                )
+            })
+            .unwrap_or((0, "no cache matches the host, using the first".to_string())),
+    };
+    let (path, arch) = candidates.remove(pos);
+    Ok(Selection {
+        path,
+        arch,
+        reason: Some(reason),
+        alternatives: candidates,
+    })
+}
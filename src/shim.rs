@@ -0,0 +1,166 @@
+//! Linker inputs for a shim dylib that stands in for a cache image, e.g. to interpose some of
+//! its functions: the install name and versions to copy, the symbols it exports and the
+//! libraries and symbols it re-exports from other dylibs.
+
+use object::LittleEndian;
+use object::read::macho::{DyldCache, DyldCacheImage, LoadCommandVariant, MachOFile64};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::dylibs::{self, LinkKind, format_version};
+use crate::exports::{self, ExportKind};
+
+/// What a shim needs to look like an image to its clients.
+pub struct Shim {
+    pub install_name: String,
+    pub current_version: u32,
+    pub compatibility_version: u32,
+    /// Dylibs re-exported as a whole (`LC_REEXPORT_DYLIB`).
+    pub reexported_libraries: Vec<String>,
+    /// Symbols the image defines, for `-exported_symbols_list`.
+    pub exported: Vec<String>,
+    /// Symbols re-exported from another dylib under their own name, with that dylib.
+    pub reexported: Vec<(String, String)>,
+    /// Symbols re-exported under another name, as `(name in the dylib, exported name, dylib)`.
+    pub aliases: Vec<(String, String, String)>,
+}
+
+impl Shim {
+    pub fn new(
+        cache: &DyldCache<LittleEndian>,
+        image: &DyldCacheImage<LittleEndian>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let e = LittleEndian;
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+        let mut id = None;
+        let mut commands = file.macho_load_commands()?;
+        while let Some(command) = commands.next()? {
+            if let LoadCommandVariant::IdDylib(dylib) = command.variant()? {
+                id = Some((
+                    String::from_utf8_lossy(command.string(e, dylib.dylib.name)?).into_owned(),
+                    dylib.dylib.current_version.get(e),
+                    dylib.dylib.compatibility_version.get(e),
+                ));
+            }
+        }
+        let (install_name, current_version, compatibility_version) =
+            id.ok_or("Image has no LC_ID_DYLIB")?;
+
+        let linked = dylibs::linked_dylibs(&file)?;
+        // Re-exports name their dylib by library ordinal, 1 for the first linked dylib.
+        let dylib = |ordinal: u64| {
+            (ordinal as usize)
+                .checked_sub(1)
+                .and_then(|i| linked.get(i))
+                .map(|d| d.path.clone())
+                .ok_or_else(|| format!("Re-export from unknown library ordinal {}", ordinal))
+        };
+        let mut shim = Shim {
+            install_name,
+            current_version,
+            compatibility_version,
+            reexported_libraries: linked
+                .iter()
+                .filter(|d| d.kind == LinkKind::Reexport)
+                .map(|d| d.path.clone())
+                .collect(),
+            exported: Vec::new(),
+            reexported: Vec::new(),
+            aliases: Vec::new(),
+        };
+        for entry in exports::image_exports(cache, image)? {
+            match entry.kind {
+                ExportKind::Reexport { ordinal, import } => match import {
+                    Some(import) if import != entry.name => {
+                        shim.aliases.push((import, entry.name, dylib(ordinal)?));
+                    }
+                    _ => shim.reexported.push((entry.name, dylib(ordinal)?)),
+                },
+                _ => shim.exported.push(entry.name),
+            }
+        }
+        shim.exported.sort();
+        shim.reexported.sort();
+        shim.aliases.sort();
+        Ok(shim)
+    }
+
+    /// Dylibs the re-exported and aliased symbols come from, which the shim must link.
+    pub fn symbol_sources(&self) -> Vec<&str> {
+        let mut sources: Vec<&str> = self
+            .reexported
+            .iter()
+            .map(|(_, dylib)| dylib.as_str())
+            .chain(self.aliases.iter().map(|(_, _, dylib)| dylib.as_str()))
+            .filter(|dylib| !self.reexported_libraries.iter().any(|l| l == dylib))
+            .collect();
+        sources.sort();
+        sources.dedup();
+        sources
+    }
+
+    /// Write `exported_symbols.txt`, `reexported_symbols.txt`, `aliases.txt` and `flags.txt`,
+    /// the clang options that use them, to `dir`.
+    pub fn write(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let write = |name: &str, lines: Vec<String>| -> Result<String, Box<dyn Error>> {
+            let path = dir.join(name);
+            let mut text = lines.join("\n");
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            fs::write(&path, text)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            Ok(path.display().to_string())
+        };
+
+        // Aliases are symbols of the shim too, so they must be listed to stay visible.
+        let mut exported = self.exported.clone();
+        exported.extend(self.aliases.iter().map(|(_, name, _)| name.clone()));
+        exported.sort();
+        let exported = write("exported_symbols.txt", exported)?;
+        let reexported = write(
+            "reexported_symbols.txt",
+            self.reexported
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+        )?;
+        let aliases = write(
+            "aliases.txt",
+            self.aliases
+                .iter()
+                .map(|(import, name, _)| format!("{} {}", import, name))
+                .collect(),
+        )?;
+
+        let mut flags = vec![
+            format!("-Wl,-install_name,{}", self.install_name),
+            format!(
+                "-Wl,-current_version,{}",
+                format_version(self.current_version)
+            ),
+            format!(
+                "-Wl,-compatibility_version,{}",
+                format_version(self.compatibility_version)
+            ),
+            format!("-Wl,-exported_symbols_list,{}", exported),
+        ];
+        for library in &self.reexported_libraries {
+            flags.push(format!("-Wl,-reexport_library,{}", library));
+        }
+        for source in self.symbol_sources() {
+            flags.push(source.to_string());
+        }
+        if !self.reexported.is_empty() {
+            flags.push(format!("-Wl,-reexported_symbols_list,{}", reexported));
+        }
+        if !self.aliases.is_empty() {
+            flags.push(format!("-Wl,-alias_list,{}", aliases));
+        }
+        write("flags.txt", flags)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use object::macho::{S_ATTR_PURE_INSTRUCTIONS, S_ATTR_SOME_INSTRUCTIONS};
+use object::read::macho::{DyldCache, DyldCacheImage};
+use object::{LittleEndian, Object, ObjectSection, SectionFlags};
+use std::error::Error;
+
+pub use crate::parsers::pattern::{Scanner, Signature, find_pattern, parse, parse_pattern};
+use crate::vm;
+
+/// Scan the executable sections of an image, returning (signature index, address) pairs.
+pub fn scan_image(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    scanner: &Scanner,
+    align: usize,
+) -> Result<Vec<(usize, u64)>, Box<dyn Error>> {
+    let file = image.parse_object()?;
+    let mut matches = Vec::new();
+    for section in file.sections() {
+        let SectionFlags::MachO { flags } = section.flags() else {
+            continue;
+        };
+        if flags & (S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS) == 0 {
+            continue;
+        }
+        let data = vm::read_bytes(cache, section.address(), section.size())?;
+        matches.extend(
+            scanner
+                .scan(&data, align)
+                .into_iter()
+                .map(|(i, offset)| (i, section.address() + offset as u64)),
+        );
+    }
+    Ok(matches)
+}
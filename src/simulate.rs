@@ -0,0 +1,234 @@
+use object::LittleEndian;
+use object::read::macho::{DyldCache, DyldCacheImage, FatArch, MachOFatFile32, MachOFile64};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::path::Path;
+
+use crate::dylibs::{self, LinkKind, Provider};
+use crate::exports;
+
+/// A dylib dyld would load for the executable.
+pub struct Load {
+    pub path: String,
+    /// Image whose load command asked for it, `None` for the executable.
+    pub parent: Option<String>,
+    pub kind: LinkKind,
+    /// Found in the cache, otherwise on disk (where its own dependencies are not followed).
+    pub in_cache: bool,
+}
+
+/// A dependency that could not be found.
+pub struct Missing {
+    pub path: String,
+    pub parent: Option<String>,
+    pub kind: LinkKind,
+}
+
+/// An import of the executable that the dylib it names (or a dylib that one re-exports) does
+/// not export.
+pub struct MissingSymbol {
+    pub name: String,
+    pub dylib: String,
+    pub weak: bool,
+}
+
+pub struct Simulation {
+    pub loads: Vec<Load>,
+    pub missing: Vec<Missing>,
+    pub missing_symbols: Vec<MissingSymbol>,
+}
+
+/// The slice of a (possibly fat) executable matching the CPU type of the cache.
+pub fn executable_slice<'a>(
+    data: &'a [u8],
+    cache: &DyldCache<LittleEndian>,
+) -> Result<&'a [u8], Box<dyn Error>> {
+    let Ok(fat) = MachOFatFile32::parse(data) else {
+        return Ok(data);
+    };
+    let image = cache.images().next().ok_or("Cache has no images")?;
+    let header = MachOFile64::<LittleEndian>::parse_dyld_cache_image(&image)?.macho_header();
+    let cputype = header.cputype.get(LittleEndian);
+    let subtype = header.cpusubtype.get(LittleEndian) & !object::macho::CPU_SUBTYPE_MASK;
+    let arches = fat.arches();
+    let arch = arches
+        .iter()
+        .find(|a| {
+            a.cputype() == cputype && a.cpusubtype() & !object::macho::CPU_SUBTYPE_MASK == subtype
+        })
+        .or_else(|| arches.iter().find(|a| a.cputype() == cputype))
+        .ok_or("Executable has no slice for the cache architecture")?;
+    Ok(arch.data(data)?)
+}
+
+/// Follow the dependencies of `executable` (located at `exe_path`) through the cache the way
+/// dyld would, breadth first, and check its two-level namespace imports.
+pub fn simulate(
+    cache: &DyldCache<LittleEndian>,
+    executable: &MachOFile64<LittleEndian>,
+    exe_path: &str,
+) -> Result<Simulation, Box<dyn Error>> {
+    let e = LittleEndian;
+    let images: HashMap<&str, DyldCacheImage<LittleEndian>> = cache
+        .images()
+        .filter_map(|image| Some((image.path().ok()?, image)))
+        .collect();
+    let exe_dir = Path::new(exe_path)
+        .parent()
+        .map_or(String::new(), |p| p.display().to_string());
+    let exe_rpaths = dylibs::relationships(executable)?.rpaths;
+
+    let mut simulation = Simulation {
+        loads: Vec::new(),
+        missing: Vec::new(),
+        missing_symbols: Vec::new(),
+    };
+    // Install path each linked dylib of the executable resolved to, by load command index.
+    let mut exe_deps = Vec::new();
+    let mut loaded = HashSet::new();
+    let mut on_disk = HashSet::new();
+    let mut queue = VecDeque::new();
+    for dylib in dylibs::linked_dylibs(executable)? {
+        queue.push_back((dylib.path, dylib.kind, None::<String>, exe_dir.clone()));
+    }
+    // The queue is breadth first, so the executable's own dependencies come out first.
+    let mut from_exe = queue.len();
+
+    while let Some((request, kind, parent, loader_dir)) = queue.pop_front() {
+        let mut rpaths = exe_rpaths.clone();
+        let parent_image = parent.as_deref().and_then(|p| images.get(p));
+        if let Some(image) = parent_image {
+            let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+            rpaths.splice(0..0, dylibs::relationships(&file)?.rpaths);
+        }
+        let candidates = expand(&request, &rpaths, &exe_dir, &loader_dir);
+        let found = candidates.iter().find(|c| images.contains_key(c.as_str()));
+        let resolved = match found {
+            Some(path) => {
+                let image = &images[path.as_str()];
+                // Aliases share a header; load each image once.
+                if loaded.insert(image.info().address.get(e)) {
+                    let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+                    let dir = parent_dir(path);
+                    for dylib in dylibs::linked_dylibs(&file)? {
+                        queue.push_back((dylib.path, dylib.kind, Some(path.clone()), dir.clone()));
+                    }
+                    simulation.loads.push(Load {
+                        path: path.clone(),
+                        parent: parent.clone(),
+                        kind,
+                        in_cache: true,
+                    });
+                }
+                Some(path.clone())
+            }
+            None => match candidates.iter().find(|c| Path::new(c).exists()) {
+                Some(path) => {
+                    if on_disk.insert(path.clone()) {
+                        simulation.loads.push(Load {
+                            path: path.clone(),
+                            parent: parent.clone(),
+                            kind,
+                            in_cache: false,
+                        });
+                    }
+                    None
+                }
+                None => {
+                    simulation.missing.push(Missing {
+                        path: request,
+                        parent,
+                        kind,
+                    });
+                    None
+                }
+            },
+        };
+        if from_exe > 0 {
+            exe_deps.push(resolved);
+            from_exe -= 1;
+        }
+    }
+
+    // Two-level namespace: every import names the dylib expected to export it.
+    let mut exported: HashMap<String, HashSet<String>> = HashMap::new();
+    for import in dylibs::imports(executable) {
+        let Provider::Dylib(i) = import.provider else {
+            continue;
+        };
+        let Some(Some(dylib)) = exe_deps.get(i) else {
+            continue;
+        };
+        if !exports_symbol(cache, &images, &mut exported, dylib, &import.name)? {
+            simulation.missing_symbols.push(MissingSymbol {
+                name: import.name,
+                dylib: dylib.clone(),
+                weak: import.weak,
+            });
+        }
+    }
+    Ok(simulation)
+}
+
+/// Paths a load command path can stand for, in the order dyld tries them.
+pub fn expand(request: &str, rpaths: &[String], exe_dir: &str, loader_dir: &str) -> Vec<String> {
+    let substitute = |path: &str| {
+        if let Some(rest) = path.strip_prefix("@executable_path") {
+            format!("{}{}", exe_dir, rest)
+        } else if let Some(rest) = path.strip_prefix("@loader_path") {
+            format!("{}{}", loader_dir, rest)
+        } else {
+            path.to_string()
+        }
+    };
+    match request.strip_prefix("@rpath") {
+        Some(rest) => rpaths
+            .iter()
+            .map(|rpath| format!("{}{}", substitute(rpath), rest))
+            .collect(),
+        None => vec![substitute(request)],
+    }
+}
+
+pub fn parent_dir(path: &str) -> String {
+    path.rsplit_once('/')
+        .map_or(String::new(), |(dir, _)| dir.to_string())
+}
+
+/// Whether a cached dylib exports `name`, directly or through the dylibs it re-exports.
+fn exports_symbol(
+    cache: &DyldCache<LittleEndian>,
+    images: &HashMap<&str, DyldCacheImage<LittleEndian>>,
+    exported: &mut HashMap<String, HashSet<String>>,
+    dylib: &str,
+    name: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let mut pending = vec![dylib.to_string()];
+    let mut seen = HashSet::new();
+    while let Some(path) = pending.pop() {
+        let Some(image) = images.get(path.as_str()) else {
+            continue;
+        };
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        if !exported.contains_key(&path) {
+            // Re-exported entries in the trie provide the symbol under their own name.
+            let names = exports::image_exports(cache, image)?
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect();
+            exported.insert(path.clone(), names);
+        }
+        if exported[&path].contains(name) {
+            return Ok(true);
+        }
+        let file = MachOFile64::<LittleEndian>::parse_dyld_cache_image(image)?;
+        for dylib in dylibs::linked_dylibs(&file)? {
+            if dylib.kind == LinkKind::Reexport {
+                pending.push(dylib.path);
+            }
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,727 @@
+use clap::ValueEnum;
+use object::macho::{
+    DYLD_CACHE_SLIDE_PAGE_ATTR_END, DYLD_CACHE_SLIDE_PAGE_ATTR_EXTRA,
+    DYLD_CACHE_SLIDE_PAGE_ATTR_NO_REBASE, DYLD_CACHE_SLIDE_V3_PAGE_ATTR_NO_REBASE,
+    DYLD_CACHE_SLIDE_V5_PAGE_ATTR_NO_REBASE, DyldCacheHeader, DyldCacheMappingAndSlideInfo,
+    DyldCacheSlidePointer3, DyldCacheSlidePointer5, PtrauthKey, VM_PROT_WRITE,
+};
+use object::read::macho::{
+    DyldCache, DyldCacheMappingSlice, DyldCacheSlideInfo, DyldRelocation, DyldRelocationAuth,
+};
+use object::{LittleEndian, U64};
+use std::error::Error;
+use std::ops::Range;
+
+/// How pointers stored in a mapping are encoded on disk.
+#[derive(Clone, Copy)]
+enum Format {
+    /// Plain pointers, or slide info this tool does not understand.
+    Raw,
+    V2 {
+        delta_mask: u64,
+        value_add: u64,
+    },
+    V3 {
+        auth_value_add: u64,
+    },
+    V5 {
+        value_add: u64,
+    },
+}
+
+/// Decodes pointers stored in cache data mappings.
+///
+/// On disk, rebased pointers carry slide-info metadata (chain deltas, PAC diversity, high bits)
+/// and some formats store targets as offsets from the cache base, so a raw 8-byte read is not
+/// the address the pointer refers to at runtime.
+pub struct PointerReader<'a> {
+    cache: &'a DyldCache<'a, LittleEndian>,
+    /// (start, end, format) for every mapping described with slide info.
+    mappings: Vec<(u64, u64, Format)>,
+}
+
+impl<'a> PointerReader<'a> {
+    pub fn new(cache: &'a DyldCache<'a, LittleEndian>) -> Self {
+        let e = LittleEndian;
+        let mut mappings = Vec::new();
+        for (data, info, _) in slid_mappings(cache) {
+            let format = match info.slide(e, data) {
+                Ok(DyldCacheSlideInfo::V2 { slide, .. }) => Format::V2 {
+                    delta_mask: slide.delta_mask.get(e),
+                    value_add: slide.value_add.get(e),
+                },
+                Ok(DyldCacheSlideInfo::V3 { slide, .. }) => Format::V3 {
+                    auth_value_add: slide.auth_value_add.get(e),
+                },
+                Ok(DyldCacheSlideInfo::V5 { slide, .. }) => Format::V5 {
+                    value_add: slide.value_add.get(e),
+                },
+                _ => Format::Raw,
+            };
+            let start = info.address.get(e);
+            mappings.push((start, start + info.size.get(e), format));
+        }
+        PointerReader { cache, mappings }
+    }
+
+    pub fn cache(&self) -> &'a DyldCache<'a, LittleEndian> {
+        self.cache
+    }
+
+    /// Read the 8-byte pointer stored at `addr` and decode it to its target address.
+    pub fn read(&self, addr: u64) -> Option<u64> {
+        let (data, offset) = self.cache.data_and_offset_for_address(addr)?;
+        let offset = offset as usize;
+        let raw = u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?);
+        Some(self.decode(addr, raw))
+    }
+
+    /// Decode a raw pointer value that was stored at `addr`.
+    pub fn decode(&self, addr: u64, raw: u64) -> u64 {
+        self.mappings
+            .iter()
+            .find(|&&(start, end, _)| addr >= start && addr < end)
+            .map_or(Format::Raw, |m| m.2)
+            .decode(raw)
+    }
+}
+
+impl Format {
+    /// The target address of a pointer stored as `raw` in a mapping of this format.
+    fn decode(self, raw: u64) -> u64 {
+        if raw == 0 {
+            return 0;
+        }
+        match self {
+            Format::Raw => raw,
+            Format::V2 {
+                delta_mask,
+                value_add,
+            } => {
+                let value = raw & !delta_mask;
+                if value == 0 {
+                    0
+                } else {
+                    value.wrapping_add(value_add)
+                }
+            }
+            Format::V3 { auth_value_add } => {
+                let pointer = DyldCacheSlidePointer3(raw);
+                if pointer.is_auth() {
+                    pointer.runtime_offset().wrapping_add(auth_value_add)
+                } else {
+                    pointer.target()
+                }
+            }
+            Format::V5 { value_add } => DyldCacheSlidePointer5(raw)
+                .runtime_offset()
+                .wrapping_add(value_add),
+        }
+    }
+}
+
+/// Mappings described with slide info in every file of the cache, with the data of their file
+/// and whether it is the data mapping of a legacy cache (see [`legacy_slide_mapping`]).
+fn slid_mappings<'data>(
+    cache: &DyldCache<'data, LittleEndian>,
+) -> Vec<(
+    &'data [u8],
+    DyldCacheMappingAndSlideInfo<LittleEndian>,
+    bool,
+)> {
+    let e = LittleEndian;
+    let mut out = Vec::new();
+    let mut seen = Vec::new();
+    for mapping in cache.mappings() {
+        // Each cache file describes its own mappings; find the file backing this one.
+        let Some((data, _)) = cache.data_and_offset_for_address(mapping.address()) else {
+            continue;
+        };
+        if seen.contains(&data.as_ptr()) {
+            continue;
+        }
+        seen.push(data.as_ptr());
+        let Ok(header) = DyldCacheHeader::<LittleEndian>::parse(data) else {
+            continue;
+        };
+        match header.mappings(e, data) {
+            Ok(DyldCacheMappingSlice::V2(infos)) => {
+                out.extend(infos.iter().map(|info| (data, *info, false)));
+            }
+            Ok(DyldCacheMappingSlice::V1(_)) => {
+                out.extend(legacy_slide_mapping(header, data).map(|info| (data, info, true)));
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The data mapping of a cache file from before dyld-832 (iOS 14 and macOS 11), the only one
+/// with slide info, which the header then describes instead of a `mapping_with_slide` entry.
+/// Caches of arm64e devices from iOS 12 and 13 use the threaded (v3) format in there.
+pub fn legacy_slide_mapping(
+    header: &DyldCacheHeader<LittleEndian>,
+    data: &[u8],
+) -> Option<DyldCacheMappingAndSlideInfo<LittleEndian>> {
+    let e = LittleEndian;
+    let Ok(DyldCacheMappingSlice::V1(infos)) = header.mappings(e, data) else {
+        return None;
+    };
+    let info = infos.get(1)?;
+    (header.slide_info_size_unused.get(e) != 0).then(|| DyldCacheMappingAndSlideInfo {
+        address: info.address,
+        size: info.size,
+        file_offset: info.file_offset,
+        slide_info_file_offset: header.slide_info_offset_unused,
+        slide_info_file_size: header.slide_info_size_unused,
+        flags: U64::new(e, 0),
+        max_prot: info.max_prot,
+        init_prot: info.init_prot,
+    })
+}
+
+/// Walk the pointer chains of the pages of a legacy data mapping overlapping `range`, offsets
+/// in the mapping. `object` only walks the chains of newer caches.
+fn legacy_relocations(
+    data: &[u8],
+    info: &DyldCacheMappingAndSlideInfo<LittleEndian>,
+    range: Range<u64>,
+) -> Result<Vec<DyldRelocation>, Box<dyn Error>> {
+    let e = LittleEndian;
+    let mapping = info.file_offset.get(e);
+    let read = |offset: u64| -> Result<u64, Box<dyn Error>> {
+        let start = mapping
+            .checked_add(offset)
+            .filter(|_| offset + 8 <= info.size.get(e))
+            .ok_or("Slide chain leaves its mapping")? as usize;
+        let bytes = data
+            .get(start..start + 8)
+            .ok_or("Slide chain leaves the cache file")?;
+        Ok(u64::from_le_bytes(bytes.try_into()?))
+    };
+    // Chains do not cross pages, so only the pages overlapping `range` are walked.
+    let overlaps = |page: u64, size: u64| page < range.end && range.start < page + size;
+    let mut relocations = Vec::new();
+    match info.slide(e, data)? {
+        DyldCacheSlideInfo::V2 {
+            slide,
+            page_starts,
+            page_extras,
+        } => {
+            let page_size = slide.page_size.get(e) as u64;
+            let delta_mask = slide.delta_mask.get(e);
+            // Deltas count 4-byte units.
+            let delta_shift = delta_mask.trailing_zeros().saturating_sub(2);
+            let value_add = slide.value_add.get(e);
+            let mut chain = |page: u64, mut offset: u64| -> Result<(), Box<dyn Error>> {
+                loop {
+                    if offset >= page + page_size {
+                        return Err("Slide chain leaves its page".into());
+                    }
+                    let raw = read(offset)?;
+                    let mut value = raw & !delta_mask;
+                    // A corrupt value_add wraps around, as it would in the runtime.
+                    if value != 0 {
+                        value = value.wrapping_add(value_add);
+                    }
+                    relocations.push(DyldRelocation {
+                        offset,
+                        value,
+                        auth: None,
+                    });
+                    let delta = (raw & delta_mask) >> delta_shift;
+                    if delta == 0 {
+                        return Ok(());
+                    }
+                    offset += delta;
+                }
+            };
+            for (i, start) in page_starts.iter().enumerate() {
+                let page = i as u64 * page_size;
+                let start = start.get(e);
+                if !overlaps(page, page_size) || start == DYLD_CACHE_SLIDE_PAGE_ATTR_NO_REBASE {
+                    continue;
+                }
+                if start & DYLD_CACHE_SLIDE_PAGE_ATTR_EXTRA == 0 {
+                    chain(page, page + start as u64 * 4)?;
+                    continue;
+                }
+                // Pages with several chains list them in the extras.
+                for extra in page_extras.iter().skip((start & 0x3FFF) as usize) {
+                    let extra = extra.get(e);
+                    chain(page, page + (extra & 0x3FFF) as u64 * 4)?;
+                    if extra & DYLD_CACHE_SLIDE_PAGE_ATTR_END != 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        DyldCacheSlideInfo::V3 { slide, page_starts } => {
+            let page_size = slide.page_size.get(e) as u64;
+            let auth_value_add = slide.auth_value_add.get(e);
+            for (i, start) in page_starts.iter().enumerate() {
+                let page = i as u64 * page_size;
+                let start = start.get(e);
+                if !overlaps(page, page_size) || start == DYLD_CACHE_SLIDE_V3_PAGE_ATTR_NO_REBASE {
+                    continue;
+                }
+                let mut offset = page + start as u64;
+                loop {
+                    if offset >= page + page_size {
+                        return Err("Slide chain leaves its page".into());
+                    }
+                    let pointer = DyldCacheSlidePointer3(read(offset)?);
+                    relocations.push(if pointer.is_auth() {
+                        DyldRelocation {
+                            offset,
+                            value: pointer.runtime_offset().wrapping_add(auth_value_add),
+                            auth: Some(DyldRelocationAuth {
+                                key: match pointer.key() {
+                                    1 => PtrauthKey::IB,
+                                    2 => PtrauthKey::DA,
+                                    3 => PtrauthKey::DB,
+                                    _ => PtrauthKey::IA,
+                                },
+                                diversity: pointer.diversity(),
+                                addr_div: pointer.addr_div(),
+                            }),
+                        }
+                    } else {
+                        DyldRelocation {
+                            offset,
+                            value: pointer.target() | pointer.high8() << 56,
+                            auth: None,
+                        }
+                    });
+                    // Threaded chains link every 8 bytes.
+                    match pointer.next() {
+                        0 => break,
+                        next => offset += next * 8,
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(relocations)
+}
+
+/// How authenticated (arm64e) pointers are written when rebasing extracted data.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthMode {
+    /// Write the target address, unsigned, and list the key and diversity needed to sign it in
+    /// the JSON only.
+    Keep,
+    /// Write the target address and drop the signing information.
+    Strip,
+}
+
+/// Signing information of an authenticated (arm64e) pointer.
+pub struct PointerAuth {
+    pub key: &'static str,
+    pub diversity: u16,
+    pub addr_div: bool,
+}
+
+/// A pointer rewritten by [`rebase`].
+pub struct Rebase {
+    pub address: u64,
+    /// The value as stored in the cache, with slide-info metadata.
+    pub old: u64,
+    /// The address it points to at runtime.
+    pub new: u64,
+    pub auth: Option<PointerAuth>,
+}
+
+/// Rewrite the slide-info encoded pointers in `buf`, a copy of the memory at `base`, to the
+/// addresses they point to at runtime. Authenticated pointers are returned with the key and
+/// diversity needed to sign them again.
+pub fn rebase(
+    cache: &DyldCache<LittleEndian>,
+    base: u64,
+    buf: &mut [u8],
+) -> Result<Vec<Rebase>, Box<dyn Error>> {
+    let end = base + buf.len() as u64;
+    let mut rebases = Vec::new();
+    let mut apply = |start: u64, relocation: DyldRelocation| -> Result<(), Box<dyn Error>> {
+        let address = start + relocation.offset;
+        if address < base || address + 8 > end {
+            return Ok(());
+        }
+        let offset = (address - base) as usize;
+        let slot = &mut buf[offset..offset + 8];
+        let old = u64::from_le_bytes((&*slot).try_into()?);
+        slot.copy_from_slice(&relocation.value.to_le_bytes());
+        rebases.push(Rebase {
+            address,
+            old,
+            new: relocation.value,
+            auth: relocation.auth.map(|auth| PointerAuth {
+                key: match auth.key {
+                    PtrauthKey::IA => "ia",
+                    PtrauthKey::IB => "ib",
+                    PtrauthKey::DA => "da",
+                    PtrauthKey::DB => "db",
+                },
+                diversity: auth.diversity,
+                addr_div: auth.addr_div,
+            }),
+        });
+        Ok(())
+    };
+    for mapping in cache.mappings() {
+        let start = mapping.address();
+        if start >= end || start + mapping.size() <= base {
+            continue;
+        }
+        for relocation in mapping.relocations()? {
+            apply(start, relocation?)?;
+        }
+    }
+    for (data, info, legacy) in slid_mappings(cache) {
+        let start = info.address.get(LittleEndian);
+        if !legacy || start >= end || start + info.size.get(LittleEndian) <= base {
+            continue;
+        }
+        let range = base.saturating_sub(start)..end - start;
+        for relocation in legacy_relocations(data, &info, range)? {
+            apply(start, relocation)?;
+        }
+    }
+    Ok(rebases)
+}
+
+/// Addresses of the pointers in the data mappings of the cache that point to `target`.
+/// Pointers described by slide info are decoded first, and the top-byte tag of the result is
+/// ignored, as are the signing bits of authenticated pointers. Data mappings without slide info
+/// hold plain pointers, compared as stored at every 8-byte aligned address.
+pub fn references(
+    cache: &DyldCache<LittleEndian>,
+    target: u64,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    let e = LittleEndian;
+    const TAG_MASK: u64 = 0xFF << 56;
+    let slid = slid_mappings(cache);
+    let mut found = Vec::new();
+    for mapping in cache.mappings() {
+        let start = mapping.address();
+        if mapping.max_prot() & VM_PROT_WRITE == 0 {
+            continue;
+        }
+        let described = slid.iter().any(|(_, info, _)| {
+            info.address.get(e) == start && info.slide_info_file_size.get(e) != 0
+        });
+        if described {
+            for relocation in mapping.relocations()? {
+                let relocation = relocation?;
+                if relocation.value & !TAG_MASK == target {
+                    found.push(start + relocation.offset);
+                }
+            }
+            continue;
+        }
+        let data = mapping.data()?;
+        let data = &data[..std::cmp::min(data.len(), mapping.size() as usize)];
+        for (i, slot) in data.chunks_exact(8).enumerate() {
+            if u64::from_le_bytes(slot.try_into()?) & !TAG_MASK == target {
+                found.push(start + i as u64 * 8);
+            }
+        }
+    }
+    // object only walks the chains of newer caches.
+    for (data, info, legacy) in &slid {
+        if !legacy {
+            continue;
+        }
+        let start = info.address.get(e);
+        for relocation in legacy_relocations(data, info, 0..info.size.get(e))? {
+            if relocation.value & !TAG_MASK == target {
+                found.push(start + relocation.offset);
+            }
+        }
+    }
+    found.sort_unstable();
+    found.dedup();
+    Ok(found)
+}
+
+/// The pages of a mapping described by slide info, and which of them hold pointers to rebase.
+pub struct SlideCoverage {
+    pub address: u64,
+    pub size: u64,
+    /// Slide info format: 2 for arm64 and x86_64, 3 for arm64e, 5 for arm64e since iOS 18 and
+    /// macOS 15 (1 and 4 are older or 32-bit formats).
+    pub version: u32,
+    pub page_size: u64,
+    /// Whether each page holds pointers to rebase; empty for formats this tool does not decode.
+    pub rebased: Vec<bool>,
+}
+
+impl SlideCoverage {
+    /// Among the pages overlapping `[start, end)`, how many hold pointers to rebase and how many
+    /// there are, or `None` when the pages are not decoded or the range is outside the mapping.
+    pub fn rebased_pages(&self, start: u64, end: u64) -> Option<(usize, usize)> {
+        let start = start.max(self.address);
+        let end = end.min(self.address + self.size);
+        if self.rebased.is_empty() || start >= end || self.page_size == 0 {
+            return None;
+        }
+        let first = ((start - self.address) / self.page_size) as usize;
+        let last = ((end - 1 - self.address) / self.page_size) as usize;
+        let pages = self.rebased.get(first..=last.min(self.rebased.len() - 1))?;
+        Some((pages.iter().filter(|&&r| r).count(), last - first + 1))
+    }
+}
+
+/// Slide info of every mapping that has some, in every file of the cache.
+pub fn coverage(cache: &DyldCache<LittleEndian>) -> Vec<SlideCoverage> {
+    let e = LittleEndian;
+    let mut out = Vec::new();
+    for (data, info, _) in slid_mappings(cache) {
+        let offset = info.slide_info_file_offset.get(e) as usize;
+        if info.slide_info_file_size.get(e) == 0 {
+            continue;
+        }
+        let Some(version) = data
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        else {
+            continue;
+        };
+        let (page_size, rebased) = match info.slide(e, data) {
+            Ok(DyldCacheSlideInfo::V2 {
+                slide, page_starts, ..
+            }) => (
+                slide.page_size.get(e) as u64,
+                page_starts
+                    .iter()
+                    .map(|s| s.get(e) != DYLD_CACHE_SLIDE_PAGE_ATTR_NO_REBASE)
+                    .collect(),
+            ),
+            Ok(DyldCacheSlideInfo::V3 { slide, page_starts }) => (
+                slide.page_size.get(e) as u64,
+                page_starts
+                    .iter()
+                    .map(|s| s.get(e) != DYLD_CACHE_SLIDE_V3_PAGE_ATTR_NO_REBASE)
+                    .collect(),
+            ),
+            Ok(DyldCacheSlideInfo::V5 { slide, page_starts }) => (
+                slide.page_size.get(e) as u64,
+                page_starts
+                    .iter()
+                    .map(|s| s.get(e) != DYLD_CACHE_SLIDE_V5_PAGE_ATTR_NO_REBASE)
+                    .collect(),
+            ),
+            _ => (0, Vec::new()),
+        };
+        out.push(SlideCoverage {
+            address: info.address.get(e),
+            size: info.size.get(e),
+            version,
+            page_size,
+            rebased,
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::U32;
+
+    const PAGE: u64 = 0x1000;
+    /// Where the mappings of the tests load, the usual arm64 shared region start.
+    const BASE: u64 = 0x1_8000_0000;
+    /// `delta_mask` of arm64 caches.
+    const V2_DELTA_MASK: u64 = 0x00FF_FF00_0000_0000;
+
+    /// A cache file holding `mapping`, followed by the slide info `slide`.
+    fn file(mapping: &[u8], slide: &[u8]) -> (Vec<u8>, DyldCacheMappingAndSlideInfo<LittleEndian>) {
+        let e = LittleEndian;
+        let info = DyldCacheMappingAndSlideInfo {
+            address: U64::new(e, BASE),
+            size: U64::new(e, mapping.len() as u64),
+            file_offset: U64::new(e, 0),
+            slide_info_file_offset: U64::new(e, mapping.len() as u64),
+            slide_info_file_size: U64::new(e, slide.len() as u64),
+            flags: U64::new(e, 0),
+            max_prot: U32::new(e, VM_PROT_WRITE),
+            init_prot: U32::new(e, VM_PROT_WRITE),
+        };
+        ([mapping, slide].concat(), info)
+    }
+
+    fn put(mapping: &mut [u8], offset: u64, value: u64) {
+        let offset = offset as usize;
+        mapping[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// `dyld_cache_slide_info2` for 4 KiB pages with the arm64 delta mask, followed by the
+    /// page starts and extras.
+    fn slide_v2(value_add: u64, starts: &[u16], extras: &[u16]) -> Vec<u8> {
+        let mut slide = Vec::new();
+        for field in [2, PAGE as u32, 40, starts.len() as u32] {
+            slide.extend_from_slice(&field.to_le_bytes());
+        }
+        let extras_offset = 40 + 2 * starts.len() as u32;
+        slide.extend_from_slice(&extras_offset.to_le_bytes());
+        slide.extend_from_slice(&(extras.len() as u32).to_le_bytes());
+        slide.extend_from_slice(&V2_DELTA_MASK.to_le_bytes());
+        slide.extend_from_slice(&value_add.to_le_bytes());
+        for value in starts.iter().chain(extras) {
+            slide.extend_from_slice(&value.to_le_bytes());
+        }
+        slide
+    }
+
+    /// A v2 pointer to `value`, followed by the next one `delta` bytes further.
+    fn pointer_v2(value: u64, delta: u64) -> u64 {
+        value | (delta / 4) << 40
+    }
+
+    /// `dyld_cache_slide_info3` or `5` for 4 KiB pages, followed by the page starts.
+    fn slide_v3_v5(version: u32, value_add: u64, starts: &[u16]) -> Vec<u8> {
+        let mut slide = Vec::new();
+        for field in [version, PAGE as u32, starts.len() as u32, 0] {
+            slide.extend_from_slice(&field.to_le_bytes());
+        }
+        slide.extend_from_slice(&value_add.to_le_bytes());
+        for value in starts {
+            slide.extend_from_slice(&value.to_le_bytes());
+        }
+        slide
+    }
+
+    /// Offset, value and signing information of a relocation.
+    type Summary = (u64, u64, Option<(PtrauthKey, u16, bool)>);
+
+    fn summary(relocations: &[DyldRelocation]) -> Vec<Summary> {
+        relocations
+            .iter()
+            .map(|r| {
+                let auth = r.auth.as_ref().map(|a| (a.key, a.diversity, a.addr_div));
+                (r.offset, r.value, auth)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn v2_chains_and_extras() {
+        let mut mapping = vec![0u8; 3 * PAGE as usize];
+        // Page 0: one chain from 0x10, through a null pointer.
+        put(&mut mapping, 0x10, pointer_v2(0x4000, 8));
+        put(&mut mapping, 0x18, pointer_v2(0, 8));
+        put(&mut mapping, 0x20, pointer_v2(0x4010, 0));
+        // Page 1: two chains listed in the extras.
+        put(&mut mapping, 0x1020, pointer_v2(0x4020, 0));
+        put(&mut mapping, 0x1040, pointer_v2(0x4030, 0x10));
+        put(&mut mapping, 0x1050, pointer_v2(0x4040, 0));
+        // Page 2 is not rebased: its pointer-looking value is left alone.
+        put(&mut mapping, 0x2000, pointer_v2(0x4050, 0));
+        let starts = [
+            0x10 / 4,
+            DYLD_CACHE_SLIDE_PAGE_ATTR_EXTRA,
+            DYLD_CACHE_SLIDE_PAGE_ATTR_NO_REBASE,
+        ];
+        let extras = [0x20 / 4, DYLD_CACHE_SLIDE_PAGE_ATTR_END | (0x40 / 4)];
+        let (data, info) = file(&mapping, &slide_v2(BASE, &starts, &extras));
+
+        let relocations = legacy_relocations(&data, &info, 0..3 * PAGE).unwrap();
+        assert_eq!(
+            summary(&relocations),
+            [
+                (0x10, BASE + 0x4000, None),
+                (0x18, 0, None),
+                (0x20, BASE + 0x4010, None),
+                (0x1020, BASE + 0x4020, None),
+                (0x1040, BASE + 0x4030, None),
+                (0x1050, BASE + 0x4040, None),
+            ]
+        );
+        // Only the pages overlapping the range are walked.
+        let relocations = legacy_relocations(&data, &info, 0x1800..0x1808).unwrap();
+        assert_eq!(relocations.len(), 3);
+        assert_eq!(relocations[0].offset, 0x1020);
+    }
+
+    #[test]
+    fn v3_threaded_chains() {
+        let mut mapping = vec![0u8; 2 * PAGE as usize];
+        // A plain pointer with a top byte, 2 slots before the next.
+        let plain = (BASE + 0x4000) | 0x12 << 43 | 2 << 51;
+        put(&mut mapping, 0x8, plain);
+        // An authenticated pointer, by offset from the cache base: DA key, diversity 0x1234,
+        // address diversity, end of the chain.
+        let auth = 1 << 63 | 2 << 49 | 1 << 48 | 0x1234 << 32 | 0x4010;
+        put(&mut mapping, 0x18, auth);
+        put(&mut mapping, 0x1000, plain);
+        let starts = [0x8, DYLD_CACHE_SLIDE_V3_PAGE_ATTR_NO_REBASE];
+        let (data, info) = file(&mapping, &slide_v3_v5(3, BASE, &starts));
+
+        let relocations = legacy_relocations(&data, &info, 0..2 * PAGE).unwrap();
+        assert_eq!(
+            summary(&relocations),
+            [
+                (0x8, (BASE + 0x4000) | (0x12 << 56), None),
+                (0x18, BASE + 0x4010, Some((PtrauthKey::DA, 0x1234, true))),
+            ]
+        );
+    }
+
+    #[test]
+    fn chains_leaving_their_page_or_mapping() {
+        let mut mapping = vec![0u8; 2 * PAGE as usize];
+        put(&mut mapping, 0xFF8, pointer_v2(0x4000, 8));
+        let (data, info) = file(&mapping, &slide_v2(0, &[0xFF8 / 4, 0x4000], &[]));
+        let error = legacy_relocations(&data, &info, 0..PAGE).unwrap_err();
+        assert_eq!(error.to_string(), "Slide chain leaves its page");
+
+        put(&mut mapping, 0xFF8, BASE | 1 << 51);
+        let starts = [0xFF8, DYLD_CACHE_SLIDE_V3_PAGE_ATTR_NO_REBASE];
+        let (data, info) = file(&mapping, &slide_v3_v5(3, 0, &starts));
+        let error = legacy_relocations(&data, &info, 0..PAGE).unwrap_err();
+        assert_eq!(error.to_string(), "Slide chain leaves its page");
+
+        // A mapping ending inside its last page.
+        put(&mut mapping, 0x7F8, BASE | 1 << 51);
+        let starts = [0x7F8];
+        let (data, info) = file(&mapping[..0x800], &slide_v3_v5(3, 0, &starts));
+        let error = legacy_relocations(&data, &info, 0..PAGE).unwrap_err();
+        assert_eq!(error.to_string(), "Slide chain leaves its mapping");
+    }
+
+    #[test]
+    fn decoding_pointers() {
+        let v2 = Format::V2 {
+            delta_mask: V2_DELTA_MASK,
+            value_add: BASE,
+        };
+        assert_eq!(v2.decode(pointer_v2(0x4000, 8)), BASE + 0x4000);
+        assert_eq!(v2.decode(pointer_v2(0, 8)), 0);
+
+        let v3 = Format::V3 {
+            auth_value_add: BASE,
+        };
+        assert_eq!(
+            v3.decode((BASE + 0x4000) | 0x12 << 43 | 2 << 51),
+            BASE + 0x4000
+        );
+        assert_eq!(
+            v3.decode(1 << 63 | 2 << 49 | 0x1234 << 32 | 0x4010),
+            BASE + 0x4010
+        );
+
+        // v5 stores offsets from the cache base, plain or authenticated.
+        let v5 = Format::V5 { value_add: BASE };
+        assert_eq!(v5.decode(0x12 << 34 | 3 << 52 | 0x4000), BASE + 0x4000);
+        let auth = 1 << 63 | 1 << 51 | 1 << 50 | 0x1234 << 34 | 0x4010;
+        assert_eq!(v5.decode(auth), BASE + 0x4010);
+
+        assert_eq!(Format::Raw.decode(BASE + 0x4000), BASE + 0x4000);
+        for format in [v2, v3, v5, Format::Raw] {
+            assert_eq!(format.decode(0), 0);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use object::macho::{S_CSTRING_LITERALS, SECTION_TYPE};
+use object::read::macho::{DyldCache, DyldCacheImage};
+use object::{LittleEndian, Object, ObjectSection, SectionFlags};
+use std::error::Error;
+
+use crate::vm;
+
+/// A string found in a C-string literal section.
+pub struct CString {
+    pub address: u64,
+    /// Section name, e.g. `__cstring`, `__oslogstring` or `__objc_methname`.
+    pub section: String,
+    pub value: String,
+}
+
+/// Extract the strings of every C-string literal section of an image.
+pub fn image_strings(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+) -> Result<Vec<CString>, Box<dyn Error>> {
+    scan_strings(cache, image, false)
+}
+
+/// Extract the NUL-terminated strings of an image's C-string literal sections or, with
+/// `all_sections`, the runs of bytes between NULs in every section with contents, as
+/// `strings(1)` would find them. Most runs outside string sections are not text; see
+/// `printable_ratio` to tell them apart.
+pub fn scan_strings(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    all_sections: bool,
+) -> Result<Vec<CString>, Box<dyn Error>> {
+    let file = image.parse_object()?;
+    let mut strings = Vec::new();
+    for section in file.sections() {
+        let SectionFlags::MachO { flags } = section.flags() else {
+            continue;
+        };
+        if all_sections {
+            if section.kind().is_bss() {
+                continue;
+            }
+        } else if flags & SECTION_TYPE != S_CSTRING_LITERALS {
+            continue;
+        }
+        let name = section.name().unwrap_or("").to_string();
+        let Ok(data) = vm::read_bytes(cache, section.address(), section.size()) else {
+            continue;
+        };
+        let mut address = section.address();
+        for chunk in data.split(|&b| b == 0) {
+            if !chunk.is_empty() {
+                strings.push(CString {
+                    address,
+                    section: name.clone(),
+                    value: String::from_utf8_lossy(chunk).into_owned(),
+                });
+            }
+            address += chunk.len() as u64 + 1;
+        }
+    }
+    Ok(strings)
+}
+
+/// Fraction of the characters of `s` that are printable: neither control characters other
+/// than tabs and line breaks, nor replacements for invalid UTF-8.
+pub fn printable_ratio(s: &str) -> f64 {
+    let (mut printable, mut total) = (0usize, 0usize);
+    for c in s.chars() {
+        total += 1;
+        if matches!(c, '\t' | '\n' | '\r') || !(c.is_control() || c == char::REPLACEMENT_CHARACTER)
+        {
+            printable += 1;
+        }
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    printable as f64 / total as f64
+}
+
+/// Find the strings of the C-string literal sections of an image equal to `needle`, or
+/// containing it with `substring`. Section data is searched directly, without splitting it into
+/// strings first; a string containing `needle` several times is reported once.
+pub fn find_strings(
+    cache: &DyldCache<LittleEndian>,
+    image: &DyldCacheImage<LittleEndian>,
+    needle: &str,
+    substring: bool,
+) -> Result<Vec<CString>, Box<dyn Error>> {
+    let file = image.parse_object()?;
+    let needle = needle.as_bytes();
+    let mut found = Vec::new();
+    if needle.is_empty() {
+        return Ok(found);
+    }
+    for section in file.sections() {
+        let SectionFlags::MachO { flags } = section.flags() else {
+            continue;
+        };
+        if flags & SECTION_TYPE != S_CSTRING_LITERALS {
+            continue;
+        }
+        let Ok(data) = vm::read_bytes(cache, section.address(), section.size()) else {
+            continue;
+        };
+        let name = section.name().unwrap_or("");
+        let mut from = 0;
+        while let Some(pos) = data[from..]
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|p| from + p)
+        {
+            let start = data[..pos]
+                .iter()
+                .rposition(|&b| b == 0)
+                .map_or(0, |nul| nul + 1);
+            let end = data[pos..]
+                .iter()
+                .position(|&b| b == 0)
+                .map_or(data.len(), |nul| pos + nul);
+            if substring || (start == pos && end == pos + needle.len()) {
+                found.push(CString {
+                    address: section.address() + start as u64,
+                    section: name.to_string(),
+                    value: String::from_utf8_lossy(&data[start..end]).into_owned(),
+                });
+            }
+            // Carry on after this string.
+            from = end + 1;
+            if from >= data.len() {
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Offsets of every occurrence of `needle` in `data`, in ascending order. Occurrences may
+/// overlap.
+pub fn find_bytes(data: &[u8], needle: &[u8]) -> Vec<usize> {
+    let mut found = Vec::new();
+    let Some((&first, rest)) = needle.split_first() else {
+        return found;
+    };
+    let Some(last) = data.len().checked_sub(needle.len()) else {
+        return found;
+    };
+    let mut pos = 0;
+    while pos <= last {
+        // Skip ahead to the next candidate first byte before comparing the rest.
+        let Some(skip) = data[pos..=last].iter().position(|&b| b == first) else {
+            break;
+        };
+        pos += skip;
+        if &data[pos + 1..pos + needle.len()] == rest {
+            found.push(pos);
+        }
+        pos += 1;
+    }
+    found
+}
+
+/// Escape control characters so that each string prints on a single line.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,186 @@
+//! Symbol patterns loaded from a file to leave noisy symbols (Swift reflection thunks, block
+//! helpers, ...) out of listings and diffs, or to keep only the interesting ones.
+
+use std::collections::HashSet;
+use std::error::Error;
+
+use crate::utils::wildcard_match;
+
+/// Shell-style patterns (see `wildcard_match`), split by shape so that large symbol sets are
+/// checked quickly: exact names and `prefix*` or `*suffix` patterns are hash lookups, and only
+/// the rest are matched one by one.
+#[derive(Default)]
+struct Patterns {
+    exact: HashSet<String>,
+    prefixes: HashSet<String>,
+    suffixes: HashSet<String>,
+    /// Distinct lengths of `prefixes` and `suffixes`, the only ones worth looking up.
+    prefix_lengths: Vec<usize>,
+    suffix_lengths: Vec<usize>,
+    other: Vec<String>,
+}
+
+impl Patterns {
+    fn add(&mut self, pattern: &str) {
+        let wild = |s: &str| s.contains(['*', '?']);
+        if !wild(pattern) {
+            self.exact.insert(pattern.to_string());
+        } else if let Some(prefix) = pattern.strip_suffix('*').filter(|p| !wild(p)) {
+            self.prefixes.insert(prefix.to_string());
+            if !self.prefix_lengths.contains(&prefix.len()) {
+                self.prefix_lengths.push(prefix.len());
+            }
+        } else if let Some(suffix) = pattern.strip_prefix('*').filter(|s| !wild(s)) {
+            self.suffixes.insert(suffix.to_string());
+            if !self.suffix_lengths.contains(&suffix.len()) {
+                self.suffix_lengths.push(suffix.len());
+            }
+        } else {
+            self.other.push(pattern.to_string());
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.exact.is_empty()
+            && self.prefixes.is_empty()
+            && self.suffixes.is_empty()
+            && self.other.is_empty()
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.exact.contains(name)
+            || self.prefix_lengths.iter().any(|&len| {
+                name.get(..len)
+                    .is_some_and(|prefix| self.prefixes.contains(prefix))
+            })
+            || self.suffix_lengths.iter().any(|&len| {
+                name.len()
+                    .checked_sub(len)
+                    .and_then(|start| name.get(start..))
+                    .is_some_and(|suffix| self.suffixes.contains(suffix))
+            })
+            || self.other.iter().any(|p| wildcard_match(p, name))
+    }
+}
+
+/// Which symbols to show. The default keeps every symbol.
+#[derive(Default)]
+pub struct SymbolFilter {
+    exclude: Patterns,
+    include: Patterns,
+}
+
+impl SymbolFilter {
+    /// Parse a patterns file: one shell-style pattern per line excluding the symbols it
+    /// matches, or, prefixed with `+`, including them. When any pattern includes symbols, only
+    /// those are kept, minus the excluded ones. `#` starts a comment line:
+    ///
+    /// ```text
+    /// # Swift reabstraction thunks and type metadata accessors
+    /// _$s*TR
+    /// _$s*Ma
+    /// # Block helpers
+    /// ___*_block_invoke*
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut filter = SymbolFilter::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.strip_prefix('+') {
+                Some(pattern) => filter.include.add(pattern.trim_start()),
+                None => filter.exclude.add(line),
+            }
+        }
+        filter
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Ok(Self::parse(&text))
+    }
+
+    /// Whether `name` should be shown.
+    pub fn keeps(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.matches(name)) && !self.exclude.matches(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Patterns {
+        let mut patterns = Patterns::default();
+        for pattern in list {
+            patterns.add(pattern);
+        }
+        patterns
+    }
+
+    #[test]
+    fn pattern_shapes() {
+        let p = patterns(&["_exact", "_objc_*", "*_block_invoke", "_$s*Ma", "__?"]);
+        assert!(p.exact.contains("_exact"));
+        assert!(p.prefixes.contains("_objc_"));
+        assert!(p.suffixes.contains("_block_invoke"));
+        assert_eq!(p.other, ["_$s*Ma", "__?"]);
+
+        assert!(p.matches("_exact"));
+        assert!(!p.matches("_exact2"));
+        assert!(!p.matches("exact"));
+        assert!(p.matches("_objc_msgSend"));
+        assert!(p.matches("_objc_"));
+        assert!(!p.matches("_objc"));
+        assert!(p.matches("___main_block_invoke"));
+        assert!(p.matches("_block_invoke"));
+        assert!(!p.matches("___main_block_invoke_2"));
+        assert!(p.matches("_$s3Foo3BarCMa"));
+        assert!(p.matches("__a"));
+        assert!(!p.matches("__ab"));
+    }
+
+    #[test]
+    fn prefixes_and_suffixes_of_several_lengths() {
+        let p = patterns(&["_a*", "_abc*", "*x", "*xyz"]);
+        assert_eq!(p.prefix_lengths, [2, 4]);
+        assert_eq!(p.suffix_lengths, [1, 3]);
+        assert!(p.matches("_abcd"));
+        assert!(p.matches("_ab"));
+        assert!(p.matches("wxyz"));
+        assert!(!p.matches("_b"));
+        // Lengths that fall inside a multibyte character are skipped, not sliced.
+        assert!(!p.matches("\u{e9}"));
+        assert!(p.matches("\u{e9}x"));
+    }
+
+    #[test]
+    fn degenerate_patterns() {
+        // A lone `*` is an empty prefix, and matches everything.
+        let p = patterns(&["*"]);
+        assert!(p.matches("") && p.matches("anything"));
+        // Wildcards on both ends, or doubled, go through the general matcher.
+        let p = patterns(&["*foo*", "**bar"]);
+        assert_eq!(p.other, ["*foo*", "**bar"]);
+        assert!(p.matches("_foo_") && p.matches("bar") && !p.matches("fo"));
+        // Other characters have no special meaning.
+        let p = patterns(&["[ab]", "a\\*"]);
+        assert!(p.matches("[ab]") && !p.matches("a"));
+        assert!(p.matches("a\\") && p.matches("a\\bc") && !p.matches("a*"));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let filter = SymbolFilter::parse("# comment\n\n_$s*TR\n+ _$s*\n+_objc_*\n");
+        assert!(filter.keeps("_$s3FooMa"));
+        assert!(!filter.keeps("_$s3FooTR"));
+        assert!(filter.keeps("_objc_msgSend"));
+        assert!(!filter.keeps("_main"));
+        assert!(!filter.keeps("# comment"));
+        let filter = SymbolFilter::parse("_main");
+        assert!(!filter.keeps("_main") && filter.keeps("_start"));
+        assert!(SymbolFilter::default().keeps("_main"));
+    }
+}
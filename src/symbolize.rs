@@ -0,0 +1,164 @@
+use object::{Object, ObjectSymbol};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use crate::exports::{self, ExportKind};
+use crate::index::CacheIndex;
+use crate::labels::Labels;
+use crate::linkedit::{self, StartsSource};
+
+/// Address-to-name lookup across the cache. Each image's table is built on first use from its
+/// symbol table and export trie, and kept sorted by address.
+pub struct Symbolizer<'i, 'c> {
+    index: &'i CacheIndex<'c>,
+    tables: Vec<OnceLock<Vec<(u64, String)>>>,
+    starts: Vec<OnceLock<(Vec<u64>, StartsSource)>>,
+    labels: Option<&'i Labels>,
+    demangle: bool,
+}
+
+impl<'i, 'c> Symbolizer<'i, 'c> {
+    pub fn new(index: &'i CacheIndex<'c>) -> Self {
+        Symbolizer {
+            index,
+            tables: (0..index.len()).map(|_| OnceLock::new()).collect(),
+            starts: (0..index.len()).map(|_| OnceLock::new()).collect(),
+            labels: None,
+            demangle: false,
+        }
+    }
+
+    pub fn index(&self) -> &'i CacheIndex<'c> {
+        self.index
+    }
+
+    /// Name addresses inside user-defined labels after the innermost label, ahead of symbols.
+    pub fn with_labels(mut self, labels: &'i Labels) -> Self {
+        self.labels = Some(labels);
+        self
+    }
+
+    /// Follow Swift and C++ symbols in names by their demangled form.
+    pub fn with_demangling(mut self, demangle: bool) -> Self {
+        self.demangle = demangle;
+        self
+    }
+
+    pub fn symbols(&self, image: usize) -> &[(u64, String)] {
+        self.tables[image].get_or_init(|| {
+            let cache = self.index.cache();
+            let Some(dyld_image) = cache.images().nth(image) else {
+                return Vec::new();
+            };
+            let mut symbols = Vec::new();
+            if let Ok(file) = self.index.file(image) {
+                for symbol in file.symbols() {
+                    if symbol.is_definition()
+                        && symbol.address() != 0
+                        && let Ok(name) = symbol.name()
+                    {
+                        symbols.push((symbol.address(), name.to_string()));
+                    }
+                }
+            }
+            if let Ok(entries) = exports::image_exports(cache, &dyld_image) {
+                for entry in entries {
+                    let address = match entry.kind {
+                        ExportKind::Regular { address } if !entry.is_absolute() => address,
+                        ExportKind::StubAndResolver { stub, .. } => stub,
+                        _ => continue,
+                    };
+                    symbols.push((address, entry.name));
+                }
+            }
+            symbols.sort();
+            symbols.dedup();
+            symbols
+        })
+    }
+
+    /// Function starts of an image, from `LC_FUNCTION_STARTS` or its unwind info, read on first
+    /// use. Empty when the image has neither or cannot be parsed.
+    pub fn function_starts(&self, image: usize) -> (&[u64], StartsSource) {
+        let (starts, source) = self.starts[image].get_or_init(|| {
+            self.index
+                .file(image)
+                .ok()
+                .and_then(|file| linkedit::function_boundaries(self.index.cache(), &file).ok())
+                .unwrap_or((Vec::new(), StartsSource::FunctionStarts))
+        });
+        (starts, *source)
+    }
+
+    /// The nearest symbol at or before `addr` in the same section, and the offset from it.
+    pub fn lookup(&self, addr: u64) -> Option<(&str, u64)> {
+        if let Some(label) = self.labels.and_then(|labels| labels.at(addr)) {
+            return Some((label.name.as_str(), addr - label.start));
+        }
+        let (index, image) = self.index.image_at(addr)?;
+        let section = image.section_at(addr)?;
+        let symbols = self.symbols(index);
+        let pos = symbols.partition_point(|s| s.0 <= addr);
+        let (start, name) = symbols[..pos].last()?;
+        (*start >= section.start).then(|| (name.as_str(), addr - start))
+    }
+
+    /// Format `addr` as `symbol` or `symbol+0xOFF`.
+    pub fn name(&self, addr: u64) -> Option<String> {
+        self.lookup(addr).map(|(name, offset)| {
+            let name = if self.demangle {
+                with_demangled(name)
+            } else {
+                Cow::Borrowed(name)
+            };
+            if offset == 0 {
+                name.into_owned()
+            } else {
+                format!("{}+0x{:X}", name, offset)
+            }
+        })
+    }
+
+    /// Find a symbol by name, optionally only in the image matching `module` (install path or
+    /// file name). Returns the image index and address.
+    pub fn find(&self, name: &str, module: Option<&str>) -> Option<(usize, u64)> {
+        (0..self.index.len())
+            .filter(|&i| {
+                module.is_none_or(|m| {
+                    let image = self.index.image(i);
+                    image.path == m || image.name() == m
+                })
+            })
+            .find_map(|i| {
+                self.symbols(i)
+                    .iter()
+                    .find(|s| s.1 == name)
+                    .map(|s| (i, s.0))
+            })
+    }
+}
+
+/// The demangled form of a Swift or C++ symbol.
+pub fn demangle(name: &str) -> Option<String> {
+    crate::parsers::demangle::demangle(name).or_else(|| demangle_cpp(name))
+}
+
+/// Itanium C++ names, with the underscore Mach-O adds (`__Z`) or as the blocks they contain
+/// (`___Z..._block_invoke`).
+fn demangle_cpp(name: &str) -> Option<String> {
+    if !["_Z", "__Z", "___Z"].iter().any(|p| name.starts_with(p)) {
+        return None;
+    }
+    cpp_demangle::Symbol::new(name.as_bytes())
+        .ok()?
+        .demangle()
+        .ok()
+}
+
+/// `name`, followed by its demangled form in parentheses if it is a Swift or C++ symbol.
+pub fn with_demangled(name: &str) -> Cow<'_, str> {
+    match demangle(name) {
+        Some(demangled) => Cow::Owned(format!("{} ({})", name, demangled)),
+        None => Cow::Borrowed(name),
+    }
+}
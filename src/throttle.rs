@@ -0,0 +1,80 @@
+//! Low priority mode for long batch runs, so a full-cache extraction can run in the background
+//! without starving interactive work. Threads created afterwards inherit the priority.
+
+/// Nice value applied to the CPU priority.
+#[cfg(unix)]
+const NICE: i32 = 10;
+
+/// Lower the CPU priority of the calling thread and move its IO to the platform's background
+/// class (throttled IO on macOS, the idle IO class on Linux).
+pub fn lower_priority() -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        // SAFETY: plain syscalls on the calling process, with no pointers involved.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, NICE) } != 0 {
+            return Err(format!(
+                "setpriority failed: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        lower_io_priority()
+    }
+    #[cfg(not(unix))]
+    {
+        Err("Lowering the priority is not supported on this platform".to_string())
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn lower_io_priority() -> Result<(), String> {
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_PROCESS: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+    unsafe extern "C" {
+        fn setiopolicy_np(
+            iotype: libc::c_int,
+            scope: libc::c_int,
+            policy: libc::c_int,
+        ) -> libc::c_int;
+    }
+    // SAFETY: sets a policy of the calling process, with no pointers involved.
+    if unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) } != 0 {
+        return Err(format!(
+            "setiopolicy_np failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn lower_io_priority() -> Result<(), String> {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    // SAFETY: sets the IO priority of the calling thread, with no pointers involved.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "ioprio_set failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(all(
+    unix,
+    not(target_vendor = "apple"),
+    not(any(target_os = "linux", target_os = "android"))
+))]
+fn lower_io_priority() -> Result<(), String> {
+    Ok(())
+}
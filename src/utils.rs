@@ -1,27 +1,36 @@
-pub fn print_hex_dump(start_addr: u64, data: &[u8]) {
-    for (row_idx, row) in data.chunks(16).enumerate() {
-        let addr = start_addr + (row_idx * 16) as u64;
-        print!("{:016X}: ", addr);
-        for b in row {
-            print!("{:02X} ", b);
-        }
-
-        if row.len() < 16 {
-            for _ in 0..(16 - row.len()) {
-                print!("   ");
-            }
-        }
-
-        print!(" |");
+pub fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: Vec<String> = uuid.iter().map(|b| format!("{:02X}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex[0..4].concat(),
+        hex[4..6].concat(),
+        hex[6..8].concat(),
+        hex[8..10].concat(),
+        hex[10..16].concat()
+    )
+}
 
-        for b in row {
-            let ch = if b.is_ascii_graphic() || *b == b' ' {
-                *b as char
-            } else {
-                '.'
-            };
-            print!("{}", ch);
+/// Match `text` against a shell-style pattern where `*` matches any run of characters and `?`
+/// any single character.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let (p, t) = (pattern.as_bytes(), text.as_bytes());
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` and the text position it was tried against.
+    let mut star = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == b'?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == b'*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
         }
-        println!("|");
     }
+    p[pi..].iter().all(|&c| c == b'*')
 }
@@ -0,0 +1,212 @@
+use object::LittleEndian;
+use object::macho::{self, DyldCacheHeader};
+use object::read::macho::{DyldCache, DyldCacheMappingSlice};
+
+use crate::slide;
+
+/// Read `size` bytes starting at `addr`, following the cache mappings so that a range
+/// spanning several mappings (possibly in different subcache files) is assembled correctly.
+pub fn read_bytes(
+    cache: &DyldCache<LittleEndian>,
+    addr: u64,
+    size: u64,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(size as usize);
+    let mut cur = addr;
+    let end = addr
+        .checked_add(size)
+        .ok_or_else(|| format!("Range 0x{:X}+0x{:X} overflows", addr, size))?;
+
+    while cur < end {
+        let mapping = cache
+            .mappings()
+            .find(|m| cur >= m.address() && cur < m.address() + m.size())
+            .ok_or_else(|| format!("Address 0x{:X} is not mapped", cur))?;
+        let data = mapping
+            .data()
+            .map_err(|e| format!("Failed to read mapping at 0x{:X}: {}", mapping.address(), e))?;
+        let start = (cur - mapping.address()) as usize;
+        let len = std::cmp::min((end - cur) as usize, data.len().saturating_sub(start));
+        if len == 0 {
+            return Err(format!("Mapping data for 0x{:X} is truncated", cur));
+        }
+        out.extend_from_slice(&data[start..start + len]);
+        cur += len as u64;
+    }
+    Ok(out)
+}
+
+/// Read up to `max` bytes from `addr`, stopping where mapped memory ends. The range may
+/// continue into a mapping backed by another subcache file.
+pub fn read_mapped(
+    cache: &DyldCache<LittleEndian>,
+    addr: u64,
+    max: u64,
+) -> Result<Vec<u8>, String> {
+    read_bytes(cache, addr, mapped_len(cache, addr, max))
+}
+
+/// Number of bytes, up to `max`, that are mapped contiguously from `addr`, possibly across
+/// several mappings and backing files.
+pub fn mapped_len(cache: &DyldCache<LittleEndian>, addr: u64, max: u64) -> u64 {
+    let end = addr.saturating_add(max);
+    let mut cur = addr;
+    while cur < end {
+        let Some(mapping) = cache
+            .mappings()
+            .find(|m| cur >= m.address() && cur < m.address() + m.size())
+        else {
+            break;
+        };
+        let available = mapping.data().map_or(0, |d| d.len() as u64);
+        let mapping_end = mapping.address() + std::cmp::min(mapping.size(), available);
+        if mapping_end <= cur {
+            break;
+        }
+        cur = mapping_end;
+    }
+    std::cmp::min(cur, end) - addr
+}
+
+pub fn read_u32(cache: &DyldCache<LittleEndian>, addr: u64) -> Option<u32> {
+    let (data, offset) = cache.data_and_offset_for_address(addr)?;
+    let offset = offset as usize;
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Translate a file offset in the main cache file to the VM address it is mapped at.
+pub fn file_offset_to_address(cache: &DyldCache<LittleEndian>, offset: u64) -> Option<u64> {
+    main_file_mappings(cache.data())?
+        .into_iter()
+        .find(|&(_, size, file_offset)| offset >= file_offset && offset < file_offset + size)
+        .map(|(address, _, file_offset)| address + (offset - file_offset))
+}
+
+/// The `(address, size, file_offset)` of the mappings a cache file describes in its own
+/// header, which are those of that file only.
+pub fn main_file_mappings(data: &[u8]) -> Option<Vec<(u64, u64, u64)>> {
+    let e = LittleEndian;
+    let header = DyldCacheHeader::<LittleEndian>::parse(data).ok()?;
+    match header.mappings(e, data).ok()? {
+        DyldCacheMappingSlice::V1(m) => Some(
+            m.iter()
+                .map(|m| (m.address.get(e), m.size.get(e), m.file_offset.get(e)))
+                .collect(),
+        ),
+        DyldCacheMappingSlice::V2(m) => Some(
+            m.iter()
+                .map(|m| (m.address.get(e), m.size.get(e), m.file_offset.get(e)))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Read a NUL-terminated UTF-8 string at `addr`.
+pub fn read_cstr<'data>(cache: &DyldCache<'data, LittleEndian>, addr: u64) -> Option<&'data str> {
+    let (data, offset) = cache.data_and_offset_for_address(addr)?;
+    let bytes = data.get(offset as usize..)?;
+    let len = bytes.iter().position(|&b| b == 0)?;
+    std::str::from_utf8(&bytes[..len]).ok()
+}
+
+/// A mapping of the cache, with the file it is stored in.
+pub struct Mapping {
+    pub address: u64,
+    pub size: u64,
+    pub file_offset: u64,
+    pub max_prot: u32,
+    pub init_prot: u32,
+    /// Index of the file holding the mapping: 0 for the main cache file, then the subcaches
+    /// in the order of `DyldCache::subcache_suffixes`.
+    pub file: usize,
+    /// `DYLD_CACHE_MAPPING_*` flags and slide info (file offset, size), recorded by caches
+    /// with `mapping_with_slide` entries.
+    pub flags: Option<u64>,
+    pub slide_info: Option<(u64, u64)>,
+}
+
+impl Mapping {
+    /// Region name dyld gives the mapping, e.g. `__TEXT`, `__AUTH_CONST` or `__LINKEDIT`.
+    pub fn name(&self) -> &'static str {
+        let flags = self.flags.unwrap_or(0);
+        let has = |flag: u64| flags & flag != 0;
+        if has(macho::DYLD_CACHE_MAPPING_TEXT_STUBS) {
+            "__TEXT_STUBS"
+        } else if self.init_prot & macho::VM_PROT_EXECUTE != 0 {
+            "__TEXT"
+        } else if self.max_prot & macho::VM_PROT_WRITE == 0 {
+            "__LINKEDIT"
+        } else if has(macho::DYLD_CACHE_MAPPING_AUTH_DATA) {
+            if has(macho::DYLD_CACHE_MAPPING_CONST_DATA) {
+                "__AUTH_CONST"
+            } else {
+                "__AUTH"
+            }
+        } else if has(macho::DYLD_CACHE_MAPPING_DIRTY_DATA) {
+            "__DATA_DIRTY"
+        } else if has(macho::DYLD_CACHE_MAPPING_CONST_DATA) {
+            "__DATA_CONST"
+        } else {
+            "__DATA"
+        }
+    }
+}
+
+/// Every mapping of the cache, main file first. A file's mappings start with the one holding
+/// its header, at file offset 0, which is also where its `mapping_with_slide` entries are read.
+pub fn mappings(cache: &DyldCache<LittleEndian>) -> Vec<Mapping> {
+    let e = LittleEndian;
+    let mut out: Vec<Mapping> = Vec::new();
+    let mut slide_entries = Vec::new();
+    let mut legacy = false;
+    for (i, mapping) in cache.mappings().enumerate() {
+        let file = match out.last() {
+            Some(last) if mapping.file_offset() == 0 => last.file + 1,
+            Some(last) => last.file,
+            None => 0,
+        };
+        if i == 0 || mapping.file_offset() == 0 {
+            let entries = mapping
+                .data()
+                .ok()
+                .and_then(|data| {
+                    let header = DyldCacheHeader::<LittleEndian>::parse(data).ok()?;
+                    match header.mappings(e, data).ok()? {
+                        DyldCacheMappingSlice::V2(m) => Some((m.to_vec(), false)),
+                        // Older caches only describe the slide info of their data mapping.
+                        DyldCacheMappingSlice::V1(_) => Some((
+                            slide::legacy_slide_mapping(header, data)
+                                .into_iter()
+                                .collect(),
+                            true,
+                        )),
+                        _ => None,
+                    }
+                })
+                .unwrap_or_default();
+            (slide_entries, legacy) = entries;
+        }
+        // Entries of the current file, matched by address.
+        let entry = slide_entries
+            .iter()
+            .find(|m| m.address.get(e) == mapping.address());
+        out.push(Mapping {
+            address: mapping.address(),
+            size: mapping.size(),
+            file_offset: mapping.file_offset(),
+            max_prot: mapping.max_prot(),
+            init_prot: mapping.init_prot(),
+            file,
+            flags: entry.filter(|_| !legacy).map(|m| m.flags.get(e)),
+            slide_info: entry.map(|m| {
+                (
+                    m.slide_info_file_offset.get(e),
+                    m.slide_info_file_size.get(e),
+                )
+            }),
+        });
+    }
+    out
+}
@@ -0,0 +1,336 @@
+//! Parsing, resolution and extraction on synthetic caches from `dsc::fixture`. Run with
+//! `cargo test --features fixtures`.
+
+use dsc::disasm::Disassembler;
+use dsc::exports::{self, ExportKind};
+use dsc::fixture::{BASE_ADDRESS, Fixture, UUID};
+use dsc::resolve::{self, AddressFormat, Location};
+use dsc::slide::AuthMode;
+use dsc::symbolize::Symbolizer;
+use dsc::{CacheIndex, Explain, ExtractOptions};
+use object::LittleEndian;
+use object::read::macho::DyldCache;
+
+const RET: [u8; 4] = [0xC0, 0x03, 0x5F, 0xD6];
+const NOP: [u8; 4] = [0x1F, 0x20, 0x03, 0xD5];
+
+fn fixture() -> Fixture {
+    Fixture::new()
+        .image("/usr/lib/libfoo.dylib")
+        .function("_foo", &[NOP, RET].concat())
+        .function("_foo_helper", &RET)
+        .data(&0x1234u64.to_le_bytes())
+        .image("/usr/lib/libbar.dylib")
+        .function("_bar", &RET)
+}
+
+#[test]
+fn parses_images() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let paths: Vec<_> = cache.images().map(|i| i.path().unwrap()).collect();
+    assert_eq!(paths, ["/usr/lib/libfoo.dylib", "/usr/lib/libbar.dylib"]);
+    assert_eq!(cache.mappings().count(), 3);
+
+    let index = CacheIndex::new(&cache);
+    let foo = index.image(0);
+    assert_eq!(foo.base, fixture.image_address(0).unwrap());
+    assert_eq!(foo.base, BASE_ADDRESS + 0x4000);
+    assert_eq!(foo.uuid.unwrap()[..12], UUID[..12]);
+    let segments: Vec<_> = foo.segments.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(segments, ["__TEXT", "__DATA", "__LINKEDIT"]);
+}
+
+#[test]
+fn resolves_addresses() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let index = CacheIndex::new(&cache);
+
+    let bar = fixture.address("_bar").unwrap();
+    assert_eq!(index.image_at(bar).unwrap().0, 1);
+    match resolve::resolve(&index, fixture.data_address(0).unwrap() + 4) {
+        Some(Location::Image {
+            path,
+            section,
+            offset,
+            ..
+        }) => {
+            assert_eq!(path, "/usr/lib/libfoo.dylib");
+            assert_eq!(section.as_deref(), Some("__data"));
+            assert_eq!(offset, 4);
+        }
+        _ => panic!("data address not resolved"),
+    }
+    assert!(index.image_at(BASE_ADDRESS).is_none());
+}
+
+#[test]
+fn symbolizes() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let index = CacheIndex::new(&cache);
+    let symbolizer = Symbolizer::new(&index);
+
+    let foo = fixture.address("_foo").unwrap();
+    assert_eq!(symbolizer.lookup(foo + 4), Some(("_foo", 4)));
+    assert_eq!(
+        symbolizer.find("_bar", None).map(|f| f.1),
+        fixture.address("_bar")
+    );
+    assert_eq!(symbolizer.function_starts(0).0, [foo, foo + 8]);
+
+    let image = cache.images().next().unwrap();
+    let names: Vec<_> = exports::image_exports(&cache, &image)
+        .unwrap()
+        .into_iter()
+        .map(|e| match e.kind {
+            ExportKind::Regular { address } => (e.name, address),
+            _ => panic!("{} is not a regular export", e.name),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("_foo".to_string(), foo),
+            ("_foo_helper".to_string(), foo + 8)
+        ]
+    );
+}
+
+#[test]
+fn disassembles_functions() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let index = CacheIndex::new(&cache);
+    let symbolizer = Symbolizer::new(&index);
+    let fmt = AddressFormat::new(&index, true);
+    let disassembler = Disassembler::new(&index, &symbolizer, &fmt);
+
+    let foo = fixture.address("_foo").unwrap();
+    let (end, _) = disassembler.function_end(0, foo).unwrap();
+    assert_eq!(end, foo + 8);
+    let mut out = Vec::new();
+    disassembler.write(&mut out, foo, end).unwrap();
+    let lines: Vec<Vec<String>> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_whitespace().map(str::to_string).collect())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].last().map(String::as_str), Some("nop"));
+    assert_eq!(lines[1].last().map(String::as_str), Some("ret"));
+    assert!(lines[1][0].starts_with("libfoo.dylib+0x"));
+}
+
+#[test]
+fn extracts_images() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let image = cache.images().next().unwrap();
+    let extracted = dsc::extract(
+        &cache,
+        &image,
+        &ExtractOptions::default(),
+        &mut Explain::new(false),
+    )
+    .unwrap();
+
+    let base = fixture.image_address(0).unwrap();
+    assert_eq!(extracted.base, base);
+    let foo = (fixture.address("_foo").unwrap() - base) as usize;
+    assert_eq!(extracted.data[foo..foo + 8], [NOP, RET].concat());
+    let data = (fixture.data_address(0).unwrap() - base) as usize;
+    assert_eq!(extracted.data[data..data + 8], 0x1234u64.to_le_bytes());
+}
+
+#[test]
+fn strict_extraction_of_a_complete_image() {
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let image = cache.images().nth(1).unwrap();
+    let mut options = ExtractOptions::default();
+    options.auth = Some(AuthMode::Strip);
+    options.strict = true;
+    let extracted = dsc::extract(&cache, &image, &options, &mut Explain::new(false)).unwrap();
+    assert!(extracted.warnings.is_empty());
+    assert!(extracted.metadata.pretty().contains("\"warnings\": []"));
+}
+
+#[test]
+fn rebase_info_makes_a_loadable_image() {
+    use object::macho::{LC_DYLD_INFO_ONLY, LC_FUNCTION_STARTS, MH_DYLIB_IN_CACHE};
+    use object::read::macho::{LoadCommandVariant, MachOFile64};
+    use object::{Object, ObjectSection, ObjectSegment};
+
+    let fixture = fixture();
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let image = cache.images().next().unwrap();
+    let mut options = ExtractOptions::default();
+    options.auth = Some(AuthMode::Strip);
+    options.rebase_info = true;
+    let extracted = dsc::extract(&cache, &image, &options, &mut Explain::new(false)).unwrap();
+
+    let base = extracted.base;
+    let file = MachOFile64::<LittleEndian>::parse(&*extracted.data).unwrap();
+    let names: Vec<String> = file
+        .segments()
+        .map(|s| s.name().unwrap().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["__TEXT", "__DATA", "__LINKEDIT"]);
+    for segment in file.segments() {
+        assert_eq!(segment.file_range().0, segment.address() - base);
+    }
+    let text = file.section_by_name("__text").unwrap();
+    let foo = (fixture.address("_foo").unwrap() - text.address()) as usize;
+    assert_eq!(text.data().unwrap()[foo..foo + 8], [NOP, RET].concat());
+    assert_eq!(
+        file.section_by_name("__data").unwrap().data().unwrap()[..8],
+        0x1234u64.to_le_bytes()
+    );
+    assert_eq!(
+        file.macho_header().flags.get(LittleEndian) & MH_DYLIB_IN_CACHE,
+        0
+    );
+
+    let mut info = None;
+    let mut commands = file.macho_load_commands().unwrap();
+    while let Some(command) = commands.next().unwrap() {
+        assert_ne!(command.cmd(), LC_FUNCTION_STARTS);
+        if let LoadCommandVariant::DyldInfo(dyld_info) = command.variant().unwrap() {
+            assert_eq!(command.cmd(), LC_DYLD_INFO_ONLY);
+            info = Some(dyld_info);
+        }
+    }
+    let info = info.expect("LC_DYLD_INFO_ONLY");
+    let offset = info.rebase_off.get(LittleEndian) as usize;
+    let opcodes = extracted.rebase_info.unwrap();
+    assert_eq!(extracted.data[offset..offset + opcodes.len()], opcodes);
+}
+
+#[test]
+fn fixes_selectors() {
+    use dsc::parsers::rebase;
+    use object::read::macho::MachOFile64;
+    use object::{Object, ObjectSection, ObjectSegment};
+
+    // libobjc holds the uniqued strings, which libfoo's selector reference and method list
+    // point at in the cache.
+    let fixture = Fixture::new()
+        .image("/usr/lib/libobjc.A.dylib")
+        .function("_objc_msgSend", &RET)
+        .selector("init")
+        .selector("dealloc")
+        .image("/usr/lib/libfoo.dylib")
+        .function("_foo", &RET)
+        .selector("init")
+        .method("init")
+        .method("dealloc");
+    let data = fixture.build();
+    let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+    let image = cache.images().nth(1).unwrap();
+    let mut options = ExtractOptions::default();
+    options.auth = Some(AuthMode::Strip);
+    options.fix_selectors = true;
+    options.rebase_info = true;
+    let extracted = dsc::extract(&cache, &image, &options, &mut Explain::new(false)).unwrap();
+    assert!(extracted.warnings.is_empty());
+
+    let blob = &extracted.data;
+    let base = extracted.base;
+    let read_u64 = |address: u64| {
+        let offset = (address - base) as usize;
+        u64::from_le_bytes(blob[offset..offset + 8].try_into().unwrap())
+    };
+    let read_u32 = |address: u64| {
+        let offset = (address - base) as usize;
+        u32::from_le_bytes(blob[offset..offset + 4].try_into().unwrap())
+    };
+    let string = |address: u64| {
+        let offset = (address - base) as usize;
+        let end = blob[offset..].iter().position(|&b| b == 0).unwrap();
+        std::str::from_utf8(&blob[offset..offset + end]).unwrap()
+    };
+
+    let file = MachOFile64::<LittleEndian>::parse(&**blob).unwrap();
+    let methname = file.section_by_name("__objc_methname").unwrap();
+    let local = methname.address()..methname.address() + methname.size();
+    assert!(!local.contains(&fixture.selector_address("init").unwrap()));
+
+    // The selector reference points at libfoo's own string.
+    let selref = file.section_by_name("__objc_selrefs").unwrap().address();
+    assert_eq!(read_u64(selref), methname.address());
+    assert_eq!(string(read_u64(selref)), "init");
+
+    // The method list is switched from direct selectors to selector references: `init` uses
+    // the image's reference, `dealloc`, which the image has no string for, one appended with
+    // its string after the last segment.
+    let list = file.section_by_name("__objc_const").unwrap().address() + 48;
+    assert_eq!(read_u32(list), 0x8000_000C);
+    let name = |index: u64| {
+        let field = list + 8 + 12 * index;
+        field.wrapping_add_signed(read_u32(field) as i32 as i64)
+    };
+    assert_eq!(name(0), selref);
+    let appended = file
+        .segments()
+        .find(|s| s.name() == Ok(Some("__DSC_SELECTORS")))
+        .unwrap();
+    let appended = appended.address()..appended.address() + appended.size();
+    assert!(appended.contains(&name(1)));
+    assert!(appended.contains(&read_u64(name(1))));
+    assert_eq!(string(read_u64(name(1))), "dealloc");
+
+    let json = extracted.metadata.pretty();
+    assert!(json.contains("\"selrefs\": 1"), "{}", json);
+    assert!(json.contains("\"methods\": 2"), "{}", json);
+    assert!(json.contains("\"direct_lists\": 1"), "{}", json);
+
+    // Both selector references are slid with the image, in __DATA and the appended segment
+    // that follows it.
+    let segments: Vec<(String, u64, u64)> = file
+        .segments()
+        .map(|s| {
+            (
+                s.name().unwrap().unwrap().to_string(),
+                s.address(),
+                s.size(),
+            )
+        })
+        .collect();
+    assert_eq!(segments[1].0, "__DATA");
+    assert_eq!(segments[2].0, "__DSC_SELECTORS");
+    let sizes: Vec<u64> = segments.iter().map(|s| s.2).collect();
+    assert_eq!(
+        rebase::decode(&extracted.rebase_info.unwrap(), &sizes).unwrap(),
+        [(1, selref - segments[1].1), (2, name(1) - segments[2].1)]
+    );
+}
+
+#[test]
+fn rejects_oversized_segments() {
+    let fixture = fixture();
+    // vmsize of `__DATA`, the second segment command after the header and `__TEXT` with its
+    // section.
+    let header = (fixture.image_address(0).unwrap() - BASE_ADDRESS) as usize;
+    let vmsize = header + 32 + 72 + 80 + 32;
+    for (size, error) in [
+        (u64::MAX, "runs past the end of the address space"),
+        (1 << 33, "a blob can hold"),
+    ] {
+        let mut data = fixture.build();
+        data[vmsize..vmsize + 8].copy_from_slice(&size.to_le_bytes());
+        let cache = DyldCache::<LittleEndian>::parse(&*data, &[]).unwrap();
+        let image = cache.images().next().unwrap();
+        let options = ExtractOptions::default();
+        let result = dsc::extract(&cache, &image, &options, &mut Explain::new(false));
+        assert!(result.err().unwrap().to_string().contains(error));
+    }
+}